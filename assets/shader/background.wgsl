@@ -0,0 +1,33 @@
+// Uniforms
+struct BackgroundUniform {
+	top: vec4<f32>,
+	bottom: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> background: BackgroundUniform;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	// how far up the viewport the pixel is, from 0 at the bottom to 1 at the top
+	@location(0) height: f32,
+}
+
+// Draws a single triangle covering the whole viewport
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	let ndc = uv * 2.0 - 1.0;
+
+	var out: VertexOutput;
+
+	out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+	out.height = uv.y;
+
+	return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	return mix(background.bottom, background.top, saturate(input.height));
+}
@@ -0,0 +1,42 @@
+// Uniforms
+struct DepthDebugUniform {
+	// the distance of the camera's near plane
+	near: f32,
+	// the distance shown as white, nearer depths fading to black
+	range: f32,
+	// whether the depth is reversed, 1 at the near plane and 0 at infinity
+	reversed_z: u32,
+}
+
+// the depth texture, bound as a float texture since some backends can't load from depth textures.
+// Replaced by a multisampled texture when the scene is multisampled, the last argument of
+// `textureLoad` then selecting the sample rather than the mip level
+@group(0) @binding(0)
+var depth: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> depth_debug: DepthDebugUniform;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+	return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Shows the linear distance of the depth under the pixel as a shade of gray. The projection has
+// no far plane, so a depth `d` lies `near / (1 - d)` units away, or `near / d` if it's reversed
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+	var d = textureLoad(depth, vec2<i32>(position.xy), 0).r;
+
+	if depth_debug.reversed_z == 0u {
+		d = 1.0 - d;
+	}
+
+	let distance = depth_debug.near / max(d, 1e-7);
+	let shade = saturate(distance / depth_debug.range);
+
+	return vec4<f32>(vec3<f32>(shade), 1.0);
+}
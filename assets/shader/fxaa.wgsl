@@ -0,0 +1,71 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+// How far along an edge the samples may reach, in texels
+const SPAN_MAX: f32 = 8.0;
+// Dampens the edge direction in dark areas, where aliasing is less visible
+const REDUCE_MUL: f32 = 1.0 / 8.0;
+const REDUCE_MIN: f32 = 1.0 / 128.0;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+	return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// The perceived brightness of a linear color. The square root approximates gamma encoding, so that
+// edges are detected the way they're seen rather than in linear space
+fn luma(color: vec3<f32>) -> f32 {
+	return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn sample(uv: vec2<f32>) -> vec4<f32> {
+	return textureSampleLevel(source, source_sampler, uv, 0.0);
+}
+
+// Smooths edges by blending along the direction of the local luma gradient
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+	let texel = 1.0 / vec2<f32>(textureDimensions(source));
+	let uv = position.xy * texel;
+
+	let center = sample(uv);
+
+	let luma_nw = luma(sample(uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+	let luma_ne = luma(sample(uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+	let luma_sw = luma(sample(uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+	let luma_se = luma(sample(uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+	let luma_m = luma(center.rgb);
+
+	let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	var direction = vec2<f32>(
+		(luma_sw + luma_se) - (luma_nw + luma_ne),
+		(luma_nw + luma_sw) - (luma_ne + luma_se),
+	);
+
+	let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+	let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+	direction = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+	let inner = 0.5 * (
+		sample(uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+		sample(uv + direction * (2.0 / 3.0 - 0.5)).rgb
+	);
+	let outer = inner * 0.5 + 0.25 * (
+		sample(uv - direction * 0.5).rgb +
+		sample(uv + direction * 0.5).rgb
+	);
+
+	// the wider blend overshoots if it crosses into another edge
+	let luma_outer = luma(outer);
+	let color = select(outer, inner, luma_outer < luma_min || luma_outer > luma_max);
+
+	return vec4<f32>(color, center.a);
+}
@@ -0,0 +1,101 @@
+// Uniforms
+struct GridCameraUniform {
+	// the inverse of the camera's view-projection matrix, to reconstruct the pixels' rays
+	inverse_view_proj: mat4x4<f32>,
+	view_proj: mat4x4<f32>,
+	eye: vec3<f32>,
+	// the width of the lines in physical pixels
+	line_width: f32,
+	// the axis pointing up, +Y or +Z, which the plane of the grid faces
+	up: vec3<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> grid_camera: GridCameraUniform;
+
+struct GridParamsUniform {
+	color: vec4<f32>,
+	major_color: vec4<f32>,
+	spacing: f32,
+	major_interval: f32,
+	fade_distance: f32,
+}
+
+@group(0) @binding(1)
+var<uniform> grid: GridParamsUniform;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) ndc: vec2<f32>,
+}
+
+struct FragmentOutput {
+	@location(0) color: vec4<f32>,
+	@builtin(frag_depth) depth: f32,
+}
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	let ndc = uv * 2.0 - 1.0;
+
+	var out: VertexOutput;
+
+	out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+	out.ndc = ndc;
+
+	return out;
+}
+
+// The world position at a depth along the ray of a pixel
+fn unproject(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+	let point = grid_camera.inverse_view_proj * vec4<f32>(ndc, depth, 1.0);
+
+	return point.xyz / point.w;
+}
+
+// The coverage of a pixel by the lines between the cells of `coord`, the lines being as wide as
+// the camera's line width, antialiased over the width of a pixel and faded out once the cells get
+// too small on screen to tell apart
+fn lines(coord: vec2<f32>) -> f32 {
+	let derivative = fwidth(coord);
+	let distance = abs(fract(coord - 0.5) - 0.5) / derivative;
+	let coverage = saturate(grid_camera.line_width / 2.0 + 0.5 - min(distance.x, distance.y));
+
+	return coverage * saturate(2.0 - 2.0 * max(derivative.x, derivative.y));
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> FragmentOutput {
+	// the projection is infinite, so the ray is found between the eye and a point at half the
+	// depth, which lies in front of the camera whether the depth is reversed or not
+	let near = grid_camera.eye;
+	let far = unproject(input.ndc, 0.5);
+	let t = dot(near, grid_camera.up) / dot(near - far, grid_camera.up);
+	let position = near + t * (far - near);
+
+	// the plane is spanned by x and whichever of y and z doesn't point up. The derivatives are
+	// taken before discarding anything, while all of the pixels are running
+	let coord = select(position.xz, position.xy, grid_camera.up.z != 0.0) / grid.spacing;
+	let minor = lines(coord);
+	let major = lines(coord / grid.major_interval);
+
+	// the ray doesn't hit the plane in front of the camera (or runs parallel to it)
+	if !(t > 0.0) {
+		discard;
+	}
+
+	let clip = grid_camera.view_proj * vec4<f32>(position, 1.0);
+	let fade = saturate(1.0 - distance(position, grid_camera.eye) / grid.fade_distance);
+
+	var color = mix(grid.color, grid.major_color, major);
+	color.a *= max(minor, major) * fade * fade;
+
+	var out: FragmentOutput;
+
+	out.color = color;
+	out.depth = clip.z / clip.w;
+
+	return out;
+}
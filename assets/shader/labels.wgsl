@@ -0,0 +1,89 @@
+// Uniforms
+struct CameraUniform {
+	view_proj: mat4x4<f32>,
+	// the size of the viewport being drawn into in physical pixels
+	viewport_size: vec2<f32>,
+	line_width: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// the coverage of every glyph's pixels, laid out in a single row of glyphs
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+
+// the size of a glyph in the atlas, in texels
+const GLYPH_SIZE = vec2<f32>(5.0, 7.0);
+
+struct InstanceInput {
+	@location(0) position: vec3<f32>,
+	@location(1) glyph: u32,
+	@location(2) offset: vec2<f32>,
+	@location(3) scale: f32,
+	@location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	// the position within the glyph, in texels from its top left corner
+	@location(1) texel: vec2<f32>,
+	@location(2) @interpolate(flat) glyph: u32,
+}
+
+// Draws a rectangle facing the screen for every glyph, as two triangles of six vertices offset in
+// pixels from where the label's anchor is projected to
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 1.0),
+	);
+
+	let corner = corners[index];
+	let clip = camera.view_proj * vec4<f32>(instance.position, 1.0);
+
+	var out: VertexOutput;
+
+	out.color = instance.color;
+	out.texel = corner * GLYPH_SIZE;
+	out.glyph = instance.glyph;
+
+	// anchors behind the camera would be mirrored onto the screen, so their glyphs are moved
+	// outside of the clip volume instead
+	if clip.w <= 0.0 {
+		out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+		return out;
+	}
+
+	// the anchor is snapped to a pixel so that the glyphs' pixels line up with the screen's
+	let ndc = clip.xy / clip.w;
+	let anchor = round((ndc * vec2<f32>(0.5, -0.5) + 0.5) * camera.viewport_size);
+	let pixel = anchor + instance.offset + out.texel * instance.scale;
+
+	out.clip_position = vec4<f32>(
+		(pixel / camera.viewport_size * 2.0 - 1.0) * vec2<f32>(1.0, -1.0),
+		0.0,
+		1.0,
+	);
+
+	return out;
+}
+
+// Shades the pixels of the glyph which are set in the atlas
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	let local = clamp(vec2<i32>(floor(input.texel)), vec2<i32>(0), vec2<i32>(GLYPH_SIZE) - 1);
+	let texel = vec2<i32>(i32(input.glyph) * i32(GLYPH_SIZE.x), 0) + local;
+
+	if textureLoad(atlas, texel, 0).r < 0.5 {
+		discard;
+	}
+
+	return input.color;
+}
@@ -0,0 +1,155 @@
+// Uniforms
+struct CameraUniform {
+	view_proj: mat4x4<f32>,
+	// the size of the viewport in physical pixels, which the lines' width is measured in
+	viewport_size: vec2<f32>,
+	line_width: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct PostParamsUniform {
+	gamma: f32,
+	brightness: f32,
+}
+
+@group(0) @binding(1)
+var<uniform> post_params: PostParamsUniform;
+
+// the plane equation fragments in front of are discarded, which no point is in front of while
+// clipping is off
+@group(0) @binding(2)
+var<uniform> clip_plane: vec4<f32>;
+
+struct MaterialUniform {
+	base_color: vec4<f32>,
+	// the fallback of `draw_tint` on devices without push constants
+	tint: vec4<f32>,
+	emissive: vec3<f32>,
+	normal_scale: f32,
+	// a bit for every texture the material has, in the order of their bindings
+	textures: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> material: MaterialUniform;
+
+struct VertexInput {
+	@location(0) start: vec3<f32>,
+	@location(1) end: vec3<f32>,
+	@location(2) color: vec4<f32>,
+	// along the segment from 0 to 1, and across it from -1 to 1
+	@location(3) corner: vec2<f32>,
+}
+
+struct InstanceInput {
+	@location(4) model_0: vec4<f32>,
+	@location(5) model_1: vec4<f32>,
+	@location(6) model_2: vec4<f32>,
+	@location(7) model_3: vec4<f32>,
+}
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	// the distance from the middle of the line in pixels
+	@location(1) across: f32,
+	// the camera's line width, which the fragment shader can't read from the camera itself
+	@location(2) @interpolate(flat) line_width: f32,
+	@location(3) world_position: vec3<f32>,
+}
+
+// How far the end of a segment has to be moved towards the other end to bring it onto the near
+// plane, if it's behind the camera
+fn near_factor(point: vec4<f32>, other: vec4<f32>) -> f32 {
+	if point.z >= 0.0 {
+		return 0.0;
+	}
+
+	return point.z / (point.z - other.z);
+}
+
+// Expands every segment into a quad, offsetting its corners perpendicular to the segment on screen
+// by half the line's width plus a pixel, which the edges are antialiased over
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let model = mat4x4<f32>(
+		instance.model_0,
+		instance.model_1,
+		instance.model_2,
+		instance.model_3,
+	);
+
+	let world_start = model * vec4<f32>(input.start, 1.0);
+	let world_end = model * vec4<f32>(input.end, 1.0);
+
+	let start = camera.view_proj * world_start;
+	let end = camera.view_proj * world_end;
+
+	// the projection is linear, so the world positions are moved along with the clip positions
+	let start_factor = near_factor(start, end);
+	let end_factor = near_factor(end, start);
+
+	let clipped_start = mix(start, end, start_factor);
+	let clipped_end = mix(end, start, end_factor);
+
+	let screen_start = clipped_start.xy / clipped_start.w * camera.viewport_size / 2.0;
+	let screen_end = clipped_end.xy / clipped_end.w * camera.viewport_size / 2.0;
+
+	let direction = screen_end - screen_start;
+	var normal = vec2<f32>(0.0, 1.0);
+
+	if dot(direction, direction) > 0.0 {
+		normal = normalize(vec2<f32>(-direction.y, direction.x));
+	}
+
+	let half_width = camera.line_width / 2.0 + 1.0;
+	let across = input.corner.y * half_width;
+
+	var position = clipped_start;
+	var world_position = mix(world_start, world_end, start_factor);
+
+	if input.corner.x > 0.5 {
+		position = clipped_end;
+		world_position = mix(world_end, world_start, end_factor);
+	}
+
+	let offset = normal * across / (camera.viewport_size / 2.0) * position.w;
+
+	var out: VertexOutput;
+
+	out.clip_position = vec4<f32>(position.xy + offset, position.zw);
+	out.color = input.color;
+	out.across = across;
+	out.line_width = camera.line_width;
+	out.world_position = world_position.xyz;
+
+	return out;
+}
+
+// Applies the gamma and brightness adjustments to a linear color, before it's encoded for the
+// surface
+fn adjust(color: vec4<f32>) -> vec4<f32> {
+	let rgb = pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / post_params.gamma));
+
+	return vec4<f32>(rgb * post_params.brightness, color.a);
+}
+
+// Shades the line in its color, fading out over the pixel at either edge
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	if dot(input.world_position, clip_plane.xyz) + clip_plane.w > 0.0 {
+		discard;
+	}
+
+	let coverage = saturate(input.line_width / 2.0 + 0.5 - abs(input.across));
+
+	if coverage <= 0.0 {
+		discard;
+	}
+
+	let color = input.color * material.base_color * draw_tint();
+
+	return adjust(vec4<f32>(color.rgb, color.a * coverage));
+}
@@ -1,35 +1,295 @@
 // Uniforms
 struct CameraUniform {
-	view_proj: mat4x4<f32>
+	view_proj: mat4x4<f32>,
+	// the size of the viewport and the width of thick lines, read by lines.wgsl
+	viewport_size: vec2<f32>,
+	line_width: f32,
 }
 
 @group(0) @binding(0) 
 var<uniform> camera: CameraUniform;
 
+struct PostParamsUniform {
+	gamma: f32,
+	brightness: f32,
+}
+
+@group(0) @binding(1)
+var<uniform> post_params: PostParamsUniform;
+
+// the plane equation fragments in front of are discarded, which no point is in front of while
+// clipping is off
+@group(0) @binding(2)
+var<uniform> clip_plane: vec4<f32>;
+
+struct MaterialUniform {
+	base_color: vec4<f32>,
+	// the fallback of `draw_tint` on devices without push constants
+	tint: vec4<f32>,
+	emissive: vec3<f32>,
+	normal_scale: f32,
+	// a bit for every texture the material has, in the order of their bindings
+	textures: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> material: MaterialUniform;
+
+// the pose of the joints skinned meshes are deformed by, a single identity for other meshes
+@group(1) @binding(1)
+var<storage, read> joint_matrices: array<mat4x4<f32>>;
+
+// the textures of the material, each followed by its sampler. Slots without a texture are bound to
+// a placeholder, which isn't sampled
+@group(1) @binding(2)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var base_color_sampler: sampler;
+@group(1) @binding(4)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(5)
+var normal_sampler: sampler;
+@group(1) @binding(6)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(7)
+var emissive_sampler: sampler;
+
+const BASE_COLOR_TEXTURE = 1u;
+const NORMAL_TEXTURE = 2u;
+const EMISSIVE_TEXTURE = 4u;
+
+struct PointLight {
+	position: vec3<f32>,
+	radius: f32,
+	color: vec3<f32>,
+}
+
+const MAX_POINT_LIGHTS: u32 = 32u;
+
+struct PointLightsUniform {
+	count: u32,
+	lights: array<PointLight, MAX_POINT_LIGHTS>,
+}
+
+@group(2) @binding(0)
+var<uniform> point_lights: PointLightsUniform;
+
+struct DirectionalLightUniform {
+	view_proj: mat4x4<f32>,
+	direction: vec3<f32>,
+	enabled: u32,
+	color: vec3<f32>,
+}
+
+@group(3) @binding(0)
+var<uniform> directional_light: DirectionalLightUniform;
+
+@group(3) @binding(1)
+var shadow_map: texture_depth_2d;
+
+@group(3) @binding(2)
+var shadow_sampler: sampler_comparison;
+
 struct VertexInput {
 	@location(0) position: vec3<f32>,
-	@location(1) color: vec3<f32>,
+	@location(1) color: vec4<f32>,
+	@location(2) normal: vec3<f32>,
+	@location(3) uv: vec2<f32>,
+	@location(11) joint_indices: vec4<u32>,
+	@location(12) joint_weights: vec4<f32>,
+}
+
+struct InstanceInput {
+	@location(4) model_0: vec4<f32>,
+	@location(5) model_1: vec4<f32>,
+	@location(6) model_2: vec4<f32>,
+	@location(7) model_3: vec4<f32>,
+	@location(8) normal_0: vec3<f32>,
+	@location(9) normal_1: vec3<f32>,
+	@location(10) normal_2: vec3<f32>,
 }
 
 struct VertexOutput {
 	@builtin(position) clip_position: vec4<f32>,
-	@location(0) color: vec3<f32>,
+	@location(0) color: vec4<f32>,
+	@location(1) world_position: vec3<f32>,
+	@location(2) normal: vec3<f32>,
+	@location(3) uv: vec2<f32>,
+}
+
+// The blend of the matrices of the joints a vertex is weighted to (linear blend skinning), the
+// identity for vertices without any weights
+fn skin_matrix(indices: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+	if all(weights == vec4<f32>(0.0)) {
+		return mat4x4<f32>(
+			vec4<f32>(1.0, 0.0, 0.0, 0.0),
+			vec4<f32>(0.0, 1.0, 0.0, 0.0),
+			vec4<f32>(0.0, 0.0, 1.0, 0.0),
+			vec4<f32>(0.0, 0.0, 0.0, 1.0),
+		);
+	}
+
+	return joint_matrices[indices.x] * weights.x
+		+ joint_matrices[indices.y] * weights.y
+		+ joint_matrices[indices.z] * weights.z
+		+ joint_matrices[indices.w] * weights.w;
 }
 
 @vertex
 fn vs_main(
 	input: VertexInput,
+	instance: InstanceInput,
 ) -> VertexOutput {
+	let model = mat4x4<f32>(
+		instance.model_0,
+		instance.model_1,
+		instance.model_2,
+		instance.model_3,
+	);
+
 	var out: VertexOutput;
 
+	let skin = skin_matrix(input.joint_indices, input.joint_weights);
+	let world_position = model * skin * vec4<f32>(input.position, 1.0);
+
 	//out.clip_position = vec4<f32>(input.position, 1.0);
-	out.clip_position = camera.view_proj * vec4<f32>(input.position, 1.0);
+	out.clip_position = camera.view_proj * world_position;
 	out.color = input.color;
+	out.world_position = world_position.xyz;
+	out.uv = input.uv;
+	// normals are transformed by the inverse transpose of the model matrix, which keeps them
+	// perpendicular to the surface when it's scaled unevenly
+	let normal_matrix = mat3x3<f32>(instance.normal_0, instance.normal_1, instance.normal_2);
+	// the joints are assumed to be scaled evenly, so their matrices transform normals as they are
+	let skin_normal = mat3x3<f32>(skin[0].xyz, skin[1].xyz, skin[2].xyz) * input.normal;
+	out.normal = normal_matrix * skin_normal;
 
 	return out;
 }
 
+// The light reaching a surface from a point light, fading out smoothly towards the light's radius
+fn point_light(light: PointLight, position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+	let to_light = light.position - position;
+	let distance = length(to_light);
+
+	let window = saturate(1.0 - pow(distance / light.radius, 4.0));
+	let attenuation = window * window / (distance * distance + 1.0);
+
+	return light.color * attenuation * max(dot(normal, to_light / distance), 0.0);
+}
+
+// The fraction of the directional light reaching a position, averaging a 3x3 block of shadow map
+// comparisons (percentage closer filtering) to soften the edges of the shadows
+fn shadow(position: vec3<f32>) -> f32 {
+	let clip = directional_light.view_proj * vec4<f32>(position, 1.0);
+	let ndc = clip.xyz / clip.w;
+	let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+	// everything outside of the shadow map is lit
+	if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+		return 1.0;
+	}
+
+	let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+	var lit = 0.0;
+
+	for (var y = -1; y <= 1; y++) {
+		for (var x = -1; x <= 1; x++) {
+			let offset = vec2<f32>(f32(x), f32(y)) * texel;
+			lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, ndc.z);
+		}
+	}
+
+	return lit / 9.0;
+}
+
+// Bends a normal by a texel of the normal texture, in a tangent frame derived from the screen-space
+// derivatives of the position and texture coordinates as the vertices have no tangents
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, texel: vec3<f32>) -> vec3<f32> {
+	let dp_dx = dpdx(position);
+	let dp_dy = dpdy(position);
+	let duv_dx = dpdx(uv);
+	let duv_dy = dpdy(uv);
+
+	let dp_dy_perp = cross(dp_dy, normal);
+	let dp_dx_perp = cross(normal, dp_dx);
+	let tangent = dp_dy_perp * duv_dx.x + dp_dx_perp * duv_dy.x;
+	// green points up the texture, towards decreasing v
+	let bitangent = -(dp_dy_perp * duv_dx.y + dp_dx_perp * duv_dy.y);
+
+	// surfaces without texture coordinates have no tangent frame to bend the normal in
+	let length = max(dot(tangent, tangent), dot(bitangent, bitangent));
+	if length == 0.0 {
+		return normal;
+	}
+
+	let scale = inverseSqrt(length);
+	let bend = (texel * 2.0 - 1.0) * vec3<f32>(vec2<f32>(material.normal_scale), 1.0);
+
+	return normalize(mat3x3<f32>(tangent * scale, bitangent * scale, normal) * bend);
+}
+
+// Applies the gamma and brightness adjustments to a linear color, before it's encoded for the
+// surface
+fn adjust(color: vec4<f32>) -> vec4<f32> {
+	let rgb = pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / post_params.gamma));
+
+	return vec4<f32>(rgb * post_params.brightness, color.a);
+}
+
+// The lit color of the fragment, in linear space
+fn shade(input: VertexOutput) -> vec4<f32> {
+	// sampled before branching, as the derivatives of the texture coordinates are only known where
+	// every fragment of a quad takes the same path
+	let base_color_texel = textureSample(base_color_texture, base_color_sampler, input.uv);
+	let normal_texel = textureSample(normal_texture, normal_sampler, input.uv).rgb;
+	let emissive_texel = textureSample(emissive_texture, emissive_sampler, input.uv).rgb;
+
+	var color = input.color * material.base_color;
+	var emissive = material.emissive;
+
+	if (material.textures & BASE_COLOR_TEXTURE) != 0u {
+		color *= base_color_texel;
+	}
+
+	if (material.textures & EMISSIVE_TEXTURE) != 0u {
+		emissive *= emissive_texel;
+	}
+
+	var normal = input.normal;
+
+	if (material.textures & NORMAL_TEXTURE) != 0u && dot(normal, normal) != 0.0 {
+		normal = perturb_normal(normalize(normal), input.world_position, input.uv, normal_texel);
+	}
+
+	let lit = point_lights.count > 0u || directional_light.enabled != 0u;
+
+	// without any lights (or normals, like lines) the colors are shown as they are
+	if !lit || dot(normal, normal) == 0.0 {
+		return vec4<f32>(color.rgb + emissive, color.a);
+	}
+
+	normal = normalize(normal);
+	var light = vec3<f32>(0.0);
+
+	if directional_light.enabled != 0u {
+		let diffuse = max(dot(normal, -directional_light.direction), 0.0);
+		light += directional_light.color * diffuse * shadow(input.world_position);
+	}
+
+	for (var i = 0u; i < min(point_lights.count, MAX_POINT_LIGHTS); i++) {
+		light += point_light(point_lights.lights[i], input.world_position, normal);
+	}
+
+	return vec4<f32>(color.rgb * light + emissive, color.a);
+}
+
+// `draw_tint` is appended by the renderer, from either tint_push_constant.wgsl or tint_uniform.wgsl
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-	return vec4<f32>(input.color, 1.0);
+	if dot(input.world_position, clip_plane.xyz) + clip_plane.w > 0.0 {
+		discard;
+	}
+
+	return adjust(shade(input) * draw_tint());
 }
@@ -0,0 +1,61 @@
+// Uniforms
+struct ParticleCameraUniform {
+	view_proj: mat4x4<f32>,
+	// the axes of the camera's view, which the particles' squares are spanned by so they face it
+	right: vec3<f32>,
+	up: vec3<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> particle_camera: ParticleCameraUniform;
+
+struct InstanceInput {
+	@location(0) position: vec3<f32>,
+	@location(1) size: f32,
+	@location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	// the position within the particle's square, from -1 to 1 along both axes
+	@location(1) offset: vec2<f32>,
+}
+
+// Draws a square facing the camera around every particle, as two triangles of six vertices
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(-1.0, -1.0),
+		vec2<f32>(1.0, -1.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(-1.0, -1.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(-1.0, 1.0),
+	);
+
+	let offset = corners[index];
+	let half = instance.size / 2.0;
+	let position = instance.position
+		+ (particle_camera.right * offset.x + particle_camera.up * offset.y) * half;
+
+	var out: VertexOutput;
+
+	out.clip_position = particle_camera.view_proj * vec4<f32>(position, 1.0);
+	out.color = instance.color;
+	out.offset = offset;
+
+	return out;
+}
+
+// Shades the square as a round dot fading out towards its edge
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	let falloff = saturate(1.0 - length(input.offset));
+
+	if falloff <= 0.0 {
+		discard;
+	}
+
+	return vec4<f32>(input.color.rgb, input.color.a * falloff);
+}
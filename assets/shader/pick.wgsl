@@ -0,0 +1,85 @@
+// Uniforms
+struct CameraUniform {
+	view_proj: mat4x4<f32>,
+	viewport_size: vec2<f32>,
+	line_width: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// the plane the meshes are clipped against, the same as in the main shader
+@group(0) @binding(2)
+var<uniform> clip_plane: vec4<f32>;
+
+// the pose of the joints skinned meshes are deformed by, from the mesh's material
+@group(1) @binding(1)
+var<storage, read> joint_matrices: array<mat4x4<f32>>;
+
+// the id of the mesh being drawn, one more than its index so that 0 is left for the background
+struct PickUniform {
+	id: u32,
+}
+
+@group(2) @binding(0)
+var<uniform> pick: PickUniform;
+
+struct VertexInput {
+	@location(0) position: vec3<f32>,
+	@location(11) joint_indices: vec4<u32>,
+	@location(12) joint_weights: vec4<f32>,
+}
+
+struct InstanceInput {
+	@location(4) model_0: vec4<f32>,
+	@location(5) model_1: vec4<f32>,
+	@location(6) model_2: vec4<f32>,
+	@location(7) model_3: vec4<f32>,
+}
+
+// The blend of the joint matrices a vertex follows, the same as in the main shader
+fn skin_matrix(indices: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+	if all(weights == vec4<f32>(0.0)) {
+		return mat4x4<f32>(
+			vec4<f32>(1.0, 0.0, 0.0, 0.0),
+			vec4<f32>(0.0, 1.0, 0.0, 0.0),
+			vec4<f32>(0.0, 0.0, 1.0, 0.0),
+			vec4<f32>(0.0, 0.0, 0.0, 1.0),
+		);
+	}
+
+	return joint_matrices[indices.x] * weights.x
+		+ joint_matrices[indices.y] * weights.y
+		+ joint_matrices[indices.z] * weights.z
+		+ joint_matrices[indices.w] * weights.w;
+}
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) world_position: vec3<f32>,
+}
+
+// Places the vertex the same way as the main shader
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+	let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+	let skin = skin_matrix(vertex.joint_indices, vertex.joint_weights);
+	let world_position = model * skin * vec4<f32>(vertex.position, 1.0);
+
+	var out: VertexOutput;
+
+	out.clip_position = camera.view_proj * world_position;
+	out.world_position = world_position.xyz;
+
+	return out;
+}
+
+// Writes the id of the mesh, unless it's clipped away where it can't be seen
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) u32 {
+	if dot(input.world_position, clip_plane.xyz) + clip_plane.w > 0.0 {
+		discard;
+	}
+
+	return pick.id;
+}
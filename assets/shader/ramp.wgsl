@@ -0,0 +1,12 @@
+@group(0) @binding(0)
+var<storage, read_write> data: array<f32>;
+
+// Writes the index of every element into it, producing a ramp of 0, 1, 2, ...
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+	if id.x >= arrayLength(&data) {
+		return;
+	}
+
+	data[id.x] = f32(id.x);
+}
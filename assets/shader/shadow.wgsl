@@ -0,0 +1,55 @@
+struct DirectionalLightUniform {
+	view_proj: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> light: DirectionalLightUniform;
+
+// the pose of the joints skinned meshes are deformed by, a single identity for other meshes
+@group(1) @binding(1)
+var<storage, read> joint_matrices: array<mat4x4<f32>>;
+
+struct VertexInput {
+	@location(0) position: vec3<f32>,
+	@location(11) joint_indices: vec4<u32>,
+	@location(12) joint_weights: vec4<f32>,
+}
+
+struct InstanceInput {
+	@location(4) model_0: vec4<f32>,
+	@location(5) model_1: vec4<f32>,
+	@location(6) model_2: vec4<f32>,
+	@location(7) model_3: vec4<f32>,
+}
+
+// The blend of the matrices of the joints a vertex is weighted to, like the main shader's
+fn skin_matrix(indices: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+	if all(weights == vec4<f32>(0.0)) {
+		return mat4x4<f32>(
+			vec4<f32>(1.0, 0.0, 0.0, 0.0),
+			vec4<f32>(0.0, 1.0, 0.0, 0.0),
+			vec4<f32>(0.0, 0.0, 1.0, 0.0),
+			vec4<f32>(0.0, 0.0, 0.0, 1.0),
+		);
+	}
+
+	return joint_matrices[indices.x] * weights.x
+		+ joint_matrices[indices.y] * weights.y
+		+ joint_matrices[indices.z] * weights.z
+		+ joint_matrices[indices.w] * weights.w;
+}
+
+// Transforms the vertices into the light's clip space, only their depth being written
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+	let model = mat4x4<f32>(
+		instance.model_0,
+		instance.model_1,
+		instance.model_2,
+		instance.model_3,
+	);
+
+	let skin = skin_matrix(input.joint_indices, input.joint_weights);
+
+	return light.view_proj * model * skin * vec4<f32>(input.position, 1.0);
+}
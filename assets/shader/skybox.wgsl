@@ -0,0 +1,42 @@
+// Uniforms
+struct SkyboxUniform {
+	// the inverse of the camera's view-projection matrix, without the translation
+	inverse_rotation_proj: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> skybox: SkyboxUniform;
+
+@group(0) @binding(1)
+var skybox_texture: texture_cube<f32>;
+
+@group(0) @binding(2)
+var skybox_sampler: sampler;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) ndc: vec2<f32>,
+}
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	let ndc = uv * 2.0 - 1.0;
+
+	var out: VertexOutput;
+
+	out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+	out.ndc = ndc;
+
+	return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	// the camera sits at the origin, so any point along the pixel's ray gives its direction
+	let point = skybox.inverse_rotation_proj * vec4<f32>(input.ndc, 0.5, 1.0);
+	let direction = point.xyz / point.w;
+
+	return textureSample(skybox_texture, skybox_sampler, direction);
+}
@@ -0,0 +1,10 @@
+// The tint of the current draw, pushed as a constant before every draw call
+struct DrawConstants {
+	tint: vec4<f32>,
+}
+
+var<push_constant> draw: DrawConstants;
+
+fn draw_tint() -> vec4<f32> {
+	return draw.tint;
+}
@@ -0,0 +1,4 @@
+// The tint of the current draw, stored in the material on devices without push constants
+fn draw_tint() -> vec4<f32> {
+	return material.tint;
+}
@@ -0,0 +1,38 @@
+// Uniforms
+struct TonemapUniform {
+	exposure: f32
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> tonemap: TonemapUniform;
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+	return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve, mapping linear hdr colors into 0 to 1
+fn aces(color: vec3<f32>) -> vec3<f32> {
+	let a = 2.51;
+	let b = 0.03;
+	let c = 2.43;
+	let d = 0.59;
+	let e = 0.14;
+
+	return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Maps the hdr source texel under the pixel into the displayable range. The result stays linear,
+// since writing to an srgb target encodes it
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+	let color = textureLoad(source, vec2<i32>(position.xy), 0);
+
+	return vec4<f32>(aces(color.rgb * tonemap.exposure), color.a);
+}
@@ -0,0 +1,42 @@
+//! Renders the golden scenes headlessly and compares them against the golden images in
+//! `assets/golden`, failing if any of them changed. Mismatches leave the rendered frame and a
+//! diff image next to the golden image. Set `BLESS=1` to write the golden images instead:
+//!
+//! ```sh
+//! cargo run --example golden --features golden
+//! BLESS=1 cargo run --example golden --features golden
+//! ```
+
+use anyhow::{bail, Result};
+use d3_template::{
+    config::AppConfig,
+    golden::{self, GoldenOutcome, Tolerance},
+};
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let config = AppConfig::default();
+    let dir = golden::golden_dir();
+
+    let mut failed = 0;
+
+    for scene in &golden::SCENES {
+        let frame = golden::render(scene, &config)?;
+
+        match golden::check(&dir, scene.name, &frame, Tolerance::DEFAULT) {
+            Ok(GoldenOutcome::Matched) => println!("{}: ok", scene.name),
+            Ok(GoldenOutcome::Blessed) => println!("{}: blessed", scene.name),
+            Err(error) => {
+                println!("{error:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} golden scenes changed", golden::SCENES.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,15 @@
+//! Opens a window with a fountain of sparks rising from the origin and falling back down, emitted
+//! by the renderer's particle system:
+//!
+//! ```sh
+//! cargo run --example particles
+//! ```
+
+use anyhow::Result;
+use d3_template::{config::AppConfig, particles::ParticleParams};
+
+fn main() -> Result<()> {
+    d3_template::run_with_init(AppConfig::default(), |renderer| {
+        renderer.set_particles(ParticleParams::DEFAULT);
+    })
+}
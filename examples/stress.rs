@@ -0,0 +1,42 @@
+//! Benchmarks the renderer headlessly with scenes of increasing numbers of meshes, printing the
+//! frame times as CSV. The mesh counts can be given as arguments:
+//!
+//! ```sh
+//! cargo run --release --example stress -- 100 1000 10000
+//! ```
+
+use anyhow::Result;
+use d3_template::{benchmark, benchmark::BenchmarkResult, config::AppConfig};
+use winit::dpi::PhysicalSize;
+
+/// The number of frames measured for every mesh count.
+const FRAMES: usize = 300;
+/// The mesh counts measured when none are given.
+const MESH_COUNTS: [usize; 4] = [1, 10, 100, 1000];
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let counts = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse())
+        .collect::<Result<Vec<usize>, _>>()?;
+
+    let counts = if counts.is_empty() {
+        MESH_COUNTS.to_vec()
+    } else {
+        counts
+    };
+
+    let config = AppConfig::default();
+    let size = PhysicalSize::new(1280, 720);
+
+    println!("{}", BenchmarkResult::CSV_HEADER);
+
+    for meshes in counts {
+        let result = benchmark::run(&config, size, meshes, FRAMES)?;
+        println!("{}", result.to_csv());
+    }
+
+    Ok(())
+}
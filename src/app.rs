@@ -1,32 +1,146 @@
-use std::{collections::HashSet, f32::consts::FRAC_PI_2, sync::Arc, time::Instant};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
 
 use winit::{
     application::*,
     dpi::*,
     event::*,
     event_loop::*,
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::*,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use log::*;
 
 use glam::*;
 
-use crate::{camera::Camera, renderer::Renderer};
+#[cfg(feature = "debug-ui")]
+use crate::debug_ui::DebugUi;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::GamepadInput;
+use crate::{
+    camera::Camera,
+    command::{AppCommand, CommandReceiver},
+    config::{AppConfig, RedrawMode},
+    frame_stats::FrameStats,
+    frame_timings::FrameTimings,
+    light::{DirectionalLight, PointLight},
+    loader::MeshLoad,
+    mesh::Mesh,
+    renderer::Renderer,
+    scene::MeshId,
+    touch::TouchInput,
+};
+
+/// A callback run once for every window with the window's index (0 for the first one) and its
+/// renderer, right after the window's app has been created and before its first frame is rendered,
+/// for example to add meshes to the scene.
+pub type InitHook = Box<dyn FnMut(usize, &mut Renderer)>;
+
+/// A callback run by `App::update` every frame with the renderer, the camera and the times of the
+/// most recent frames, for example to move meshes or the camera around.
+pub type UpdateHook = Box<dyn FnMut(&mut Renderer, &mut Camera, &FrameStats)>;
+
+/// A callback shared by the apps of every window, run every frame with the window's index along
+/// with the arguments of an `UpdateHook`.
+pub type WindowUpdateHook = Rc<RefCell<dyn FnMut(usize, &mut Renderer, &mut Camera, &FrameStats)>>;
+
+/// The `UpdateHook` of an app, if any, only showing whether it's set when debug printed.
+struct OnUpdate(Option<UpdateHook>);
+
+impl std::fmt::Debug for OnUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("OnUpdate").field(&self.0.is_some()).finish()
+    }
+}
+
+/// The system clipboard of an app once it has been opened, only showing whether it is when debug
+/// printed.
+#[cfg(feature = "clipboard")]
+#[derive(Default)]
+struct Clipboard(Option<arboard::Clipboard>);
+
+#[cfg(feature = "clipboard")]
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Clipboard").field(&self.0.is_some()).finish()
+    }
+}
+
+/// The callbacks the apps of the windows are set up with.
+#[derive(Default)]
+pub struct Hooks {
+    /// Run with the renderer of every window once its app has been created.
+    pub on_init: Option<InitHook>,
+    /// Run every frame for every window.
+    pub on_update: Option<WindowUpdateHook>,
+    /// The commands given to the app of the first window.
+    pub commands: Option<CommandReceiver>,
+}
 
-/// The load-state of the application, whether the window has been created yet or not.
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_init", &self.on_init.is_some())
+            .field("on_update", &self.on_update.is_some())
+            .field("commands", &self.commands)
+            .finish()
+    }
+}
+
+/// The load-state of the application, whether the windows have been created yet or not.
 #[derive(Debug)]
 pub enum AppLoadState {
+    /// The windows haven't been created yet.
+    Unloaded {
+        /// The configuration the windows and apps are created with.
+        config: AppConfig,
+        /// The callbacks the apps are set up with.
+        hooks: Hooks,
+    },
+    /// The windows have been created, each with an app of its own which is either still being
+    /// created or ready for updates. The event loop exits once the last window is closed.
+    Running {
+        /// The open windows by their id.
+        windows: HashMap<WindowId, AppWindow>,
+        /// The configuration the apps are created with.
+        config: AppConfig,
+        /// The callbacks the apps are set up with.
+        hooks: Hooks,
+    },
+    /// Creating or running the app failed, exiting the event loop so that `run` returns the error.
+    Failed(anyhow::Error),
+}
+
+/// The load-state of a single window of the application, each of which has its own renderer,
+/// scene and camera.
+#[derive(Debug)]
+pub enum AppWindow {
+    /// The window has been created and is responding to events, while its renderer is being
+    /// created on a separate thread.
+    Initializing {
+        /// The target surface for rendering, nothing being drawn into it yet.
+        window: Arc<Window>,
+        /// The index of the window, in the order the windows were created.
+        index: usize,
+        /// Receives the renderer, and the camera it was created with, once initialization finishes.
+        receiver: mpsc::Receiver<Result<(Renderer, Camera)>>,
+    },
     /// The window and app have been initialized and are ready for updates.
     Loaded {
         /// The target surface for rendering.
         window: Arc<Window>,
         /// The actual application.
-        app: App,
+        app: Box<App>,
     },
-    /// The window hasn't been created yet.
-    Unloaded,
 }
 
 /// The application state.
@@ -36,6 +150,12 @@ pub struct App {
     renderer: Renderer,
     /// The first-person camera used as the origin for rendering.
     camera: Camera,
+    /// The camera as it was before the last fixed update, which the `camera` is interpolated from
+    /// while `camera_smoothing`. Follows the `camera` every frame otherwise.
+    previous_camera: Camera,
+    /// Whether the camera moves in the fixed updates and is rendered interpolated between the
+    /// last two of them.
+    camera_smoothing: bool,
     /// The window onto which the app is rendered.
     window: Arc<Window>,
 
@@ -43,56 +163,895 @@ pub struct App {
     last_frame: Instant,
     /// The time since, the last frame (always one frame behind). Also known as `dt`.
     delta_time: f32,
+    /// The times of the most recent frames.
+    frame_stats: FrameStats,
+    /// The time spent handling events since the last update, the input phase of the next frame.
+    input_time: Duration,
+    /// The timings of the frame being prepared by the last update, completed once it's rendered.
+    timings: FrameTimings,
+    /// The timings of the last rendered frame.
+    last_timings: FrameTimings,
+    /// The maximum number of frames rendered per second, or `None` if uncapped.
+    fps_cap: Option<u32>,
+    /// Whether frames are rendered continuously or only when something changes.
+    redraw_mode: RedrawMode,
+    /// Whether the app stopped requesting frames after the last one, waiting for input.
+    idle: bool,
+    /// Whether another frame was explicitly requested with `App::request_redraw`.
+    redraw_requested: bool,
+    /// The latest size the window was resized to, applied at the start of the next `App::update`
+    /// so that a storm of resize events while dragging the window's edge reconfigures the surface
+    /// only once per frame.
+    pending_size: Option<PhysicalSize<u32>>,
+
+    /// The constant step in seconds by which `fixed_update` advances the simulation.
+    fixed_timestep: f32,
+    /// The real time which has passed but hasn't been simulated by `fixed_update` yet.
+    accumulator: f32,
+    /// The total time simulated by `fixed_update`.
+    simulation_time: f32,
+    /// Whether the simulation and animations are frozen, while the camera and rendering keep
+    /// running.
+    paused: bool,
+    /// Whether a single step of the paused simulation has been requested for the next frame.
+    step_requested: bool,
+    /// Whether the lights orbit around the scene, toggled with `KeyCode::KeyL`.
+    light_orbit: bool,
 
     /// Represents if the cursor is currently locked onto the window.
     has_focus: bool,
+    /// Whether the cursor was locked when the window lost focus, to lock it again once the window
+    /// regains focus.
+    refocus_cursor: bool,
+    /// How the cursor is preferably held in place while the window has focus.
+    cursor_grab_mode: CursorGrabMode,
+    /// How the cursor is actually held in place, after falling back from the preferred
+    /// `cursor_grab_mode` on platforms which don't support it.
+    cursor_grab: CursorGrabMode,
+    /// The latest position of the cursor over the window in physical pixels from the top left
+    /// corner, while it isn't locked for looking around. `None` once the cursor left the window.
+    cursor_position: Option<PhysicalPosition<f64>>,
 
     /// A collection of the keys currently being held down.
     keys_down: HashSet<KeyCode>,
+    /// The keys which went down since the last frame, cleared at the end of every `update`.
+    keys_pressed: HashSet<KeyCode>,
+    /// The modifier keys currently being held down, as last reported by the window.
+    modifiers: ModifiersState,
+    /// The raw mouse motion reported since the last frame, applied to the camera all at once in
+    /// `update` so that the rotation doesn't depend on how the motion was split into events.
+    mouse_delta: DVec2,
+
+    /// Run every frame after the app's own update, before the camera is uploaded.
+    on_update: OnUpdate,
+    /// The commands sent into the app from other threads, applied at the start of every `update`.
+    commands: Option<CommandReceiver>,
+    /// The files being loaded on worker threads, along with the placeholder shown in the scene
+    /// until their meshes replace it.
+    loads: Vec<(MeshLoad, MeshId)>,
+
+    /// The touchscreen and touchpad gestures moving the camera.
+    touch: TouchInput,
+    /// The gamepad input moving the camera, if gamepads could be initialized.
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<GamepadInput>,
+
+    /// The overlay for tweaking the app's settings live.
+    #[cfg(feature = "debug-ui")]
+    debug_ui: DebugUi,
+
+    /// The system clipboard frames are copied to with Ctrl+C, opened on the first copy. It's kept
+    /// open since on some platforms the copied image is only served while it is.
+    #[cfg(feature = "clipboard")]
+    clipboard: Clipboard,
 }
 
 impl App {
-    pub fn new(window: Arc<Window>) -> Result<Self> {
-        let camera = Camera::new(vec3(0.0, 0.0, 3.0), -FRAC_PI_2, 0.0, window.inner_size());
-        let renderer = pollster::block_on(Renderer::new(window.clone(), &camera))?;
+    /// The maximum number of fixed updates run in a single frame. When the simulation falls further
+    /// behind than this (for example after a long stall) the remaining time is dropped, rather than
+    /// trying to catch up and slowing the next frame down even more.
+    pub const MAX_FIXED_STEPS: u32 = 8;
+    /// The file the camera is quicksaved to with `KeyCode::F5`, and quickloaded from with
+    /// `KeyCode::F9`.
+    #[cfg(feature = "serde")]
+    pub const CAMERA_SAVE_PATH: &'static str = "camera.json";
+    /// The file the scene is quicksaved to with `KeyCode::F6`, and quickloaded from with
+    /// `KeyCode::F7`.
+    #[cfg(feature = "serde")]
+    pub const SCENE_SAVE_PATH: &'static str = "scene.json";
+    /// The number of pixels scrolled on touchpads which count as a line of a mouse wheel.
+    pub const PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+    /// How far the clip plane moves along its normal with every press of `KeyCode::BracketLeft`
+    /// or `KeyCode::BracketRight`.
+    pub const CLIP_PLANE_STEP: f32 = 0.1;
+    /// The angular velocity the lights orbit the scene at, in radians per second.
+    pub const LIGHT_ORBIT_SPEED: f32 = 0.5;
+    /// The light added when the lights start orbiting a scene without any, so that there's
+    /// something to see.
+    pub const ORBIT_LIGHT: DirectionalLight = DirectionalLight {
+        direction: Vec3::new(-0.6, -0.8, 0.0),
+        color: Vec3::ONE,
+    };
+    /// The color of the cube shown in place of a file being loaded by `App::load_mesh_async`.
+    pub const PLACEHOLDER_COLOR: Vec4 = Vec4::new(0.5, 0.5, 0.5, 1.0);
+    /// The length of the edges of the placeholder cube.
+    pub const PLACEHOLDER_SIZE: f32 = 0.25;
+    /// The angular velocity the placeholder cube spins at, in radians per second.
+    pub const PLACEHOLDER_SPIN_RATE: f32 = 2.0;
+
+    pub fn new(window: Arc<Window>, config: &AppConfig) -> Result<Self> {
+        let camera = Self::initial_camera(&window, config);
+        let renderer = pollster::block_on(Renderer::new(window.clone(), &camera, config))?;
+
+        Ok(Self::with_renderer(window, config, camera, renderer))
+    }
 
-        Ok(Self {
+    /// Creates the app around an already created renderer, and the camera it was created with.
+    pub fn with_renderer(
+        window: Arc<Window>,
+        config: &AppConfig,
+        camera: Camera,
+        renderer: Renderer,
+    ) -> Self {
+        #[cfg(feature = "gamepad")]
+        let gamepad = GamepadInput::new(config.gamepad_deadzone)
+            .inspect_err(|error| warn!("gamepad input is unavailable: {error}"))
+            .ok();
+
+        Self {
             renderer,
+            previous_camera: camera.clone(),
             camera,
+            camera_smoothing: config.camera_smoothing,
+            loads: Vec::new(),
+            touch: TouchInput::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad,
+            #[cfg(feature = "debug-ui")]
+            debug_ui: DebugUi::new(&window),
+            #[cfg(feature = "clipboard")]
+            clipboard: Clipboard::default(),
             window,
             keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            modifiers: ModifiersState::empty(),
+            mouse_delta: DVec2::ZERO,
             last_frame: Instant::now(),
             delta_time: 0.0,
+            frame_stats: FrameStats::new(),
+            input_time: Duration::ZERO,
+            timings: FrameTimings::default(),
+            last_timings: FrameTimings::default(),
+            fps_cap: config.fps_cap.filter(|&fps| fps > 0),
+            redraw_mode: config.redraw_mode,
+            idle: false,
+            redraw_requested: false,
+            pending_size: None,
+            fixed_timestep: 1.0 / config.fixed_update_rate.max(1) as f32,
+            accumulator: 0.0,
+            simulation_time: 0.0,
+            paused: false,
+            step_requested: false,
+            light_orbit: false,
             has_focus: false,
-        })
+            refocus_cursor: false,
+            cursor_grab_mode: config.cursor_grab_mode,
+            cursor_grab: CursorGrabMode::None,
+            cursor_position: None,
+            on_update: OnUpdate(None),
+            commands: None,
+        }
+    }
+
+    /// Sets the callback run every frame by `App::update`, after the camera has moved and the scene
+    /// has been animated but before the camera is uploaded for rendering. Replaces the previous
+    /// callback, `None` removing it.
+    pub fn set_on_update(&mut self, on_update: Option<UpdateHook>) {
+        self.on_update = OnUpdate(on_update);
     }
 
+    /// Sets the receiver of the commands applied at the start of every `App::update`, replacing the
+    /// previous one. `None` stops receiving commands.
+    pub fn set_commands(&mut self, commands: Option<CommandReceiver>) {
+        self.commands = commands;
+    }
+
+    /// Applies the commands sent since the last frame, without waiting for more.
+    fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.as_ref().and_then(CommandReceiver::try_recv) {
+            match command {
+                AppCommand::LoadMesh(path) => self.load_mesh_async(path),
+                AppCommand::SetCameraPosition(position) => self.camera.set_position(position),
+                AppCommand::LookAt(target) => self.camera.look_at(target),
+                AppCommand::SetDirectionalLight(light) => {
+                    self.renderer.set_directional_light(light)
+                }
+                AppCommand::SetPointLights(lights) => self.renderer.set_point_lights(&lights),
+                AppCommand::SetClearColor(color) => self.renderer.set_clear_color(color),
+                AppCommand::Run(run) => run(&mut self.renderer, &mut self.camera),
+            }
+        }
+    }
+
+    /// Creates the camera the app starts out with, from the viewpoint of the configuration.
+    fn initial_camera(window: &Window, config: &AppConfig) -> Camera {
+        let mut camera = Camera::new(
+            config.camera_eye,
+            config.camera_yaw,
+            config.camera_pitch,
+            window.inner_size(),
+        );
+        camera.fov_y = config.camera_fov_y;
+        camera.set_coordinate_system(config.coordinate_system);
+
+        camera
+    }
+
+    /// Renders the next frame, first rebuilding the renderer if its device was lost.
     pub fn render(&mut self) -> Result<()> {
-        self.renderer.render()
+        if self.renderer.device_lost() {
+            pollster::block_on(self.renderer.rebuild())?;
+        }
+
+        self.renderer.render()?;
+
+        self.timings.encode = self.renderer.last_encode_time();
+        self.timings.gpu = self.renderer.last_gpu_time();
+        self.last_timings = self.timings;
+
+        Ok(())
+    }
+
+    /// Returns the size of the window's client area in physical pixels.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.window.inner_size()
+    }
+
+    /// Requests resizing the window's client area to `size` in physical pixels, which the surface
+    /// and camera follow at the start of the next `App::update`. Some platforms resize the window
+    /// right away (without necessarily sending a `Resized` event), while others resize it later
+    /// and report the new size with one, which is handled like any other resize. The window
+    /// manager may also pick a different size, so check `App::size` once the frame was rendered.
+    pub fn set_size(&mut self, size: PhysicalSize<u32>) {
+        if let Some(size) = self.window.request_inner_size(size) {
+            self.pending_size = Some(size);
+        }
+
+        self.window.request_redraw();
+    }
+
+    /// Returns the time the last frame took in seconds.
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    /// Returns the number of frames rendered per second, averaged over the last
+    /// `FrameStats::WINDOW` frames.
+    pub fn fps(&self) -> f32 {
+        self.frame_stats.fps()
+    }
+
+    /// Returns where the time of the last rendered frame went, split into handling input, updating,
+    /// recording the frame's commands and the gpu's work.
+    pub fn last_timings(&self) -> FrameTimings {
+        self.last_timings
+    }
+
+    /// Returns the times of the most recent frames.
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Returns the maximum number of frames rendered per second, or `None` if uncapped.
+    pub fn fps_cap(&self) -> Option<u32> {
+        self.fps_cap
+    }
+
+    /// Caps the number of frames rendered per second, `None` meaning uncapped.
+    pub fn set_fps_cap(&mut self, cap: Option<u32>) {
+        self.fps_cap = cap.filter(|&fps| fps > 0);
+    }
+
+    /// Returns whether frames are rendered continuously or only when something changes.
+    pub fn redraw_mode(&self) -> RedrawMode {
+        self.redraw_mode
+    }
+
+    /// Sets whether frames are rendered continuously or only when something changes. The event
+    /// loop keeps waiting for events between frames if the app was started with
+    /// `RedrawMode::OnDemand`, so switching back to continuous rendering only stops it from going
+    /// idle.
+    pub fn set_redraw_mode(&mut self, mode: RedrawMode) {
+        self.redraw_mode = mode;
+    }
+
+    /// Requests another frame to be rendered, waking the app up if it's idle. With
+    /// `RedrawMode::OnDemand`, this is how changes made outside of input handling (such as by a
+    /// background task) are brought on screen.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+        self.window.request_redraw();
+    }
+
+    /// Returns whether another frame should be rendered right away, rather than waiting for input.
+    pub fn needs_redraw(&self) -> bool {
+        let animating = !self.paused
+            && (self.light_orbit
+                || self.renderer.scene().iter().any(|(_, mesh)| {
+                    mesh.spin().is_some()
+                        || mesh.skin().is_some_and(|skin| skin.playing().is_some())
+                })
+                || self
+                    .renderer
+                    .particles()
+                    .is_some_and(|particles| particles.is_active()));
+
+        self.redraw_mode == RedrawMode::Continuous
+            || animating
+            || !self.loads.is_empty()
+            || self.redraw_requested
+            || self.step_requested
+            || (self.has_focus && !self.keys_down.is_empty())
+            || self.camera.velocity() != Vec3::ZERO
+    }
+
+    /// Requests the next frame if it's needed, otherwise going idle until input arrives.
+    fn request_next_frame(&mut self) {
+        if self.needs_redraw() {
+            self.window.request_redraw();
+        } else {
+            self.idle = true;
+        }
+    }
+
+    /// Returns the number of fixed updates run per second of real time.
+    pub fn fixed_update_rate(&self) -> f32 {
+        1.0 / self.fixed_timestep
+    }
+
+    /// Sets the number of fixed updates run per second of real time.
+    pub fn set_fixed_update_rate(&mut self, rate: u32) {
+        self.fixed_timestep = 1.0 / rate.max(1) as f32;
+    }
+
+    /// Returns whether the camera moves in the fixed updates and is interpolated between them.
+    pub fn camera_smoothing(&self) -> bool {
+        self.camera_smoothing
+    }
+
+    /// Sets whether the camera moves in the fixed updates (instead of once per frame), and is
+    /// rendered interpolated between the last two of them by how far the accumulated time has
+    /// gotten towards the next one. This keeps fast camera motion smooth when the frame rate and
+    /// the fixed update rate disagree, at the cost of rendering the camera up to a fixed update
+    /// behind. While paused the camera moves once per frame either way.
+    pub fn set_camera_smoothing(&mut self, smoothing: bool) {
+        self.camera_smoothing = smoothing;
+        self.previous_camera.clone_from(&self.camera);
+    }
+
+    /// Returns the total time simulated by the fixed updates, which advances in constant steps.
+    pub fn simulation_time(&self) -> f32 {
+        self.simulation_time
+    }
+
+    /// Returns whether the simulation and animations are paused.
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes the simulation and animations. The camera can still be moved and the
+    /// frozen frame keeps being rendered while paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.step_requested = false;
+        // the time which had been accumulated towards the next step before pausing is dropped, so
+        // resuming doesn't immediately run a step
+        self.accumulator = 0.0;
+
+        info!("{}", if paused { "paused" } else { "resumed" });
+    }
+
+    /// Advances the paused simulation by exactly one fixed update in the next frame, along with
+    /// the animations. Does nothing while not paused.
+    pub fn step(&mut self) {
+        self.step_requested = self.paused;
+    }
+
+    /// Returns whether the lights orbit around the scene.
+    pub fn light_orbit(&self) -> bool {
+        self.light_orbit
+    }
+
+    /// Starts or stops orbiting the directional light and the point lights around the vertical
+    /// axis through the center of the scene, as the animations advance. Stopping leaves the lights
+    /// where they are. Starting without any lights adds `App::ORBIT_LIGHT`.
+    pub fn set_light_orbit(&mut self, light_orbit: bool) {
+        self.light_orbit = light_orbit;
+
+        if light_orbit
+            && self.renderer.directional_light().is_none()
+            && self.renderer.point_lights().is_empty()
+        {
+            self.renderer.set_directional_light(Some(Self::ORBIT_LIGHT));
+        }
+
+        info!(
+            "{} orbiting the lights",
+            if light_orbit { "started" } else { "stopped" }
+        );
+    }
+
+    /// Returns the latest position of the cursor over the window, in physical pixels from the top
+    /// left corner as `Camera::screen_ray` expects. `None` while the cursor is locked for looking
+    /// around, or outside of the window.
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor_position.filter(|_| !self.has_focus)
+    }
+
+    /// Returns the world-space ray (origin and normalized direction) through the cursor, built with
+    /// `Camera::screen_ray` from the camera of the first viewport across the whole window. While
+    /// the cursor is locked, the ray goes through the center of the window it's held at. `None`
+    /// if the cursor is outside of the window.
+    pub fn cursor_ray(&self) -> Option<(Vec3, Vec3)> {
+        let size = self.window.inner_size();
+
+        let cursor = if self.has_focus {
+            PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0)
+        } else {
+            self.cursor_position?
+        };
+
+        Some(self.camera.screen_ray(cursor, size))
     }
 
+    /// Returns the nearest visible mesh under the cursor (or the center of the window while the
+    /// cursor is locked) and the distance to it, using `Scene::raycast` along `App::cursor_ray`.
+    pub fn pick(&self) -> Option<(MeshId, f32)> {
+        let (origin, dir) = self.cursor_ray()?;
+
+        self.renderer.scene().raycast(origin, dir)
+    }
+
+    /// Starts loading the meshes of an OBJ or glTF file on a worker thread with `Mesh::load_async`,
+    /// showing a spinning `App::PLACEHOLDER_COLOR` cube in the scene until they're loaded. The
+    /// loaded meshes are uploaded and replace the placeholder during the first `App::update` after
+    /// the worker is done, while a file which fails to load only removes it with an error.
+    pub fn load_mesh_async(&mut self, path: impl Into<PathBuf>) {
+        let load = Mesh::load_async(path);
+        info!("loading {}", load.path().display());
+
+        let mut placeholder = Mesh::cube(
+            self.renderer.device(),
+            Self::PLACEHOLDER_SIZE,
+            Self::PLACEHOLDER_COLOR,
+        );
+        placeholder.set_spin(Vec3::Y, Self::PLACEHOLDER_SPIN_RATE);
+
+        let placeholder = self.renderer.scene_mut().add(placeholder);
+        self.loads.push((load, placeholder));
+    }
+
+    /// Returns the number of files still being loaded by `App::load_mesh_async`.
+    pub fn pending_loads(&self) -> usize {
+        self.loads.len()
+    }
+
+    /// Replaces the placeholders of the loads which finished since the last frame with their
+    /// meshes, uploading them on this thread.
+    fn poll_loads(&mut self) {
+        let mut index = 0;
+
+        while index < self.loads.len() {
+            let (load, placeholder) = &mut self.loads[index];
+
+            let Some(result) = load.poll() else {
+                index += 1;
+                continue;
+            };
+
+            let placeholder = *placeholder;
+            let (load, _) = self.loads.remove(index);
+
+            self.renderer.remove_mesh(placeholder);
+
+            match result {
+                Ok(meshes) => {
+                    info!(
+                        "loaded {} meshes from {}",
+                        meshes.len(),
+                        load.path().display()
+                    );
+
+                    for data in meshes {
+                        let mesh = data.upload(self.renderer.device());
+                        self.renderer.scene_mut().add(mesh);
+                    }
+                }
+                Err(error) => error!("{error:#}"),
+            }
+        }
+    }
+
+    /// Rotates the lights around the `up` axis through the center of the scene by the angle they
+    /// orbit in `dt` seconds.
+    fn orbit_lights(&mut self, up: Vec3, dt: f32) {
+        let rotation = Quat::from_axis_angle(up, Self::LIGHT_ORBIT_SPEED * dt);
+        let bounds = self.renderer.scene().bounds();
+        let center = if bounds.is_empty() {
+            Vec3::ZERO
+        } else {
+            bounds.center()
+        };
+
+        if let Some(light) = self.renderer.directional_light() {
+            self.renderer.set_directional_light(Some(DirectionalLight {
+                direction: rotation * light.direction,
+                ..light
+            }));
+        }
+
+        let point_lights = self
+            .renderer
+            .point_lights()
+            .iter()
+            .map(|&light| PointLight {
+                position: center + rotation * (light.position - center),
+                ..light
+            })
+            .collect::<Vec<_>>();
+
+        if !point_lights.is_empty() {
+            self.renderer.set_point_lights(&point_lights);
+        }
+    }
+
+    /// Returns whether the key is currently being held down.
+    pub fn key_held(&self, code: KeyCode) -> bool {
+        self.keys_down.contains(&code)
+    }
+
+    /// Returns whether the key went down since the last frame, for actions that should only happen
+    /// once per press (such as jumping) rather than for as long as the key is held.
+    pub fn key_just_pressed(&self, code: KeyCode) -> bool {
+        self.keys_pressed.contains(&code)
+    }
+
+    /// Returns the modifier keys currently being held down, regardless of which side of the
+    /// keyboard they're on.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Returns whether exactly the given combination of modifiers is being held down, so that
+    /// `Ctrl+S` doesn't also trigger while `Ctrl+Shift+S` is pressed.
+    pub fn modifiers_held(&self, modifiers: ModifiersState) -> bool {
+        self.modifiers == modifiers
+    }
+
+    /// Advances the simulation by a constant step, independently of the frame rate. Anything that
+    /// needs deterministic steps (such as physics) belongs here rather than in `update`.
+    fn fixed_update(&mut self, dt: f32) {
+        self.simulation_time += dt;
+    }
+
+    /// Runs as many fixed updates as fit into the real time accumulated so far, moving the camera
+    /// along with them while `camera_smoothing`.
+    fn run_fixed_updates(&mut self, dt: f32) {
+        self.accumulator += dt;
+
+        // the input is given once per frame, and applies to every step run in it
+        let (input, roll_input) = self.camera.pending_input();
+        let mut steps = 0;
+
+        while self.accumulator >= self.fixed_timestep {
+            if steps == Self::MAX_FIXED_STEPS {
+                self.accumulator = 0.0;
+                break;
+            }
+
+            self.fixed_update(self.fixed_timestep);
+
+            if self.camera_smoothing {
+                if steps > 0 {
+                    self.camera.move_relative(input);
+                    self.camera.roll_relative(roll_input);
+                }
+
+                self.previous_camera.clone_from(&self.camera);
+                self.camera.update(self.fixed_timestep);
+            }
+
+            self.accumulator -= self.fixed_timestep;
+            steps += 1;
+        }
+    }
+
+    /// Updates the app once per rendered frame, with a variable time step. Input and the camera are
+    /// handled here, while the simulation is advanced through `fixed_update`.
     pub fn update(&mut self) -> Result<()> {
+        // wait out the rest of the frame's interval, the time spent rendering since the last update
+        // already counting towards it
+        if let Some(fps) = self.fps_cap {
+            let interval = Duration::from_secs_f64(1.0 / fps as f64);
+            let elapsed = self.last_frame.elapsed();
+
+            if elapsed < interval {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+
         let now = Instant::now();
-        let dt = (now - self.last_frame).as_secs_f32();
+        let mut dt = (now - self.last_frame).as_secs_f32();
+
+        // the frame being updated is the one requested, further requests made while updating
+        // (such as by the update hook) render another one
+        self.redraw_requested = false;
+
+        if let Some(size) = self.pending_size.take() {
+            self.resize(size);
+        }
+
+        self.apply_commands();
+        self.poll_loads();
+
+        // the time spent idle waiting for input didn't pass in the app, and would otherwise be
+        // simulated all at once
+        if std::mem::take(&mut self.idle) {
+            dt = dt.min(self.fixed_timestep);
+        }
 
         self.last_frame = now;
         self.delta_time = dt;
+        self.frame_stats.push(dt);
+
+        // the input is read ahead of the fixed updates, which move the camera while smoothing
+        let mouse_delta = std::mem::take(&mut self.mouse_delta);
 
         if self.has_focus {
-            self.camera.update_position(&self.keys_down, dt);
+            self.camera.update_position(&self.keys_down);
+            self.camera.look_by_mouse(mouse_delta.into());
+
+            // a confined cursor would stop at the edges of the window, mouse motion being read
+            // from the device so that moving it back doesn't turn the camera
+            if self.cursor_grab == CursorGrabMode::Confined {
+                self.center_cursor();
+            }
+        }
+
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &mut self.gamepad {
+            gamepad.update(&mut self.camera, dt);
+        }
+
+        // while paused the frame time is still measured, so that it doesn't pile up into one huge
+        // step on resume, but the simulation only advances when a step is requested
+        let animation_dt = if !self.paused {
+            self.run_fixed_updates(dt);
+            dt
+        } else if std::mem::take(&mut self.step_requested) {
+            self.fixed_update(self.fixed_timestep);
+            self.fixed_timestep
+        } else {
+            0.0
+        };
+
+        let smoothed = self.camera_smoothing && !self.paused;
+
+        if !smoothed {
+            self.camera.update(dt);
+        }
+
+        #[cfg(feature = "debug-ui")]
+        {
+            let frame = self.debug_ui.run(&self.window, |context| {
+                Self::build_debug_ui(
+                    context,
+                    &mut self.camera,
+                    &mut self.renderer,
+                    dt,
+                    &self.last_timings,
+                )
+            });
+
+            self.renderer.set_debug_ui_frame(frame);
+        }
+
+        self.renderer.scene_mut().animate(animation_dt);
+        self.renderer.update_particles(animation_dt);
+
+        if self.light_orbit && animation_dt > 0.0 {
+            self.orbit_lights(self.camera.coordinate_system().up(), animation_dt);
+        }
+
+        if let Some(on_update) = &mut self.on_update.0 {
+            on_update(&mut self.renderer, &mut self.camera, &self.frame_stats);
+        }
+
+        if smoothed {
+            let alpha = (self.accumulator / self.fixed_timestep).clamp(0.0, 1.0);
+            let camera = self.previous_camera.lerp(&self.camera, alpha);
+
+            self.renderer.update_camera_buffer(&camera);
+        } else {
+            self.previous_camera.clone_from(&self.camera);
+            self.renderer.update_camera_buffer(&self.camera);
         }
 
-        self.renderer
-            .update_camera_buffer(self.camera.view_projection());
+        self.keys_pressed.clear();
+
+        self.timings = FrameTimings {
+            input: std::mem::take(&mut self.input_time).as_secs_f32() * 1000.0,
+            update: now.elapsed().as_secs_f32() * 1000.0,
+            ..Default::default()
+        };
 
         Ok(())
     }
 
+    /// Lays out the debug overlay's panel, applying any edited settings.
+    #[cfg(feature = "debug-ui")]
+    fn build_debug_ui(
+        context: &egui::Context,
+        camera: &mut Camera,
+        renderer: &mut Renderer,
+        dt: f32,
+        timings: &FrameTimings,
+    ) {
+        egui::Window::new("Debug").show(context, |ui| {
+            let frame_time = dt * 1000.0;
+            ui.label(format!("{:.0} fps ({frame_time:.2} ms)", 1.0 / dt));
+
+            // the bars are scaled to the frame time, or whichever of the cpu and gpu took longer
+            let scale = frame_time
+                .max(timings.cpu())
+                .max(timings.gpu.unwrap_or(0.0))
+                .max(f32::EPSILON);
+            let phases = [
+                ("input", Some(timings.input)),
+                ("update", Some(timings.update)),
+                ("encode", Some(timings.encode)),
+                ("gpu", timings.gpu),
+            ];
+
+            for (name, time) in phases {
+                if let Some(time) = time {
+                    ui.add(
+                        egui::ProgressBar::new(time / scale).text(format!("{name}: {time:.2} ms")),
+                    );
+                }
+            }
+
+            let stats = renderer.last_render_stats();
+            ui.label(format!(
+                "{} draw calls, {} triangles, {} vertices",
+                stats.draw_calls, stats.triangles, stats.vertices
+            ));
+
+            let mut speed = camera.speed();
+            if ui
+                .add(egui::Slider::new(&mut speed, 0.1..=50.0).text("camera speed"))
+                .changed()
+            {
+                camera.set_speed(speed);
+            }
+
+            let wgpu::Color { r, g, b, a } = renderer.clear_color();
+            let mut color = [r as f32, g as f32, b as f32];
+
+            ui.horizontal(|ui| {
+                if ui.color_edit_button_rgb(&mut color).changed() {
+                    let [r, g, b] = color.map(f64::from);
+                    renderer.set_clear_color(wgpu::Color { r, g, b, a });
+                }
+
+                ui.label("clear color");
+            });
+
+            let mut grid_visible = renderer.grid_visible();
+            if ui.checkbox(&mut grid_visible, "grid").changed() {
+                renderer.set_grid_visible(grid_visible);
+            }
+
+            let mut grid_params = renderer.grid_params();
+            let spacing = ui.add(
+                egui::Slider::new(&mut grid_params.spacing, 0.1..=10.0)
+                    .logarithmic(true)
+                    .text("grid spacing"),
+            );
+            let fade_distance = ui.add(
+                egui::Slider::new(&mut grid_params.fade_distance, 1.0..=1000.0)
+                    .logarithmic(true)
+                    .text("grid fade distance"),
+            );
+
+            if spacing.changed() || fade_distance.changed() {
+                renderer.set_grid_params(grid_params);
+            }
+
+            let mut axes_visible = renderer.axes_visible();
+            if ui.checkbox(&mut axes_visible, "axes").changed() {
+                renderer.set_axes_visible(axes_visible);
+            }
+
+            let mut line_width = renderer.line_width();
+            if ui
+                .add(
+                    egui::Slider::new(&mut line_width, 1.0..=Renderer::MAX_LINE_WIDTH)
+                        .text("line width"),
+                )
+                .changed()
+            {
+                renderer.set_line_width(line_width);
+            }
+
+            let mut exposure = renderer.exposure();
+            if ui
+                .add(egui::Slider::new(&mut exposure, 0.1..=10.0).text("exposure"))
+                .changed()
+            {
+                renderer.set_exposure(exposure);
+            }
+
+            let mut post_params = renderer.post_params();
+            let gamma = ui.add(egui::Slider::new(&mut post_params.gamma, 0.2..=5.0).text("gamma"));
+            let brightness = ui
+                .add(egui::Slider::new(&mut post_params.brightness, 0.0..=4.0).text("brightness"));
+
+            if gamma.changed() || brightness.changed() {
+                renderer.set_post_params(post_params);
+            }
+
+            let mut depth_debug = renderer.depth_debug();
+            if ui.checkbox(&mut depth_debug, "depth").changed() {
+                renderer.set_depth_debug(depth_debug);
+            }
+
+            let mut fxaa = renderer.fxaa();
+            if ui.checkbox(&mut fxaa, "fxaa").changed() {
+                renderer.set_fxaa(fxaa);
+            }
+
+            let mut skybox_visible = renderer.skybox_visible();
+            if ui.checkbox(&mut skybox_visible, "skybox").changed() {
+                renderer.set_skybox_visible(skybox_visible);
+            }
+
+            if let Some(particles) = renderer.particles_mut() {
+                let mut emitting = particles.emitting();
+                if ui.checkbox(&mut emitting, "emit particles").changed() {
+                    particles.set_emitting(emitting);
+                }
+            }
+        });
+    }
+
     fn handle_window_event(&mut self, event: WindowEvent) {
+        // wakes up an idle app, so that the input is reflected on screen
+        self.window.request_redraw();
+
+        // events used by the overlay (such as clicking on its panel) shouldn't reach the app,
+        // except for ones letting go of input, which would otherwise leave keys stuck down
+        #[cfg(feature = "debug-ui")]
+        if self.debug_ui.handle_window_event(&self.window, &event) && !Self::releases_input(&event)
+        {
+            return;
+        }
+
         match event {
-            WindowEvent::Resized(size) => {
-                self.renderer.resize(size);
-                self.camera.resize(size)
+            WindowEvent::Resized(size) => self.pending_size = Some(size),
+
+            // the physical size changes along with the scale factor, so that the logical size stays
+            // the same. Some platforms don't follow up with a `Resized` event. To check it by hand,
+            // drag the window from a monitor scaled at 100% to one at 150%: the change is logged,
+            // and the frame stays sharp at 1.5 times the size in pixels instead of being stretched
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                let size = self.window.inner_size();
+                info!(
+                    "scale factor changed to {scale_factor}, resizing to {}x{}",
+                    size.width, size.height
+                );
+
+                self.pending_size = Some(size);
             }
 
             WindowEvent::KeyboardInput {
@@ -100,30 +1059,49 @@ impl App {
                     KeyEvent {
                         physical_key,
                         state,
+                        repeat,
                         ..
                     },
                 ..
             } => {
+                // the os repeats held keys, which are already tracked in `keys_down`
+                if repeat {
+                    return;
+                }
+
                 let code = match physical_key {
                     PhysicalKey::Code(code) => code,
                     PhysicalKey::Unidentified(other) => {
-                        eprintln!("unrecognized key code, {other:?}");
+                        warn!("unrecognized key code, {other:?}");
                         return;
                     }
                 };
 
-                if code == KeyCode::Escape {
+                if code == KeyCode::Escape && state == ElementState::Pressed {
                     self.has_focus = false;
                     self.set_cursor_state();
                     return;
                 }
 
                 match state {
-                    ElementState::Pressed => self.keys_down.insert(code),
-                    ElementState::Released => self.keys_down.remove(&code),
+                    ElementState::Pressed => {
+                        if self.keys_down.insert(code) {
+                            self.keys_pressed.insert(code);
+                            self.handle_key_pressed(code);
+                        }
+                    }
+                    ElementState::Released => {
+                        self.keys_down.remove(&code);
+                    }
                 };
             }
 
+            // while locked, the cursor is re-centered and only its motion is used to look around
+            WindowEvent::CursorMoved { position, .. } if !self.has_focus => {
+                self.cursor_position = Some(position)
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+
             WindowEvent::MouseInput {
                 state: ElementState::Pressed,
                 ..
@@ -132,79 +1110,496 @@ impl App {
                 self.set_cursor_state()
             }
 
+            // scrolling up zooms in, easing the field of view towards the new target
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => lines,
+                    MouseScrollDelta::PixelDelta(pixels) => {
+                        pixels.y as f32 / Self::PIXELS_PER_SCROLL_LINE
+                    }
+                };
+
+                self.camera.zoom(steps);
+            }
+
+            WindowEvent::Touch(touch) => {
+                self.touch
+                    .handle_touch(touch, &mut self.camera, self.delta_time)
+            }
+            WindowEvent::PinchGesture { delta, .. } => TouchInput::pinch(&mut self.camera, delta),
+            WindowEvent::RotationGesture { delta, .. } => {
+                TouchInput::rotate(&mut self.camera, delta)
+            }
+
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+
+            // the cursor would otherwise stay locked on some platforms while using other windows.
+            // Keys released while another window has focus never report being released either,
+            // which would leave them (and modifier combinations) stuck down
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.modifiers = ModifiersState::empty();
+                self.refocus_cursor = self.has_focus;
+                self.has_focus = false;
+                self.set_cursor_state();
+            }
+
+            WindowEvent::Focused(true) if self.refocus_cursor => {
+                self.refocus_cursor = false;
+                self.has_focus = true;
+                self.set_cursor_state();
+            }
+
+            WindowEvent::DroppedFile(path) => self.load_mesh_async(path),
+
+            _ => {}
+        }
+    }
+
+    /// Returns whether the event lets go of input the app tracks, a key being released, the
+    /// modifiers changing or the focus of the window changing, which the app has to see even when
+    /// the overlay used the event.
+    #[cfg(feature = "debug-ui")]
+    fn releases_input(event: &WindowEvent) -> bool {
+        matches!(
+            event,
+            WindowEvent::KeyboardInput {
+                event: KeyEvent {
+                    state: ElementState::Released,
+                    ..
+                },
+                ..
+            } | WindowEvent::ModifiersChanged(_)
+                | WindowEvent::Focused(_)
+        )
+    }
+
+    /// Resizes the render target and camera to the new physical size of the window. Zero sized
+    /// windows (such as minimized ones) are ignored, since there's nothing to render to.
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        self.renderer.resize(size);
+        self.camera.resize(size);
+    }
+
+    /// Handles the actions bound to a single key press, called once when the key goes down with
+    /// the `modifiers` held at the time.
+    fn handle_key_pressed(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter if self.modifiers_held(ModifiersState::ALT) => {
+                let fullscreen = match self.window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                self.window.set_fullscreen(fullscreen);
+            }
+            #[cfg(feature = "clipboard")]
+            KeyCode::KeyC if self.modifiers_held(ModifiersState::CONTROL) => {
+                match self.copy_frame_to_clipboard() {
+                    Ok(()) => info!("copied the frame to the clipboard"),
+                    Err(error) => error!("failed to copy the frame to the clipboard: {error:#}"),
+                }
+            }
+            KeyCode::KeyP => self.set_paused(!self.paused),
+            KeyCode::Period => self.step(),
+            KeyCode::KeyG => self
+                .renderer
+                .set_grid_visible(!self.renderer.grid_visible()),
+            KeyCode::KeyX => self
+                .renderer
+                .set_axes_visible(!self.renderer.axes_visible()),
+            KeyCode::KeyF => self.camera.frame(&self.renderer.scene().bounds()),
+            KeyCode::KeyL => self.set_light_orbit(!self.light_orbit),
+            // a plane through the middle of the scene, clipping away everything beyond it along x
+            KeyCode::KeyC => {
+                let plane = match self.renderer.clip_plane() {
+                    Some(_) => None,
+                    None => {
+                        let center = self.renderer.scene().bounds().center();
+                        Some(Vec3::X.extend(-center.x))
+                    }
+                };
+
+                self.renderer.set_clip_plane(plane);
+            }
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                if let Some(plane) = self.renderer.clip_plane() {
+                    let step = if code == KeyCode::BracketLeft {
+                        -Self::CLIP_PLANE_STEP
+                    } else {
+                        Self::CLIP_PLANE_STEP
+                    };
+
+                    // moving a plane along its normal only changes its distance from the origin
+                    let offset = step * plane.truncate().length();
+                    self.renderer.set_clip_plane(Some(plane - Vec4::W * offset));
+                }
+            }
+            KeyCode::KeyV => {
+                let wireframe = !self.renderer.wireframe();
+                self.renderer.set_wireframe(wireframe);
+            }
+            KeyCode::KeyZ => {
+                let depth_debug = !self.renderer.depth_debug();
+                self.renderer.set_depth_debug(depth_debug);
+            }
+            KeyCode::KeyB => {
+                let modes = Renderer::CULL_MODES;
+                let current = modes
+                    .iter()
+                    .position(|&mode| mode == self.renderer.cull_mode());
+                let cull_mode = modes[current.map_or(0, |index| (index + 1) % modes.len())];
+
+                info!("culling {cull_mode:?} faces");
+                self.renderer.set_cull_mode(cull_mode);
+            }
+
+            #[cfg(feature = "serde")]
+            KeyCode::F5 => match self.camera.save(Self::CAMERA_SAVE_PATH) {
+                Ok(()) => info!("saved the camera to {}", Self::CAMERA_SAVE_PATH),
+                Err(error) => error!("failed to save the camera: {error}"),
+            },
+            #[cfg(feature = "serde")]
+            KeyCode::F6 => match self.renderer.scene().save(Self::SCENE_SAVE_PATH) {
+                Ok(()) => info!("saved the scene to {}", Self::SCENE_SAVE_PATH),
+                Err(error) => error!("failed to save the scene: {error:#}"),
+            },
+            #[cfg(feature = "serde")]
+            KeyCode::F7 => match self.renderer.load_scene(Self::SCENE_SAVE_PATH) {
+                Ok(()) => info!("loaded the scene from {}", Self::SCENE_SAVE_PATH),
+                Err(error) => error!("failed to load the scene: {error:#}"),
+            },
+            #[cfg(feature = "serde")]
+            KeyCode::F9 => match Camera::load(Self::CAMERA_SAVE_PATH, self.window.inner_size()) {
+                Ok(mut camera) => {
+                    // the movement settings belong to the app rather than to the viewpoint
+                    camera.set_speed(self.camera.speed());
+                    camera.set_acceleration(self.camera.acceleration());
+                    camera.set_damping(self.camera.damping());
+                    camera.set_fov_speed(self.camera.fov_speed());
+                    self.camera = camera;
+                }
+                Err(error) => error!("failed to load the camera: {error}"),
+            },
+
             _ => {}
         }
     }
 
+    /// Renders a frame with `Renderer::capture_frame` and copies it to the system clipboard as an
+    /// opaque image, opening the clipboard if it isn't yet.
+    #[cfg(feature = "clipboard")]
+    fn copy_frame_to_clipboard(&mut self) -> Result<()> {
+        let frame = self.renderer.capture_frame()?;
+
+        let clipboard = match &mut self.clipboard.0 {
+            Some(clipboard) => clipboard,
+            None => self
+                .clipboard
+                .0
+                .insert(arboard::Clipboard::new().context("failed to open the clipboard")?),
+        };
+
+        clipboard.set_image(frame.to_clipboard_image())?;
+
+        Ok(())
+    }
+
     fn handle_device_event(&mut self, event: DeviceEvent) {
+        // unlike window events, device events also arrive while the window isn't focused
+        if self.has_focus {
+            self.window.request_redraw();
+        }
+
         match event {
             DeviceEvent::MouseMotion { delta } if self.has_focus => {
-                self.camera.update_rotation_angles(delta, self.delta_time)
+                self.mouse_delta += DVec2::from(delta)
             }
 
             _ => {}
         }
     }
 
+    /// Grabs and hides the cursor while the window has focus, or releases it otherwise. The
+    /// preferred `cursor_grab_mode` is tried first, platforms which can't lock the cursor in place
+    /// confining it to the window instead, or leaving it free if neither is supported.
     fn set_cursor_state(&mut self) {
         if self.has_focus {
-            self.window.set_cursor_grab(CursorGrabMode::Locked).unwrap();
+            let modes: &[CursorGrabMode] = match self.cursor_grab_mode {
+                CursorGrabMode::Locked => &[CursorGrabMode::Locked, CursorGrabMode::Confined],
+                CursorGrabMode::Confined => &[CursorGrabMode::Confined],
+                CursorGrabMode::None => &[],
+            };
+
+            self.cursor_grab = CursorGrabMode::None;
+
+            for &mode in modes {
+                match self.window.set_cursor_grab(mode) {
+                    Ok(()) => {
+                        self.cursor_grab = mode;
+                        break;
+                    }
+                    Err(error) => warn!("failed to grab the cursor as {mode:?}: {error}"),
+                }
+            }
+
+            if self.cursor_grab != self.cursor_grab_mode {
+                warn!(
+                    "falling back to grabbing the cursor as {:?}",
+                    self.cursor_grab
+                );
+            }
+
+            if self.cursor_grab == CursorGrabMode::Confined {
+                self.center_cursor();
+            }
+
             self.window.set_cursor_visible(false);
         } else {
-            self.window.set_cursor_grab(CursorGrabMode::None).unwrap();
+            if let Err(error) = self.window.set_cursor_grab(CursorGrabMode::None) {
+                warn!("failed to release the cursor: {error}");
+            }
+
+            self.cursor_grab = CursorGrabMode::None;
             self.window.set_cursor_visible(true);
         }
     }
+
+    /// Moves the cursor to the center of the window, emulating a locked cursor while it's only
+    /// confined to the window.
+    fn center_cursor(&self) {
+        let size = self.window.inner_size();
+        let center = PhysicalPosition::new(size.width / 2, size.height / 2);
+
+        if let Err(error) = self.window.set_cursor_position(center) {
+            debug!("failed to center the cursor: {error}");
+        }
+    }
 }
 
-impl ApplicationHandler for AppLoadState {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop
-            .create_window(
-                Window::default_attributes()
-                    .with_title("d3 template")
-                    .with_inner_size(LogicalSize::new(1920, 1080)),
-            )
-            .expect("failed to create window");
+impl AppLoadState {
+    /// Exits the event loop with the error, which `run` then returns.
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: anyhow::Error) {
+        event_loop.exit();
+        *self = Self::Failed(error);
+    }
+}
 
-        let window = Arc::new(window);
+impl AppWindow {
+    /// Returns the window the app renders to.
+    pub fn window(&self) -> &Arc<Window> {
+        match self {
+            Self::Initializing { window, .. } | Self::Loaded { window, .. } => window,
+        }
+    }
 
-        window.request_redraw();
+    /// Creates the `index`th window, starting to create its renderer on a separate thread since
+    /// creating the device can take a while. The event loop meanwhile keeps the window responsive,
+    /// and the window is redrawn once the renderer is ready.
+    fn create(event_loop: &ActiveEventLoop, config: &AppConfig, index: usize) -> Result<Self> {
+        let (width, height) = config.size;
+
+        let title = match index {
+            0 => config.title.clone(),
+            _ => format!("{} ({})", config.title, index + 1),
+        };
+
+        let window = Arc::new(
+            event_loop
+                .create_window(
+                    Window::default_attributes()
+                        .with_title(title)
+                        .with_inner_size(LogicalSize::new(width, height))
+                        .with_resizable(config.resizable)
+                        .with_window_icon(config.icon.clone())
+                        .with_transparent(config.transparent),
+                )
+                .context("failed to create window")?,
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let init_window = window.clone();
+        let init_config = config.clone();
+
+        thread::Builder::new()
+            .name("renderer-init".into())
+            .spawn(move || {
+                let camera = App::initial_camera(&init_window, &init_config);
+                let renderer =
+                    pollster::block_on(Renderer::new(init_window.clone(), &camera, &init_config));
+
+                // the receiver is gone if the window was closed in the meantime
+                let _ = sender.send(renderer.map(|renderer| (renderer, camera)));
+                init_window.request_redraw();
+            })
+            .context("failed to spawn the renderer thread")?;
+
+        Ok(Self::Initializing {
+            window,
+            index,
+            receiver,
+        })
+    }
 
-        *self = Self::Loaded {
-            app: App::new(window.clone()).expect("failed to initialize app"),
+    /// Checks whether the renderer of an initializing window is ready, swapping to `Self::Loaded`
+    /// and setting up the app with the hooks if it is. The app catches up with the window's size,
+    /// which may have changed in the meantime.
+    fn poll_initialization(&mut self, config: &AppConfig, hooks: &mut Hooks) -> Result<()> {
+        let Self::Initializing {
             window,
+            index,
+            receiver,
+        } = self
+        else {
+            return Ok(());
         };
+
+        let index = *index;
+
+        let (renderer, camera) = match receiver.try_recv() {
+            Ok(created) => created.context("failed to initialize app")?,
+            Err(mpsc::TryRecvError::Empty) => return Ok(()),
+            Err(mpsc::TryRecvError::Disconnected) => bail!("the renderer thread panicked"),
+        };
+
+        let mut app = Box::new(App::with_renderer(window.clone(), config, camera, renderer));
+
+        if let Some(on_update) = &hooks.on_update {
+            let on_update = on_update.clone();
+
+            app.set_on_update(Some(Box::new(move |renderer, camera, frame_stats| {
+                (on_update.borrow_mut())(index, renderer, camera, frame_stats)
+            })));
+        }
+
+        if let Some(on_init) = &mut hooks.on_init {
+            on_init(index, &mut app.renderer);
+        }
+
+        if index == 0 {
+            app.set_commands(hooks.commands.take());
+        }
+
+        app.resize(window.inner_size());
+        window.request_redraw();
+
+        let window = window.clone();
+        *self = Self::Loaded { window, app };
+
+        Ok(())
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
-        let Self::Loaded { window, app } = self else {
-            return;
+    /// Handles an event of the window, other than closing it. Events arriving while the window is
+    /// initializing are dropped.
+    fn handle_event(
+        &mut self,
+        event: WindowEvent,
+        config: &AppConfig,
+        hooks: &mut Hooks,
+    ) -> Result<()> {
+        let Self::Loaded { app, .. } = self else {
+            return self.poll_initialization(config, hooks);
         };
 
         match event {
-            WindowEvent::CloseRequested => {
-                event_loop.exit();
-            }
-
             WindowEvent::RedrawRequested => {
-                app.render().unwrap();
-                app.update().unwrap();
-
-                window.request_redraw();
+                app.render()?;
+                app.update()?;
+                app.request_next_frame();
             }
 
             _ => {
+                let start = Instant::now();
                 app.handle_window_event(event);
+                app.input_time += start.elapsed();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ApplicationHandler for AppLoadState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Self::Unloaded { config, hooks } = self else {
+            return;
+        };
+
+        let windows = (0..config.window_count.max(1))
+            .map(|index| {
+                let window = AppWindow::create(event_loop, config, index)?;
+
+                Ok((window.window().id(), window))
+            })
+            .collect::<Result<HashMap<_, _>>>();
+
+        match windows {
+            Ok(windows) => {
+                *self = Self::Running {
+                    windows,
+                    config: config.clone(),
+                    hooks: std::mem::take(hooks),
+                }
             }
+            Err(error) => self.fail(event_loop, error),
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let Self::Running {
+            windows,
+            config,
+            hooks,
+        } = self
+        else {
+            return;
+        };
+
+        if event == WindowEvent::CloseRequested {
+            windows.remove(&id);
+
+            if windows.is_empty() {
+                event_loop.exit();
+            }
+
+            return;
+        }
+
+        let Some(window) = windows.get_mut(&id) else {
+            return;
+        };
+
+        if let Err(error) = window.handle_event(event, config, hooks) {
+            self.fail(event_loop, error);
+        }
+    }
+
+    // sent by a `CommandSender`, so that the commands are applied even while waiting for events
+    fn user_event(&mut self, _: &ActiveEventLoop, _: ()) {
+        let Self::Running { windows, .. } = self else {
+            return;
+        };
+
+        for window in windows.values() {
+            window.window().request_redraw();
         }
     }
 
     fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
-        let Self::Loaded { app, .. } = self else {
+        let Self::Running { windows, .. } = self else {
             return;
         };
 
-        app.handle_device_event(event);
+        // device events aren't tied to a window, each app only using them while it's focused
+        for window in windows.values_mut() {
+            if let AppWindow::Loaded { app, .. } = window {
+                let start = Instant::now();
+                app.handle_device_event(event.clone());
+                app.input_time += start.elapsed();
+            }
+        }
     }
 }
@@ -0,0 +1,203 @@
+use wgpu::{util::*, *};
+
+/// A vertical gradient drawn behind the scene in place of the solid clear color, spanning every
+/// viewport from its top to its bottom color.
+#[derive(Debug)]
+pub struct BackgroundGradient {
+    /// The color at the top of the viewports.
+    top: Color,
+    /// The color at the bottom of the viewports.
+    bottom: Color,
+    /// The uniform buffer of the two colors.
+    uniform_buffer: Buffer,
+    /// The layout of the `bind_group`, kept around to recreate the pipeline.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the uniform buffer.
+    bind_group: BindGroup,
+    /// The shader module of the pipeline.
+    shader: ShaderModule,
+    /// Draws a fullscreen triangle blending between the colors.
+    pipeline: RenderPipeline,
+}
+
+/// The colors of a gradient laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct BackgroundUniform {
+    top: [f32; 4],
+    bottom: [f32; 4],
+}
+
+impl BackgroundUniform {
+    /// Converts the colors of a gradient, given in linear rgba like the clear color.
+    fn new(top: Color, bottom: Color) -> Self {
+        let rgba =
+            |color: Color| [color.r, color.g, color.b, color.a].map(|channel| channel as f32);
+
+        Self {
+            top: rgba(top),
+            bottom: rgba(bottom),
+        }
+    }
+}
+
+impl BackgroundGradient {
+    /// Creates a gradient from `top` to `bottom` (in linear rgba, like the clear color), drawn onto
+    /// a target with the given formats and sample count.
+    pub fn new(
+        device: &Device,
+        top: Color,
+        bottom: Color,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Background Uniform Buffer"),
+            contents: bytemuck::bytes_of(&BackgroundUniform::new(top, bottom)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Background Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Background Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/background.wgsl").into()),
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &shader,
+            &bind_group_layout,
+            format,
+            depth_format,
+            sample_count,
+        );
+
+        Self {
+            top,
+            bottom,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            shader,
+            pipeline,
+        }
+    }
+
+    /// Creates the pipeline drawing the gradient onto a target with the given formats and sample
+    /// count.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Background Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    write_mask: ColorWrites::ALL,
+                    blend: None,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            // like the skybox, the gradient is drawn before anything else without writing depth
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Recreates the pipeline for a target with the given formats and sample count.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.shader,
+            &self.bind_group_layout,
+            format,
+            depth_format,
+            sample_count,
+        );
+    }
+
+    /// Returns the colors at the top and the bottom of the viewports.
+    pub fn colors(&self) -> (Color, Color) {
+        (self.top, self.bottom)
+    }
+
+    /// Sets the colors at the top and the bottom of the viewports, in linear rgba.
+    pub fn set_colors(&mut self, queue: &Queue, top: Color, bottom: Color) {
+        self.top = top;
+        self.bottom = bottom;
+
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&BackgroundUniform::new(top, bottom)),
+        );
+    }
+
+    /// Records drawing the gradient over the current viewport into the render pass.
+    pub fn draw(&self, render_pass: &mut RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use glam::*;
+use winit::dpi::PhysicalSize;
+
+use crate::{camera::Camera, config::AppConfig, renderer::Renderer};
+
+/// The frame times of a headless benchmark run, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkResult {
+    /// The number of meshes in the rendered scene.
+    pub meshes: usize,
+    /// The number of frames measured.
+    pub frames: usize,
+    /// The average time it took to update and render a frame.
+    pub average: f64,
+    /// The time of the fastest frame.
+    pub min: f64,
+    /// The time of the slowest frame.
+    pub max: f64,
+}
+
+impl BenchmarkResult {
+    /// The header of the columns `BenchmarkResult::to_csv` writes.
+    pub const CSV_HEADER: &'static str = "meshes,frames,average_ms,min_ms,max_ms";
+
+    /// Formats the result as a line of comma separated values, in the columns of
+    /// `BenchmarkResult::CSV_HEADER`.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{:.3},{:.3},{:.3}",
+            self.meshes, self.frames, self.average, self.min, self.max
+        )
+    }
+}
+
+/// Renders `frames` frames of a `Scene::stress_test` of `meshes` cubes with a headless renderer of
+/// the given size, measuring how long it takes to animate the scene, upload the camera and render
+/// every frame until the gpu has finished it. The camera looks down onto the whole scene, and a
+/// first frame is rendered before measuring, so that creating the pipelines isn't counted.
+pub fn run(
+    config: &AppConfig,
+    size: PhysicalSize<u32>,
+    meshes: usize,
+    frames: usize,
+) -> Result<BenchmarkResult> {
+    let mut camera = Camera::new(Vec3::ZERO, -90f32.to_radians(), -45f32.to_radians(), size);
+
+    let mut renderer = pollster::block_on(Renderer::new_headless(size, &camera, config))?;
+    renderer.load_stress_test(meshes);
+
+    camera.frame(&renderer.scene().bounds());
+    renderer.update_camera_buffer(&camera);
+    renderer.render()?;
+
+    let mut times = Vec::with_capacity(frames);
+
+    for _ in 0..frames {
+        let start = Instant::now();
+
+        renderer.scene_mut().animate(1.0 / 60.0);
+        renderer.update_camera_buffer(&camera);
+        renderer.render()?;
+
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    let frames = times.len();
+
+    Ok(BenchmarkResult {
+        meshes,
+        frames,
+        average: times.iter().sum::<f64>() / frames.max(1) as f64,
+        min: times.iter().copied().reduce(f64::min).unwrap_or(0.0),
+        max: times.iter().copied().reduce(f64::max).unwrap_or(0.0),
+    })
+}
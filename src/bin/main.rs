@@ -1,3 +1,3 @@
-fn main() {
-    d3_template::run();
+fn main() -> anyhow::Result<()> {
+    d3_template::run()
 }
@@ -0,0 +1,269 @@
+use glam::*;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// The corner of the box with the smallest coordinates.
+    pub min: Vec3,
+    /// The corner of the box with the largest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// A box containing nothing, which grows to exactly fit whatever it is united with.
+    pub const EMPTY: Self = Self {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+
+    /// Returns the smallest box containing all of the given points, or `Aabb::EMPTY` if there are
+    /// none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    /// Returns whether the box contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    /// Returns the point in the middle of the box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Returns the lengths of the box along each axis.
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Returns the eight corners of the box.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let Self { min, max } = *self;
+
+        [
+            vec3(min.x, min.y, min.z),
+            vec3(max.x, min.y, min.z),
+            vec3(min.x, max.y, min.z),
+            vec3(max.x, max.y, min.z),
+            vec3(min.x, min.y, max.z),
+            vec3(max.x, min.y, max.z),
+            vec3(min.x, max.y, max.z),
+            vec3(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the smallest axis-aligned box containing this box after being transformed by the
+    /// given matrix.
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        Self::from_points(
+            self.corners()
+                .map(|corner| transform.transform_point3(corner)),
+        )
+    }
+}
+
+/// The volume visible to a camera, bounded by six planes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// The left, right, bottom, top, near and far planes, as `(normal, distance)` with normals
+    /// pointing inside of the frustum. The planes aren't normalized, and the far plane of an
+    /// infinite projection degenerates to one containing everything.
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes of a view-projection matrix with a depth range of 0 to 1.
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z],
+        }
+    }
+
+    /// Returns whether any part of the box may be inside of the frustum. Boxes which are outside of
+    /// the frustum but cross the extensions of several of its planes are conservatively reported as
+    /// intersecting.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            // the corner of the box furthest along the plane's normal
+            let corner = Vec3::select(plane.xyz().cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            plane.xyz().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The unit cube centered around the origin.
+    const CUBE: Aabb = Aabb {
+        min: Vec3::splat(-0.5),
+        max: Vec3::splat(0.5),
+    };
+
+    #[test]
+    fn bounds_of_unit_cube() {
+        // the corners of the cube along with the centers of its faces
+        let points =
+            CUBE.corners()
+                .into_iter()
+                .chain([Vec3::X / 2.0, Vec3::NEG_Y / 2.0, Vec3::Z / 2.0]);
+
+        let aabb = Aabb::from_points(points);
+        assert_eq!(aabb, CUBE);
+        assert_eq!(aabb.center(), Vec3::ZERO);
+        assert_eq!(aabb.size(), Vec3::ONE);
+        assert!(!aabb.is_empty());
+    }
+
+    #[test]
+    fn bounds_of_transformed_cube() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0))
+            * Mat4::from_rotation_y(std::f32::consts::FRAC_PI_4)
+            * Mat4::from_scale(vec3(2.0, 3.0, 2.0));
+        let aabb = CUBE.transformed(transform);
+
+        let half = std::f32::consts::SQRT_2;
+        assert!(
+            aabb.min
+                .abs_diff_eq(vec3(1.0 - half, 0.5, 3.0 - half), 1e-5),
+            "{aabb:?}"
+        );
+        assert!(
+            aabb.max
+                .abs_diff_eq(vec3(1.0 + half, 3.5, 3.0 + half), 1e-5),
+            "{aabb:?}"
+        );
+    }
+
+    /// Returns the frustums of a camera at the origin facing -Z, through infinite and finite
+    /// projections with a normal and a reversed depth, the finite ones ending at a depth of 100.
+    fn frustums() -> [(&'static str, Frustum); 4] {
+        let (fov_y, aspect_ratio, near, far) = (std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+
+        [
+            (
+                "infinite",
+                Mat4::perspective_infinite_rh(fov_y, aspect_ratio, near),
+            ),
+            (
+                "infinite reversed",
+                Mat4::perspective_infinite_reverse_rh(fov_y, aspect_ratio, near),
+            ),
+            (
+                "finite",
+                Mat4::perspective_rh(fov_y, aspect_ratio, near, far),
+            ),
+            // swapping the planes maps the near one to 1 and the far one to 0
+            (
+                "finite reversed",
+                Mat4::perspective_rh(fov_y, aspect_ratio, far, near),
+            ),
+        ]
+        .map(|(name, projection)| (name, Frustum::from_view_projection(projection)))
+    }
+
+    /// Returns the box of the given size centered around the point.
+    fn cube_at(center: Vec3, size: f32) -> Aabb {
+        Aabb {
+            min: center - size / 2.0,
+            max: center + size / 2.0,
+        }
+    }
+
+    #[test]
+    fn frustum_contains_box_inside() {
+        for (name, frustum) in frustums() {
+            assert!(
+                frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, -5.0), 1.0)),
+                "{name}"
+            );
+            assert!(
+                frustum.intersects_aabb(&cube_at(vec3(2.0, -2.0, -50.0), 1.0)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn frustum_culls_box_outside() {
+        // behind the camera, beside the field of view, above it and in front of the near plane
+        for center in [
+            vec3(0.0, 0.0, 5.0),
+            vec3(-10.0, 0.0, -5.0),
+            vec3(0.0, 10.0, -5.0),
+            vec3(0.0, 0.0, -0.04),
+        ] {
+            for (name, frustum) in frustums() {
+                assert!(
+                    !frustum.intersects_aabb(&cube_at(center, 0.05)),
+                    "{name}: {center}"
+                );
+            }
+        }
+        assert!(!frustums()[1].1.intersects_aabb(&Aabb::EMPTY));
+    }
+
+    #[test]
+    fn frustum_contains_box_straddling_plane() {
+        // the field of view is 90 degrees, so the left plane passes through (-5, 0, -5), and the
+        // near plane is 0.1 in front of the camera
+        for center in [
+            vec3(-5.0, 0.0, -5.0),
+            vec3(0.0, 5.0, -5.0),
+            vec3(0.0, 0.0, -0.1),
+        ] {
+            for (name, frustum) in frustums() {
+                assert!(
+                    frustum.intersects_aabb(&cube_at(center, 0.05)),
+                    "{name}: {center}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finite_frustum_culls_beyond_far_plane() {
+        for (name, frustum) in frustums() {
+            let far = frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, -200.0), 1.0));
+            assert_eq!(far, name.starts_with("infinite"), "{name}");
+
+            // straddling the far plane
+            assert!(
+                frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, -100.0), 1.0)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_bounds() {
+        assert!(Aabb::from_points([]).is_empty());
+        assert!(Aabb::EMPTY
+            .transformed(Mat4::from_scale(Vec3::NEG_ONE))
+            .is_empty());
+        assert_eq!(Aabb::EMPTY.union(&CUBE), CUBE);
+    }
+}
@@ -1,25 +1,139 @@
 use glam::*;
-
-use wgpu::{util::*, *};
-use winit::{dpi::PhysicalSize, keyboard::KeyCode};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    keyboard::KeyCode,
+};
 
 use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use std::{fs, path::Path};
+
+#[cfg(feature = "serde")]
+use anyhow::Result;
+use log::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bounds::{Aabb, Frustum};
 
 /// Represents a camera in 3D space.
 #[derive(Debug, Clone)]
 pub struct Camera {
     /// The position of the camera in the right handed coordinate system.
     pub eye: Vec3,
-    /// The up vector of the camera (usually `Vec3::Y`, unless the roll angle is modified).
-    pub up: Vec3,
+    /// Which axis of the world points up, which the `yaw` and `pitch` are measured against and the
+    /// camera moves horizontally perpendicular to.
+    coordinate_system: CoordinateSystem,
+    /// The normalized up vector of the world (usually the `coordinate_system`'s), which the
+    /// camera's `roll` is relative to.
+    up: Vec3,
 
     /// The euler-yaw angle of the camera in radians.
     pub yaw: f32,
     /// The euler-pitch angle of the camera in radians.
     pub pitch: f32,
+    /// The angle in radians the camera is rotated by around where it's facing, positive angles
+    /// tilting the top of the view to the right.
+    pub roll: f32,
+
+    /// The vertical field of view of the camera in radians. With `FovMode::HorizontalFixed` this is
+    /// the vertical field of view at `Camera::REFERENCE_ASPECT_RATIO`.
+    pub fov_y: f32,
+    /// Which field of view is kept constant as the aspect ratio changes.
+    pub fov_mode: FovMode,
+    /// The field of view the `fov_y` eases towards in `Camera::update`, `None` once it's reached.
+    target_fov_y: Option<f32>,
+    /// The rate at which the `fov_y` eases towards the `target_fov_y`, per second.
+    fov_speed: f32,
+
+    /// The movement speed scalar of the camera.
+    speed: f32,
+    /// The velocity of the camera, in units per second.
+    velocity: Vec3,
+    /// The movement input given since the last `Camera::update`, relative to where it's facing.
+    input: Vec3,
+    /// The roll input given since the last `Camera::update`, positive values rolling right.
+    roll_input: f32,
+    /// The rate at which the velocity eases towards the input, per second.
+    acceleration: f32,
+    /// The rate at which the velocity eases towards rest without any input, per second.
+    damping: f32,
 
     /// The aspect ratio of the rendering surface.
     aspect_ratio: f32,
+    /// Whether the projection maps the near plane to a depth of 1 and infinity to 0, see
+    /// `Camera::set_reversed_z`.
+    reversed_z: bool,
+}
+
+/// Which field of view of a camera is kept constant as the aspect ratio of its target changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FovMode {
+    /// The vertical field of view stays at `Camera::fov_y`, wider targets seeing more to the sides.
+    #[default]
+    VerticalFixed,
+    /// The horizontal field of view stays at the one `Camera::fov_y` has at
+    /// `Camera::REFERENCE_ASPECT_RATIO`, wider targets seeing less above and below instead.
+    HorizontalFixed,
+}
+
+/// Which axis of the world points up. Both are right handed and only differ by a quarter turn
+/// around `+X`, so the projection is the same and only the orientation of the world is changed:
+/// a point above the camera is at the top of the view in either.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CoordinateSystem {
+    /// `+Y` points up, as in glTF and most game engines. A yaw of 0 faces `+X`, and increasing
+    /// yaws turn right towards `+Z`.
+    #[default]
+    YUp,
+    /// `+Z` points up, as in most CAD and GIS data. A yaw of 0 faces `+X`, and increasing yaws
+    /// turn right towards `-Y`.
+    ZUp,
+}
+
+impl CoordinateSystem {
+    /// Returns the axis pointing up.
+    pub fn up(self) -> Vec3 {
+        match self {
+            Self::YUp => Vec3::Y,
+            Self::ZUp => Vec3::Z,
+        }
+    }
+
+    /// Converts a vector from the `YUp` coordinate system into this one.
+    pub fn from_y_up(self, vector: Vec3) -> Vec3 {
+        match self {
+            Self::YUp => vector,
+            Self::ZUp => vec3(vector.x, -vector.z, vector.y),
+        }
+    }
+
+    /// Converts a vector from this coordinate system into the `YUp` one.
+    pub fn to_y_up(self, vector: Vec3) -> Vec3 {
+        match self {
+            Self::YUp => vector,
+            Self::ZUp => vec3(vector.x, vector.z, -vector.y),
+        }
+    }
+}
+
+/// The part of a camera's state which is saved to and loaded from files. The aspect ratio is left
+/// out, since it depends on the window the camera is loaded into.
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCamera {
+    eye: Vec3,
+    yaw: f32,
+    pitch: f32,
+    fov_y: f32,
+    #[serde(default)]
+    fov_mode: FovMode,
+    #[serde(default)]
+    roll: f32,
+    #[serde(default)]
+    coordinate_system: CoordinateSystem,
 }
 
 /// Calculates the aspect ratio given a size.
@@ -29,75 +143,381 @@ fn calculate_aspect_ratio(size: PhysicalSize<u32>) -> f32 {
 }
 
 impl Camera {
-    /// The movement speed scalar of the camera.
+    /// The default movement speed scalar of the camera.
     pub const SPEED: f32 = 5.0;
-    /// The mouse sensitivity of the camera.
+    /// The sensitivity of the camera to input given over time (such as from a stick), in radians
+    /// per second per unit of input.
     pub const SENSITIVITY: f32 = 0.1;
+    /// The sensitivity of the camera to mouse motion, in radians per count the mouse reports.
+    pub const MOUSE_SENSITIVITY: f32 = 0.002;
+    /// The default vertical field of view of the camera in radians.
+    pub const FOV_Y: f32 = std::f32::consts::FRAC_PI_4;
+    /// The narrowest vertical field of view the camera zooms in to, a degree.
+    pub const MIN_FOV_Y: f32 = 1.0 * std::f32::consts::PI / 180.0;
+    /// The widest vertical field of view the camera zooms out to, short of the half turn at which
+    /// the projection degenerates.
+    pub const MAX_FOV_Y: f32 = 170.0 * std::f32::consts::PI / 180.0;
+    /// The default rate at which the field of view eases towards its target, per second.
+    pub const FOV_SPEED: f32 = 10.0;
+    /// The factor the field of view is narrowed by for every step of `Camera::zoom`.
+    pub const ZOOM_STEP: f32 = 1.1;
+    /// The distance of the near plane from the camera. The projection has no far plane.
+    pub const Z_NEAR: f32 = 0.01;
+    /// The aspect ratio at which `Camera::fov_y` applies with `FovMode::HorizontalFixed`.
+    pub const REFERENCE_ASPECT_RATIO: f32 = 16.0 / 9.0;
+    /// The distance in bytes between the matrices of consecutive cameras in a buffer created by
+    /// `camera_uniform::create_buffer`, the largest uniform offset alignment a device may require.
+    pub const UNIFORM_STRIDE: u64 = 256;
+    /// The largest pitch `Camera::look_at` turns the camera to, just short of looking straight up
+    /// or down where the view matrix degenerates.
+    pub const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+    /// The default acceleration rate of the camera. Infinite, so the camera moves at full speed as
+    /// soon as it's given input.
+    pub const ACCELERATION: f32 = f32::INFINITY;
+    /// The default damping rate of the camera. Infinite, so the camera stops as soon as the input
+    /// is released.
+    pub const DAMPING: f32 = f32::INFINITY;
+    /// The squared length of the cross product between where the camera is facing and the `up`
+    /// vector, under which the two are considered parallel and the view's orientation undefined.
+    pub const PARALLEL_EPSILON: f32 = 1e-8;
+    /// The rate at which the camera rolls while `KeyCode::KeyQ` or `KeyCode::KeyE` is held, in
+    /// radians per second.
+    pub const ROLL_SPEED: f32 = std::f32::consts::FRAC_PI_2;
 
+    /// Creates a camera at `eye` facing along the `yaw` and `pitch` in radians, in the `YUp`
+    /// coordinate system.
     pub fn new(eye: Vec3, yaw: f32, pitch: f32, size: PhysicalSize<u32>) -> Self {
-        let up = Vec3::Y;
+        let coordinate_system = CoordinateSystem::default();
 
         Self {
             eye,
-            up,
+            coordinate_system,
+            up: coordinate_system.up(),
             yaw,
             pitch,
+            roll: 0.0,
+            fov_y: Self::FOV_Y,
+            fov_mode: FovMode::default(),
+            target_fov_y: None,
+            fov_speed: Self::FOV_SPEED,
+            speed: Self::SPEED,
+            velocity: Vec3::ZERO,
+            input: Vec3::ZERO,
+            roll_input: 0.0,
+            acceleration: Self::ACCELERATION,
+            damping: Self::DAMPING,
             aspect_ratio: calculate_aspect_ratio(size),
+            reversed_z: false,
         }
     }
 
-    /// Returns the forward vector of the camera based on the `yaw` and `pitch`.
+    /// Returns the forward vector of the camera based on the `yaw` and `pitch`, in its
+    /// `coordinate_system`.
     pub fn forward(&self) -> Vec3 {
-        vec3(
+        self.coordinate_system.from_y_up(vec3(
             self.yaw.cos() * self.pitch.cos(),
             self.pitch.sin(),
             self.yaw.sin() * self.pitch.cos(),
-        )
+        ))
+    }
+
+    /// Returns which axis of the world points up.
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.coordinate_system
+    }
+
+    /// Sets which axis of the world points up, resetting the `up` vector to it. The `yaw` and
+    /// `pitch` are kept, so the camera faces the same way relative to the new up axis; its `eye`
+    /// isn't converted, being a position in whichever coordinate system the scene is in.
+    pub fn set_coordinate_system(&mut self, coordinate_system: CoordinateSystem) {
+        self.coordinate_system = coordinate_system;
+        self.up = coordinate_system.up();
+    }
+
+    /// Returns the up vector of the world, which the camera's `roll` is relative to.
+    pub fn up(&self) -> Vec3 {
+        self.up
+    }
+
+    /// Sets the up vector of the world (usually the `coordinate_system`'s), normalizing it. Zero
+    /// and non-finite vectors are ignored with a warning, keeping the previous up vector.
+    pub fn set_up(&mut self, up: Vec3) {
+        match up.try_normalize() {
+            Some(up) => self.up = up,
+            None => warn!("ignoring degenerate camera up vector {up}"),
+        }
+    }
+
+    /// Returns the world's `up` vector rotated around where the camera is facing by its `roll`,
+    /// which the view is oriented by. While facing along the up vector, where no orientation
+    /// follows from it, some vector perpendicular to where the camera is facing is used instead.
+    pub fn rolled_up(&self) -> Vec3 {
+        let forward = self.forward();
+
+        let up = if forward.cross(self.up).length_squared() < Self::PARALLEL_EPSILON {
+            forward.any_orthonormal_vector()
+        } else {
+            self.up
+        };
+
+        Quat::from_axis_angle(forward, self.roll) * up
+    }
+
+    /// Returns the vector pointing to the right of the camera, perpendicular to where it's facing
+    /// and its `rolled_up` vector.
+    pub fn right(&self) -> Vec3 {
+        self.forward().cross(self.rolled_up()).normalize_or_zero()
+    }
+
+    /// Returns the vector pointing up out of the top of the camera's view, perpendicular to where
+    /// it's facing. Unlike `Camera::up` this tilts along with the pitch.
+    pub fn up_vector(&self) -> Vec3 {
+        self.right().cross(self.forward())
+    }
+
+    /// Moves the camera to the given position, keeping its orientation.
+    pub fn set_position(&mut self, position: Vec3) {
+        self.eye = position;
+    }
+
+    /// Turns the camera to face the given point. Points straight above or below the camera keep
+    /// its current yaw, and the pitch is limited to `Camera::PITCH_LIMIT`.
+    pub fn look_at(&mut self, target: Vec3) {
+        let direction = self.coordinate_system.to_y_up(target - self.eye);
+
+        if direction == Vec3::ZERO {
+            return;
+        }
+
+        let horizontal = vec2(direction.x, direction.z).length();
+
+        if horizontal > f32::EPSILON {
+            self.yaw = direction.z.atan2(direction.x);
+        }
+
+        self.pitch = direction
+            .y
+            .atan2(horizontal)
+            .clamp(-Self::PITCH_LIMIT, Self::PITCH_LIMIT);
+    }
+
+    /// Returns the view matrix of the camera, transforming world space into view space.
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.forward() + self.eye, self.rolled_up())
+    }
+
+    /// Returns the vertical field of view the camera currently renders with in radians, derived
+    /// from `fov_y` and the aspect ratio according to the `fov_mode`.
+    pub fn effective_fov_y(&self) -> f32 {
+        match self.fov_mode {
+            FovMode::VerticalFixed => self.fov_y,
+            FovMode::HorizontalFixed => {
+                let half_fov_x_tan = (self.fov_y / 2.0).tan() * Self::REFERENCE_ASPECT_RATIO;
+                2.0 * (half_fov_x_tan / self.aspect_ratio).atan()
+            }
+        }
+    }
+
+    /// Returns the horizontal field of view the camera currently renders with in radians.
+    pub fn effective_fov_x(&self) -> f32 {
+        2.0 * ((self.effective_fov_y() / 2.0).tan() * self.aspect_ratio).atan()
+    }
+
+    /// Returns the projection matrix of the camera, transforming view space into clip space. The
+    /// projection has no far plane, and is reversed if `reversed_z`.
+    pub fn projection(&self) -> Mat4 {
+        let fov_y = self.effective_fov_y();
+
+        if self.reversed_z {
+            Mat4::perspective_infinite_reverse_rh(fov_y, self.aspect_ratio, Self::Z_NEAR)
+        } else {
+            Mat4::perspective_infinite_rh(fov_y, self.aspect_ratio, Self::Z_NEAR)
+        }
+    }
+
+    /// Returns whether the projection maps the near plane to a depth of 1 and infinity to 0.
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Sets whether the projection is reversed, mapping the near plane to a depth of 1 and infinity
+    /// to 0 rather than the other way around. Floats are much denser near 0, which a reversed
+    /// depth spends on the distance where a standard depth has barely any precision left, so that
+    /// far away surfaces stop z-fighting; in exchange the depth buffer has to be cleared to 0 and
+    /// tested with `CompareFunction::Greater`, which the renderer does with
+    /// `Renderer::set_reversed_z` (forcing the flag onto the cameras of its viewports). Only the
+    /// 32 bit float depth format gains from it.
+    pub fn set_reversed_z(&mut self, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+    }
+
+    /// Returns the depth of the near plane in the projection, 1 if `reversed_z` and 0 otherwise.
+    pub fn near_depth(&self) -> f32 {
+        if self.reversed_z {
+            1.0
+        } else {
+            0.0
+        }
     }
 
     /// Returns the view-projection matrix of the camera.
     pub fn view_projection(&self) -> Mat4 {
-        let forward = self.forward();
+        self.projection() * self.view()
+    }
+
+    /// Returns the view-projection matrix of the camera with the translation stripped from the
+    /// view, so that whatever is drawn with it stays infinitely far away as the camera moves.
+    pub fn rotation_projection(&self) -> Mat4 {
+        self.projection() * Mat4::from_mat3(Mat3::from_mat4(self.view()))
+    }
+
+    /// Saves the camera's position, orientation and field of view to a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let saved = SavedCamera {
+            eye: self.eye,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            fov_y: self.fov_y,
+            fov_mode: self.fov_mode,
+            roll: self.roll,
+            coordinate_system: self.coordinate_system,
+        };
+
+        fs::write(path, serde_json::to_string_pretty(&saved)?)?;
+        Ok(())
+    }
+
+    /// Loads a camera saved with `Camera::save`, rendering onto a target of the given size.
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>, size: PhysicalSize<u32>) -> Result<Self> {
+        let saved: SavedCamera = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let mut camera = Self {
+            fov_y: saved.fov_y,
+            fov_mode: saved.fov_mode,
+            roll: saved.roll,
+            ..Self::new(saved.eye, saved.yaw, saved.pitch, size)
+        };
+        camera.set_coordinate_system(saved.coordinate_system);
+
+        Ok(camera)
+    }
+
+    /// Returns the volume visible to the camera.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(self.view_projection())
+    }
+
+    /// Moves the camera back along its current viewing direction until the whole box fits in
+    /// view, given the current field of view and aspect ratio. Empty boxes are ignored.
+    pub fn frame(&mut self, aabb: &Aabb) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        // fit the sphere around the box inside of the narrower of the two fields of view
+        let radius = aabb.size().length() / 2.0;
+        let half_fov = self.effective_fov_y().min(self.effective_fov_x()) / 2.0;
+
+        let distance = radius / half_fov.sin();
+
+        self.eye = aabb.center() - self.forward() * distance;
+    }
+
+    /// Returns a world-space ray (origin and normalized direction) passing through the given cursor
+    /// position on a target of the given size, by unprojecting it through the inverse
+    /// view-projection matrix. The origin lies on the near plane.
+    pub fn screen_ray(
+        &self,
+        cursor: PhysicalPosition<f64>,
+        size: PhysicalSize<u32>,
+    ) -> (Vec3, Vec3) {
+        let x = 2.0 * cursor.x as f32 / size.width as f32 - 1.0;
+        let y = 1.0 - 2.0 * cursor.y as f32 / size.height as f32;
+
+        let inverse = self.view_projection().inverse();
+
+        // the projection is infinite, so any depth in (0, 1) lies along the ray
+        let near = inverse.project_point3(vec3(x, y, self.near_depth()));
+        let far = inverse.project_point3(vec3(x, y, 0.5));
+
+        (near, (far - near).normalize())
+    }
+
+    /// Sets the vertical field of view right away, clamped between `Camera::MIN_FOV_Y` and
+    /// `Camera::MAX_FOV_Y`, and stops easing towards a target.
+    pub fn set_fov(&mut self, fov_y: f32) {
+        self.fov_y = fov_y.clamp(Self::MIN_FOV_Y, Self::MAX_FOV_Y);
+        self.target_fov_y = None;
+    }
+
+    /// Returns the vertical field of view the camera is easing towards, the current one if it
+    /// isn't.
+    pub fn target_fov(&self) -> f32 {
+        self.target_fov_y.unwrap_or(self.fov_y)
+    }
+
+    /// Sets the vertical field of view the camera eases towards over the next updates at the
+    /// `fov_speed`, clamped like with `Camera::set_fov`.
+    pub fn set_target_fov(&mut self, fov_y: f32) {
+        self.target_fov_y = Some(fov_y.clamp(Self::MIN_FOV_Y, Self::MAX_FOV_Y));
+    }
+
+    /// Zooms in by `steps` (out for negative steps), narrowing the target field of view by
+    /// `Camera::ZOOM_STEP` for each, such as for every line scrolled.
+    pub fn zoom(&mut self, steps: f32) {
+        self.set_target_fov(self.target_fov() / Self::ZOOM_STEP.powf(steps));
+    }
+
+    /// Returns the rate at which the field of view eases towards its target, per second.
+    pub fn fov_speed(&self) -> f32 {
+        self.fov_speed
+    }
+
+    /// Sets the rate at which the field of view eases towards its target, per second. Higher rates
+    /// get there quicker, `f32::INFINITY` snapping to it right away.
+    pub fn set_fov_speed(&mut self, speed: f32) {
+        self.fov_speed = speed.max(0.0);
+    }
+
+    /// Returns the movement speed scalar of the camera.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
 
-        let view = Mat4::look_at_rh(self.eye, forward + self.eye, self.up);
-        let proj = Mat4::perspective_infinite_rh(45.0f32.to_radians(), self.aspect_ratio, 0.01);
-
-        proj * view
-    }
-
-    /// Creates a new buffer, bind group layout, and bind group describing the camera's view-projection
-    /// matrix.
-    pub fn create_buffer(&self, device: &Device) -> (Buffer, BindGroupLayout, BindGroup) {
-        let buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Camera Uniform Buffer"),
-            contents: bytemuck::cast_slice(&self.view_projection().to_cols_array()),
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        });
-
-        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Camera Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-
-        let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-        });
-
-        (buffer, layout, bind_group)
+    /// Sets the movement speed scalar of the camera, in units per second.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Returns the rate at which the camera's velocity eases towards the input, per second.
+    pub fn acceleration(&self) -> f32 {
+        self.acceleration
+    }
+
+    /// Sets the rate at which the camera's velocity eases towards the input, per second. Higher
+    /// rates reach full speed quicker, `f32::INFINITY` reaching it instantly.
+    pub fn set_acceleration(&mut self, acceleration: f32) {
+        self.acceleration = acceleration.max(0.0);
+    }
+
+    /// Returns the rate at which the camera's velocity eases towards rest without any input, per
+    /// second.
+    pub fn damping(&self) -> f32 {
+        self.damping
+    }
+
+    /// Sets the rate at which the camera's velocity eases towards rest without any input, per
+    /// second. Lower rates let the camera coast further after the input is released, `0.0` never
+    /// stopping it and `f32::INFINITY` stopping it instantly.
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.max(0.0);
+    }
+
+    /// Returns the velocity of the camera, in units per second.
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
     }
 
     /// Updates the aspect ratio of the camera given a new target size.
@@ -105,45 +525,454 @@ impl Camera {
         self.aspect_ratio = calculate_aspect_ratio(size);
     }
 
-    /// Moves the camera in space based on which keys are currently being held down.
-    /// Controls follow the default 'WASD' to move around the xz plane, `KeyCode::Space` to move up
-    /// and `KeyCode::Shift` to move down.
-    pub fn update_position(&mut self, keys_down: &HashSet<KeyCode>, dt: f32) {
-        let mut delta = Vec3::ZERO;
-
-        let mut forward = self.forward();
-        let right = forward.cross(self.up);
-
-        forward.y = 0.0;
+    /// Gives the camera movement input based on which keys are currently being held down, see
+    /// `Camera::move_relative`. Controls follow the default 'WASD' to move around the horizontal
+    /// plane, `KeyCode::Space` to move up and `KeyCode::ShiftLeft` or `KeyCode::ShiftRight` to
+    /// move down, while `KeyCode::KeyQ` and `KeyCode::KeyE` roll the camera left and right.
+    pub fn update_position(&mut self, keys_down: &HashSet<KeyCode>) {
+        let mut input = Vec3::ZERO;
 
         if keys_down.contains(&KeyCode::KeyW) {
-            delta += forward;
+            input.z += 1.0;
         }
 
         if keys_down.contains(&KeyCode::KeyS) {
-            delta -= forward;
+            input.z -= 1.0;
         }
 
         if keys_down.contains(&KeyCode::KeyD) {
-            delta += right;
+            input.x += 1.0;
         }
         if keys_down.contains(&KeyCode::KeyA) {
-            delta -= right;
+            input.x -= 1.0;
         }
 
         if keys_down.contains(&KeyCode::Space) {
-            delta += self.up;
+            input.y += 1.0;
+        }
+        if keys_down.contains(&KeyCode::ShiftLeft) || keys_down.contains(&KeyCode::ShiftRight) {
+            input.y -= 1.0;
+        }
+
+        self.move_relative(input.normalize_or_zero());
+
+        if keys_down.contains(&KeyCode::KeyQ) {
+            self.roll_relative(-1.0);
+        }
+        if keys_down.contains(&KeyCode::KeyE) {
+            self.roll_relative(1.0);
+        }
+    }
+
+    /// Gives the camera input to roll until the next `Camera::update`, positive values rolling
+    /// right. Input from several sources adds up, an input of 1 rolling at `Camera::ROLL_SPEED`.
+    pub fn roll_relative(&mut self, input: f32) {
+        self.roll_input += input;
+    }
+
+    /// Gives the camera input to move relative to where it's facing until the next
+    /// `Camera::update`, `input.x` moving it right, `input.y` up and `input.z` forward along the
+    /// horizontal plane of the `coordinate_system`. Input from several sources adds up, an input
+    /// of length 1 moving the camera at full speed.
+    pub fn move_relative(&mut self, input: Vec3) {
+        self.input += input;
+    }
+
+    /// Moves the camera by `dt` seconds worth of its velocity, which eases towards the input given
+    /// since the last update at the `acceleration` rate, or towards rest at the `damping` rate if
+    /// there was none. The velocity is integrated exactly, so that the motion doesn't depend on
+    /// the frame rate.
+    pub fn update(&mut self, dt: f32) {
+        let input = std::mem::take(&mut self.input).clamp_length_max(1.0);
+        let roll_input = std::mem::take(&mut self.roll_input).clamp(-1.0, 1.0);
+
+        self.roll += Self::ROLL_SPEED * roll_input * dt;
+        self.update_fov(dt);
+
+        let up = self.coordinate_system.up();
+        let right = self.right();
+        let forward = self
+            .forward()
+            .reject_from_normalized(up)
+            .normalize_or_zero();
+
+        let target = self.speed * (input.x * right + input.y * up + input.z * forward);
+
+        let rate = if input == Vec3::ZERO {
+            self.damping
+        } else {
+            self.acceleration
+        };
+
+        // the velocity approaches the target as `target + (velocity - target) * e^(-rate * t)`,
+        // `remaining` being the part of the difference left after `dt` and `eased` the integral of
+        // the exponential over it
+        let (remaining, eased) = if rate.is_infinite() {
+            (0.0, 0.0)
+        } else if rate > 0.0 {
+            let remaining = (-rate * dt).exp();
+            (remaining, (1.0 - remaining) / rate)
+        } else {
+            (1.0, dt)
+        };
+
+        self.eye += target * dt + (self.velocity - target) * eased;
+        self.velocity = target + (self.velocity - target) * remaining;
+    }
+
+    /// Eases the field of view towards its target by `dt` seconds, exponentially so that it slows
+    /// down as it gets close without depending on the frame rate. The target is dropped once it's
+    /// reached.
+    fn update_fov(&mut self, dt: f32) {
+        let Some(target) = self.target_fov_y else {
+            self.fov_y = self.fov_y.clamp(Self::MIN_FOV_Y, Self::MAX_FOV_Y);
+            return;
+        };
+
+        // an infinite speed times a step of zero would otherwise be undefined
+        let remaining = if self.fov_speed.is_infinite() {
+            0.0
+        } else {
+            (-self.fov_speed * dt).exp()
+        };
+        self.fov_y =
+            (target + (self.fov_y - target) * remaining).clamp(Self::MIN_FOV_Y, Self::MAX_FOV_Y);
+
+        // a ten thousandth of a degree is well under a pixel
+        if (self.fov_y - target).abs() < 1e-6 {
+            self.fov_y = target;
+            self.target_fov_y = None;
         }
-        if keys_down.contains(&KeyCode::ShiftLeft) {
-            delta -= self.up;
+    }
+
+    /// Returns the movement and roll input given since the last `Camera::update`, see
+    /// `Camera::move_relative` and `Camera::roll_relative`.
+    pub fn pending_input(&self) -> (Vec3, f32) {
+        (self.input, self.roll_input)
+    }
+
+    /// Returns the camera between this one (at `t = 0`) and `other` (at `t = 1`), linearly
+    /// interpolating the position, angles and field of view. Everything else is taken from
+    /// `other`.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        Camera {
+            eye: self.eye.lerp(other.eye, t),
+            yaw: lerp(self.yaw, other.yaw),
+            pitch: lerp(self.pitch, other.pitch),
+            roll: lerp(self.roll, other.roll),
+            fov_y: lerp(self.fov_y, other.fov_y),
+            ..other.clone()
         }
+    }
 
-        self.eye += Self::SPEED * delta.normalize_or_zero() * dt;
+    /// Turns the camera by the raw motion of the mouse, `dx` to the right and `dy` down. The
+    /// motion is a distance rather than a rate, so it isn't scaled by the frame time: however the
+    /// motion is split up, the camera turns by the same angle.
+    pub fn look_by_mouse(&mut self, (dx, dy): (f64, f64)) {
+        self.yaw += Self::MOUSE_SENSITIVITY * dx as f32;
+        self.pitch -= Self::MOUSE_SENSITIVITY * dy as f32;
     }
 
-    /// Adjusts the camera's yaw and pitch based on mouse movements.
+    /// Adjusts the camera's yaw and pitch based on input held for `dt` seconds, such as a stick's
+    /// deflection.
     pub fn update_rotation_angles(&mut self, (dx, dy): (f64, f64), dt: f32) {
         self.yaw += Self::SENSITIVITY * dx as f32 * dt;
         self.pitch -= Self::SENSITIVITY * dy as f32 * dt;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+
+    /// The size of the target the cameras of the tests render onto.
+    const SIZE: PhysicalSize<u32> = PhysicalSize::new(1600, 900);
+
+    #[test]
+    fn projects_known_point() {
+        // facing -Z from the origin
+        let camera = Camera::new(Vec3::ZERO, -FRAC_PI_2, 0.0, SIZE);
+
+        let center = camera
+            .view_projection()
+            .project_point3(vec3(0.0, 0.0, -5.0));
+        assert!(center.truncate().abs_diff_eq(Vec2::ZERO, 1e-5), "{center}");
+
+        // a point at the edge of the vertical field of view lands on the top of the view
+        let height = 5.0 * (Camera::FOV_Y / 2.0).tan();
+        let top = camera
+            .view_projection()
+            .project_point3(vec3(0.0, height, -5.0));
+        assert!(top.truncate().abs_diff_eq(vec2(0.0, 1.0), 1e-5), "{top}");
+
+        // the same for the horizontal one, which is wider by the aspect ratio
+        let right = camera
+            .view_projection()
+            .project_point3(vec3(height * 16.0 / 9.0, 0.0, -5.0));
+        assert!(
+            right.truncate().abs_diff_eq(vec2(1.0, 0.0), 1e-5),
+            "{right}"
+        );
+
+        // points in front of the camera have a depth between the near plane and infinity
+        assert!(center.z > 0.0 && center.z < 1.0);
+    }
+
+    #[test]
+    fn forward_at_cardinal_yaws() {
+        for (yaw, forward) in [
+            (0.0, Vec3::X),
+            (FRAC_PI_2, Vec3::Z),
+            (PI, Vec3::NEG_X),
+            (3.0 * FRAC_PI_2, Vec3::NEG_Z),
+        ] {
+            let camera = Camera::new(Vec3::ZERO, yaw, 0.0, SIZE);
+            assert!(
+                camera.forward().abs_diff_eq(forward, 1e-6),
+                "yaw {yaw}: {}",
+                camera.forward()
+            );
+        }
+    }
+
+    #[test]
+    fn view_projection_is_projection_times_view() {
+        let mut camera = Camera::new(vec3(1.0, 2.0, 3.0), 0.7, -0.3, SIZE);
+        camera.roll = 0.2;
+
+        let expected = camera.projection() * camera.view();
+        assert!(camera.view_projection().abs_diff_eq(expected, 1e-6));
+    }
+
+    #[test]
+    fn up_along_forward_stays_finite() {
+        let mut camera = Camera::new(vec3(1.0, 2.0, 3.0), 0.7, -0.3, SIZE);
+        camera.set_up(camera.forward());
+        assert!(camera.rolled_up().is_finite());
+        assert!(camera.view_projection().is_finite());
+
+        // facing straight along the default up vector as well
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, FRAC_PI_2, SIZE);
+        camera.set_up(-camera.forward());
+        assert!(camera.view_projection().is_finite());
+    }
+
+    #[test]
+    fn either_shift_moves_down() {
+        for key in [KeyCode::ShiftLeft, KeyCode::ShiftRight] {
+            let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+            camera.update_position(&HashSet::from([key]));
+            camera.update(0.1);
+            assert!(camera.eye.y < 0.0, "{key:?}: {}", camera.eye);
+        }
+    }
+
+    #[test]
+    fn point_above_renders_at_top() {
+        for coordinate_system in [CoordinateSystem::YUp, CoordinateSystem::ZUp] {
+            let mut camera = Camera::new(vec3(1.0, 2.0, 3.0), 0.7, 0.0, SIZE);
+            camera.set_coordinate_system(coordinate_system);
+
+            let point = camera.eye + 5.0 * camera.forward() + coordinate_system.up();
+            let projected = camera.view_projection().project_point3(point);
+            assert!(
+                projected.x.abs() < 1e-5 && projected.y > 0.0,
+                "{coordinate_system:?}: {projected}"
+            );
+        }
+    }
+
+    #[test]
+    fn moves_up_along_coordinate_system() {
+        for coordinate_system in [CoordinateSystem::YUp, CoordinateSystem::ZUp] {
+            let mut camera = Camera::new(Vec3::ZERO, 0.7, 0.0, SIZE);
+            camera.set_coordinate_system(coordinate_system);
+            // a tilted world up vector only rolls the view
+            camera.set_up(coordinate_system.up() + 0.5 * camera.right());
+
+            camera.move_relative(Vec3::Y);
+            camera.update(0.1);
+            assert!(
+                camera
+                    .eye
+                    .normalize()
+                    .abs_diff_eq(coordinate_system.up(), 1e-5),
+                "{coordinate_system:?}: {}",
+                camera.eye
+            );
+        }
+    }
+
+    #[test]
+    fn coasts_to_a_stop_after_release() {
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+        camera.set_damping(2.0);
+
+        camera.move_relative(vec3(0.0, 0.0, 1.0));
+        camera.update(0.1);
+        assert!(camera.velocity().abs_diff_eq(Camera::SPEED * Vec3::X, 1e-5));
+        let released = camera.eye;
+
+        // the camera keeps moving forward while slowing down
+        let mut last = camera.velocity().x;
+        for _ in 0..10 {
+            camera.update(0.1);
+            assert!(camera.velocity().x < last && camera.velocity().x > 0.0);
+            last = camera.velocity().x;
+        }
+        assert!(camera.eye.x > released.x);
+
+        // and comes to rest `speed / damping` past where it was released
+        for _ in 0..490 {
+            camera.update(0.1);
+        }
+        assert!(camera.velocity().length() < 1e-6, "{}", camera.velocity());
+        let coasted = camera.eye - released;
+        assert!(
+            coasted.abs_diff_eq(Camera::SPEED / 2.0 * Vec3::X, 1e-4),
+            "{coasted}"
+        );
+    }
+
+    #[test]
+    fn coasting_is_independent_of_frame_rate() {
+        let coast = |steps: u32| {
+            let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+            camera.set_acceleration(3.0);
+            camera.set_damping(2.0);
+
+            camera.move_relative(vec3(0.0, 0.0, 1.0));
+            camera.update(0.5);
+            for _ in 0..steps {
+                camera.update(1.0 / steps as f32);
+            }
+            camera.eye
+        };
+
+        assert!(coast(1).abs_diff_eq(coast(100), 1e-4));
+    }
+
+    #[test]
+    fn stops_instantly_without_damping() {
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+        camera.move_relative(vec3(1.0, 0.0, 0.0));
+        camera.update(0.1);
+        let released = camera.eye;
+
+        camera.update(0.1);
+        assert_eq!(camera.velocity(), Vec3::ZERO);
+        assert_eq!(camera.eye, released);
+    }
+
+    #[test]
+    fn mouse_motion_turns_by_distance() {
+        let mut split = Camera::new(Vec3::ZERO, 0.3, 0.1, SIZE);
+        for _ in 0..100 {
+            split.look_by_mouse((1.5, -0.5));
+        }
+
+        let mut whole = Camera::new(Vec3::ZERO, 0.3, 0.1, SIZE);
+        whole.look_by_mouse((150.0, -50.0));
+
+        assert!(
+            (split.yaw - whole.yaw).abs() < 1e-5,
+            "{} {}",
+            split.yaw,
+            whole.yaw
+        );
+        assert!(
+            (split.pitch - whole.pitch).abs() < 1e-5,
+            "{} {}",
+            split.pitch,
+            whole.pitch
+        );
+        assert!((whole.yaw - (0.3 + 150.0 * Camera::MOUSE_SENSITIVITY)).abs() < 1e-6);
+        assert!((whole.pitch - (0.1 + 50.0 * Camera::MOUSE_SENSITIVITY)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fov_converges_to_target() {
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+        let target = 1.2;
+        camera.set_target_fov(target);
+
+        // the field of view only ever moves towards the target, without overshooting it
+        let mut last = camera.fov_y;
+        let mut steps = 0;
+        while camera.target_fov() != camera.fov_y {
+            camera.update(1.0 / 60.0);
+            assert!(
+                camera.fov_y > last && camera.fov_y <= target,
+                "{}",
+                camera.fov_y
+            );
+            last = camera.fov_y;
+
+            steps += 1;
+            assert!(steps < 600, "never reached the target");
+        }
+        assert_eq!(camera.fov_y, target);
+    }
+
+    #[test]
+    fn fov_stays_clamped() {
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+
+        camera.zoom(1000.0);
+        assert_eq!(camera.target_fov(), Camera::MIN_FOV_Y);
+        for _ in 0..600 {
+            camera.update(1.0 / 60.0);
+            assert!(camera.fov_y >= Camera::MIN_FOV_Y, "{}", camera.fov_y);
+        }
+        assert_eq!(camera.fov_y, Camera::MIN_FOV_Y);
+
+        camera.set_target_fov(10.0);
+        assert_eq!(camera.target_fov(), Camera::MAX_FOV_Y);
+        camera.set_fov_speed(f32::INFINITY);
+        camera.update(0.0);
+        assert_eq!(camera.fov_y, Camera::MAX_FOV_Y);
+
+        // setting the field of view directly is clamped too
+        camera.set_fov(-1.0);
+        assert_eq!(camera.fov_y, Camera::MIN_FOV_Y);
+        camera.fov_y = 4.0;
+        camera.update(1.0 / 60.0);
+        assert_eq!(camera.fov_y, Camera::MAX_FOV_Y);
+    }
+
+    #[test]
+    fn fov_modes_across_aspect_ratios() {
+        let projection = |fov_mode, width| {
+            let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, PhysicalSize::new(width, 900));
+            camera.fov_mode = fov_mode;
+            camera.projection()
+        };
+
+        // both modes agree at the reference aspect ratio
+        let reference = projection(FovMode::VerticalFixed, 1600);
+        assert!(projection(FovMode::HorizontalFixed, 1600).abs_diff_eq(reference, 1e-6));
+
+        // keeping the vertical field of view, twice as wide a target sees twice as far to the sides
+        let vertical = projection(FovMode::VerticalFixed, 3200);
+        assert!((vertical.x_axis.x - reference.x_axis.x / 2.0).abs() < 1e-6);
+        assert!((vertical.y_axis.y - reference.y_axis.y).abs() < 1e-6);
+
+        // keeping the horizontal one, it sees as far to the sides and half as far up and down
+        let horizontal = projection(FovMode::HorizontalFixed, 3200);
+        assert!((horizontal.x_axis.x - reference.x_axis.x).abs() < 1e-6);
+        assert!((horizontal.y_axis.y - reference.y_axis.y * 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn degenerate_up_is_ignored() {
+        let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, SIZE);
+        camera.set_up(vec3(0.0, 0.0, 2.0));
+        camera.set_up(Vec3::ZERO);
+        camera.set_up(Vec3::NAN);
+        assert_eq!(camera.up(), Vec3::Z);
+    }
+}
@@ -0,0 +1,140 @@
+use std::mem::size_of;
+
+use glam::*;
+use wgpu::{util::*, *};
+
+use crate::camera::Camera;
+
+/// The camera of a viewport along with what thick lines are widened by, laid out as the main
+/// shader uses it.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct CameraUniform {
+    /// The camera's view-projection matrix.
+    pub view_proj: [[f32; 4]; 4],
+    /// The size of the viewport in physical pixels.
+    pub viewport_size: [f32; 2],
+    /// The width of thick lines in physical pixels.
+    pub line_width: f32,
+    /// Pads the uniform to a multiple of 16 bytes.
+    pub _padding: f32,
+}
+
+/// Creates a new buffer with room for the `CameraUniform`s of `count` cameras (the given
+/// camera's being the first, its viewport size left for the first frame to fill in), along with a
+/// bind group layout and bind group selecting one of them through a dynamic offset, a multiple of
+/// `Camera::UNIFORM_STRIDE`. The bind group also binds the `post_params` buffer (created with
+/// `PostParams::create_buffer`) and the `clip_plane` buffer (created with
+/// `create_clip_plane_buffer`) for the fragment shader, which applies them to every view alike.
+/// Kept apart from `Camera`, so that the camera's math doesn't depend on a device.
+pub fn create_buffer(
+    device: &Device,
+    camera: &Camera,
+    count: u32,
+    post_params: &Buffer,
+    clip_plane: &Buffer,
+) -> (Buffer, BindGroupLayout, BindGroup) {
+    let uniform = CameraUniform {
+        view_proj: camera.view_projection().to_cols_array_2d(),
+        viewport_size: [0.0; 2],
+        line_width: 1.0,
+        _padding: 0.0,
+    };
+
+    let mut contents = vec![0; (Camera::UNIFORM_STRIDE * count.max(1) as BufferAddress) as usize];
+    contents[..size_of::<CameraUniform>()].copy_from_slice(bytemuck::bytes_of(&uniform));
+
+    let usage = BufferUsages::UNIFORM | BufferUsages::COPY_DST;
+    // the matrices are copied out of the buffer by `Renderer::dump_camera_uniform`
+    #[cfg(feature = "debug")]
+    let usage = usage | BufferUsages::COPY_SRC;
+
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Camera Uniform Buffer"),
+        contents: &contents,
+        usage,
+    });
+
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("Camera Bind Group Layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(size_of::<CameraUniform>() as BufferAddress),
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Camera Bind Group"),
+        layout: &layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: BufferSize::new(size_of::<CameraUniform>() as BufferAddress),
+                }),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: post_params.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: clip_plane.as_entire_binding(),
+            },
+        ],
+    });
+
+    (buffer, layout, bind_group)
+}
+
+/// The plane equation uploaded while clipping is off, which no point is in front of.
+const NO_CLIP_PLANE: Vec4 = Vec4::new(0.0, 0.0, 0.0, -1.0);
+
+/// Creates the uniform buffer holding the plane equation `(a, b, c, d)` the fragments of meshes
+/// are clipped against, see `Renderer::set_clip_plane`.
+pub fn create_clip_plane_buffer(device: &Device, plane: Option<Vec4>) -> Buffer {
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Clip Plane Uniform Buffer"),
+        contents: bytemuck::bytes_of(&plane.unwrap_or(NO_CLIP_PLANE).to_array()),
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    })
+}
+
+/// Uploads the plane equation to a buffer created with `create_clip_plane_buffer`.
+pub fn write_clip_plane(queue: &Queue, buffer: &Buffer, plane: Option<Vec4>) {
+    queue.write_buffer(
+        buffer,
+        0,
+        bytemuck::bytes_of(&plane.unwrap_or(NO_CLIP_PLANE).to_array()),
+    );
+}
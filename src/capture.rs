@@ -0,0 +1,166 @@
+use std::sync::mpsc;
+
+use anyhow::{anyhow, Result};
+use wgpu::*;
+
+/// A frame read back from the rendering device, as opaque rgba pixels with 8 bits per channel.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// The width of the frame in pixels.
+    pub width: u32,
+    /// The height of the frame in pixels.
+    pub height: u32,
+    /// The pixels of the frame row by row from the top left corner, four bytes each. Colors are
+    /// encoded the same way as on screen (in srgb), and the alpha is always opaque.
+    pub rgba: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// Returns the frame as an image for the system clipboard: straight (not premultiplied) rgba
+    /// pixels row by row from the top left corner. Every pixel is made opaque, since applications
+    /// pasting the image would otherwise show whatever is behind it through the frame; frames
+    /// read back from the device already are.
+    #[cfg(feature = "clipboard")]
+    pub fn to_clipboard_image(&self) -> arboard::ImageData<'static> {
+        let mut rgba = self.rgba.clone();
+
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel[3] = u8::MAX;
+        }
+
+        arboard::ImageData {
+            width: self.width as usize,
+            height: self.height as usize,
+            bytes: rgba.into(),
+        }
+    }
+}
+
+/// A copy of a texture into a buffer, which can be read back once the copy has been submitted.
+#[derive(Debug)]
+pub(crate) struct FrameReadback {
+    /// The buffer the texture is copied into, with rows padded to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+    buffer: Buffer,
+    /// The width of the texture in pixels.
+    width: u32,
+    /// The height of the texture in pixels.
+    height: u32,
+    /// The size in bytes of a padded row of the `buffer`.
+    padded_bytes_per_row: u32,
+    /// Whether the texture stores its pixels in bgra rather than rgba order.
+    bgra: bool,
+}
+
+impl FrameReadback {
+    /// Checks whether textures of the given format and usages can be read back, which needs an 8
+    /// bit rgba or bgra format and `TextureUsages::COPY_SRC`. Returns whether the pixels are stored
+    /// in bgra order.
+    pub fn check(format: TextureFormat, usage: TextureUsages) -> Result<bool> {
+        if !usage.contains(TextureUsages::COPY_SRC) {
+            return Err(anyhow!("the frame's texture can't be copied from"));
+        }
+
+        match format.remove_srgb_suffix() {
+            TextureFormat::Rgba8Unorm => Ok(false),
+            TextureFormat::Bgra8Unorm => Ok(true),
+            format => Err(anyhow!("frames of format {format:?} can't be captured")),
+        }
+    }
+
+    /// Records copying the texture into a new buffer, failing if `FrameReadback::check` does.
+    pub fn new(device: &Device, encoder: &mut CommandEncoder, texture: &Texture) -> Result<Self> {
+        let bgra = Self::check(texture.format(), texture.usage())?;
+
+        let (width, height) = (texture.width(), texture.height());
+        let padded_bytes_per_row = (4 * width).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Frame Readback Buffer"),
+            size: padded_bytes_per_row as BufferAddress * height as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Ok(Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+        })
+    }
+
+    /// Waits for the copy to finish and reads the frame back. Has to be called after the encoder
+    /// passed to `FrameReadback::new` has been submitted.
+    pub fn read(self, device: &Device) -> Result<CapturedFrame> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+
+        device.poll(Maintain::Wait);
+        receiver.recv()??;
+
+        let mut rgba = Vec::with_capacity((4 * self.width * self.height) as usize);
+
+        {
+            let view = self.buffer.slice(..).get_mapped_range();
+
+            for row in view.chunks_exact(self.padded_bytes_per_row as usize) {
+                for pixel in row[..(4 * self.width) as usize].chunks_exact(4) {
+                    let [r, g, b] = if self.bgra {
+                        [pixel[2], pixel[1], pixel[0]]
+                    } else {
+                        [pixel[0], pixel[1], pixel[2]]
+                    };
+
+                    // the alpha of the surface is meaningless when it's composited opaquely, so
+                    // it's dropped rather than leaving see-through pixels behind
+                    rgba.extend([r, g, b, u8::MAX]);
+                }
+            }
+        }
+
+        self.buffer.unmap();
+
+        Ok(CapturedFrame {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "clipboard"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_image_is_opaque() {
+        let frame = CapturedFrame {
+            width: 2,
+            height: 1,
+            rgba: vec![255, 0, 0, 255, 10, 20, 30, 0],
+        };
+
+        let image = frame.to_clipboard_image();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.bytes[..], [255, 0, 0, 255, 10, 20, 30, 255]);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, SendError, SyncSender, TrySendError},
+        Arc, OnceLock,
+    },
+};
+
+use glam::*;
+use wgpu::Color;
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    camera::Camera,
+    light::{DirectionalLight, PointLight},
+    renderer::Renderer,
+};
+
+/// An operation sent into the running app from another thread through a `CommandSender`, applied
+/// by the app of the first window at the start of its next `App::update`.
+pub enum AppCommand {
+    /// Loads the meshes of a file on a worker thread, see `App::load_mesh_async`.
+    LoadMesh(PathBuf),
+    /// Moves the camera to a position, keeping where it's facing.
+    SetCameraPosition(Vec3),
+    /// Turns the camera to face a point, see `Camera::look_at`.
+    LookAt(Vec3),
+    /// Replaces the directional light, `None` turning it off.
+    SetDirectionalLight(Option<DirectionalLight>),
+    /// Replaces the point lights.
+    SetPointLights(Vec<PointLight>),
+    /// Replaces the color the surface is cleared to.
+    SetClearColor(Color),
+    /// Runs a callback with the renderer and camera, for anything the other commands don't cover.
+    Run(CommandCallback),
+}
+
+/// A callback sent with `AppCommand::Run`.
+pub type CommandCallback = Box<dyn FnOnce(&mut Renderer, &mut Camera) + Send>;
+
+impl std::fmt::Debug for AppCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LoadMesh(path) => f.debug_tuple("LoadMesh").field(path).finish(),
+            Self::SetCameraPosition(position) => {
+                f.debug_tuple("SetCameraPosition").field(position).finish()
+            }
+            Self::LookAt(target) => f.debug_tuple("LookAt").field(target).finish(),
+            Self::SetDirectionalLight(light) => {
+                f.debug_tuple("SetDirectionalLight").field(light).finish()
+            }
+            Self::SetPointLights(lights) => f.debug_tuple("SetPointLights").field(lights).finish(),
+            Self::SetClearColor(color) => f.debug_tuple("SetClearColor").field(color).finish(),
+            Self::Run(_) => f.debug_tuple("Run").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// The proxy of the running event loop, set once the app starts, which wakes it up when a
+/// command is sent so that apps waiting for events (see `RedrawMode::OnDemand`) apply it.
+type Waker = Arc<OnceLock<EventLoopProxy<()>>>;
+
+/// Sends commands into the running app, created with `command_channel`. Can be cloned and moved to
+/// other threads.
+#[derive(Debug, Clone)]
+pub struct CommandSender {
+    /// The sending half of the bounded channel.
+    sender: SyncSender<AppCommand>,
+    /// Wakes the event loop up after sending.
+    waker: Waker,
+}
+
+impl CommandSender {
+    /// Sends a command, blocking while the channel is full. Fails once the app stopped.
+    pub fn send(&self, command: AppCommand) -> Result<(), SendError<AppCommand>> {
+        self.sender.send(command)?;
+        self.wake();
+
+        Ok(())
+    }
+
+    /// Sends a command without blocking, failing if the channel is full or the app stopped.
+    pub fn try_send(&self, command: AppCommand) -> Result<(), TrySendError<AppCommand>> {
+        self.sender.try_send(command)?;
+        self.wake();
+
+        Ok(())
+    }
+
+    /// Wakes the event loop up, if it started already. Commands sent before it started are
+    /// applied with the first frame.
+    fn wake(&self) {
+        if let Some(proxy) = self.waker.get() {
+            // the event loop may have exited since, in which case there's nothing to wake
+            let _ = proxy.send_event(());
+        }
+    }
+}
+
+/// Receives the commands of a `command_channel`, given to `run_with_commands`.
+#[derive(Debug)]
+pub struct CommandReceiver {
+    /// The receiving half of the bounded channel.
+    receiver: Receiver<AppCommand>,
+    /// The waker shared with the senders, set by `CommandReceiver::set_proxy`.
+    waker: Waker,
+}
+
+impl CommandReceiver {
+    /// Lets the senders wake up the event loop the commands are received on.
+    pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        let _ = self.waker.set(proxy);
+    }
+
+    /// Returns the next command sent, without blocking. `None` once there are none left for now,
+    /// or every sender was dropped.
+    pub fn try_recv(&self) -> Option<AppCommand> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Creates a channel for sending commands into the app, holding up to `capacity` commands which
+/// haven't been applied yet before `CommandSender::send` blocks (and `CommandSender::try_send`
+/// fails). The receiver is passed to `run_with_commands`, and the sender to the threads giving
+/// the commands.
+pub fn command_channel(capacity: usize) -> (CommandSender, CommandReceiver) {
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    let waker = Waker::default();
+
+    (
+        CommandSender {
+            sender,
+            waker: waker.clone(),
+        },
+        CommandReceiver { receiver, waker },
+    )
+}
@@ -0,0 +1,93 @@
+use wgpu::*;
+
+/// A compute shader reading and writing a single storage buffer, bound at group 0, binding 0.
+#[derive(Debug)]
+pub struct ComputePass {
+    /// The layout of the `bind_group`, kept around to bind other buffers.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the storage buffer.
+    bind_group: BindGroup,
+    /// Runs the entry point of the shader.
+    pipeline: ComputePipeline,
+}
+
+impl ComputePass {
+    /// An example shader writing a ramp of `0, 1, 2, ...` into a buffer of `f32`s with its
+    /// `cs_main` entry point, one workgroup covering `RAMP_WORKGROUP_SIZE` elements.
+    pub const RAMP_SHADER: &'static str = include_str!("../assets/shader/ramp.wgsl");
+    /// The number of elements written by each workgroup of the `RAMP_SHADER`.
+    pub const RAMP_WORKGROUP_SIZE: u32 = 64;
+
+    /// Creates a pass running `entry_point` of the WGSL `source` over `buffer`, which has to have
+    /// been created with the `STORAGE` usage.
+    pub fn new(device: &Device, source: &str, entry_point: &str, buffer: &Buffer) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Compute Shader"),
+            source: ShaderSource::Wgsl(source.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Compute Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Compute Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point,
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Self {
+            bind_group: Self::create_bind_group(device, &bind_group_layout, buffer),
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Binds a new storage buffer for the shader to run over.
+    pub fn set_buffer(&mut self, device: &Device, buffer: &Buffer) {
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, buffer);
+    }
+
+    /// Creates the bind group of the storage buffer.
+    fn create_bind_group(device: &Device, layout: &BindGroupLayout, buffer: &Buffer) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute Bind Group"),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        })
+    }
+
+    /// Records dispatching `x * y * z` workgroups of the shader into the encoder.
+    pub fn dispatch(&self, encoder: &mut CommandEncoder, x: u32, y: u32, z: u32) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(x, y, z);
+    }
+}
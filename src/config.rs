@@ -0,0 +1,333 @@
+use std::f32::consts::FRAC_PI_2;
+
+use anyhow::Result;
+use glam::*;
+use wgpu::{Backends, CompositeAlphaMode, MemoryHints, PowerPreference, TextureFormat};
+use winit::window::{CursorGrabMode, Icon};
+
+use crate::{
+    camera::{Camera, CoordinateSystem},
+    light::DirectionalLight,
+};
+
+/// The configuration the application is started with.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// The title of the window.
+    pub title: String,
+    /// The initial logical size of the window, as `(width, height)`.
+    pub size: (u32, u32),
+    /// Whether the window can be resized by the user.
+    pub resizable: bool,
+    /// The number of windows opened, each rendering a scene of its own from its own camera. The
+    /// windows after the first are titled with their number.
+    pub window_count: usize,
+    /// The icon of the window, or `None` for the platform's default.
+    pub icon: Option<Icon>,
+    /// Whether the window's background is transparent, letting the desktop show through wherever
+    /// the frames are. Only works on platforms and compositors which support it, together with an
+    /// `alpha_mode` blending the frames and a clear color with an alpha of 0.
+    pub transparent: bool,
+    /// How the alpha of the frames is composited with what's behind the window, or `None` for the
+    /// platform's default. Falls back to the default with a warning if the surface doesn't support
+    /// the mode, which is usually opaque.
+    pub alpha_mode: Option<CompositeAlphaMode>,
+    /// Whether presenting frames waits for the display's vertical blank, capping the frame rate
+    /// to the refresh rate.
+    pub vsync: bool,
+    /// The number of frames the gpu may queue up ahead of the one being displayed, from 1 to 3.
+    /// Lower values reduce the latency between input and the frame showing it, higher ones smooth
+    /// out uneven frame times.
+    pub frame_latency: u32,
+    /// The format of the offscreen texture the scene is rendered into before being copied onto the
+    /// window, or `None` to render straight onto the window.
+    pub offscreen_format: Option<TextureFormat>,
+    /// Whether the depth is reversed for more precision far away from the camera, see
+    /// `Renderer::set_reversed_z`.
+    pub reversed_z: bool,
+    /// The maximum number of frames rendered per second, or `None` if uncapped.
+    pub fps_cap: Option<u32>,
+    /// Whether frames are rendered continuously or only when something changes.
+    pub redraw_mode: RedrawMode,
+    /// What the scene of every window's renderer starts out with.
+    pub default_scene: DefaultScene,
+    /// The number of fixed updates run per second of real time, stepping the simulation at a
+    /// constant rate independently of the frame rate.
+    pub fixed_update_rate: u32,
+    /// Whether the camera moves in the fixed updates and is interpolated between the last two of
+    /// them when rendering, see `App::set_camera_smoothing`.
+    pub camera_smoothing: bool,
+    /// The graphics backends an adapter may be picked from. Overridden by the
+    /// `D3_TEMPLATE_BACKEND` environment variable, and falls back to all backends if none of the
+    /// given ones have a compatible adapter.
+    pub backends: Backends,
+    /// Whether to prefer a high performance or a low power adapter, when several are available.
+    pub power_preference: PowerPreference,
+    /// Whether the device allocates memory favoring speed or a small footprint.
+    pub memory_hints: MemoryHints,
+    /// The stick deflection (from 0 to 1) under which gamepad input is ignored. Only used with the
+    /// `gamepad` feature.
+    pub gamepad_deadzone: f32,
+    /// How the cursor is held in place while the window has focus for looking around. Platforms
+    /// which don't support the mode fall back from `CursorGrabMode::Locked` to
+    /// `CursorGrabMode::Confined` (re-centering the cursor every frame to emulate locking it) and
+    /// then to `CursorGrabMode::None`.
+    pub cursor_grab_mode: CursorGrabMode,
+    /// Which axis of the world points up, see `CoordinateSystem`. The camera's starting position,
+    /// yaw and pitch are given in it, and the reference grid and the orbit of the lights follow
+    /// it. The primitives of `Mesh` are always built with `+Y` up.
+    pub coordinate_system: CoordinateSystem,
+    /// The position the camera starts out at.
+    pub camera_eye: Vec3,
+    /// The yaw the camera starts out with in radians, see `Camera::yaw`.
+    pub camera_yaw: f32,
+    /// The pitch the camera starts out with in radians, see `Camera::pitch`.
+    pub camera_pitch: f32,
+    /// The vertical field of view the camera starts out with in radians, see `Camera::fov_y`.
+    pub camera_fov_y: f32,
+}
+
+/// What the scene of a new renderer starts out with, see `Scene::from_default`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultScene {
+    /// A triangle of red, green and blue corners facing the camera, spinning around its middle.
+    #[default]
+    Triangle,
+    /// A grey cube spinning around the vertical axis, lit by a directional light.
+    Cube,
+    /// A grey sphere, lit by a directional light.
+    Sphere,
+    /// Nothing, for apps adding meshes of their own.
+    Empty,
+}
+
+impl DefaultScene {
+    /// The light the lit default scenes start out with, shining down from the upper left.
+    pub const LIGHT: DirectionalLight = DirectionalLight {
+        direction: Vec3::new(-0.4, -1.0, -0.6),
+        color: Vec3::splat(0.9),
+    };
+
+    /// Returns the directional light the renderer starts out with for the scene, if any.
+    pub fn light(self) -> Option<DirectionalLight> {
+        match self {
+            Self::Cube | Self::Sphere => Some(Self::LIGHT),
+            Self::Triangle | Self::Empty => None,
+        }
+    }
+}
+
+/// How often the app renders frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Frames are rendered one after the other as fast as allowed, polling for events in between.
+    #[default]
+    Continuous,
+    /// The event loop sleeps until input arrives, and frames are only rendered while handling
+    /// input or while something is moving (the camera, or meshes spinning while not paused),
+    /// which saves power for viewer-style apps. Gamepads don't wake the app up, so they only
+    /// steer the camera while it's already rendering.
+    OnDemand,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            title: "d3 template".to_string(),
+            size: (1920, 1080),
+            resizable: true,
+            window_count: 1,
+            icon: None,
+            transparent: false,
+            alpha_mode: None,
+            vsync: false,
+            frame_latency: 2,
+            offscreen_format: None,
+            reversed_z: false,
+            fps_cap: None,
+            redraw_mode: RedrawMode::Continuous,
+            default_scene: DefaultScene::Triangle,
+            fixed_update_rate: 60,
+            camera_smoothing: false,
+            backends: Backends::all(),
+            power_preference: PowerPreference::HighPerformance,
+            memory_hints: MemoryHints::Performance,
+            gamepad_deadzone: 0.15,
+            cursor_grab_mode: CursorGrabMode::Locked,
+            coordinate_system: CoordinateSystem::YUp,
+            // looking down the negative z axis at the origin
+            camera_eye: vec3(0.0, 0.0, 3.0),
+            camera_yaw: -FRAC_PI_2,
+            camera_pitch: 0.0,
+            camera_fov_y: Camera::FOV_Y,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Creates the default configuration tuned for saving battery, such as for viewers on
+    /// laptops: the low power adapter is preferred, memory is allocated favoring a small
+    /// footprint, and frames are synced to the display's refresh rate.
+    pub fn low_power() -> Self {
+        Self {
+            power_preference: PowerPreference::LowPower,
+            memory_hints: MemoryHints::MemoryUsage,
+            vsync: true,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the title of the window.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the initial logical size of the window.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Sets whether the window can be resized by the user.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Sets the number of windows opened, at least one.
+    pub fn with_window_count(mut self, count: usize) -> Self {
+        self.window_count = count;
+        self
+    }
+
+    /// Sets the icon of the window from `width * height` pixels of rgba bytes, failing if the amount
+    /// of bytes doesn't match the size.
+    pub fn with_icon(mut self, rgba: Vec<u8>, width: u32, height: u32) -> Result<Self> {
+        self.icon = Some(Icon::from_rgba(rgba, width, height)?);
+        Ok(self)
+    }
+
+    /// Sets whether the window's background is transparent. Pair it with an `alpha_mode` such as
+    /// `CompositeAlphaMode::PreMultiplied` and a clear color with an alpha of 0.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Sets how the alpha of the frames is composited with what's behind the window, `None`
+    /// meaning the platform's default.
+    pub fn with_alpha_mode(mut self, mode: Option<CompositeAlphaMode>) -> Self {
+        self.alpha_mode = mode;
+        self
+    }
+
+    /// Sets whether presenting frames is synced to the display's refresh rate.
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Sets the number of frames the gpu may queue up ahead of the one being displayed, clamped to
+    /// the range from 1 to 3 when the renderer is created.
+    pub fn with_frame_latency(mut self, latency: u32) -> Self {
+        self.frame_latency = latency;
+        self
+    }
+
+    /// Sets the format of the offscreen texture the scene is rendered into, `None` meaning the scene
+    /// is rendered straight onto the window.
+    pub fn with_offscreen_format(mut self, format: Option<TextureFormat>) -> Self {
+        self.offscreen_format = format;
+        self
+    }
+
+    /// Sets whether the depth is reversed for more precision far away from the camera.
+    pub fn with_reversed_z(mut self, reversed_z: bool) -> Self {
+        self.reversed_z = reversed_z;
+        self
+    }
+
+    /// Caps the number of frames rendered per second, `None` meaning uncapped.
+    pub fn with_fps_cap(mut self, cap: Option<u32>) -> Self {
+        self.fps_cap = cap;
+        self
+    }
+
+    /// Sets whether frames are rendered continuously or only when something changes.
+    pub fn with_redraw_mode(mut self, mode: RedrawMode) -> Self {
+        self.redraw_mode = mode;
+        self
+    }
+
+    /// Sets what the scene of every window's renderer starts out with.
+    pub fn with_default_scene(mut self, scene: DefaultScene) -> Self {
+        self.default_scene = scene;
+        self
+    }
+
+    /// Sets the number of fixed updates run per second of real time.
+    pub fn with_fixed_update_rate(mut self, rate: u32) -> Self {
+        self.fixed_update_rate = rate;
+        self
+    }
+
+    /// Sets whether the camera moves in the fixed updates and is interpolated between them when
+    /// rendering.
+    pub fn with_camera_smoothing(mut self, smoothing: bool) -> Self {
+        self.camera_smoothing = smoothing;
+        self
+    }
+
+    /// Sets the graphics backends an adapter may be picked from.
+    pub fn with_backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// Sets whether to prefer a high performance or a low power adapter.
+    pub fn with_power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Sets whether the device allocates memory favoring speed or a small footprint.
+    pub fn with_memory_hints(mut self, memory_hints: MemoryHints) -> Self {
+        self.memory_hints = memory_hints;
+        self
+    }
+
+    /// Sets the stick deflection under which gamepad input is ignored.
+    pub fn with_gamepad_deadzone(mut self, deadzone: f32) -> Self {
+        self.gamepad_deadzone = deadzone;
+        self
+    }
+
+    /// Sets how the cursor is preferably held in place while the window has focus.
+    pub fn with_cursor_grab_mode(mut self, mode: CursorGrabMode) -> Self {
+        self.cursor_grab_mode = mode;
+        self
+    }
+
+    /// Sets which axis of the world points up. The default camera position is for `+Y` pointing
+    /// up, so with `CoordinateSystem::ZUp` a camera position such as `(0, -3, 0)` with the default
+    /// yaw and pitch looks at the origin the same way.
+    pub fn with_coordinate_system(mut self, coordinate_system: CoordinateSystem) -> Self {
+        self.coordinate_system = coordinate_system;
+        self
+    }
+
+    /// Sets the position, yaw and pitch (in radians) the camera of every window starts out with.
+    pub fn with_camera(mut self, eye: Vec3, yaw: f32, pitch: f32) -> Self {
+        self.camera_eye = eye;
+        self.camera_yaw = yaw;
+        self.camera_pitch = pitch;
+        self
+    }
+
+    /// Sets the vertical field of view (in radians) the camera starts out with.
+    pub fn with_camera_fov_y(mut self, fov_y: f32) -> Self {
+        self.camera_fov_y = fov_y;
+        self
+    }
+}
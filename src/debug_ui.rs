@@ -0,0 +1,153 @@
+use std::fmt;
+
+use egui::{epaint::ClippedPrimitive, Context, TexturesDelta, ViewportId};
+use egui_wgpu::ScreenDescriptor;
+use wgpu::*;
+use winit::{event::WindowEvent, window::Window};
+
+/// The egui state behind the debug overlay, collecting input from the window and laying out the ui.
+pub struct DebugUi {
+    /// The egui context the ui is laid out with.
+    context: Context,
+    /// Translates winit events into egui input.
+    state: egui_winit::State,
+}
+
+/// A laid out frame of the debug overlay, ready to be painted.
+pub struct UiFrame {
+    /// The tessellated shapes of the ui.
+    primitives: Vec<ClippedPrimitive>,
+    /// The textures to upload before, and free after painting the frame.
+    textures_delta: TexturesDelta,
+    /// The scale factor the frame was laid out with.
+    pixels_per_point: f32,
+}
+
+/// Paints frames of the debug overlay onto a render target.
+pub struct UiPainter {
+    /// The egui backend drawing through wgpu.
+    renderer: egui_wgpu::Renderer,
+}
+
+impl DebugUi {
+    pub fn new(window: &Window) -> Self {
+        let context = Context::default();
+
+        let state = egui_winit::State::new(
+            context.clone(),
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            None,
+        );
+
+        Self { context, state }
+    }
+
+    /// Feeds a window event to egui, returning whether egui consumed it (for example a click on a
+    /// panel), in which case the app shouldn't react to it.
+    pub fn handle_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
+    }
+
+    /// Lays out the ui using `build`, returning the frame to be painted.
+    pub fn run(&mut self, window: &Window, build: impl FnMut(&Context)) -> UiFrame {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, build);
+
+        self.state
+            .handle_platform_output(window, output.platform_output);
+
+        UiFrame {
+            primitives: self
+                .context
+                .tessellate(output.shapes, output.pixels_per_point),
+            textures_delta: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        }
+    }
+}
+
+impl UiPainter {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        Self {
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1, false),
+        }
+    }
+
+    /// Records painting `frame` on top of the contents of `view` into the encoder. Returns the
+    /// command buffers which have to be submitted before the encoder.
+    pub fn paint(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        size: [u32; 2],
+        frame: &UiFrame,
+    ) -> Vec<CommandBuffer> {
+        let screen = ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: frame.pixels_per_point,
+        };
+
+        for (id, delta) in &frame.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+
+        let command_buffers =
+            self.renderer
+                .update_buffers(device, queue, encoder, &frame.primitives, &screen);
+
+        {
+            let render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Debug UI Render Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            self.renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &frame.primitives,
+                &screen,
+            );
+        }
+
+        for id in &frame.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+
+        command_buffers
+    }
+}
+
+impl fmt::Debug for DebugUi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugUi").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for UiFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UiFrame")
+            .field("primitives", &self.primitives.len())
+            .field("pixels_per_point", &self.pixels_per_point)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for UiPainter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UiPainter").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,161 @@
+use wgpu::{util::*, *};
+
+use crate::{
+    camera::Camera,
+    render_target::{create_fullscreen_pipeline, draw_fullscreen},
+};
+
+/// The settings of the depth visualization, laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct DepthDebugUniform {
+    /// The distance of the camera's near plane.
+    near: f32,
+    /// The distance shown as white.
+    range: f32,
+    /// Whether the depth is reversed (1) or not (0), see `Camera::set_reversed_z`.
+    reversed_z: u32,
+    /// Pads the uniform to the 16 bytes required by some backends.
+    _padding: u32,
+}
+
+/// Draws the depth buffer onto a texture as shades of gray, from black at the near plane to white
+/// at `range` units away and beyond, to debug depth precision and z-fighting.
+#[derive(Debug)]
+pub struct DepthDebugPass {
+    /// The uniform buffer of the `DepthDebugUniform`.
+    uniform_buffer: Buffer,
+    /// The layout of the bind group of the depth texture, which is recreated every frame since
+    /// the depth texture is replaced on resize.
+    bind_group_layout: BindGroupLayout,
+    /// Draws the fullscreen triangle.
+    pipeline: RenderPipeline,
+    /// Whether the depth is reversed, the near plane having a depth of 1 rather than 0.
+    reversed_z: bool,
+}
+
+impl DepthDebugPass {
+    /// The default distance shown as white.
+    pub const DEFAULT_RANGE: f32 = 50.0;
+
+    /// Creates a pass drawing a depth texture with the given sample count onto textures of the
+    /// given format, reversed or not.
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        sample_count: u32,
+        range: f32,
+        reversed_z: bool,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Depth Debug Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Self::uniform(range, reversed_z)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let multisampled = sample_count > 1;
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Depth Debug Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        // loaded as a float texture, since some backends can't load from depth textures
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let source = include_str!("../assets/shader/depth_debug.wgsl");
+
+        // the first sample of a multisampled texture is loaded the same way as the first mip level
+        // of a regular one
+        let source = if multisampled {
+            source.replace("texture_2d<f32>", "texture_multisampled_2d<f32>")
+        } else {
+            source.to_string()
+        };
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Depth Debug Shader"),
+            source: ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline =
+            create_fullscreen_pipeline(device, "Depth Debug", &shader, &bind_group_layout, format);
+
+        Self {
+            uniform_buffer,
+            bind_group_layout,
+            pipeline,
+            reversed_z,
+        }
+    }
+
+    /// Updates the distance shown as white.
+    pub fn set_range(&self, queue: &Queue, range: f32) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&Self::uniform(range, self.reversed_z)),
+        );
+    }
+
+    /// Records drawing the depth texture behind `depth` onto `view` into the encoder.
+    pub fn draw(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        depth: &TextureView,
+        view: &TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Depth Debug Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(depth),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        draw_fullscreen(
+            encoder,
+            "Depth Debug Render Pass",
+            view,
+            &self.pipeline,
+            &bind_group,
+        );
+    }
+
+    /// Returns the uniform of the given range.
+    fn uniform(range: f32, reversed_z: bool) -> DepthDebugUniform {
+        DepthDebugUniform {
+            near: Camera::Z_NEAR,
+            range,
+            reversed_z: reversed_z as u32,
+            _padding: 0,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+/// The times of the most recent frames, summarized over a rolling window.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// The durations of the last `FrameStats::WINDOW` frames in seconds, oldest first.
+    frame_times: VecDeque<f32>,
+}
+
+impl FrameStats {
+    /// The number of frames the statistics are computed over.
+    pub const WINDOW: usize = 120;
+
+    /// Creates the statistics without any frames recorded yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the duration of a frame in seconds, dropping the oldest frame once the window is
+    /// full.
+    pub fn push(&mut self, dt: f32) {
+        if self.frame_times.len() == Self::WINDOW {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(dt);
+    }
+
+    /// Returns the number of frames in the window, up to `FrameStats::WINDOW`.
+    pub fn len(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    /// Returns whether no frames have been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.frame_times.is_empty()
+    }
+
+    /// Returns the duration of the last frame in seconds, zero if there is none.
+    pub fn last(&self) -> f32 {
+        self.frame_times.back().copied().unwrap_or(0.0)
+    }
+
+    /// Returns the shortest frame in the window in seconds, zero if there is none.
+    pub fn min(&self) -> f32 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the longest frame in the window in seconds, zero if there is none.
+    pub fn max(&self) -> f32 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the average duration of the frames in the window in seconds, zero if there is none.
+    pub fn average(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        self.frame_times.iter().sum::<f32>() / self.len() as f32
+    }
+
+    /// Returns the number of frames rendered per second, averaged over the window. Zero if there
+    /// are no frames yet.
+    pub fn fps(&self) -> f32 {
+        match self.average() {
+            average if average > 0.0 => 1.0 / average,
+            _ => 0.0,
+        }
+    }
+}
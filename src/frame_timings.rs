@@ -0,0 +1,28 @@
+/// Where the time of a frame went, in milliseconds, to tell whether the app is held back by the
+/// cpu or the gpu. The cpu phases are measured with `Instant`s around them, and the gpu time with
+/// the timestamp queries of `GpuTimer`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTimings {
+    /// The time spent handling the window and device events which arrived since the last frame.
+    pub input: f32,
+    /// The time spent in `App::update`, not counting the wait of the fps cap.
+    pub update: f32,
+    /// The time spent recording and submitting the frame's commands.
+    pub encode: f32,
+    /// The time the gpu spent on the frame's render pass, `None` if the device doesn't support
+    /// timestamp queries. Read back asynchronously, so it lags a frame or two behind the others.
+    pub gpu: Option<f32>,
+}
+
+impl FrameTimings {
+    /// Returns the total time the cpu spent on the frame.
+    pub fn cpu(&self) -> f32 {
+        self.input + self.update + self.encode
+    }
+
+    /// Returns whether the gpu took longer on the frame than the cpu, `None` if the gpu time isn't
+    /// known.
+    pub fn gpu_bound(&self) -> Option<bool> {
+        self.gpu.map(|gpu| gpu > self.cpu())
+    }
+}
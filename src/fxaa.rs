@@ -0,0 +1,107 @@
+use wgpu::*;
+
+use crate::render_target::{create_fullscreen_pipeline, draw_fullscreen, RenderTarget};
+
+/// Applies fast approximate anti-aliasing while copying a render target onto another texture of
+/// the same size, as a cheaper alternative to multisampling. The source has to be a filterable
+/// format.
+#[derive(Debug)]
+pub struct FxaaPass {
+    /// The layout of the `bind_group`, kept around to rebind resized sources.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the source texture and its sampler.
+    bind_group: BindGroup,
+    /// Samples the source texture linearly, blending neighbouring texels along edges.
+    sampler: Sampler,
+    /// Draws the fullscreen triangle.
+    pipeline: RenderPipeline,
+}
+
+impl FxaaPass {
+    /// Creates a pass anti-aliasing `source` onto textures of the given format.
+    pub fn new(device: &Device, source: &RenderTarget, format: TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("FXAA Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("FXAA Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("FXAA Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/fxaa.wgsl").into()),
+        });
+
+        let pipeline =
+            create_fullscreen_pipeline(device, "FXAA", &shader, &bind_group_layout, format);
+
+        Self {
+            bind_group: Self::create_bind_group(device, &bind_group_layout, &sampler, source),
+            bind_group_layout,
+            sampler,
+            pipeline,
+        }
+    }
+
+    /// Binds a new source to anti-alias, such as after the previous one was resized.
+    pub fn set_source(&mut self, device: &Device, source: &RenderTarget) {
+        self.bind_group =
+            Self::create_bind_group(device, &self.bind_group_layout, &self.sampler, source);
+    }
+
+    /// Creates the bind group of the source texture and sampler.
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        source: &RenderTarget,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("FXAA Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source.view()),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// Records anti-aliasing the source onto `view` into the encoder.
+    pub fn draw(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        draw_fullscreen(
+            encoder,
+            "FXAA Render Pass",
+            view,
+            &self.pipeline,
+            &self.bind_group,
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+
+use gilrs::{Axis, Button, Gamepad, Gilrs};
+use glam::*;
+
+use crate::camera::Camera;
+
+/// Reads the sticks of connected gamepads to fly the camera around, in addition to the keyboard
+/// and mouse.
+#[derive(Debug)]
+pub struct GamepadInput {
+    /// The connection to the gamepads.
+    gilrs: Gilrs,
+    /// The stick deflection (from 0 to 1) under which input is ignored, to filter out drift.
+    deadzone: f32,
+}
+
+impl GamepadInput {
+    /// How fast the camera turns when the right stick is fully deflected, in the same units as
+    /// mouse movement per second.
+    pub const LOOK_SPEED: f64 = 600.0;
+
+    pub fn new(deadzone: f32) -> Result<Self> {
+        let gilrs = Gilrs::new().map_err(|error| anyhow!("failed to initialize gilrs: {error}"))?;
+
+        Ok(Self { gilrs, deadzone })
+    }
+
+    /// Polls the gamepads, moving the camera with the left stick (and the bumpers for up and down)
+    /// and rotating it with the right stick.
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        // events have to be drained for the gamepads' state to be updated
+        while self.gilrs.next_event().is_some() {}
+
+        let Some((_, gamepad)) = self.gilrs.gamepads().next() else {
+            return;
+        };
+
+        let movement = self.stick(&gamepad, Axis::LeftStickX, Axis::LeftStickY);
+        let look = self.stick(&gamepad, Axis::RightStickX, Axis::RightStickY);
+
+        let vertical = gamepad.is_pressed(Button::RightTrigger) as i32 as f32
+            - gamepad.is_pressed(Button::LeftTrigger) as i32 as f32;
+
+        camera.move_relative(vec3(movement.x, vertical, movement.y).clamp_length_max(1.0));
+
+        // the stick's y axis points up, while the mouse's points down
+        let look = look.as_dvec2() * Self::LOOK_SPEED;
+        camera.update_rotation_angles((look.x, -look.y), dt);
+    }
+
+    /// Reads a stick's deflection, applying the deadzone and rescaling the remaining range so that
+    /// the input starts from zero right outside of it.
+    fn stick(&self, gamepad: &Gamepad, x: Axis, y: Axis) -> Vec2 {
+        let value = vec2(gamepad.value(x), gamepad.value(y));
+        let length = value.length();
+
+        if length <= self.deadzone {
+            return Vec2::ZERO;
+        }
+
+        value / length * ((length - self.deadzone) / (1.0 - self.deadzone)).min(1.0)
+    }
+}
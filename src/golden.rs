@@ -0,0 +1,480 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::*;
+use log::*;
+use png::{BitDepth, ColorType, Transformations};
+use wgpu::{Color, Face};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    camera::Camera,
+    capture::CapturedFrame,
+    config::AppConfig,
+    light::{DirectionalLight, PointLight},
+    mesh::{Mesh, Vertex},
+    renderer::Renderer,
+    scene::Scene,
+    transform::Transform,
+};
+
+/// A known scene rendered headlessly and compared against an image stored in the golden
+/// directory, to catch changes to how things are drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct GoldenScene {
+    /// The name of the scene, which its golden image is stored as (with a `.png` extension).
+    pub name: &'static str,
+    /// Sets up the scene on a renderer which already has everything but the defaults of the scene
+    /// turned off, and places the camera.
+    pub setup: fn(&mut Renderer, &mut Camera),
+    /// The number of samples per pixel the scene is rendered with. Multisampling varies a lot
+    /// between drivers, so most scenes are rendered with a single sample.
+    pub sample_count: u32,
+}
+
+/// How far a rendered frame may differ from its golden image, absorbing the small differences
+/// between drivers in rasterization and rounding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// The largest difference of a channel of a pixel (from 0 to 255) for which the pixel still
+    /// matches.
+    pub channel: u8,
+    /// The fraction of the pixels (from 0 to 1) which may not match.
+    pub mismatched: f32,
+}
+
+impl Tolerance {
+    /// The tolerance golden images are compared with by default: a few steps of a channel, and a
+    /// fraction of a percent of the pixels for edges which are rasterized differently.
+    pub const DEFAULT: Self = Self {
+        channel: 8,
+        mismatched: 0.005,
+    };
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The result of comparing a rendered frame against its golden image.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// The number of pixels differing by more than the tolerance in some channel.
+    pub mismatched: usize,
+    /// The largest difference of a channel over all pixels.
+    pub max_difference: u8,
+    /// The golden image dimmed to grey, with the mismatched pixels in red.
+    pub diff: CapturedFrame,
+}
+
+impl Comparison {
+    /// Returns whether few enough pixels mismatched for the frame to pass.
+    pub fn passes(&self, tolerance: Tolerance) -> bool {
+        let pixels = (self.diff.width * self.diff.height).max(1) as f32;
+
+        self.mismatched as f32 / pixels <= tolerance.mismatched
+    }
+}
+
+/// What checking a frame against its golden image did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    /// The frame matched the golden image within the tolerance.
+    Matched,
+    /// The golden image was written from the frame, as blessing was requested.
+    Blessed,
+}
+
+/// The size frames of the golden scenes are rendered at.
+pub const SIZE: PhysicalSize<u32> = PhysicalSize::new(128, 128);
+
+/// The environment variable which, when set to anything but `0`, makes `check` write the golden
+/// images from the rendered frames instead of comparing against them.
+pub const BLESS_VAR: &str = "BLESS";
+
+/// The scenes with golden images in `golden_dir`.
+pub const SCENES: [GoldenScene; 9] = [
+    GoldenScene {
+        name: "triangle",
+        setup: setup_triangle,
+        sample_count: 1,
+    },
+    GoldenScene {
+        name: "lit_cube",
+        setup: setup_lit_cube,
+        sample_count: 1,
+    },
+    GoldenScene {
+        name: "random",
+        setup: setup_random,
+        sample_count: 1,
+    },
+    // the triangle's diagonal edges, which are only smoothed if the multisampled frame is resolved
+    // before it's read back
+    GoldenScene {
+        name: "triangle_msaa",
+        setup: setup_triangle,
+        sample_count: 4,
+    },
+    // a decal tessellated differently than the floor it lies on, which fights the floor wherever
+    // their depths round differently unless it's depth biased
+    GoldenScene {
+        name: "depth_bias",
+        setup: setup_depth_bias,
+        sample_count: 1,
+    },
+    // a sphere squashed to a disc, whose shading only follows its surface if its normals are
+    // transformed by the normal matrix rather than the model matrix
+    GoldenScene {
+        name: "squashed_sphere",
+        setup: setup_squashed_sphere,
+        sample_count: 1,
+    },
+    // two triangles wound clockwise, like those of a mesh exported with the opposite handedness,
+    // of which only the right one is flipped back and escapes being culled
+    GoldenScene {
+        name: "flip_winding",
+        setup: setup_flip_winding,
+        sample_count: 1,
+    },
+    // a plane drawn as one triangle strip, which has holes if stitching its rows together flips the
+    // winding of the rows after them
+    GoldenScene {
+        name: "strip_plane",
+        setup: setup_strip_plane,
+        sample_count: 1,
+    },
+    // a yellow cube whose magenta material leaves only red, as the colors are multiplied
+    GoldenScene {
+        name: "material_color",
+        setup: setup_material_color,
+        sample_count: 1,
+    },
+];
+
+/// Returns the directory the golden images of the repository are stored in.
+pub fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/golden")
+}
+
+/// Returns whether blessing the golden images was requested through `BLESS_VAR`.
+pub fn bless_requested() -> bool {
+    env::var_os(BLESS_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Keeps the demo triangle the renderer starts out with, still and facing the camera.
+fn setup_triangle(renderer: &mut Renderer, _camera: &mut Camera) {
+    for (_, mesh) in renderer.scene_mut().iter_mut() {
+        mesh.set_spin(Vec3::Z, 0.0);
+    }
+}
+
+/// Replaces the scene with a cube turned to show three of its faces, lit by the directional light
+/// and a point light in front of it.
+fn setup_lit_cube(renderer: &mut Renderer, camera: &mut Camera) {
+    let mut cube = Mesh::cube(renderer.device(), 1.0, vec4(0.8, 0.8, 0.8, 1.0));
+    cube.set_transform(Transform::from_rotation(Quat::from_euler(
+        EulerRot::YXZ,
+        0.6,
+        0.5,
+        0.0,
+    )));
+
+    renderer.set_directional_light(Some(DirectionalLight {
+        direction: vec3(-0.4, -1.0, -0.6),
+        color: Vec3::splat(0.8),
+    }));
+    renderer.set_point_lights(&[PointLight {
+        position: vec3(1.5, 1.0, 2.0),
+        color: vec3(1.0, 0.6, 0.3),
+        radius: 6.0,
+    }]);
+
+    stage(renderer, camera, vec3(0.0, 0.0, 3.0), [cube]);
+}
+
+/// Replaces the scene with the cubes of `Scene::random` from a fixed seed, framed by the camera.
+fn setup_random(renderer: &mut Renderer, camera: &mut Camera) {
+    const SEED: u64 = 0x5eed;
+
+    renderer.load_random_scene(SEED, 24);
+    camera.frame(&renderer.scene().bounds());
+}
+
+/// Replaces the scene with a red floor and a green decal lying on it, turned and finely tessellated
+/// so that their triangles don't line up, with the decal depth biased to show over the floor.
+fn setup_depth_bias(renderer: &mut Renderer, camera: &mut Camera) {
+    let floor = Mesh::plane(renderer.device(), 3.0, 1, vec4(1.0, 0.0, 0.0, 1.0));
+
+    let mut decal = Mesh::plane(renderer.device(), 2.0, 13, vec4(0.0, 1.0, 0.0, 1.0));
+    decal.set_transform(Transform::from_rotation(Quat::from_rotation_y(0.3)));
+    decal.set_depth_biased(true);
+
+    // a grazing angle, where coplanar faces fight the most
+    stage(renderer, camera, vec3(0.0, 0.6, 2.0), [floor, decal]);
+}
+
+/// Replaces the scene with a sphere flattened along the y axis, lit by a point light above it.
+fn setup_squashed_sphere(renderer: &mut Renderer, camera: &mut Camera) {
+    let mut sphere = Mesh::sphere(renderer.device(), 1.0, 32, 16, vec4(0.8, 0.8, 0.8, 1.0));
+    sphere.set_transform(Transform::from_scale(vec3(1.0, 0.3, 1.0)));
+
+    renderer.set_point_lights(&[PointLight {
+        position: vec3(-1.0, 1.5, 1.0),
+        color: Vec3::ONE,
+        radius: 8.0,
+    }]);
+
+    stage(renderer, camera, vec3(0.0, 1.0, 2.5), [sphere]);
+}
+
+/// Replaces the scene with two clockwise triangles facing the camera, with back faces culled and
+/// the winding of the right one flipped.
+fn setup_flip_winding(renderer: &mut Renderer, camera: &mut Camera) {
+    let triangle = |x: f32, color: Vec4| {
+        // counter-clockwise from the camera, before the last two vertices are swapped
+        let vertices = [
+            vec3(-0.4, -0.4, 0.0),
+            vec3(0.4, -0.4, 0.0),
+            vec3(0.0, 0.4, 0.0),
+        ]
+        .map(|pos| Vertex {
+            pos: (pos + vec3(x, 0.0, 0.0)).to_array(),
+            color: color.to_array(),
+            normal: [0.0, 0.0, -1.0],
+            ..Default::default()
+        });
+
+        Mesh::new(renderer.device(), &vertices, &[0, 2, 1])
+    };
+
+    let culled = triangle(-0.5, vec4(1.0, 0.0, 0.0, 1.0));
+    let mut flipped = triangle(0.5, vec4(0.0, 1.0, 0.0, 1.0));
+    flipped.flip_winding(renderer.device());
+
+    renderer.set_cull_mode(Some(Face::Back));
+
+    stage(renderer, camera, vec3(0.0, 0.0, 3.0), [culled, flipped]);
+}
+
+/// Replaces the scene with a plane of five rows of quads, seen from above with back faces culled
+/// and lit by a point light over one of its corners.
+fn setup_strip_plane(renderer: &mut Renderer, camera: &mut Camera) {
+    let plane = Mesh::plane(renderer.device(), 2.0, 5, vec4(0.8, 0.8, 0.8, 1.0));
+
+    renderer.set_cull_mode(Some(Face::Back));
+    renderer.set_point_lights(&[PointLight {
+        position: vec3(-1.0, 0.5, -1.0),
+        color: Vec3::ONE,
+        radius: 4.0,
+    }]);
+
+    stage(renderer, camera, vec3(0.0, 2.0, 2.0), [plane]);
+}
+
+/// Replaces the scene with an unlit cube of yellow vertices and a magenta material, seen face on.
+fn setup_material_color(renderer: &mut Renderer, camera: &mut Camera) {
+    let cube = Mesh::cube(renderer.device(), 1.0, vec4(1.0, 1.0, 0.0, 1.0));
+    cube.set_material_color(renderer.queue(), vec4(1.0, 0.0, 1.0, 1.0));
+
+    stage(renderer, camera, vec3(0.0, 0.0, 2.0), [cube]);
+}
+
+/// Replaces the scene of the renderer with the meshes, and places the camera at `eye` looking at
+/// the origin.
+pub fn stage(
+    renderer: &mut Renderer,
+    camera: &mut Camera,
+    eye: Vec3,
+    meshes: impl IntoIterator<Item = Mesh>,
+) {
+    let scene = renderer.scene_mut();
+    *scene = Scene::new();
+
+    for mesh in meshes {
+        scene.add(mesh);
+    }
+
+    camera.set_position(eye);
+    camera.look_at(Vec3::ZERO);
+}
+
+/// Renders a golden scene with a headless renderer of `SIZE` and the scene's `sample_count`. The
+/// grid and the axis gizmo are hidden, as they vary the most between drivers.
+pub fn render(scene: &GoldenScene, config: &AppConfig) -> Result<CapturedFrame> {
+    let mut camera = Camera::new(vec3(0.0, 0.0, 2.0), -90f32.to_radians(), 0.0, SIZE);
+
+    let mut renderer = pollster::block_on(Renderer::new_headless(SIZE, &camera, config))?;
+    renderer.set_sample_count(scene.sample_count);
+    renderer.set_grid_visible(false);
+    renderer.set_axes_visible(false);
+    renderer.set_clear_color(Color::BLACK);
+
+    (scene.setup)(&mut renderer, &mut camera);
+
+    renderer.update_camera_buffer(&camera);
+    renderer.capture_frame()
+}
+
+/// Compares a rendered frame against its golden image pixel by pixel, failing if their sizes
+/// differ.
+pub fn compare(
+    actual: &CapturedFrame,
+    expected: &CapturedFrame,
+    tolerance: Tolerance,
+) -> Result<Comparison> {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        bail!(
+            "the frame is {}x{}, but the golden image is {}x{}",
+            actual.width,
+            actual.height,
+            expected.width,
+            expected.height
+        );
+    }
+
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+
+    let rgba = actual
+        .rgba
+        .chunks_exact(4)
+        .zip(expected.rgba.chunks_exact(4))
+        .flat_map(|(actual, expected)| {
+            let difference = actual
+                .iter()
+                .zip(expected)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+
+            max_difference = max_difference.max(difference);
+
+            if difference > tolerance.channel {
+                mismatched += 1;
+                [u8::MAX, 0, 0, u8::MAX]
+            } else {
+                let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 3;
+                let grey = (luma / 4) as u8;
+                [grey, grey, grey, u8::MAX]
+            }
+        })
+        .collect();
+
+    Ok(Comparison {
+        mismatched,
+        max_difference,
+        diff: CapturedFrame {
+            width: expected.width,
+            height: expected.height,
+            rgba,
+        },
+    })
+}
+
+/// Checks a rendered frame against the golden image `name` in `dir`, or writes the golden image if
+/// `bless_requested`. On a mismatch the frame and a diff image are written next to the golden
+/// image as `<name>.actual.png` and `<name>.diff.png` and an error is returned, while a match
+/// removes the ones of an earlier mismatch.
+pub fn check(
+    dir: &Path,
+    name: &str,
+    frame: &CapturedFrame,
+    tolerance: Tolerance,
+) -> Result<GoldenOutcome> {
+    let path = dir.join(format!("{name}.png"));
+
+    if bless_requested() {
+        fs::create_dir_all(dir)?;
+        write_png(&path, frame)?;
+        info!("blessed {}", path.display());
+
+        return Ok(GoldenOutcome::Blessed);
+    }
+
+    let expected = read_png(&path).with_context(|| {
+        format!("failed to read the golden image, run with {BLESS_VAR}=1 to write it")
+    })?;
+    let comparison = compare(frame, &expected, tolerance)?;
+
+    let actual_path = dir.join(format!("{name}.actual.png"));
+    let diff_path = dir.join(format!("{name}.diff.png"));
+
+    if comparison.passes(tolerance) {
+        // the images of an earlier mismatch would be stale now
+        for path in [&actual_path, &diff_path] {
+            let _ = fs::remove_file(path);
+        }
+
+        return Ok(GoldenOutcome::Matched);
+    }
+
+    write_png(&actual_path, frame)?;
+    write_png(&diff_path, &comparison.diff)?;
+
+    Err(anyhow!(
+        "{name}: {} pixels differ by up to {} (tolerating {} by up to {}), see {} and {}",
+        comparison.mismatched,
+        comparison.max_difference,
+        (tolerance.mismatched * (frame.width * frame.height) as f32) as usize,
+        tolerance.channel,
+        actual_path.display(),
+        diff_path.display()
+    ))
+}
+
+/// Writes a frame to a png file.
+pub fn write_png(path: &Path, frame: &CapturedFrame) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frame.width, frame.height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&frame.rgba)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Reads a png file into a frame, expanding it into 8 bit rgba pixels.
+pub fn read_png(path: &Path) -> Result<CapturedFrame> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(Transformations::normalize_to_color8() | Transformations::ALPHA);
+
+    let mut reader = decoder.read_info()?;
+    let size = reader
+        .output_buffer_size()
+        .ok_or_else(|| anyhow!("{} is too large", path.display()))?;
+
+    let mut rgba = vec![0; size];
+    let info = reader.next_frame(&mut rgba)?;
+
+    if info.color_type != ColorType::Rgba || info.bit_depth != BitDepth::Eight {
+        bail!(
+            "{} has {:?} pixels of depth {:?} rather than 8 bit rgba",
+            path.display(),
+            info.color_type,
+            info.bit_depth
+        );
+    }
+
+    rgba.truncate(info.buffer_size());
+
+    Ok(CapturedFrame {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+
+use wgpu::*;
+
+/// A summary of the capabilities of the gpu the renderer runs on, for including in bug reports.
+/// Printing it with `Display` lists everything on separate lines.
+#[derive(Debug, Clone)]
+pub struct GpuReport {
+    /// The name of the adapter.
+    pub name: String,
+    /// The graphics backend the adapter is driven through.
+    pub backend: Backend,
+    /// The kind of device the adapter is, such as a discrete or integrated gpu.
+    pub device_type: DeviceType,
+    /// The name of the driver.
+    pub driver: String,
+    /// Further information about the driver, such as its version.
+    pub driver_info: String,
+    /// The features the adapter supports.
+    pub features: Features,
+    /// The features enabled on the device the renderer created.
+    pub enabled_features: Features,
+    /// The limits of the adapter.
+    pub limits: Limits,
+    /// The formats the surface can be configured with, empty for headless renderers.
+    pub surface_formats: Vec<TextureFormat>,
+    /// The present modes the surface supports, empty for headless renderers.
+    pub present_modes: Vec<PresentMode>,
+}
+
+impl GpuReport {
+    /// Collects the capabilities of the adapter, the device created from it and the surface
+    /// presenting its frames, if there is one.
+    pub fn new(adapter: &Adapter, device: &Device, surface: Option<&Surface>) -> Self {
+        let info = adapter.get_info();
+        let capabilities = surface.map(|surface| surface.get_capabilities(adapter));
+
+        Self {
+            name: info.name,
+            backend: info.backend,
+            device_type: info.device_type,
+            driver: info.driver,
+            driver_info: info.driver_info,
+            features: adapter.features(),
+            enabled_features: device.features(),
+            limits: adapter.limits(),
+            surface_formats: capabilities
+                .as_ref()
+                .map_or_else(Vec::new, |capabilities| capabilities.formats.clone()),
+            present_modes: capabilities
+                .map_or_else(Vec::new, |capabilities| capabilities.present_modes),
+        }
+    }
+}
+
+impl fmt::Display for GpuReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limits = &self.limits;
+
+        writeln!(f, "adapter: {} ({:?})", self.name, self.device_type)?;
+        writeln!(f, "backend: {:?}", self.backend)?;
+        // some backends only report one of the driver's name and information
+        let driver = format!("{} {}", self.driver, self.driver_info);
+        writeln!(f, "driver: {}", driver.trim())?;
+        writeln!(f, "features: {:?}", self.features)?;
+        writeln!(f, "enabled features: {:?}", self.enabled_features)?;
+        writeln!(f, "max texture size: {}", limits.max_texture_dimension_2d)?;
+        writeln!(f, "max buffer size: {}", limits.max_buffer_size)?;
+        writeln!(
+            f,
+            "max uniform binding size: {}",
+            limits.max_uniform_buffer_binding_size
+        )?;
+        writeln!(
+            f,
+            "max storage binding size: {}",
+            limits.max_storage_buffer_binding_size
+        )?;
+        writeln!(f, "max bind groups: {}", limits.max_bind_groups)?;
+        writeln!(f, "max vertex buffers: {}", limits.max_vertex_buffers)?;
+        writeln!(f, "max vertex attributes: {}", limits.max_vertex_attributes)?;
+        writeln!(
+            f,
+            "max push constant size: {}",
+            limits.max_push_constant_size
+        )?;
+
+        if self.surface_formats.is_empty() {
+            write!(f, "surface: none (headless)")
+        } else {
+            writeln!(f, "surface formats: {:?}", self.surface_formats)?;
+            write!(f, "present modes: {:?}", self.present_modes)
+        }
+    }
+}
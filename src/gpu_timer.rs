@@ -0,0 +1,155 @@
+use std::sync::mpsc::{self, Receiver};
+
+use wgpu::*;
+
+/// Measures how long the gpu spends on a render pass, using timestamp queries written at the
+/// beginning and end of the pass. The timestamps are read back asynchronously, so the measured
+/// time lags a frame or two behind.
+#[derive(Debug)]
+pub struct GpuTimer {
+    /// The two timestamps written by the render pass.
+    query_set: QuerySet,
+    /// The buffer the timestamps are resolved into on the gpu.
+    resolve_buffer: Buffer,
+    /// The buffer the resolved timestamps are copied into, to be read by the cpu.
+    readback_buffer: Buffer,
+    /// The number of nanoseconds per timestamp tick.
+    period: f32,
+    /// Whether the timestamps of the current frame are being resolved into the `readback_buffer`.
+    resolving: bool,
+    /// Receives the result of mapping the `readback_buffer`, while a mapping is in flight.
+    mapping: Option<Receiver<Result<(), BufferAsyncError>>>,
+    /// The gpu time of the last measured render pass, in milliseconds.
+    last_time: Option<f32>,
+}
+
+impl GpuTimer {
+    /// The number of timestamps written per frame.
+    const QUERY_COUNT: u32 = 2;
+    /// The size in bytes of the resolved timestamps.
+    const BUFFER_SIZE: BufferAddress =
+        Self::QUERY_COUNT as BufferAddress * QUERY_SIZE as BufferAddress;
+
+    /// Creates a timer if the device supports timestamp queries.
+    pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: QueryType::Timestamp,
+            count: Self::QUERY_COUNT,
+        });
+
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: Self::BUFFER_SIZE,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            resolving: false,
+            mapping: None,
+            last_time: None,
+        })
+    }
+
+    /// Returns the gpu time of the last measured render pass in milliseconds, or `None` if nothing
+    /// has been measured yet.
+    pub fn last_time(&self) -> Option<f32> {
+        self.last_time
+    }
+
+    /// Returns the timestamp writes to measure a render pass with.
+    pub fn timestamp_writes(&self) -> RenderPassTimestampWrites<'_> {
+        RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Records resolving the timestamps of the measured pass into the encoder. Skipped while the
+    /// previous timestamps are still being read back, since the `readback_buffer` can't be written
+    /// to while mapped.
+    pub fn resolve(&mut self, encoder: &mut CommandEncoder) {
+        self.resolving = self.mapping.is_none();
+
+        if !self.resolving {
+            return;
+        }
+
+        encoder.resolve_query_set(
+            &self.query_set,
+            0..Self::QUERY_COUNT,
+            &self.resolve_buffer,
+            0,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            Self::BUFFER_SIZE,
+        );
+    }
+
+    /// Starts reading back the timestamps resolved this frame. Has to be called after the encoder
+    /// passed to `GpuTimer::resolve` has been submitted.
+    pub fn map(&mut self) {
+        if !self.resolving {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+
+        self.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+
+        self.mapping = Some(receiver);
+        self.resolving = false;
+    }
+
+    /// Polls the device for a finished read back, updating the last measured time if there is one.
+    pub fn poll(&mut self, device: &Device) {
+        let Some(mapping) = &self.mapping else {
+            return;
+        };
+
+        device.poll(Maintain::Poll);
+
+        let Ok(result) = mapping.try_recv() else {
+            return;
+        };
+
+        if result.is_ok() {
+            let timestamps = {
+                let view = self.readback_buffer.slice(..).get_mapped_range();
+                bytemuck::pod_read_unaligned::<[u64; 2]>(&view)
+            };
+
+            let ticks = timestamps[1].saturating_sub(timestamps[0]);
+            self.last_time = Some(ticks as f32 * self.period / 1_000_000.0);
+
+            self.readback_buffer.unmap();
+        }
+
+        self.mapping = None;
+    }
+}
@@ -0,0 +1,349 @@
+use glam::*;
+use wgpu::{util::*, *};
+
+use crate::{camera::Camera, mesh::Mesh, viewport::Viewport};
+
+/// The look of the reference grid drawn on the ground plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridParams {
+    /// The distance between two neighbouring lines of the grid. Always positive.
+    pub spacing: f32,
+    /// The color of the minor lines of the grid, its alpha blending them over the scene.
+    pub color: Vec4,
+    /// The color of every `Mesh::GRID_MAJOR_INTERVAL`th line of the grid.
+    pub major_color: Vec4,
+    /// The distance from the camera at which the grid has completely faded out. Always positive.
+    pub fade_distance: f32,
+}
+
+/// The `GridParams`, laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct GridParamsUniform {
+    /// The color of the minor lines.
+    color: [f32; 4],
+    /// The color of the major lines.
+    major_color: [f32; 4],
+    /// The distance between two lines.
+    spacing: f32,
+    /// The number of minor lines between each major line.
+    major_interval: f32,
+    /// The distance at which the grid has faded out.
+    fade_distance: f32,
+    /// Pads the uniform to a multiple of 16 bytes.
+    _padding: f32,
+}
+
+/// The camera of a viewport, laid out as the grid's shader uses it.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct GridCameraUniform {
+    /// The inverse of the camera's view-projection matrix.
+    inverse_view_proj: [[f32; 4]; 4],
+    /// The camera's view-projection matrix.
+    view_proj: [[f32; 4]; 4],
+    /// The position of the camera.
+    eye: [f32; 3],
+    /// The width of the lines in physical pixels.
+    line_width: f32,
+    /// The axis of the camera's coordinate system pointing up, which the plane faces.
+    up: [f32; 3],
+    /// Pads the uniform to a multiple of 16 bytes.
+    _padding: f32,
+}
+
+impl GridParams {
+    /// The parameters of the grid drawn by default.
+    pub const DEFAULT: Self = Self {
+        spacing: 1.0,
+        color: Vec4::from_array(Mesh::GRID_MINOR_COLOR),
+        major_color: Vec4::from_array(Mesh::GRID_MAJOR_COLOR),
+        fade_distance: 100.0,
+    };
+    /// The smallest spacing and fade distance the grid is drawn with.
+    pub const MIN_DISTANCE: f32 = 0.001;
+
+    /// Returns the parameters with the spacing and fade distance clamped to `Self::MIN_DISTANCE`
+    /// from below.
+    pub fn clamped(self) -> Self {
+        Self {
+            spacing: self.spacing.max(Self::MIN_DISTANCE),
+            fade_distance: self.fade_distance.max(Self::MIN_DISTANCE),
+            ..self
+        }
+    }
+
+    /// Returns the parameters as they're laid out on the gpu.
+    fn uniform(&self) -> GridParamsUniform {
+        GridParamsUniform {
+            color: self.color.to_array(),
+            major_color: self.major_color.to_array(),
+            spacing: self.spacing,
+            major_interval: Mesh::GRID_MAJOR_INTERVAL as f32,
+            fade_distance: self.fade_distance,
+            _padding: 0.0,
+        }
+    }
+}
+
+impl Default for GridParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// An infinite reference grid on the ground plane through the origin, the xz plane or the xy plane
+/// with `CoordinateSystem::ZUp` as the camera has it, drawn as a fullscreen triangle intersecting
+/// the ray of every pixel with the plane. The lines are antialiased with the screen space derivatives
+/// of the plane's coordinates and fade out with the distance from the camera, writing the depth
+/// of the plane so that the scene's geometry hides the grid where it's in front of it.
+#[derive(Debug)]
+pub struct Grid {
+    /// The uniform buffer of the cameras, one for every viewport at multiples of
+    /// `Camera::UNIFORM_STRIDE`.
+    camera_buffer: Buffer,
+    /// The uniform buffer of the `params`.
+    params_buffer: Buffer,
+    /// The look of the grid.
+    params: GridParams,
+    /// The layout of the `bind_group`, kept around to recreate the pipeline.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the camera and parameter buffers.
+    bind_group: BindGroup,
+    /// The shader module of the pipeline.
+    shader: ShaderModule,
+    /// Draws a fullscreen triangle blending the grid over the scene.
+    pipeline: RenderPipeline,
+}
+
+impl Grid {
+    /// Creates a grid drawn onto a target with the given formats, depth test and sample count. The cameras are
+    /// uploaded with `Grid::update_camera` before drawing.
+    pub fn new(
+        device: &Device,
+        params: GridParams,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> Self {
+        let params = params.clamped();
+
+        let camera_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Grid Camera Uniform Buffer"),
+            size: Camera::UNIFORM_STRIDE * Viewport::MAX_COUNT as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Grid Params Uniform Buffer"),
+            contents: bytemuck::bytes_of(&params.uniform()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let camera_size = std::mem::size_of::<GridCameraUniform>() as BufferAddress;
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Grid Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(camera_size),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Grid Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &camera_buffer,
+                        offset: 0,
+                        size: BufferSize::new(camera_size),
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/grid.wgsl").into()),
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &shader,
+            &bind_group_layout,
+            format,
+            depth_format,
+            depth_compare,
+            sample_count,
+        );
+
+        Self {
+            camera_buffer,
+            params_buffer,
+            params,
+            bind_group_layout,
+            bind_group,
+            shader,
+            pipeline,
+        }
+    }
+
+    /// Creates the pipeline drawing the grid onto a target with the given formats, depth test and
+    /// sample count.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Grid Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    write_mask: ColorWrites::ALL,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            // the grid is tested against the depth of the plane, but mostly transparent, so it
+            // doesn't hide what's drawn behind it afterwards
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Recreates the pipeline for a target with the given formats, depth test and sample count.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.shader,
+            &self.bind_group_layout,
+            format,
+            depth_format,
+            depth_compare,
+            sample_count,
+        );
+    }
+
+    /// Returns the look of the grid.
+    pub fn params(&self) -> GridParams {
+        self.params
+    }
+
+    /// Sets the look of the grid, clamped with `GridParams::clamped`.
+    pub fn set_params(&mut self, queue: &Queue, params: GridParams) {
+        self.params = params.clamped();
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&self.params.uniform()),
+        );
+    }
+
+    /// Records updating the uniform buffer of the `index`th viewport with its camera and the width
+    /// of the lines in physical pixels through the staging belt.
+    pub fn update_camera(
+        &self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        index: usize,
+        camera: &Camera,
+        line_width: f32,
+    ) {
+        let view_proj = camera.view_projection();
+
+        let uniform = GridCameraUniform {
+            inverse_view_proj: view_proj.inverse().to_cols_array_2d(),
+            view_proj: view_proj.to_cols_array_2d(),
+            eye: camera.eye.to_array(),
+            line_width,
+            up: camera.coordinate_system().up().to_array(),
+            _padding: 0.0,
+        };
+
+        belt.write_buffer(
+            encoder,
+            &self.camera_buffer,
+            Camera::UNIFORM_STRIDE * index as BufferAddress,
+            BufferSize::new(std::mem::size_of::<GridCameraUniform>() as BufferAddress).unwrap(),
+            device,
+        )
+        .copy_from_slice(bytemuck::bytes_of(&uniform));
+    }
+
+    /// Records drawing the grid as seen by the camera of the `index`th viewport into the render
+    /// pass.
+    pub fn draw(&self, render_pass: &mut RenderPass, index: usize) {
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[offset]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use anyhow::Result;
+use log::error;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches a single file on disk, reporting whenever it has been modified.
+#[derive(Debug)]
+pub struct FileWatcher {
+    /// The path of the watched file.
+    path: PathBuf,
+    /// The underlying watcher, which stops watching once dropped.
+    _watcher: RecommendedWatcher,
+    /// The events emitted by the watcher.
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl FileWatcher {
+    /// Starts watching the file at `path`. The parent directory is watched rather than the file
+    /// itself, since many editors save by replacing the file, which would end the watch.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let (sender, events) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Returns whether the file has been written to since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+
+        for event in self.events.try_iter() {
+            match event {
+                Ok(Event {
+                    kind: EventKind::Create(_) | EventKind::Modify(_),
+                    paths,
+                    ..
+                }) => {
+                    changed |= paths
+                        .iter()
+                        .any(|path| path.file_name() == self.path.file_name());
+                }
+                Ok(_) => {}
+                Err(error) => error!("failed to watch {}: {error}", self.path.display()),
+            }
+        }
+
+        changed
+    }
+}
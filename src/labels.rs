@@ -0,0 +1,575 @@
+use glam::*;
+use wgpu::{util::StagingBelt, *};
+
+use crate::camera::Camera;
+
+/// The width and height of a glyph of the label font, in pixels.
+const GLYPH_SIZE: [u32; 2] = [5, 7];
+
+/// The horizontal and vertical distance from one glyph to the next, in pixels of the font.
+const GLYPH_ADVANCE: [u32; 2] = [6, 9];
+
+/// The first character of the label font, the font covering the printable ascii characters from
+/// it up to `~`.
+const FIRST_CHARACTER: char = ' ';
+
+/// The pixels of the printable ascii characters, seven rows of five pixels each from the top, with
+/// the most significant bit of a row being its leftmost pixel.
+#[rustfmt::skip]
+const GLYPHS: [[u8; 7]; 95] = [
+    // ' '
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+    // '!'
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+    // '"'
+    [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000],
+    // '#'
+    [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+    // '$'
+    [0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100],
+    // '%'
+    [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+    // '&'
+    [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101],
+    // '\''
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000],
+    // '('
+    [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+    // ')'
+    [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+    // '*'
+    [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000],
+    // '+'
+    [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+    // ','
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+    // '-'
+    [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+    // '.'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+    // '/'
+    [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+    // '0'
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    // '1'
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // '2'
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    // '3'
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    // '4'
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    // '5'
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    // '6'
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    // '7'
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    // '8'
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    // '9'
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+    // ':'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+    // ';'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000],
+    // '<'
+    [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+    // '='
+    [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+    // '>'
+    [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+    // '?'
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    // '@'
+    [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110],
+    // 'A'
+    [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+    // 'B'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+    // 'C'
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+    // 'D'
+    [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+    // 'E'
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+    // 'F'
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+    // 'G'
+    [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+    // 'H'
+    [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+    // 'I'
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // 'J'
+    [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+    // 'K'
+    [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+    // 'L'
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+    // 'M'
+    [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+    // 'N'
+    [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+    // 'O'
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+    // 'P'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+    // 'Q'
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+    // 'R'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+    // 'S'
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+    // 'T'
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+    // 'U'
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+    // 'V'
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+    // 'W'
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+    // 'X'
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+    // 'Y'
+    [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+    // 'Z'
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+    // '['
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110],
+    // '\\'
+    [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000],
+    // ']'
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110],
+    // '^'
+    [0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000],
+    // '_'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+    // '`'
+    [0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000],
+    // 'a'
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+    // 'b'
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110],
+    // 'c'
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110],
+    // 'd'
+    [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111],
+    // 'e'
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+    // 'f'
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000],
+    // 'g'
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    // 'h'
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+    // 'i'
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // 'j'
+    [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100],
+    // 'k'
+    [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010],
+    // 'l'
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    // 'm'
+    [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001],
+    // 'n'
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+    // 'o'
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110],
+    // 'p'
+    [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000],
+    // 'q'
+    [0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001],
+    // 'r'
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000],
+    // 's'
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110],
+    // 't'
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110],
+    // 'u'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101],
+    // 'v'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+    // 'w'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010],
+    // 'x'
+    [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001],
+    // 'y'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    // 'z'
+    [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111],
+    // '{'
+    [0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010],
+    // '|'
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+    // '}'
+    [0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000],
+    // '~'
+    [0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000],
+];
+
+/// A glyph of a label, drawn as an instance of a rectangle facing the screen.
+#[derive(Debug, Clone, Copy, Default, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct LabelGlyph {
+    /// The position in world space the label is anchored at.
+    position: [f32; 3],
+    /// The index of the glyph in the font.
+    glyph: u32,
+    /// The offset of the glyph's top left corner from the anchor on screen, in physical pixels
+    /// with y pointing down.
+    offset: [f32; 2],
+    /// The size of a pixel of the font, in physical pixels.
+    scale: f32,
+    /// The straight rgba color of the glyph.
+    color: [f32; 4],
+}
+
+impl LabelGlyph {
+    /// The attributes of a glyph, read per instance.
+    const ATTRIBS: [VertexAttribute; 5] = vertex_attr_array![
+        0 => Float32x3,
+        1 => Uint32,
+        2 => Float32x2,
+        3 => Float32,
+        4 => Float32x4,
+    ];
+}
+
+/// Text drawn over the scene at positions in the world, always facing the screen at the same size
+/// however far away it is. The labels are queued with `Labels::push` and drawn in a built-in pixel
+/// font covering the printable ascii characters, any other character being drawn as `?`. Labels with
+/// their anchor behind the camera aren't drawn.
+#[derive(Debug)]
+pub struct Labels {
+    /// The glyphs of the labels queued for the next frame.
+    glyphs: Vec<LabelGlyph>,
+    /// The vertex buffer of the uploaded `glyphs`, grown whenever they don't fit.
+    instance_buffer: Buffer,
+    /// The number of glyphs the `instance_buffer` has room for.
+    capacity: u32,
+    /// The number of glyphs uploaded to the `instance_buffer`.
+    instance_count: u32,
+    /// The bind group of the texture holding the pixels of every glyph.
+    atlas_bind_group: BindGroup,
+    /// The shader module of the pipeline.
+    shader: ShaderModule,
+    /// The layout of the pipeline, binding the cameras and the atlas.
+    layout: PipelineLayout,
+    /// Draws the glyphs as instanced rectangles.
+    pipeline: RenderPipeline,
+}
+
+impl Labels {
+    /// The color of labels drawn without one.
+    pub const DEFAULT_COLOR: Vec4 = Vec4::ONE;
+    /// The size of a pixel of the font in logical pixels.
+    pub const SCALE: f32 = 2.0;
+    /// The color of the shadow drawn below and to the right of every glyph, keeping labels readable
+    /// over parts of the scene of the same color.
+    const SHADOW_COLOR: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.75);
+    /// The number of glyphs the instance buffer of new labels has room for.
+    const INITIAL_CAPACITY: u32 = 256;
+
+    /// Creates the font atlas and the pipeline drawing labels onto a target with the given formats
+    /// and sample count, binding the cameras through the layout of the main pipelines' camera bind
+    /// group.
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        camera_bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let [width, height] = [GLYPH_SIZE[0] * GLYPHS.len() as u32, GLYPH_SIZE[1]];
+
+        let atlas = device.create_texture(&TextureDescriptor {
+            label: Some("Label Atlas Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // the glyphs are laid out next to each other, so a row of the atlas is a row of every glyph
+        let texels: Vec<u8> = (0..GLYPH_SIZE[1] as usize)
+            .flat_map(|row| {
+                GLYPHS.iter().flat_map(move |glyph| {
+                    (0..GLYPH_SIZE[0]).map(move |column| {
+                        let bit = GLYPH_SIZE[0] - 1 - column;
+                        if glyph[row] >> bit & 1 == 1 {
+                            u8::MAX
+                        } else {
+                            0
+                        }
+                    })
+                })
+            })
+            .collect();
+
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &atlas,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &texels,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let atlas_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Label Atlas Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let atlas_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Label Atlas Bind Group"),
+            layout: &atlas_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(
+                    &atlas.create_view(&TextureViewDescriptor::default()),
+                ),
+            }],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Label Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/labels.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Label Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline =
+            Self::create_pipeline(device, &shader, &layout, format, depth_format, sample_count);
+
+        Self {
+            glyphs: Vec::new(),
+            instance_buffer: Self::create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            instance_count: 0,
+            atlas_bind_group,
+            shader,
+            layout,
+            pipeline,
+        }
+    }
+
+    /// Creates the vertex buffer of the glyphs' instances, with room for `capacity` of them.
+    fn create_instance_buffer(device: &Device, capacity: u32) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("Label Instance Buffer"),
+            size: capacity as BufferAddress * std::mem::size_of::<LabelGlyph>() as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Creates the pipeline drawing the labels onto a target with the given formats and sample
+    /// count.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        layout: &PipelineLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Label Pipeline"),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<LabelGlyph>() as BufferAddress,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: &LabelGlyph::ATTRIBS,
+                }],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    write_mask: ColorWrites::ALL,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            // labels are drawn over everything, like overlays
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Recreates the pipeline for a target with the given formats and sample count.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.shader,
+            &self.layout,
+            format,
+            depth_format,
+            sample_count,
+        );
+    }
+
+    /// Queues a label for the next frame, centered on where `position` is on screen. Every line of
+    /// the text is centered on its own, and every pixel of the font is `scale` physical pixels
+    /// wide.
+    pub fn push(&mut self, position: Vec3, text: &str, color: Vec4, scale: f32) {
+        let lines: Vec<&str> = text.lines().collect();
+        let height = (lines.len() as u32 * GLYPH_ADVANCE[1])
+            .saturating_sub(GLYPH_ADVANCE[1] - GLYPH_SIZE[1]) as f32;
+
+        for (row, line) in lines.into_iter().enumerate() {
+            let width = (line.chars().count() as u32 * GLYPH_ADVANCE[0])
+                .saturating_sub(GLYPH_ADVANCE[0] - GLYPH_SIZE[0]) as f32;
+
+            for (column, character) in line.chars().enumerate() {
+                let glyph = Self::glyph(character);
+
+                // spaces are blank, so they only advance
+                if GLYPHS[glyph as usize] == [0; 7] {
+                    continue;
+                }
+
+                let offset = Vec2::new(
+                    column as f32 * GLYPH_ADVANCE[0] as f32 - width / 2.0,
+                    row as f32 * GLYPH_ADVANCE[1] as f32 - height / 2.0,
+                ) * scale;
+
+                for (offset, color) in [
+                    (
+                        offset + scale,
+                        Self::SHADOW_COLOR * Vec4::new(1.0, 1.0, 1.0, color.w),
+                    ),
+                    (offset, color),
+                ] {
+                    self.glyphs.push(LabelGlyph {
+                        position: position.to_array(),
+                        glyph,
+                        offset: offset.round().to_array(),
+                        scale,
+                        color: color.to_array(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the index of the glyph of a character in the font, that of `?` for characters it
+    /// doesn't cover.
+    fn glyph(character: char) -> u32 {
+        let index = (character as u32).wrapping_sub(FIRST_CHARACTER as u32);
+
+        if (index as usize) < GLYPHS.len() {
+            index
+        } else {
+            '?' as u32 - FIRST_CHARACTER as u32
+        }
+    }
+
+    /// Returns whether no labels are queued for the next frame.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Removes the queued labels, without affecting the ones already uploaded.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+    }
+
+    /// Records uploading the queued labels through the staging belt ahead of drawing them, growing
+    /// the instance buffer if they don't fit.
+    pub fn upload(
+        &mut self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        let count = self.glyphs.len() as u32;
+
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.capacity);
+        }
+
+        self.instance_count = count;
+
+        let bytes: &[u8] = bytemuck::cast_slice(&self.glyphs);
+
+        if let Some(size) = BufferSize::new(bytes.len() as BufferAddress) {
+            belt.write_buffer(encoder, &self.instance_buffer, 0, size, device)
+                .copy_from_slice(bytes);
+        }
+    }
+
+    /// Records drawing the uploaded labels as seen by the camera of the `index`th viewport into
+    /// the render pass, binding the cameras through the main pipelines' camera bind group.
+    pub fn draw(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup, index: usize) {
+        if self.instance_count == 0 {
+            return;
+        }
+
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[offset]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
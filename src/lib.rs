@@ -1,18 +1,159 @@
-mod app;
-mod camera;
-mod mesh;
-mod renderer;
+pub mod app;
+pub mod background;
+pub mod benchmark;
+pub mod bounds;
+pub mod camera;
+pub mod camera_uniform;
+pub mod capture;
+pub mod command;
+pub mod compute;
+pub mod config;
+#[cfg(feature = "debug-ui")]
+pub mod debug_ui;
+pub mod depth_debug;
+pub mod frame_stats;
+pub mod frame_timings;
+pub mod fxaa;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(feature = "golden")]
+pub mod golden;
+pub mod gpu_report;
+pub mod gpu_timer;
+pub mod grid;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+pub mod labels;
+pub mod light;
+pub mod lines;
+pub mod loader;
+pub mod material;
+pub mod mesh;
+pub mod mesh_arena;
+pub mod mesh_builder;
+pub mod obj;
+pub mod particles;
+pub mod picking;
+pub mod post;
+pub mod render_stats;
+pub mod render_target;
+pub mod renderer;
+pub mod scene;
+pub mod shadow;
+pub mod skin;
+pub mod skybox;
+pub mod texture;
+pub mod tonemap;
+pub mod touch;
+pub mod transform;
+pub mod viewport;
 
+use std::{cell::RefCell, rc::Rc};
+
+use anyhow::Result;
+use env_logger::Env;
 use winit::event_loop::*;
 
-use crate::app::AppLoadState;
+use crate::{
+    app::{AppLoadState, Hooks},
+    camera::Camera,
+    command::CommandReceiver,
+    config::{AppConfig, RedrawMode},
+    frame_stats::FrameStats,
+    renderer::Renderer,
+};
+
+/// Entry point into the application, sets up an event loop and runs the app with the default
+/// configuration. Returns once the windows are closed, or with the error which stopped the app.
+pub fn run() -> Result<()> {
+    run_with(AppConfig::default())
+}
+
+/// Sets up an event loop and runs the app with the given configuration.
+pub fn run_with(config: AppConfig) -> Result<()> {
+    run_app(config, Hooks::default())
+}
+
+/// Sets up an event loop and runs the app with the given configuration, calling `on_init` with the
+/// renderer of the first window once it has been created so that the scene can be set up before
+/// the first frame.
+pub fn run_with_init(
+    config: AppConfig,
+    on_init: impl FnOnce(&mut Renderer) + 'static,
+) -> Result<()> {
+    let mut on_init = Some(on_init);
+
+    let hooks = Hooks {
+        on_init: Some(Box::new(move |index, renderer| {
+            if let Some(on_init) = on_init.take().filter(|_| index == 0) {
+                on_init(renderer);
+            }
+        })),
+        on_update: None,
+        commands: None,
+    };
+
+    run_app(config, hooks)
+}
+
+/// Sets up an event loop and runs the app with the given configuration, calling `on_init` with the
+/// index and renderer of every window once it has been created, and `on_update` for every window
+/// every frame after the app's own update, with the index of the window, its renderer and camera
+/// and the times of its recent frames. This is where the application's own logic goes, moving
+/// meshes around through `Renderer::scene_mut` or steering the camera, and where the windows of
+/// `AppConfig::window_count` can be given different scenes.
+pub fn run_with_hooks(
+    config: AppConfig,
+    on_init: impl FnMut(usize, &mut Renderer) + 'static,
+    on_update: impl FnMut(usize, &mut Renderer, &mut Camera, &FrameStats) + 'static,
+) -> Result<()> {
+    let hooks = Hooks {
+        on_init: Some(Box::new(on_init)),
+        on_update: Some(Rc::new(RefCell::new(on_update))),
+        commands: None,
+    };
+
+    run_app(config, hooks)
+}
+
+/// Sets up an event loop and runs the app with the given configuration, applying the commands
+/// sent through the other end of `commands` (see `command::command_channel`) to the first window
+/// at the start of every frame. This turns the app into a viewer other threads of a larger
+/// application can control, loading meshes or moving the camera.
+pub fn run_with_commands(config: AppConfig, commands: CommandReceiver) -> Result<()> {
+    let hooks = Hooks {
+        commands: Some(commands),
+        ..Hooks::default()
+    };
+
+    run_app(config, hooks)
+}
+
+/// Sets up an event loop and runs the app until its last window is closed, or creating or
+/// rendering one of them fails.
+fn run_app(config: AppConfig, hooks: Hooks) -> Result<()> {
+    // logs of this crate are shown by default, `RUST_LOG` overrides the filter. Initializing fails
+    // if the caller already installed a logger of their own, which is then used instead
+    let _ =
+        env_logger::Builder::from_env(Env::default().default_filter_or("warn,d3_template=info"))
+            .try_init();
+
+    let event_loop = EventLoop::new()?;
+
+    if let Some(commands) = &hooks.commands {
+        commands.set_proxy(event_loop.create_proxy());
+    }
 
-/// Entry point into the application, sets up an event loop and runs the app.
-pub fn run() {
-    let event_loop = EventLoop::new().unwrap();
+    event_loop.set_control_flow(match config.redraw_mode {
+        RedrawMode::Continuous => ControlFlow::Poll,
+        RedrawMode::OnDemand => ControlFlow::Wait,
+    });
 
-    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut app = AppLoadState::Unloaded { config, hooks };
+    event_loop.run_app(&mut app)?;
 
-    let mut app = AppLoadState::Unloaded;
-    event_loop.run_app(&mut app).unwrap();
+    match app {
+        AppLoadState::Failed(error) => Err(error),
+        _ => Ok(()),
+    }
 }
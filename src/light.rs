@@ -0,0 +1,142 @@
+use bytemuck::Zeroable;
+use glam::*;
+use wgpu::{util::*, *};
+
+/// A light shining equally in every direction from a point, fading out towards its radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    /// The position of the light in world space.
+    pub position: Vec3,
+    /// The linear rgb color of the light, which may exceed 1 for brighter lights.
+    pub color: Vec3,
+    /// The distance at which the light's contribution has faded out completely.
+    pub radius: f32,
+}
+
+/// A light shining from infinitely far away in a single direction, like the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// The direction the light shines towards, in world space.
+    pub direction: Vec3,
+    /// The linear rgb color of the light, which may exceed 1 for brighter lights.
+    pub color: Vec3,
+}
+
+/// A point light laid out as it is uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct PointLightRaw {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+/// The point lights of the scene laid out as they are uploaded to the rendering device, only the
+/// first `count` of the `lights` being used.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct PointLightsUniform {
+    count: u32,
+    _padding: [u32; 3],
+    lights: [PointLightRaw; PointLights::MAX_COUNT],
+}
+
+/// The gpu-side point lights of the scene, bound to group 2 while meshes are drawn.
+#[derive(Debug)]
+pub struct PointLights {
+    /// The uniform buffer of the `PointLightsUniform`.
+    buffer: Buffer,
+    /// The bind group of the uniform buffer.
+    bind_group: BindGroup,
+    /// The lights currently uploaded, at most `PointLights::MAX_COUNT`.
+    lights: Vec<PointLight>,
+}
+
+impl From<PointLight> for PointLightRaw {
+    fn from(light: PointLight) -> Self {
+        Self {
+            position: light.position.into(),
+            radius: light.radius,
+            color: light.color.into(),
+            _padding: 0.0,
+        }
+    }
+}
+
+impl PointLights {
+    /// The maximum number of point lights lighting the scene at once, matching the size of the
+    /// array in the shader.
+    pub const MAX_COUNT: usize = 32;
+
+    /// The layout of the lights' bind group. Layouts created from the same descriptor are
+    /// interchangeable, so the pipelines can create their own.
+    pub const BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+        label: Some("Point Lights Bind Group Layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    };
+
+    /// Creates the buffer of the point lights, without any lights in it.
+    pub fn new(device: &Device) -> Self {
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Point Lights Uniform Buffer"),
+            contents: bytemuck::bytes_of(&PointLightsUniform::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Point Lights Bind Group"),
+            layout: &device.create_bind_group_layout(&Self::BIND_GROUP_LAYOUT),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            buffer,
+            bind_group,
+            lights: Vec::new(),
+        }
+    }
+
+    /// Returns the number of lights currently uploaded.
+    pub fn count(&self) -> usize {
+        self.lights.len()
+    }
+
+    /// Returns the lights currently uploaded.
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    /// Uploads the given lights, replacing the previous ones. Lights past `PointLights::MAX_COUNT`
+    /// are ignored.
+    pub fn set(&mut self, queue: &Queue, lights: &[PointLight]) {
+        let lights = &lights[..lights.len().min(Self::MAX_COUNT)];
+
+        let mut uniform = PointLightsUniform::zeroed();
+        uniform.count = lights.len() as u32;
+
+        for (raw, light) in uniform.lights.iter_mut().zip(lights) {
+            *raw = (*light).into();
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+        self.lights = lights.to_vec();
+    }
+
+    /// Returns the bind group of the lights.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+use wgpu::*;
+
+use crate::{
+    light::PointLights,
+    material::Material,
+    mesh::{InstanceRaw, Vertex},
+    renderer::Renderer,
+    shadow::ShadowMap,
+};
+
+/// A corner of the quad a line segment is expanded into, carrying both ends of the segment so
+/// that the vertex shader can offset it perpendicular to the segment on screen.
+#[derive(Debug, Clone, Copy, Default, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct LineVertex {
+    /// The position of the segment's start in local space.
+    pub start: [f32; 3],
+    /// The position of the segment's end in local space.
+    pub end: [f32; 3],
+    /// The straight rgba color of the end of the segment this corner is at.
+    pub color: [f32; 4],
+    /// Where the corner is on the quad: along the segment from 0 at the start to 1 at the end, and
+    /// across it to either side at -1 and 1.
+    pub corner: [f32; 2],
+}
+
+impl LineVertex {
+    /// The attributes of a corner, read per vertex.
+    const ATTRIBS: [VertexAttribute; 4] =
+        vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x4, 3 => Float32x2];
+
+    /// Returns the layout of a buffer of corners.
+    pub fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Expands the segments of a mesh into the corners of the two triangles of a quad each. Line lists
+/// and strips are expanded as they are, while triangles are expanded into their edges (every edge
+/// shared by two triangles only once), and points into nothing. Strip restarts are skipped.
+pub fn line_vertices(
+    vertices: &[Vertex],
+    indices: &[u32],
+    topology: PrimitiveTopology,
+) -> Vec<LineVertex> {
+    let valid = |indices: &[u32]| {
+        indices
+            .iter()
+            .all(|&index| (index as usize) < vertices.len())
+    };
+
+    let segments: Vec<(u32, u32)> = match topology {
+        PrimitiveTopology::LineList => indices
+            .chunks_exact(2)
+            .filter(|segment| valid(segment))
+            .map(|segment| (segment[0], segment[1]))
+            .collect(),
+        PrimitiveTopology::LineStrip => indices
+            .windows(2)
+            .filter(|segment| valid(segment))
+            .map(|segment| (segment[0], segment[1]))
+            .collect(),
+        PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip => {
+            let triangles: Vec<&[u32]> = if topology == PrimitiveTopology::TriangleList {
+                indices.chunks_exact(3).collect()
+            } else {
+                indices.windows(3).collect()
+            };
+
+            let mut edges = HashSet::new();
+
+            triangles
+                .into_iter()
+                .filter(|triangle| valid(triangle))
+                .flat_map(|triangle| {
+                    [
+                        (triangle[0], triangle[1]),
+                        (triangle[1], triangle[2]),
+                        (triangle[2], triangle[0]),
+                    ]
+                })
+                .filter(|&(a, b)| a != b && edges.insert((a.min(b), a.max(b))))
+                .collect()
+        }
+        PrimitiveTopology::PointList => Vec::new(),
+    };
+
+    segments
+        .into_iter()
+        .flat_map(|(start, end)| {
+            let [start, end] = [start, end].map(|index| &vertices[index as usize]);
+
+            let corner = |along: f32, across: f32| LineVertex {
+                start: start.pos,
+                end: end.pos,
+                color: if along == 0.0 { start.color } else { end.color },
+                corner: [along, across],
+            };
+
+            [
+                corner(0.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(0.0, -1.0),
+                corner(1.0, 1.0),
+                corner(0.0, 1.0),
+            ]
+        })
+        .collect()
+}
+
+/// Draws lines wider than the single pixel the rasterizer draws them with, by expanding every
+/// segment into a quad facing the screen. The quads are antialiased across their width and drawn
+/// unlit in the colors of their vertices, sharing the bind groups of the main pipelines so that
+/// switching between them doesn't rebind anything.
+#[derive(Debug)]
+pub struct LinePass {
+    /// The shader module of the pipelines.
+    shader: ShaderModule,
+    /// The layout of the pipelines, matching the main pipelines' bind groups.
+    layout: PipelineLayout,
+    /// The pipelines by whether they alpha blend and whether they're overlays, in that order.
+    pipelines: [[RenderPipeline; 2]; 2],
+}
+
+impl LinePass {
+    /// Creates the line pipelines drawing onto a target with the given format, depth test and
+    /// sample count, binding the cameras through the layout of the main pipelines' camera bind
+    /// group.
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> Self {
+        // the tint is read the same way as by the main shader
+        let tint = if device.features().contains(Features::PUSH_CONSTANTS) {
+            include_str!("../assets/shader/tint_push_constant.wgsl")
+        } else {
+            include_str!("../assets/shader/tint_uniform.wgsl")
+        };
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: ShaderSource::Wgsl(
+                (include_str!("../assets/shader/lines.wgsl").to_string() + tint).into(),
+            ),
+        });
+
+        let push_constant_ranges = if device.features().contains(Features::PUSH_CONSTANTS) {
+            &[PushConstantRange {
+                stages: ShaderStages::FRAGMENT,
+                range: 0..Renderer::PUSH_CONSTANT_SIZE,
+            }][..]
+        } else {
+            &[]
+        };
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Line Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                &device.create_bind_group_layout(&Material::BIND_GROUP_LAYOUT),
+                &device.create_bind_group_layout(&PointLights::BIND_GROUP_LAYOUT),
+                &device.create_bind_group_layout(&ShadowMap::BIND_GROUP_LAYOUT),
+            ],
+            push_constant_ranges,
+        });
+
+        let pipelines = Self::create_pipelines(
+            device,
+            &shader,
+            &layout,
+            format,
+            depth_compare,
+            sample_count,
+        );
+
+        Self {
+            shader,
+            layout,
+            pipelines,
+        }
+    }
+
+    /// Creates a pipeline for every combination of transparency and overlay.
+    fn create_pipelines(
+        device: &Device,
+        shader: &ShaderModule,
+        layout: &PipelineLayout,
+        format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> [[RenderPipeline; 2]; 2] {
+        [false, true].map(|transparent| {
+            [false, true].map(|overlay| {
+                device.create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("Line Pipeline"),
+                    layout: Some(layout),
+                    vertex: VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[LineVertex::desc(), InstanceRaw::desc()],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(FragmentState {
+                        module: shader,
+                        entry_point: "fs_main",
+                        // the antialiased edges are always blended, even for opaque lines
+                        targets: &[Some(ColorTargetState {
+                            format,
+                            write_mask: ColorWrites::ALL,
+                            blend: Some(BlendState::ALPHA_BLENDING),
+                        })],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    }),
+                    primitive: PrimitiveState::default(),
+                    multisample: MultisampleState {
+                        count: sample_count,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(DepthStencilState {
+                        format: Renderer::DEPTH_FORMAT,
+                        depth_write_enabled: !transparent && !overlay,
+                        depth_compare: if overlay {
+                            CompareFunction::Always
+                        } else {
+                            depth_compare
+                        },
+                        stencil: StencilState::default(),
+                        bias: DepthBiasState::default(),
+                    }),
+                    multiview: None,
+                    cache: None,
+                })
+            })
+        })
+    }
+
+    /// Recreates the pipelines for a target with the given format, depth test and sample count.
+    pub fn recreate_pipelines(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) {
+        self.pipelines = Self::create_pipelines(
+            device,
+            &self.shader,
+            &self.layout,
+            format,
+            depth_compare,
+            sample_count,
+        );
+    }
+
+    /// Returns the pipeline drawing lines with the given transparency, as an overlay or not.
+    pub fn pipeline(&self, transparent: bool, overlay: bool) -> &RenderPipeline {
+        &self.pipelines[transparent as usize][overlay as usize]
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use anyhow::{anyhow, Context, Result};
+use log::*;
+
+use crate::mesh::{Mesh, MeshData};
+
+/// A file whose meshes are being loaded on a worker thread, started with `Mesh::load_async`. The
+/// meshes are only parsed on the worker, as the buffers have to be created by the thread owning
+/// the device once `MeshLoad::poll` returns them.
+#[derive(Debug)]
+pub struct MeshLoad {
+    /// The path of the file being loaded.
+    path: PathBuf,
+    /// Receives the result of the load once the worker is done, or `None` once it was received.
+    receiver: Option<Receiver<Result<Vec<MeshData>>>>,
+}
+
+impl MeshLoad {
+    /// Spawns a worker thread loading the file at `path` with `Mesh::load`.
+    pub(crate) fn spawn(path: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let worker_path = path.clone();
+
+        let spawned = thread::Builder::new()
+            .name(format!("load {}", path.display()))
+            .spawn(move || {
+                let result = Mesh::load(&worker_path)
+                    .with_context(|| format!("failed to load {}", worker_path.display()));
+
+                // the handle may have been dropped while loading, in which case nobody is waiting
+                let _ = sender.send(result);
+            });
+
+        // a worker which can't be spawned drops its sender right away, which `poll` reports
+        if let Err(error) = spawned {
+            error!(
+                "failed to spawn a thread loading {}: {error}",
+                path.display()
+            );
+        }
+
+        Self {
+            path,
+            receiver: Some(receiver),
+        }
+    }
+
+    /// Returns the path of the file being loaded.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the result of the load has already been returned by `MeshLoad::poll`.
+    pub fn is_done(&self) -> bool {
+        self.receiver.is_none()
+    }
+
+    /// Returns the loaded meshes (or why they couldn't be loaded) once the worker is done, without
+    /// blocking. The result is only returned once, `None` being returned before and afterwards.
+    pub fn poll(&mut self) -> Option<Result<Vec<MeshData>>> {
+        let result = match self.receiver.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(anyhow!(
+                "the thread loading {} stopped without a result",
+                self.path.display()
+            )),
+        };
+
+        self.receiver = None;
+
+        Some(result)
+    }
+}
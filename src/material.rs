@@ -0,0 +1,594 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use glam::*;
+use log::*;
+use wgpu::{util::*, *};
+
+use crate::texture::{Texture, TextureOptions};
+
+/// The material properties of a mesh, laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct MaterialUniform {
+    /// The straight rgba color the vertex colors (and the base color texture) are multiplied with.
+    pub base_color: [f32; 4],
+    /// The color the shaded fragments are multiplied with, on devices without push constants
+    /// which otherwise carry the tint of every draw.
+    pub tint: [f32; 4],
+    /// The linear rgb color the emissive texture is multiplied with, added to the lit color.
+    pub emissive: [f32; 3],
+    /// How far the normal texture bends the normals sideways, `0` leaving them as they are.
+    pub normal_scale: f32,
+    /// A bit for every `TextureSlot` (at its index) which has a texture of its own, the others
+    /// being bound to a placeholder the shader doesn't sample.
+    pub textures: u32,
+    /// Pads the uniform to the alignment of its vectors.
+    pub _padding: [u32; 3],
+}
+
+/// The textures a material may have, each bound to a texture binding of the material's bind group
+/// followed by a sampler binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+    /// The srgb color the base color is multiplied with.
+    BaseColor,
+    /// The tangent-space normals bending the normals of the surface, with red pointing along the
+    /// texture coordinates' u and green up the texture (towards decreasing v), as in glTF.
+    Normal,
+    /// The srgb color the emissive color is multiplied with.
+    Emissive,
+}
+
+impl TextureSlot {
+    /// The number of slots.
+    pub const COUNT: usize = 3;
+    /// Every slot, in the order of their bindings.
+    pub const ALL: [Self; Self::COUNT] = [Self::BaseColor, Self::Normal, Self::Emissive];
+
+    /// Returns the binding of the slot's texture in the material's bind group. Its sampler is
+    /// bound right after it.
+    pub const fn binding(self) -> u32 {
+        2 + 2 * self as u32
+    }
+
+    /// Returns whether the slot holds srgb encoded colors rather than data.
+    pub fn srgb(self) -> bool {
+        self != Self::Normal
+    }
+
+    /// Returns the bit of the slot in `MaterialUniform::textures`.
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// The factors of a material, which the vertex colors and textures are multiplied with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialFactors {
+    /// The straight rgba color the vertex colors and the base color texture are multiplied with.
+    pub base_color: Vec4,
+    /// The linear rgb color the emissive texture is multiplied with, black turning emission off.
+    pub emissive: Vec3,
+    /// How far the normal texture bends the normals sideways.
+    pub normal_scale: f32,
+}
+
+impl Default for MaterialFactors {
+    fn default() -> Self {
+        Self {
+            base_color: Material::DEFAULT_COLOR,
+            emissive: Vec3::ZERO,
+            normal_scale: 1.0,
+        }
+    }
+}
+
+/// The gpu-side material of a mesh, bound to group 1 while the mesh is drawn. Also holds the
+/// palette of joint matrices skinned meshes are deformed by, which is a single identity matrix
+/// for every other mesh.
+///
+/// Cloning a material is cheap, sharing its buffers, bind group and textures, so meshes drawn
+/// with the same textures can be given clones of a single material (see `MaterialBuilder`).
+/// Changing the color of a shared material changes it for every mesh sharing it.
+#[derive(Debug, Clone)]
+pub struct Material {
+    /// The buffers and bind group shared by every clone of the material.
+    shared: Arc<SharedMaterial>,
+}
+
+/// The part of a `Material` shared by its clones.
+#[derive(Debug)]
+struct SharedMaterial {
+    /// The uniform buffer of the `MaterialUniform`.
+    buffer: Buffer,
+    /// The storage buffer of the joint matrices, as an array of column-major `mat4x4<f32>`s.
+    joint_buffer: Buffer,
+    /// The number of matrices in the `joint_buffer`.
+    joint_count: usize,
+    /// The bind group of the uniform and joint buffers and of the textures.
+    bind_group: BindGroup,
+    /// The texture of every slot, `None` for the slots bound to the placeholder.
+    textures: [Option<Arc<Texture>>; TextureSlot::COUNT],
+    /// A cpu-side copy of the factors, which can be updated through a shared reference like the
+    /// buffer itself.
+    factors: Mutex<MaterialFactors>,
+    /// A cpu-side copy of the tint.
+    tint: Mutex<Vec4>,
+}
+
+/// Returns the layout entry of a texture of the material's bind group.
+const fn texture_entry(slot: TextureSlot) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: slot.binding(),
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+/// Returns the layout entry of the sampler of a texture of the material's bind group.
+const fn sampler_entry(slot: TextureSlot) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding: slot.binding() + 1,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+impl Material {
+    /// The default base color of a material, leaving the vertex colors unchanged.
+    pub const DEFAULT_COLOR: Vec4 = Vec4::ONE;
+    /// The default tint of a material, leaving the shaded colors unchanged.
+    pub const DEFAULT_TINT: Vec4 = Vec4::ONE;
+
+    /// The layout of the material's bind group. Layouts created from the same descriptor are
+    /// interchangeable, so every material can create its own.
+    pub const BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+        label: Some("Material Bind Group Layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture_entry(TextureSlot::BaseColor),
+            sampler_entry(TextureSlot::BaseColor),
+            texture_entry(TextureSlot::Normal),
+            sampler_entry(TextureSlot::Normal),
+            texture_entry(TextureSlot::Emissive),
+            sampler_entry(TextureSlot::Emissive),
+        ],
+    };
+
+    /// Creates a material with the given base color and no textures.
+    pub fn new(device: &Device, base_color: Vec4) -> Self {
+        Self::create(
+            device,
+            MaterialFactors {
+                base_color,
+                ..Default::default()
+            },
+            Self::DEFAULT_TINT,
+            &[Mat4::IDENTITY],
+            Default::default(),
+        )
+    }
+
+    /// Returns a builder of a material with textures.
+    pub fn builder() -> MaterialBuilder {
+        MaterialBuilder::default()
+    }
+
+    /// Creates a material with the given factors, tint, joint matrices and textures.
+    fn create(
+        device: &Device,
+        factors: MaterialFactors,
+        tint: Vec4,
+        joints: &[Mat4],
+        textures: [Option<Arc<Texture>>; TextureSlot::COUNT],
+    ) -> Self {
+        // bindings can't be empty
+        let joints = if joints.is_empty() {
+            &[Mat4::IDENTITY]
+        } else {
+            joints
+        };
+
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Material Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Self::uniform(factors, tint, &textures)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let joint_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Material Joint Buffer"),
+            contents: bytemuck::cast_slice(
+                &joints.iter().map(Mat4::to_cols_array).collect::<Vec<_>>(),
+            ),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        // the slots without a texture are bound to a blank texel, which the shader knows not to
+        // sample from the uniform's bits
+        let placeholder = device
+            .create_texture(&TextureDescriptor {
+                label: Some("Material Placeholder Texture"),
+                size: Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: Texture::FORMAT,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default());
+        let placeholder_sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Material Placeholder Sampler"),
+            ..Default::default()
+        });
+
+        let mut entries = vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: joint_buffer.as_entire_binding(),
+            },
+        ];
+
+        for (slot, texture) in TextureSlot::ALL.into_iter().zip(&textures) {
+            let (view, sampler) = match texture {
+                Some(texture) => (texture.view(), texture.sampler()),
+                None => (&placeholder, &placeholder_sampler),
+            };
+
+            entries.push(BindGroupEntry {
+                binding: slot.binding(),
+                resource: BindingResource::TextureView(view),
+            });
+            entries.push(BindGroupEntry {
+                binding: slot.binding() + 1,
+                resource: BindingResource::Sampler(sampler),
+            });
+        }
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Material Bind Group"),
+            layout: &device.create_bind_group_layout(&Self::BIND_GROUP_LAYOUT),
+            entries: &entries,
+        });
+
+        Self {
+            shared: Arc::new(SharedMaterial {
+                buffer,
+                joint_buffer,
+                joint_count: joints.len(),
+                bind_group,
+                textures,
+                factors: Mutex::new(factors),
+                tint: Mutex::new(tint),
+            }),
+        }
+    }
+
+    /// Returns the uniform of a material with the given factors, tint and textures.
+    fn uniform(
+        factors: MaterialFactors,
+        tint: Vec4,
+        textures: &[Option<Arc<Texture>>; TextureSlot::COUNT],
+    ) -> MaterialUniform {
+        MaterialUniform {
+            base_color: factors.base_color.into(),
+            tint: tint.into(),
+            emissive: factors.emissive.into(),
+            normal_scale: factors.normal_scale,
+            textures: TextureSlot::ALL
+                .into_iter()
+                .zip(textures)
+                .filter(|(_, texture)| texture.is_some())
+                .fold(0, |bits, (slot, _)| bits | slot.bit()),
+            _padding: [0; 3],
+        }
+    }
+
+    /// Uploads a copy of the material onto another device. The joint matrices and textures can't
+    /// be read back, so the joints are reset to identities and the textures left out. Clones
+    /// sharing the material each get a copy of their own.
+    pub fn recreate(&self, device: &Device) -> Self {
+        if self.shared.textures.iter().any(Option::is_some) {
+            warn!("the textures of a material can't be read back, recreating it without them");
+        }
+
+        Self::create(
+            device,
+            self.factors(),
+            self.tint(),
+            &vec![Mat4::IDENTITY; self.joint_count()],
+            Default::default(),
+        )
+    }
+
+    /// Creates a copy of the material whose palette holds the given joint matrices, sized for
+    /// skinning a mesh with that many joints. The copy shares the textures, but nothing else.
+    pub fn with_joints(&self, device: &Device, joints: &[Mat4]) -> Self {
+        Self::create(
+            device,
+            self.factors(),
+            self.tint(),
+            joints,
+            self.shared.textures.clone(),
+        )
+    }
+
+    /// Returns whether the material is shared with some clone of it.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.shared) > 1
+    }
+
+    /// Gives the material buffers and a bind group of its own if it's shared, so that changing it
+    /// leaves its clones alone. The textures stay shared, and the joint matrices are reset to
+    /// identities until the pose of the mesh is uploaded again.
+    pub fn unshare(&mut self, device: &Device) {
+        if self.is_shared() {
+            *self = self.with_joints(device, &vec![Mat4::IDENTITY; self.joint_count()]);
+        }
+    }
+
+    /// Returns the storage buffer of the joint matrices, which skinned meshes upload their pose
+    /// into.
+    pub fn joint_buffer(&self) -> &Buffer {
+        &self.shared.joint_buffer
+    }
+
+    /// Returns the number of matrices in the joint buffer.
+    pub fn joint_count(&self) -> usize {
+        self.shared.joint_count
+    }
+
+    /// Returns the texture of a slot, `None` if the slot has none.
+    pub fn texture(&self, slot: TextureSlot) -> Option<&Arc<Texture>> {
+        self.shared.textures[slot as usize].as_ref()
+    }
+
+    /// Returns the factors of the material.
+    pub fn factors(&self) -> MaterialFactors {
+        *self.shared.factors.lock().unwrap()
+    }
+
+    /// Updates the factors of the material.
+    pub fn set_factors(&self, queue: &Queue, factors: MaterialFactors) {
+        *self.shared.factors.lock().unwrap() = factors;
+        self.upload(queue);
+    }
+
+    /// Returns the base color of the material.
+    pub fn color(&self) -> Vec4 {
+        self.factors().base_color
+    }
+
+    /// Updates the base color of the material.
+    pub fn set_color(&self, queue: &Queue, base_color: Vec4) {
+        self.shared.factors.lock().unwrap().base_color = base_color;
+        self.upload(queue);
+    }
+
+    /// Returns the tint of the material.
+    pub fn tint(&self) -> Vec4 {
+        *self.shared.tint.lock().unwrap()
+    }
+
+    /// Updates the tint of the material, which is only read by the shader on devices without push
+    /// constants. Use `Renderer::set_tint` to tint a mesh on any device.
+    pub fn set_tint(&self, queue: &Queue, tint: Vec4) {
+        *self.shared.tint.lock().unwrap() = tint;
+        self.upload(queue);
+    }
+
+    /// Writes the cpu-side copy of the material into its uniform buffer.
+    fn upload(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.shared.buffer,
+            0,
+            bytemuck::bytes_of(&Self::uniform(
+                self.factors(),
+                self.tint(),
+                &self.shared.textures,
+            )),
+        );
+    }
+
+    /// Returns the bind group of the material.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.shared.bind_group
+    }
+}
+
+/// Where the texture of a slot of a `MaterialBuilder` comes from.
+#[derive(Debug, Clone)]
+enum TextureSource {
+    /// A texture which was already created, possibly shared with other materials.
+    Texture(Arc<Texture>),
+    /// `width * height` pixels of rgba bytes, which the texture is created from.
+    Rgba {
+        /// The width of the texture in pixels.
+        width: u32,
+        /// The height of the texture in pixels.
+        height: u32,
+        /// The rgba bytes, srgb encoded if the slot holds colors.
+        rgba: Vec<u8>,
+    },
+}
+
+/// Assembles a `Material` out of its factors and the textures of its slots, which are checked
+/// when the material is built. The material is created once and can then be shared by cloning
+/// it into every mesh it's drawn with.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialBuilder {
+    /// The factors of the material.
+    factors: MaterialFactors,
+    /// The texture of every slot, `None` for the slots without a texture.
+    textures: [Option<TextureSource>; TextureSlot::COUNT],
+    /// The options the textures given as rgba bytes are created with.
+    options: TextureOptions,
+}
+
+impl MaterialBuilder {
+    /// Sets the base color of the material.
+    pub fn with_color(mut self, base_color: Vec4) -> Self {
+        self.factors.base_color = base_color;
+        self
+    }
+
+    /// Sets the emissive color of the material.
+    pub fn with_emissive(mut self, emissive: Vec3) -> Self {
+        self.factors.emissive = emissive;
+        self
+    }
+
+    /// Sets how far the normal texture bends the normals sideways.
+    pub fn with_normal_scale(mut self, normal_scale: f32) -> Self {
+        self.factors.normal_scale = normal_scale;
+        self
+    }
+
+    /// Sets the texture of a slot to one which was already created, such as to share it with
+    /// other materials. It should be srgb encoded if the slot holds colors (see
+    /// `TextureSlot::srgb`).
+    pub fn with_texture(mut self, slot: TextureSlot, texture: Arc<Texture>) -> Self {
+        self.textures[slot as usize] = Some(TextureSource::Texture(texture));
+        self
+    }
+
+    /// Sets the texture of a slot to one created from `width * height` pixels of rgba bytes
+    /// when the material is built, srgb encoded if the slot holds colors.
+    pub fn with_rgba(
+        mut self,
+        slot: TextureSlot,
+        width: u32,
+        height: u32,
+        rgba: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.textures[slot as usize] = Some(TextureSource::Rgba {
+            width,
+            height,
+            rgba: rgba.into(),
+        });
+        self
+    }
+
+    /// Sets the options the textures given as rgba bytes are created with, which should have
+    /// been checked with `TextureOptions::supported`. Whether they're srgb encoded is decided by
+    /// their slot.
+    pub fn with_texture_options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Creates the material, along with the textures given as rgba bytes. Fails without creating
+    /// anything if the bytes of a texture don't make up its size, or the size isn't supported by
+    /// the device.
+    pub fn build(&self, device: &Device, queue: &Queue) -> Result<Material> {
+        let max_size = device.limits().max_texture_dimension_2d;
+
+        for (slot, source) in TextureSlot::ALL.into_iter().zip(&self.textures) {
+            match source {
+                Some(TextureSource::Rgba {
+                    width,
+                    height,
+                    rgba,
+                }) => {
+                    if *width == 0 || *height == 0 || (*width).max(*height) > max_size {
+                        bail!(
+                            "the {slot:?} texture of the material is {width}x{height}, but must \
+                             be from 1x1 up to {max_size}x{max_size}"
+                        );
+                    }
+
+                    let expected = 4 * *width as usize * *height as usize;
+
+                    if rgba.len() != expected {
+                        bail!(
+                            "the {slot:?} texture of the material has {} bytes rather than the \
+                             {expected} of {width}x{height} rgba pixels",
+                            rgba.len()
+                        );
+                    }
+                }
+                Some(TextureSource::Texture(texture)) => {
+                    let format = texture.texture().format();
+
+                    if format != Texture::FORMAT && format != Texture::LINEAR_FORMAT {
+                        bail!(
+                            "the {slot:?} texture of the material has unsupported format \
+                             {format:?}"
+                        );
+                    }
+
+                    if (format == Texture::FORMAT) != slot.srgb() {
+                        warn!(
+                            "the {slot:?} texture of the material is {format:?}, which isn't \
+                             meant for the slot"
+                        );
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let textures = TextureSlot::ALL.map(|slot| {
+            self.textures[slot as usize]
+                .as_ref()
+                .map(|source| match source {
+                    TextureSource::Texture(texture) => texture.clone(),
+                    TextureSource::Rgba {
+                        width,
+                        height,
+                        rgba,
+                    } => Arc::new(Texture::from_rgba(
+                        device,
+                        queue,
+                        &format!("Material {slot:?} Texture"),
+                        *width,
+                        *height,
+                        rgba,
+                        &self.options.with_srgb(slot.srgb()),
+                    )),
+                })
+        });
+
+        Ok(Material::create(
+            device,
+            self.factors,
+            Material::DEFAULT_TINT,
+            &[Mat4::IDENTITY],
+            textures,
+        ))
+    }
+}
@@ -1,52 +1,1451 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use glam::*;
+use log::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use wgpu::{util::*, *};
 
+use crate::{
+    bounds::Aabb,
+    grid::GridParams,
+    lines,
+    loader::MeshLoad,
+    material::Material,
+    mesh_arena::{ArenaRange, MeshArena},
+    obj,
+    skin::Skin,
+    transform::Transform,
+};
+
 /// A mesh consists of a set of vertices connected by edges in triangles
 /// (the indices).
 #[derive(Debug)]
 pub struct Mesh {
-    /// The vertices uploaded to the gpu.
-    pub vertex_buffer: Buffer,
-    /// The indices uploaded to the gpu. Stored as a list of `u32`s.
-    pub index_buffer: Buffer,
+    /// Where the vertices and indices are uploaded to on the gpu.
+    pub buffers: MeshBuffers,
+
+    /// The number of vertices present in the buffer. Indexed meshes are drawn with the
+    /// `index_count` instead, which usually differs since triangles share vertices.
+    pub vertex_count: u32,
+    /// The number of indices present in the buffer, which indexed meshes are drawn with. Zero for
+    /// meshes without indices.
+    pub index_count: u32,
+
+    /// A cpu-side copy of the vertices and indices, used for queries such as ray intersections.
+    /// Kept unless discarded with `Mesh::discard_geometry`.
+    geometry: Option<(Vec<Vertex>, Vec<u32>)>,
+    /// The bounding box of the vertices in local space.
+    bounds: Aabb,
+    /// The average position of the vertices in local space.
+    local_center: Vec3,
+
+    /// The per-instance model matrices uploaded to the gpu, each already multiplied by the mesh's
+    /// `transform`.
+    pub instance_buffer: Buffer,
+    /// The number of instances present in the `instance_buffer`.
+    pub instance_count: u32,
+    /// The model matrices of the instances, relative to the mesh's `transform`. Holds a single
+    /// identity instance unless set with `Mesh::set_instances`.
+    instances: Vec<InstanceRaw>,
+
+    /// The translation, rotation and scale placing the whole mesh in the world.
+    transform: Transform,
+    /// Whether the `transform` changed since the `instance_buffer` was last uploaded.
+    transform_dirty: bool,
+    /// The rotation continuously applied to the mesh's transform, if any.
+    spin: Option<Spin>,
+    /// The joints deforming the vertices, if the mesh is skinned.
+    skin: Option<Skin>,
+    /// The segments of the mesh expanded into quads for drawing thick lines, along with the number
+    /// of their corners and the topology they were expanded for. Built the first time the mesh is
+    /// drawn with thick lines.
+    line_buffer: Option<(PrimitiveTopology, Buffer, u32)>,
+
+    /// The material the mesh is drawn with, which may be a clone shared with other meshes.
+    pub material: Material,
+    /// The color the shaded fragments of the mesh are multiplied with, see `Renderer::set_tint`.
+    tint: Option<Vec4>,
+    /// Whether the mesh is drawn with alpha blending, after all the opaque meshes.
+    pub transparent: bool,
+    /// Whether the mesh is drawn on top of everything else, after all the other meshes and without
+    /// testing against the depth buffer.
+    overlay: bool,
+    /// Whether the depth of the mesh's triangles is offset by `Renderer::depth_bias`.
+    depth_biased: bool,
+    /// Whether the mesh is drawn at all. Hidden meshes keep their buffers, so that they can be
+    /// shown again right away.
+    visible: bool,
+    /// How the indexed vertices are assembled into primitives when drawn. Only triangle lists cast
+    /// shadows, are hit by rays and can be merged.
+    pub topology: PrimitiveTopology,
+    /// Where the vertices of the mesh came from, so that a saved scene can recreate it.
+    pub source: MeshSource,
+}
+
+/// Where the vertices and indices of a mesh are stored on the gpu.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum MeshBuffers {
+    /// Buffers of the mesh's own.
+    Owned {
+        /// The vertices uploaded to the gpu.
+        vertex: Buffer,
+        /// The indices uploaded to the gpu. Stored as a list of `u32`s.
+        index: Buffer,
+    },
+    /// A vertex buffer of the mesh's own without indices, every vertex being drawn in order.
+    Unindexed {
+        /// The vertices uploaded to the gpu.
+        vertex: Buffer,
+    },
+    /// A range of the buffers of a `MeshArena`, shared with other meshes.
+    Shared(ArenaRange),
+}
+
+/// The vertices a mesh is drawn with, as returned by `Mesh::bind_buffers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawRange {
+    /// Drawn through the bound index buffer.
+    Indexed {
+        /// The range of indices drawn.
+        indices: Range<u32>,
+        /// The value added to every index before looking up its vertex.
+        base_vertex: i32,
+    },
+    /// The range of vertices drawn in order, without indices.
+    Vertices(Range<u32>),
+}
+
+impl DrawRange {
+    /// Returns the range drawing the whole of a mesh's own buffers of `vertex_count` vertices,
+    /// through `index_count` indices if it has an index buffer.
+    fn whole(vertex_count: u32, index_count: Option<u32>) -> Self {
+        match index_count {
+            Some(index_count) => Self::Indexed {
+                indices: 0..index_count,
+                base_vertex: 0,
+            },
+            None => Self::Vertices(0..vertex_count),
+        }
+    }
+
+    /// Records drawing the range for the given instances into the render pass.
+    pub fn draw(self, render_pass: &mut RenderPass, instances: Range<u32>) {
+        match self {
+            Self::Indexed {
+                indices,
+                base_vertex,
+            } => render_pass.draw_indexed(indices, base_vertex, instances),
+            Self::Vertices(vertices) => render_pass.draw(vertices, instances),
+        }
+    }
+
+    /// Returns the number of vertices each instance is drawn with, counting repeated ones.
+    pub fn count(&self) -> u32 {
+        match self {
+            Self::Indexed { indices, .. } => indices.len() as u32,
+            Self::Vertices(vertices) => vertices.len() as u32,
+        }
+    }
+}
+
+/// Where the vertices of a mesh came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MeshSource {
+    /// Created from vertices given in code, which are saved along with a scene.
+    Vertices,
+    /// The `index`th mesh returned by `Mesh::from_gltf` for the file at `path`, which is saved as a
+    /// reference to the file.
+    Gltf {
+        /// The path of the glTF file.
+        path: PathBuf,
+        /// The index of the mesh among the meshes loaded from the file.
+        index: usize,
+    },
+    /// The `index`th mesh returned by `Mesh::from_obj` for the file at `path`, which is saved as a
+    /// reference to the file.
+    Obj {
+        /// The path of the OBJ file.
+        path: PathBuf,
+        /// The index of the mesh among the meshes loaded from the file.
+        index: usize,
+    },
+}
 
-    /// The number of vertices present in the buffer.
-    pub count: u32,
+/// A constant rotation of a mesh around an axis of its own.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Spin {
+    /// The normalized axis the mesh rotates around, in the mesh's local space.
+    pub axis: Vec3,
+    /// The angular velocity of the rotation, in radians per second.
+    pub rate: f32,
 }
 
 /// Represents a vertex of a triangle, that can easily be uploaded to the rendering device.
-#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct Vertex {
     pub pos: [f32; 3],
-    pub color: [f32; 3],
+    /// The straight (non-premultiplied) rgba color of the vertex.
+    pub color: [f32; 4],
+    /// The normal of the surface at the vertex, zero for lines.
+    pub normal: [f32; 3],
+    /// The texture coordinates of the vertex.
+    pub uv: [f32; 2],
+    /// The indices of the up to four joints of the mesh's skin deforming the vertex.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub joint_indices: [u16; 4],
+    /// How much each of the `joint_indices` deforms the vertex, summing up to one. Vertices whose
+    /// weights are all zero (such as those of meshes without a skin) aren't deformed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub joint_weights: [f32; 4],
+}
+
+/// The model matrix of a single instance of a mesh, laid out as it is uploaded to the rendering
+/// device. Create it from the model matrix with `InstanceRaw::from`, which derives the
+/// `normal` matrix.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(C)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    /// The inverse transpose of the upper 3x3 of the `model` matrix, which normals are transformed
+    /// with so that they stay perpendicular to the surface under non-uniform scaling.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing))]
+    pub normal: [[f32; 3]; 3],
 }
 
 impl Mesh {
+    /// The color of the minor lines of a grid.
+    pub const GRID_MINOR_COLOR: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+    /// The color of the major lines of a grid.
+    pub const GRID_MAJOR_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+    /// The number of minor lines between each major line of a grid.
+    pub const GRID_MAJOR_INTERVAL: i32 = 10;
+    /// The distance under which the attributes of vertices are considered equal by
+    /// `Mesh::from_unindexed`.
+    pub const WELD_PRECISION: f32 = 1e-5;
+
     pub fn new(device: &Device, vertices: &[Vertex], indices: &[u32]) -> Self {
-        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Mesh Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
-            usage: BufferUsages::VERTEX,
+        Self::with_vertex_usage(device, vertices, indices, BufferUsages::VERTEX)
+    }
+
+    /// Creates a mesh whose vertex buffer can also be bound as a storage buffer, so that a compute
+    /// shader can write the vertices it is drawn with. The vertices are bound as an array of `f32`s
+    /// laid out like `Vertex`. Changes made on the gpu aren't reflected in `Mesh::vertices`.
+    pub fn new_storage(device: &Device, vertices: &[Vertex], indices: &[u32]) -> Self {
+        Self::with_vertex_usage(
+            device,
+            vertices,
+            indices,
+            BufferUsages::VERTEX | BufferUsages::STORAGE,
+        )
+    }
+
+    /// Creates a mesh from a list of triangles which doesn't share any vertices (such as the
+    /// triangle soups of some file formats), merging vertices with the same attributes into one
+    /// and indexing them. Attributes are compared after rounding to `Mesh::WELD_PRECISION`.
+    pub fn from_unindexed(device: &Device, vertices: &[Vertex]) -> Self {
+        let (unique, indices) = weld(vertices);
+
+        info!(
+            "deduplicated {} vertices into {}",
+            vertices.len(),
+            unique.len()
+        );
+
+        Self::new(device, &unique, &indices)
+    }
+
+    /// Creates a mesh without an index buffer, drawing every vertex in order, such as the lines or
+    /// quads of generated geometry which don't share any vertices. The cpu-side copy of the
+    /// geometry indexes the vertices in order, so that rays and merging treat it like any other
+    /// mesh.
+    pub fn new_unindexed(device: &Device, vertices: &[Vertex]) -> Self {
+        Self::unindexed_with_vertex_usage(device, vertices, BufferUsages::VERTEX)
+    }
+
+    /// Creates a mesh without indices whose vertex buffer has the given usages.
+    fn unindexed_with_vertex_usage(
+        device: &Device,
+        vertices: &[Vertex],
+        usage: BufferUsages,
+    ) -> Self {
+        let buffers = MeshBuffers::Unindexed {
+            vertex: create_vertex_buffer(device, vertices, usage),
+        };
+
+        let indices = (0..vertices.len() as u32).collect::<Vec<_>>();
+
+        let mut mesh = Self::with_buffers(device, buffers, vertices, &indices);
+        mesh.index_count = 0;
+        mesh
+    }
+
+    /// Creates a mesh whose vertex buffer has the given usages.
+    fn with_vertex_usage(
+        device: &Device,
+        vertices: &[Vertex],
+        indices: &[u32],
+        usage: BufferUsages,
+    ) -> Self {
+        let buffers = MeshBuffers::Owned {
+            vertex: create_vertex_buffer(device, vertices, usage),
+            index: create_index_buffer(device, indices),
+        };
+
+        Self::with_buffers(device, buffers, vertices, indices)
+    }
+
+    /// Creates a mesh whose vertices and indices are uploaded into a range of the arena's buffers
+    /// instead of buffers of its own, which is drawn from whichever renderer the arena belongs
+    /// to. Such meshes can't be driven by compute shaders.
+    pub fn new_shared(
+        device: &Device,
+        queue: &Queue,
+        arena: &mut MeshArena,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let range = arena.allocate(device, queue, vertices, indices);
+
+        Self::with_buffers(device, MeshBuffers::Shared(range), vertices, indices)
+    }
+
+    /// Creates a mesh stored in the given buffers, which already hold the vertices and indices.
+    fn with_buffers(
+        device: &Device,
+        buffers: MeshBuffers,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let instances = vec![InstanceRaw::from(Mat4::IDENTITY)];
+
+        Self {
+            buffers,
+            vertex_count: vertices.len() as u32,
+            index_count: indices.len() as u32,
+            geometry: Some((vertices.to_vec(), indices.to_vec())),
+            bounds: Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.pos))),
+            local_center: vertices
+                .iter()
+                .map(|vertex| Vec3::from(vertex.pos))
+                .sum::<Vec3>()
+                / vertices.len().max(1) as f32,
+            instance_buffer: create_instance_buffer(device, &instances),
+            instance_count: instances.len() as u32,
+            instances,
+            transform: Transform::IDENTITY,
+            transform_dirty: false,
+            spin: None,
+            skin: None,
+            line_buffer: None,
+            material: Material::new(device, Material::DEFAULT_COLOR),
+            tint: None,
+            transparent: false,
+            overlay: false,
+            depth_biased: false,
+            visible: true,
+            topology: PrimitiveTopology::TriangleList,
+            source: MeshSource::Vertices,
+        }
+    }
+
+    /// Returns whether the mesh is stored in a range of a `MeshArena`'s buffers.
+    pub fn is_shared(&self) -> bool {
+        matches!(self.buffers, MeshBuffers::Shared(_))
+    }
+
+    /// Sets the vertex, instance and index buffers of the mesh on the render pass, taking shared
+    /// meshes from the arena. Returns the range of the mesh to draw.
+    pub fn bind_buffers(&self, render_pass: &mut RenderPass, arena: &MeshArena) -> DrawRange {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        match &self.buffers {
+            MeshBuffers::Owned { vertex, index } => {
+                render_pass.set_vertex_buffer(0, vertex.slice(..));
+                render_pass.set_index_buffer(index.slice(..), IndexFormat::Uint32);
+            }
+            MeshBuffers::Unindexed { vertex } => {
+                render_pass.set_vertex_buffer(0, vertex.slice(..));
+            }
+            MeshBuffers::Shared(_) => {
+                render_pass.set_vertex_buffer(0, arena.vertex_buffer().slice(..));
+                render_pass.set_index_buffer(arena.index_buffer().slice(..), IndexFormat::Uint32);
+            }
+        }
+
+        self.draw_range()
+    }
+
+    /// Returns the range of the mesh's buffers, or of the arena's for shared meshes, to draw.
+    pub fn draw_range(&self) -> DrawRange {
+        match &self.buffers {
+            MeshBuffers::Owned { .. } => {
+                DrawRange::whole(self.vertex_count, Some(self.index_count))
+            }
+            MeshBuffers::Unindexed { .. } => DrawRange::whole(self.vertex_count, None),
+            MeshBuffers::Shared(range) => {
+                let start = range.index_offset;
+
+                DrawRange::Indexed {
+                    indices: start..start + range.count,
+                    base_vertex: range.vertex_offset as i32,
+                }
+            }
+        }
+    }
+
+    /// Returns the number of vertices each instance of the mesh is drawn with, counting repeated
+    /// ones: the number of indices, or of vertices for meshes without indices.
+    pub fn draw_count(&self) -> u32 {
+        self.draw_range().count()
+    }
+
+    /// Returns the average position of the mesh's vertices in world space.
+    pub fn center(&self) -> Vec3 {
+        self.transform.transform_point(self.local_center)
+    }
+
+    /// Returns the cpu-side copy of the vertices, or `None` if it was discarded.
+    pub fn vertices(&self) -> Option<&[Vertex]> {
+        self.geometry.as_ref().map(|(vertices, _)| &vertices[..])
+    }
+
+    /// Returns the cpu-side copy of the indices, or `None` if it was discarded.
+    pub fn indices(&self) -> Option<&[u32]> {
+        self.geometry.as_ref().map(|(_, indices)| &indices[..])
+    }
+
+    /// Uploads a copy of the mesh onto another device (such as the one replacing a lost device),
+    /// with the same transform, instances, animation and material. Vertices written by a compute
+    /// shader are reset to the cpu-side copy, and shared meshes get buffers of their own. Returns
+    /// `None` if the geometry was discarded, as the buffers of the mesh can't be read back.
+    pub fn recreate(&self, device: &Device) -> Option<Self> {
+        let (vertices, indices) = self.geometry.as_ref()?;
+
+        let mut mesh = match self.buffers {
+            MeshBuffers::Unindexed { .. } => {
+                Self::unindexed_with_vertex_usage(device, vertices, self.vertex_usage())
+            }
+            _ => Self::with_vertex_usage(device, vertices, indices, self.vertex_usage()),
+        };
+
+        mesh.transform = self.transform;
+        mesh.set_instances(device, &self.instances);
+        mesh.spin = self.spin;
+        mesh.material = self.material.recreate(device);
+
+        if let Some(skin) = &self.skin {
+            mesh.set_skin(device, skin.clone());
+        }
+
+        mesh.tint = self.tint;
+        mesh.transparent = self.transparent;
+        mesh.overlay = self.overlay;
+        mesh.depth_biased = self.depth_biased;
+        mesh.visible = self.visible;
+        mesh.topology = self.topology;
+        mesh.source = self.source.clone();
+
+        Some(mesh)
+    }
+
+    /// Frees the cpu-side copy of the vertices and indices, leaving only the buffers on the gpu.
+    /// Saves memory for meshes which don't need them, but such meshes are no longer hit by rays,
+    /// merged into static meshes or saved with a scene.
+    pub fn discard_geometry(&mut self) {
+        self.geometry = None;
+    }
+
+    /// Reverses the winding of the mesh's triangles and negates its normals, for meshes exported
+    /// from tools using the opposite handedness whose front faces are otherwise culled. The flipped
+    /// geometry is uploaded into new buffers, which are the mesh's own even if it was shared. Only
+    /// triangle lists with their geometry still around can be flipped, warning otherwise.
+    pub fn flip_winding(&mut self, device: &Device) {
+        if self.topology != PrimitiveTopology::TriangleList {
+            return warn!(
+                "only triangle lists can be flipped, not {:?}",
+                self.topology
+            );
+        }
+
+        let usage = self.vertex_usage();
+
+        let Some((vertices, indices)) = &mut self.geometry else {
+            return warn!("the winding of a mesh whose geometry was discarded can't be flipped");
+        };
+
+        // without indices the triangles are made of consecutive vertices, which are swapped instead
+        let indexed = !matches!(self.buffers, MeshBuffers::Unindexed { .. });
+
+        if indexed {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        } else {
+            for triangle in vertices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        for vertex in vertices.iter_mut() {
+            vertex.normal = (-Vec3::from(vertex.normal)).into();
+        }
+
+        let vertex = create_vertex_buffer(device, vertices, usage);
+
+        self.buffers = if indexed {
+            MeshBuffers::Owned {
+                vertex,
+                index: create_index_buffer(device, indices),
+            }
+        } else {
+            MeshBuffers::Unindexed { vertex }
+        };
+    }
+
+    /// Returns the usages of the mesh's own vertex buffer, or the default ones if it's shared.
+    fn vertex_usage(&self) -> BufferUsages {
+        match &self.buffers {
+            MeshBuffers::Owned { vertex, .. } | MeshBuffers::Unindexed { vertex } => vertex.usage(),
+            MeshBuffers::Shared(_) => BufferUsages::VERTEX,
+        }
+    }
+
+    /// Returns the bounding box of the mesh's vertices in local space.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Returns the bounding box of the mesh in world space, containing every one of its instances.
+    pub fn world_bounds(&self) -> Aabb {
+        let bounds = self.bounds();
+
+        self.instances
+            .iter()
+            .map(|instance| {
+                bounds.transformed(self.model_matrix() * Mat4::from_cols_array_2d(&instance.model))
+            })
+            .fold(Aabb::EMPTY, |world, instance| world.union(&instance))
+    }
+
+    /// Sets the base color of the mesh's material, which its vertex colors are multiplied with.
+    /// Meshes sharing the material change color too.
+    pub fn set_material_color(&self, queue: &Queue, color: Vec4) {
+        self.material.set_color(queue, color);
+    }
+
+    /// Returns the translation, rotation and scale placing the whole mesh in the world.
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Returns the model matrix placing the whole mesh in the world, built from its `transform`.
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.to_matrix()
+    }
+
+    /// Sets the transform placing the whole mesh in the world, either as a `Transform` or as a
+    /// model matrix decomposed with `Transform::from_matrix`. The change is uploaded to the gpu
+    /// with the next `Mesh::upload_transform`.
+    pub fn set_transform(&mut self, transform: impl Into<Transform>) {
+        self.transform = transform.into();
+        self.transform_dirty = true;
+    }
+
+    /// Returns the transform of the mesh to be edited in place, such as to move it a little every
+    /// frame. The transform is uploaded again with the next `Mesh::upload_transform`, whether it
+    /// was changed or not.
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        self.transform_dirty = true;
+        &mut self.transform
+    }
+
+    /// Returns the color the shaded fragments of the mesh are multiplied with, if it's tinted.
+    pub fn tint(&self) -> Option<Vec4> {
+        self.tint
+    }
+
+    /// Sets the tint of the mesh, without applying it on devices without push constants like
+    /// `Renderer::set_tint` does.
+    pub(crate) fn set_tint(&mut self, tint: Option<Vec4>) {
+        self.tint = tint;
+    }
+
+    /// Returns whether the mesh is drawn on top of everything else.
+    pub fn overlay(&self) -> bool {
+        self.overlay
+    }
+
+    /// Sets whether the mesh is drawn on top of everything else, ignoring the depth of the meshes
+    /// in front of it. Overlays are drawn after all the other meshes, in the order transparency
+    /// sorts them in, and don't write to the depth buffer, so they also always show over each other
+    /// in that order. Useful for gizmos and highlights.
+    pub fn set_overlay(&mut self, overlay: bool) {
+        self.overlay = overlay;
+    }
+
+    /// Returns whether the depth of the mesh's triangles is offset by the renderer's depth bias.
+    pub fn depth_biased(&self) -> bool {
+        self.depth_biased
+    }
+
+    /// Sets whether the depth of the mesh's triangles is offset by `Renderer::depth_bias`, so that
+    /// it wins against faces it's coplanar with (such as a decal on a wall, or a road on the
+    /// ground) instead of fighting them where their depths round differently. Meshes drawn as
+    /// thick lines and other topologies than triangles aren't biased.
+    pub fn set_depth_biased(&mut self, depth_biased: bool) {
+        self.depth_biased = depth_biased;
+    }
+
+    /// Returns whether the mesh is drawn.
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Sets whether the mesh is drawn. Hidden meshes aren't drawn or cast shadows, and don't count
+    /// towards the scene's bounds or the renderer's statistics, but keep their buffers and keep
+    /// animating.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Returns the rotation continuously applied to the mesh, if any.
+    pub fn spin(&self) -> Option<Spin> {
+        self.spin
+    }
+
+    /// Makes the mesh continuously rotate around the given axis of its own, at `rate` radians per
+    /// second.
+    pub fn set_spin(&mut self, axis: Vec3, rate: f32) {
+        self.spin = Some(Spin {
+            axis: axis.normalize(),
+            rate,
         });
+    }
+
+    /// Stops the mesh's rotation, leaving it at its current orientation.
+    pub fn stop_spin(&mut self) {
+        self.spin = None;
+    }
 
-        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Mesh Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
-            usage: BufferUsages::INDEX,
+    /// Returns the joints deforming the mesh, if it's skinned.
+    pub fn skin(&self) -> Option<&Skin> {
+        self.skin.as_ref()
+    }
+
+    /// Skins the mesh with the given joints, which its vertices are weighted to by their
+    /// `joint_indices` and `joint_weights`. The material gets a new palette sized for the joints.
+    pub fn set_skin(&mut self, device: &Device, mut skin: Skin) {
+        self.material = self.material.with_joints(device, &skin.joint_matrices());
+        skin.take_dirty();
+        self.skin = Some(skin);
+    }
+
+    /// Starts looping the skeletal animation with the given name from its beginning, warning if
+    /// the mesh isn't skinned or has no such animation.
+    pub fn play_animation(&mut self, name: &str) {
+        let Some(skin) = &mut self.skin else {
+            return warn!("a mesh without a skin can't play animation {name}");
+        };
+
+        if !skin.play(name) {
+            warn!("the mesh has no animation named {name}");
+        }
+    }
+
+    /// Stops the skeletal animation, returning the mesh to its rest pose.
+    pub fn stop_animation(&mut self) {
+        if let Some(skin) = &mut self.skin {
+            skin.stop();
+        }
+    }
+
+    /// Advances the mesh's spin and skeletal animation by `dt` seconds.
+    pub fn animate(&mut self, dt: f32) {
+        if let Some(Spin { axis, rate }) = self.spin {
+            self.transform_mut()
+                .rotate_local(Quat::from_axis_angle(axis, rate * dt));
+        }
+
+        if let Some(skin) = &mut self.skin {
+            skin.advance(dt);
+        }
+    }
+
+    /// Records uploading the instances of the mesh through the staging belt, if its transform
+    /// changed since they were last uploaded.
+    pub fn upload_transform(
+        &mut self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        if !self.transform_dirty {
+            return;
+        }
+
+        let instances = transform_instances(self.model_matrix(), &self.instances);
+        let bytes: &[u8] = bytemuck::cast_slice(&instances);
+
+        if let Some(size) = BufferSize::new(bytes.len() as BufferAddress) {
+            belt.write_buffer(encoder, &self.instance_buffer, 0, size, device)
+                .copy_from_slice(bytes);
+        }
+
+        self.transform_dirty = false;
+    }
+
+    /// Records uploading the joint matrices of the mesh's skin through the staging belt, if its
+    /// pose changed since they were last uploaded.
+    pub fn upload_joints(
+        &mut self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        let Some(skin) = &mut self.skin else {
+            return;
+        };
+
+        if !skin.take_dirty() {
+            return;
+        }
+
+        // a palette created for fewer joints only receives the ones it has room for
+        let matrices = skin
+            .joint_matrices()
+            .iter()
+            .take(self.material.joint_count())
+            .map(Mat4::to_cols_array)
+            .collect::<Vec<_>>();
+
+        let bytes: &[u8] = bytemuck::cast_slice(&matrices);
+
+        if let Some(size) = BufferSize::new(bytes.len() as BufferAddress) {
+            belt.write_buffer(encoder, self.material.joint_buffer(), 0, size, device)
+                .copy_from_slice(bytes);
+        }
+    }
+
+    /// Expands the segments of the mesh (or the edges of its triangles) into the quads thick lines
+    /// are drawn with, unless they're already up to date with its topology. Returns whether the
+    /// mesh can be drawn with thick lines, which isn't the case for points or meshes whose
+    /// geometry was discarded before they were expanded.
+    pub fn prepare_lines(&mut self, device: &Device) -> bool {
+        if self
+            .line_buffer
+            .as_ref()
+            .is_some_and(|(topology, ..)| *topology == self.topology)
+        {
+            return true;
+        }
+
+        let Some((vertices, indices)) = &self.geometry else {
+            return false;
+        };
+
+        let corners = lines::line_vertices(vertices, indices, self.topology);
+
+        if corners.is_empty() {
+            return false;
+        }
+
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Line Vertex Buffer"),
+            contents: bytemuck::cast_slice(&corners),
+            usage: BufferUsages::VERTEX,
         });
 
+        self.line_buffer = Some((self.topology, buffer, corners.len() as u32));
+
+        true
+    }
+
+    /// Returns the buffer of the quads thick lines are drawn with and the number of their corners,
+    /// if they've been expanded with `Mesh::prepare_lines` for the mesh's current topology.
+    pub fn line_buffer(&self) -> Option<(&Buffer, u32)> {
+        self.line_buffer
+            .as_ref()
+            .filter(|(topology, ..)| *topology == self.topology)
+            .map(|(_, buffer, count)| (buffer, *count))
+    }
+
+    /// Returns the model matrices of the instances, relative to the mesh's transform.
+    pub fn instances(&self) -> &[InstanceRaw] {
+        &self.instances
+    }
+
+    /// Replaces the instances of the mesh, so that a copy of it is drawn for every given model
+    /// matrix (relative to the mesh's transform) in a single draw call.
+    pub fn set_instances(&mut self, device: &Device, instances: &[InstanceRaw]) {
+        self.instance_buffer =
+            create_instance_buffer(device, &transform_instances(self.model_matrix(), instances));
+        self.instance_count = instances.len() as u32;
+        // the normal matrices are derived again, in case they weren't set (such as for instances
+        // loaded from a scene file)
+        self.instances = instances
+            .iter()
+            .map(|instance| InstanceRaw::from(Mat4::from_cols_array_2d(&instance.model)))
+            .collect();
+        self.transform_dirty = false;
+    }
+
+    /// Intersects a world-space ray with the triangles of the mesh, returning the distance along
+    /// `dir` to the nearest hit. Triangles are hit from both sides, and meshes which aren't
+    /// triangle lists or whose geometry was discarded are never hit. Only the mesh's transform is
+    /// taken into account, not its instances.
+    pub fn ray_intersect(&self, origin: Vec3, dir: Vec3) -> Option<f32> {
+        let (vertices, indices) = self.geometry.as_ref()?;
+
+        if self.topology != PrimitiveTopology::TriangleList {
+            return None;
+        }
+
+        // the direction isn't normalized after transforming, so that distances along it stay the
+        // same in both spaces
+        let inverse = self.model_matrix().inverse();
+        let origin = inverse.transform_point3(origin);
+        let dir = inverse.transform_vector3(dir);
+
+        indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(vertices[triangle[i] as usize].pos));
+                ray_triangle_intersect(origin, dir, a, b, c)
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    /// Creates a grid of lines on the xz plane centered around the origin, with lines `spacing`
+    /// units apart reaching out `extent` units in every direction, drawn as a line list. Like the
+    /// other primitives it's built `CoordinateSystem::YUp`, so it stands upright with `ZUp` unless
+    /// it's turned a quarter around `+X`. The spacing is clamped to `GridParams::MIN_DISTANCE`
+    /// and the extent to zero from below.
+    pub fn grid(device: &Device, spacing: f32, extent: f32) -> Self {
+        let vertices = grid_vertices(spacing, extent);
+        let indices = (0..vertices.len() as u32).collect::<Vec<_>>();
+
+        let mut mesh = Self::new(device, &vertices, &indices);
+        mesh.topology = PrimitiveTopology::LineList;
+        mesh
+    }
+
+    /// Creates a gizmo of the x, y and z axes as red, green and blue lines of the given `length`
+    /// starting at the origin, drawn as a line list.
+    pub fn axes(device: &Device, length: f32) -> Self {
+        let vertices = [
+            ([1.0, 0.0, 0.0, 1.0], [length, 0.0, 0.0]),
+            ([0.0, 1.0, 0.0, 1.0], [0.0, length, 0.0]),
+            ([0.0, 0.0, 1.0, 1.0], [0.0, 0.0, length]),
+        ]
+        .into_iter()
+        .flat_map(|(color, end)| {
+            [
+                Vertex {
+                    pos: [0.0, 0.0, 0.0],
+                    color,
+                    ..Default::default()
+                },
+                Vertex {
+                    pos: end,
+                    color,
+                    ..Default::default()
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+        let mut mesh = Self::new(device, &vertices, &[0, 1, 2, 3, 4, 5]);
+        mesh.topology = PrimitiveTopology::LineList;
+        mesh
+    }
+
+    /// Creates a uv sphere of the given `radius` and vertex `color` centered around the origin,
+    /// with `segments` vertices around its equator and `rings` rows of triangles from pole to pole.
+    pub fn sphere(device: &Device, radius: f32, segments: u32, rings: u32, color: Vec4) -> Self {
+        let segments = segments.max(3);
+        let rings = rings.max(2);
+
+        let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1)) as usize);
+
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let (sin_polar, cos_polar) = (v * std::f32::consts::PI).sin_cos();
+
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_azimuth, cos_azimuth) = (u * std::f32::consts::TAU).sin_cos();
+
+                let normal = vec3(sin_polar * cos_azimuth, cos_polar, sin_polar * sin_azimuth);
+
+                vertices.push(Vertex {
+                    pos: (normal * radius).into(),
+                    color: color.into(),
+                    normal: normal.into(),
+                    uv: [u, v],
+                    ..Default::default()
+                });
+            }
+        }
+
+        // the seam and poles duplicate vertices, so that every one has its own texture coordinates
+        let row = segments + 1;
+        let indices = (0..rings)
+            .flat_map(|ring| {
+                (0..segments).flat_map(move |segment| {
+                    let top = ring * row + segment;
+                    let bottom = top + row;
+
+                    [top, top + 1, bottom, top + 1, bottom + 1, bottom]
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(device, &vertices, &indices)
+    }
+
+    /// Creates a square plane on the xz plane facing `+Y`, with sides of length `size` centered
+    /// around the origin and split into `subdivisions * subdivisions` quads, such as for terrain.
+    /// It's drawn as a single triangle strip, which takes about a third of the indices of a
+    /// triangle list: every row of quads is one strip running along the x axis, stitched to the
+    /// next row by repeating the last index of the row and the first of the next, which forms
+    /// degenerate triangles without any area. Like `Mesh::grid`, it doesn't follow
+    /// `CoordinateSystem::ZUp`.
+    pub fn plane(device: &Device, size: f32, subdivisions: u32, color: Vec4) -> Self {
+        let subdivisions = subdivisions.max(1);
+        let row = subdivisions + 1;
+
+        let vertices = (0..row)
+            .flat_map(|z| (0..row).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let uv = vec2(x as f32, z as f32) / subdivisions as f32;
+
+                Vertex {
+                    pos: [(uv.x - 0.5) * size, 0.0, (uv.y - 0.5) * size],
+                    color: color.into(),
+                    normal: [0.0, 1.0, 0.0],
+                    uv: uv.into(),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // every row has an even number of indices, so the stitched strips keep the winding
+        let indices = (0..subdivisions)
+            .flat_map(|z| {
+                let strip = (0..row).flat_map(move |x| [z * row + x, (z + 1) * row + x]);
+                let stitch = (z + 1 < subdivisions)
+                    .then(|| [(z + 1) * row + subdivisions, (z + 1) * row])
+                    .into_iter()
+                    .flatten();
+
+                strip.chain(stitch)
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = Self::new(device, &vertices, &indices);
+        mesh.topology = PrimitiveTopology::TriangleStrip;
+        mesh
+    }
+
+    /// Creates an axis aligned cube with edges of length `size` and the given vertex `color`,
+    /// centered around the origin. Every face has its own four vertices, so that they're shaded
+    /// flat.
+    pub fn cube(device: &Device, size: f32, color: Vec4) -> Self {
+        let half = size / 2.0;
+
+        let vertices = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ]
+        .into_iter()
+        .flat_map(|normal| {
+            // two axes spanning the face, ordered so that its front winds counterclockwise
+            let tangent = normal.any_orthonormal_vector();
+            let bitangent = normal.cross(tangent);
+
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(u, v)| Vertex {
+                pos: ((normal + tangent * u + bitangent * v) * half).into(),
+                color: color.into(),
+                normal: normal.into(),
+                uv: [(u + 1.0) / 2.0, (1.0 - v) / 2.0],
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+
+        let indices = (0..6)
+            .flat_map(|face| [0, 1, 2, 0, 2, 3].map(|corner| face * 4 + corner))
+            .collect::<Vec<_>>();
+
+        Self::new(device, &vertices, &indices)
+    }
+
+    /// Creates a filled convex polygon from the ring of vertices around its edge, such as for
+    /// gizmos and overlays. The polygon is split into a fan of triangles sharing the first vertex
+    /// (`0, i, i + 1`), so the vertices should wind counterclockwise around its front and the
+    /// polygon must be convex for the triangles not to overlap. Fails with fewer than 3 vertices.
+    pub fn convex_polygon(device: &Device, vertices: &[Vertex]) -> Result<Self> {
+        let indices = fan_indices(vertices.len())?;
+        Ok(Self::new(device, vertices, &indices))
+    }
+
+    /// Loads the meshes of the default scene of a glTF (`.gltf` or `.glb`) file, creating a
+    /// separate mesh for every primitive, placed by the transform of its node. Vertex colors are
+    /// multiplied by the base color factor of the primitive's material. Skinned meshes get the
+    /// joints of their skin along with every animation of the file, which are played with
+    /// `Mesh::play_animation`. Features which aren't supported (such as textures, non-triangle
+    /// primitives or morph targets) are skipped with a warning.
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf(device: &Device, path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        Ok(Self::load_gltf(path)?
+            .into_iter()
+            .map(|data| data.upload(device))
+            .collect())
+    }
+
+    /// Loads the meshes of a glTF file like `Mesh::from_gltf`, without uploading them to a device.
+    #[cfg(feature = "gltf")]
+    pub fn load_gltf(path: impl AsRef<Path>) -> Result<Vec<MeshData>> {
+        let path = path.as_ref();
+        let (document, buffers, _) = gltf::import(path)?;
+
+        let Some(scene) = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        else {
+            warn!("{} contains no scenes", path.display());
+            return Ok(Vec::new());
+        };
+
+        let mut meshes = Vec::new();
+
+        for node in scene.nodes() {
+            load_gltf_node(&document, &node, Mat4::IDENTITY, &buffers, &mut meshes);
+        }
+
+        for (index, mesh) in meshes.iter_mut().enumerate() {
+            mesh.source = MeshSource::Gltf {
+                path: path.to_path_buf(),
+                index,
+            };
+        }
+
+        Ok(meshes)
+    }
+
+    /// Loads the faces of an OBJ file with `obj::load`, creating a separate mesh for the faces of
+    /// every material, colored by the material's diffuse color and the file's vertex colors.
+    pub fn from_obj(device: &Device, path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        Ok(Self::load_obj(path)?
+            .into_iter()
+            .map(|data| data.upload(device))
+            .collect())
+    }
+
+    /// Loads the faces of an OBJ file like `Mesh::from_obj`, without uploading them to a device.
+    pub fn load_obj(path: impl AsRef<Path>) -> Result<Vec<MeshData>> {
+        let path = path.as_ref();
+
+        Ok(obj::load(path)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, part)| MeshData {
+                source: MeshSource::Obj {
+                    path: path.to_path_buf(),
+                    index,
+                },
+                ..MeshData::new(part.vertices, part.indices)
+            })
+            .collect())
+    }
+
+    /// Loads the meshes of an OBJ or glTF file (told apart by the extension of `path`) without
+    /// uploading them to a device, so that it can be called from any thread.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<MeshData>> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("obj") => Self::load_obj(path),
+            #[cfg(feature = "gltf")]
+            Some("gltf" | "glb") => Self::load_gltf(path),
+            #[cfg(not(feature = "gltf"))]
+            Some("gltf" | "glb") => bail!("loading {} requires the `gltf` feature", path.display()),
+            _ => bail!("{} isn't an OBJ or glTF file", path.display()),
+        }
+    }
+
+    /// Starts loading the meshes of an OBJ or glTF file with `Mesh::load` on a worker thread, so
+    /// that parsing a large file doesn't block the thread rendering. The returned handle is polled
+    /// for the loaded meshes, which are then uploaded with `MeshData::upload` on the thread owning
+    /// the device.
+    pub fn load_async(path: impl Into<PathBuf>) -> MeshLoad {
+        MeshLoad::spawn(path.into())
+    }
+}
+
+/// The vertices of a mesh and the settings it's created with, loaded without a device so that
+/// files can be parsed on any thread. Uploaded into a `Mesh` with `MeshData::upload`.
+#[derive(Debug, Clone)]
+pub struct MeshData {
+    /// The vertices of the mesh.
+    pub vertices: Vec<Vertex>,
+    /// The indices of the vertices, assembled into primitives according to the `topology`.
+    pub indices: Vec<u32>,
+    /// How the indexed vertices are assembled into primitives.
+    pub topology: PrimitiveTopology,
+    /// The model matrix placing the mesh in the world, ignored for skinned meshes.
+    pub transform: Mat4,
+    /// Whether the mesh is drawn with alpha blending.
+    pub transparent: bool,
+    /// The joints deforming the vertices, if the mesh is skinned.
+    pub skin: Option<Skin>,
+    /// Where the vertices of the mesh came from.
+    pub source: MeshSource,
+}
+
+impl MeshData {
+    /// Creates the data of an untransformed, opaque triangle list mesh.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
         Self {
-            vertex_buffer,
-            index_buffer,
-            count: vertices.len() as u32,
+            vertices,
+            indices,
+            topology: PrimitiveTopology::TriangleList,
+            transform: Mat4::IDENTITY,
+            transparent: false,
+            skin: None,
+            source: MeshSource::Vertices,
+        }
+    }
+
+    /// Uploads the vertices into a new mesh with the data's settings.
+    pub fn upload(self, device: &Device) -> Mesh {
+        let mut mesh = Mesh::new(device, &self.vertices, &self.indices);
+        mesh.topology = self.topology;
+        mesh.transparent = self.transparent;
+        mesh.source = self.source;
+
+        match self.skin {
+            Some(skin) => mesh.set_skin(device, skin),
+            None => mesh.set_transform(self.transform),
+        }
+
+        mesh
+    }
+}
+
+/// Returns the vertices of the lines of `Mesh::grid`, four for every step along the axes.
+fn grid_vertices(spacing: f32, extent: f32) -> Vec<Vertex> {
+    let spacing = spacing.max(GridParams::MIN_DISTANCE);
+    let extent = extent.max(0.0);
+
+    let lines = (extent / spacing) as i32;
+    let mut vertices = Vec::with_capacity(4 * (2 * lines as usize + 1));
+
+    for i in -lines..=lines {
+        let offset = i as f32 * spacing;
+        let color = if i % Mesh::GRID_MAJOR_INTERVAL == 0 {
+            Mesh::GRID_MAJOR_COLOR
+        } else {
+            Mesh::GRID_MINOR_COLOR
+        };
+
+        // one line parallel to the x axis, and one parallel to the z axis
+        vertices.extend([
+            Vertex {
+                pos: [-extent, 0.0, offset],
+                color,
+                ..Default::default()
+            },
+            Vertex {
+                pos: [extent, 0.0, offset],
+                color,
+                ..Default::default()
+            },
+            Vertex {
+                pos: [offset, 0.0, -extent],
+                color,
+                ..Default::default()
+            },
+            Vertex {
+                pos: [offset, 0.0, extent],
+                color,
+                ..Default::default()
+            },
+        ]);
+    }
+
+    vertices
+}
+
+/// Returns the indices of a fan of triangles sharing the first of `vertices` vertices, as
+/// `Mesh::convex_polygon` splits a polygon into. Fails with fewer than 3 vertices.
+fn fan_indices(vertices: usize) -> Result<Vec<u32>> {
+    if vertices < 3 {
+        bail!("a convex polygon needs at least 3 vertices, but got {vertices}");
+    }
+
+    Ok((1..vertices as u32 - 1)
+        .flat_map(|i| [0, i, i + 1])
+        .collect())
+}
+
+/// Merges vertices with the same attributes, see `weld_key`, returning the unique vertices in the
+/// order they first appear and the indices of the original vertices into them.
+fn weld(vertices: &[Vertex]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut unique = Vec::new();
+    let mut indices = Vec::with_capacity(vertices.len());
+    let mut lookup = HashMap::new();
+
+    for vertex in vertices {
+        let index = *lookup.entry(weld_key(vertex)).or_insert_with(|| {
+            unique.push(*vertex);
+            unique.len() as u32 - 1
+        });
+
+        indices.push(index);
+    }
+
+    (unique, indices)
+}
+
+/// Returns the attributes of the vertex rounded to `Mesh::WELD_PRECISION`, equal for vertices which
+/// should be merged.
+fn weld_key(vertex: &Vertex) -> [i64; 20] {
+    let mut key = [0; 20];
+    let joint_indices = vertex.joint_indices.map(f32::from);
+    let attributes = vertex
+        .pos
+        .iter()
+        .chain(&vertex.color)
+        .chain(&vertex.normal)
+        .chain(&vertex.uv)
+        .chain(&joint_indices)
+        .chain(&vertex.joint_weights);
+
+    for (key, &value) in key.iter_mut().zip(attributes) {
+        // negative zero rounds to the same key as zero
+        *key = (value / Mesh::WELD_PRECISION).round() as i64;
+    }
+
+    key
+}
+
+/// Uploads the given vertices into a new vertex buffer with the given usages.
+fn create_vertex_buffer(device: &Device, vertices: &[Vertex], usage: BufferUsages) -> Buffer {
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Mesh Vertex Buffer"),
+        contents: bytemuck::cast_slice(vertices),
+        usage,
+    })
+}
+
+/// Uploads the given indices into a new index buffer.
+fn create_index_buffer(device: &Device, indices: &[u32]) -> Buffer {
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Mesh Index Buffer"),
+        contents: bytemuck::cast_slice(indices),
+        usage: BufferUsages::INDEX,
+    })
+}
+
+/// Uploads the given instances into a new vertex buffer.
+fn create_instance_buffer(device: &Device, instances: &[InstanceRaw]) -> Buffer {
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Mesh Instance Buffer"),
+        contents: bytemuck::cast_slice(instances),
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+    })
+}
+
+/// Applies a mesh's transform to each of its instances.
+fn transform_instances(transform: Mat4, instances: &[InstanceRaw]) -> Vec<InstanceRaw> {
+    instances
+        .iter()
+        .map(|instance| InstanceRaw::from(transform * Mat4::from_cols_array_2d(&instance.model)))
+        .collect()
+}
+
+/// Loads the meshes of a glTF node and all of its children into `meshes`, given the transform of
+/// the node's parent. Skinned meshes are placed by their joints instead of the node's transform.
+#[cfg(feature = "gltf")]
+fn load_gltf_node(
+    document: &gltf::Document,
+    node: &gltf::Node,
+    parent_transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+    meshes: &mut Vec<MeshData>,
+) {
+    let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        let skin = node
+            .skin()
+            .map(|skin| Skin::from_gltf(document, &skin, buffers));
+
+        for primitive in mesh.primitives() {
+            if let Some(mesh) = load_gltf_primitive(&primitive, buffers) {
+                meshes.push(MeshData {
+                    transform,
+                    skin: skin.clone(),
+                    ..mesh
+                });
+            }
+        }
+    }
+
+    for child in node.children() {
+        load_gltf_node(document, &child, transform, buffers, meshes);
+    }
+}
+
+/// Loads a single glTF primitive as the data of a mesh, returning `None` if it can't be
+/// represented.
+#[cfg(feature = "gltf")]
+fn load_gltf_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Option<MeshData> {
+    let topology = match primitive.mode() {
+        gltf::mesh::Mode::Triangles => PrimitiveTopology::TriangleList,
+        gltf::mesh::Mode::TriangleStrip => PrimitiveTopology::TriangleStrip,
+        gltf::mesh::Mode::Lines => PrimitiveTopology::LineList,
+        gltf::mesh::Mode::LineStrip => PrimitiveTopology::LineStrip,
+        gltf::mesh::Mode::Points => PrimitiveTopology::PointList,
+        mode => {
+            warn!("skipping glTF primitive with unsupported mode {mode:?}");
+            return None;
+        }
+    };
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let Some(positions) = reader.read_positions() else {
+        warn!("skipping glTF primitive without positions");
+        return None;
+    };
+
+    let material = primitive.material();
+    let pbr = material.pbr_metallic_roughness();
+
+    if pbr.base_color_texture().is_some() {
+        warn!("ignoring unsupported base color texture of glTF material");
+    }
+
+    let base_color = Vec4::from(pbr.base_color_factor());
+
+    let mut vertices = positions
+        .map(|pos| Vertex {
+            pos,
+            color: base_color.into(),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(colors) = reader.read_colors(0) {
+        for (vertex, color) in vertices.iter_mut().zip(colors.into_rgba_f32()) {
+            vertex.color = (base_color * Vec4::from(color)).into();
+        }
+    }
+
+    if let Some(normals) = reader.read_normals() {
+        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+            vertex.normal = normal;
+        }
+    }
+
+    if let Some(uvs) = reader.read_tex_coords(0) {
+        for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+            vertex.uv = uv;
         }
     }
+
+    if let Some(joints) = reader.read_joints(0) {
+        for (vertex, joints) in vertices.iter_mut().zip(joints.into_u16()) {
+            vertex.joint_indices = joints;
+        }
+    }
+
+    if let Some(weights) = reader.read_weights(0) {
+        for (vertex, weights) in vertices.iter_mut().zip(weights.into_f32()) {
+            vertex.joint_weights = weights;
+        }
+    }
+
+    // unindexed primitives draw their vertices in order
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect::<Vec<_>>(),
+    };
+
+    Some(MeshData {
+        topology,
+        transparent: material.alpha_mode() == gltf::material::AlphaMode::Blend,
+        ..MeshData::new(vertices, indices)
+    })
+}
+
+/// Intersects a ray with a single triangle using the Möller–Trumbore algorithm, returning the
+/// distance along `dir` to the hit.
+fn ray_triangle_intersect(origin: Vec3, dir: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+
+    let p = dir.cross(edge2);
+    let det = edge1.dot(p);
+
+    // the ray is parallel to the triangle
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+
+    let v = dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+    (t > 0.0).then_some(t)
 }
 
 impl Vertex {
-    const ATTRIBS: [VertexAttribute; 2] = vertex_attr_array![
+    // the joints follow the locations of the instance attributes
+    const ATTRIBS: [VertexAttribute; 6] = vertex_attr_array![
         0 => Float32x3,
-        1 => Float32x3,
+        1 => Float32x4,
+        2 => Float32x3,
+        3 => Float32x2,
+        11 => Uint16x4,
+        12 => Float32x4,
     ];
 
     /// Returns the vertex descriptor of how the vertex data is to be interpreted by the shader.
@@ -58,3 +1457,241 @@ impl Vertex {
         }
     }
 }
+
+impl InstanceRaw {
+    const ATTRIBS: [VertexAttribute; 7] = vertex_attr_array![
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x3,
+        9 => Float32x3,
+        10 => Float32x3,
+    ];
+
+    /// Returns the vertex descriptor of how the instance data is to be interpreted by the shader,
+    /// stepping once per instance rather than per vertex.
+    pub fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+impl From<Mat4> for InstanceRaw {
+    fn from(model: Mat4) -> Self {
+        let linear = Mat3::from_mat4(model);
+
+        // a matrix flattening the mesh has no inverse, its normals are then left as they are
+        let normal = if linear.determinant().abs() > f32::EPSILON {
+            linear.inverse().transpose()
+        } else {
+            linear
+        };
+
+        Self {
+            model: model.to_cols_array_2d(),
+            normal: normal.to_cols_array_2d(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+    use super::*;
+    use crate::camera::Camera;
+
+    /// A triangle on the z = -2 plane, with its right angle at the origin of the plane.
+    const TRIANGLE: [Vec3; 3] = [
+        vec3(0.0, 0.0, -2.0),
+        vec3(1.0, 0.0, -2.0),
+        vec3(0.0, 1.0, -2.0),
+    ];
+
+    fn intersect(origin: Vec3, dir: Vec3) -> Option<f32> {
+        let [a, b, c] = TRIANGLE;
+        ray_triangle_intersect(origin, dir, a, b, c)
+    }
+
+    #[test]
+    fn ray_hits_triangle_at_distance() {
+        let distance = intersect(vec3(0.25, 0.25, 0.0), Vec3::NEG_Z).unwrap();
+        assert!((distance - 2.0).abs() < 1e-6, "{distance}");
+
+        // the back of the triangle is hit too
+        let distance = intersect(vec3(0.25, 0.25, -5.0), Vec3::Z).unwrap();
+        assert!((distance - 3.0).abs() < 1e-6, "{distance}");
+    }
+
+    #[test]
+    fn ray_misses_outside_edge() {
+        // just past the hypotenuse, and just past the edge along the x axis
+        assert_eq!(intersect(vec3(0.51, 0.51, 0.0), Vec3::NEG_Z), None);
+        assert_eq!(intersect(vec3(0.5, -0.01, 0.0), Vec3::NEG_Z), None);
+    }
+
+    #[test]
+    fn parallel_ray_misses() {
+        assert_eq!(intersect(vec3(-1.0, 0.25, -2.0), Vec3::X), None);
+        assert_eq!(intersect(vec3(0.25, 0.25, 0.0), Vec3::Y), None);
+    }
+
+    #[test]
+    fn screen_ray_hits_triangle_under_cursor() {
+        // facing -Z from just inside the triangle's corner, with the center of the screen over it
+        let size = PhysicalSize::new(800, 600);
+        let camera = Camera::new(vec3(0.1, 0.1, 0.0), -FRAC_PI_2, 0.0, size);
+        let (origin, dir) = camera.screen_ray(PhysicalPosition::new(400.0, 300.0), size);
+
+        let distance = intersect(origin, dir).unwrap();
+        assert!(
+            (origin + distance * dir).abs_diff_eq(vec3(0.1, 0.1, -2.0), 1e-4),
+            "{distance}"
+        );
+
+        // the corner of the screen points away from it
+        let (origin, dir) = camera.screen_ray(PhysicalPosition::new(0.0, 600.0), size);
+        assert_eq!(intersect(origin, dir), None);
+    }
+
+    #[test]
+    fn triangle_behind_ray_misses() {
+        assert_eq!(intersect(vec3(0.25, 0.25, 0.0), Vec3::Z), None);
+    }
+
+    /// Returns a vertex at the given position, with every other attribute the same.
+    fn vertex(pos: [f32; 3]) -> Vertex {
+        Vertex {
+            pos,
+            color: [1.0; 4],
+            normal: [0.0, 0.0, 1.0],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn welds_shared_edge_of_quad() {
+        // two triangles sharing the edge from (1, 0) to (0, 1), one of them with a negative zero
+        let vertices = [
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0]),
+            vertex([0.0, 1.0, 0.0]),
+            vertex([1.0, -0.0, 0.0]),
+            vertex([1.0, 1.0, 0.0]),
+            vertex([-0.0, 1.0, -0.0]),
+        ];
+
+        let (unique, indices) = weld(&vertices);
+        assert_eq!(unique.len(), 4);
+        assert_eq!(indices, [0, 1, 2, 1, 3, 2]);
+        assert_eq!(unique[3].pos, [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn fans_polygon_into_triangles() {
+        assert_eq!(fan_indices(3).unwrap(), [0, 1, 2]);
+        assert_eq!(fan_indices(4).unwrap(), [0, 1, 2, 0, 2, 3]);
+
+        let indices = fan_indices(8).unwrap();
+        assert_eq!(indices.len(), 3 * (8 - 2));
+        assert!(indices.chunks_exact(3).all(|triangle| triangle[0] == 0));
+        assert_eq!(indices.iter().max(), Some(&7));
+    }
+
+    #[test]
+    fn fan_needs_three_vertices() {
+        assert!(fan_indices(2).is_err());
+        assert!(fan_indices(0).is_err());
+    }
+
+    #[test]
+    fn grid_clamps_spacing_and_extent() {
+        assert_eq!(grid_vertices(1.0, 2.0).len(), 4 * 5);
+
+        // only the lines through the origin are left without an extent
+        assert_eq!(grid_vertices(1.0, -2.0).len(), 4);
+        assert_eq!(grid_vertices(-1.0, 0.0).len(), 4);
+        assert_eq!(grid_vertices(f32::NAN, f32::NAN).len(), 4);
+
+        let vertices = grid_vertices(0.0, 0.01);
+        let spacing = vertices[4].pos[2] - vertices[0].pos[2];
+        assert!(
+            (spacing - GridParams::MIN_DISTANCE).abs() < 1e-6,
+            "{spacing}"
+        );
+    }
+
+    #[test]
+    fn quad_draws_its_indices() {
+        // two triangles sharing two of the four vertices
+        let vertices = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ]
+        .map(vertex);
+        let indices = [0, 1, 2, 0, 2, 3];
+
+        let range = DrawRange::whole(vertices.len() as u32, Some(indices.len() as u32));
+        assert_eq!(
+            range,
+            DrawRange::Indexed {
+                indices: 0..6,
+                base_vertex: 0
+            }
+        );
+        assert_eq!(range.count(), 6);
+
+        // without indices, every vertex is drawn once
+        assert_eq!(DrawRange::whole(vertices.len() as u32, None).count(), 4);
+    }
+
+    #[cfg(feature = "gltf")]
+    #[test]
+    fn loads_gltf_cube() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/models/cube.glb");
+        let meshes = Mesh::load_gltf(&path).unwrap();
+        assert_eq!(meshes.len(), 1);
+
+        // four vertices for each face, so that every face has its own normal
+        let cube = &meshes[0];
+        assert_eq!(cube.vertices.len(), 24);
+        assert_eq!(cube.indices.len(), 36);
+        assert!(cube.indices.iter().all(|&index| index < 24));
+        assert_eq!(cube.topology, PrimitiveTopology::TriangleList);
+
+        // the triangles wind counterclockwise around the outward normals
+        for triangle in cube.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(cube.vertices[triangle[i] as usize].pos));
+            let normal = Vec3::from(cube.vertices[triangle[0] as usize].normal);
+            assert!((b - a).cross(c - a).dot(normal) > 0.0);
+        }
+
+        // placed by its node and colored by its material
+        assert_eq!(cube.transform, Mat4::from_translation(Vec3::Y));
+        assert!(cube
+            .vertices
+            .iter()
+            .all(|vertex| vertex.color == [0.25, 0.5, 0.75, 1.0]));
+        assert_eq!(cube.source, MeshSource::Gltf { path, index: 0 });
+    }
+
+    #[test]
+    fn keeps_vertices_with_different_attributes() {
+        let mut vertices = [vertex([0.0; 3]), vertex([0.0; 3]), vertex([0.0; 3])];
+        vertices[1].normal = [0.0, 1.0, 0.0];
+        // within the precision of the weld
+        vertices[2].pos[0] = Mesh::WELD_PRECISION * 0.1;
+
+        let (unique, indices) = weld(&vertices);
+        assert_eq!(unique.len(), 2);
+        assert_eq!(indices, [0, 1, 0]);
+    }
+}
@@ -0,0 +1,173 @@
+use wgpu::*;
+
+use crate::mesh::Vertex;
+
+/// Large vertex and index buffers shared by many meshes, each mesh being drawn from its own
+/// range of them. Saves allocating a pair of small buffers for every mesh. The buffers grow by
+/// reallocating and copying over their contents once they're full, so meshes only store their
+/// ranges and are drawn from whichever buffers the arena currently holds.
+#[derive(Debug)]
+pub struct MeshArena {
+    /// The vertices of all the meshes, one after another.
+    vertex_buffer: Buffer,
+    /// The indices of all the meshes, one after another. Stored as a list of `u32`s relative to
+    /// the first vertex of their mesh.
+    index_buffer: Buffer,
+    /// The number of vertices allocated so far.
+    vertex_len: u32,
+    /// The number of indices allocated so far.
+    index_len: u32,
+}
+
+/// The range of a `MeshArena`'s buffers holding the vertices and indices of a single mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaRange {
+    /// The first vertex of the mesh, added to its indices when drawing.
+    pub vertex_offset: u32,
+    /// The first index of the mesh.
+    pub index_offset: u32,
+    /// The number of indices of the mesh.
+    pub count: u32,
+}
+
+impl MeshArena {
+    /// The number of vertices the buffers initially have room for.
+    pub const INITIAL_VERTICES: u32 = 1 << 14;
+    /// The number of indices the buffers initially have room for.
+    pub const INITIAL_INDICES: u32 = 1 << 16;
+
+    /// Creates an arena with buffers of the initial capacity, without any meshes in them.
+    pub fn new(device: &Device) -> Self {
+        Self {
+            vertex_buffer: create_vertex_buffer(device, Self::INITIAL_VERTICES),
+            index_buffer: create_index_buffer(device, Self::INITIAL_INDICES),
+            vertex_len: 0,
+            index_len: 0,
+        }
+    }
+
+    /// Uploads the vertices and indices of a mesh after the ones already in the arena, growing the
+    /// buffers if they don't fit, and returns the range they were stored in.
+    pub fn allocate(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> ArenaRange {
+        let range = ArenaRange {
+            vertex_offset: self.vertex_len,
+            index_offset: self.index_len,
+            count: indices.len() as u32,
+        };
+
+        let vertex_len = self.vertex_len + vertices.len() as u32;
+        let index_len = self.index_len + indices.len() as u32;
+
+        if vertex_len > self.vertex_capacity() || index_len > self.index_capacity() {
+            self.grow(device, queue, vertex_len, index_len);
+        }
+
+        queue.write_buffer(
+            &self.vertex_buffer,
+            Self::vertex_bytes(range.vertex_offset),
+            bytemuck::cast_slice(vertices),
+        );
+        queue.write_buffer(
+            &self.index_buffer,
+            Self::index_bytes(range.index_offset),
+            bytemuck::cast_slice(indices),
+        );
+
+        self.vertex_len = vertex_len;
+        self.index_len = index_len;
+
+        range
+    }
+
+    /// Returns the buffer holding the vertices of all the meshes.
+    pub fn vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+    /// Returns the buffer holding the indices of all the meshes.
+    pub fn index_buffer(&self) -> &Buffer {
+        &self.index_buffer
+    }
+
+    /// Returns the number of vertices allocated so far.
+    pub fn vertex_len(&self) -> u32 {
+        self.vertex_len
+    }
+
+    /// Returns the number of indices allocated so far.
+    pub fn index_len(&self) -> u32 {
+        self.index_len
+    }
+
+    /// Returns the number of vertices the vertex buffer has room for.
+    pub fn vertex_capacity(&self) -> u32 {
+        (self.vertex_buffer.size() / Self::vertex_bytes(1)) as u32
+    }
+
+    /// Returns the number of indices the index buffer has room for.
+    pub fn index_capacity(&self) -> u32 {
+        (self.index_buffer.size() / Self::index_bytes(1)) as u32
+    }
+
+    /// Reallocates the buffers which are too small to hold the given numbers of vertices and
+    /// indices, doubling their capacity until they fit, and copies the allocated data over.
+    fn grow(&mut self, device: &Device, queue: &Queue, vertices: u32, indices: u32) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Mesh Arena Grow Encoder"),
+        });
+
+        if vertices > self.vertex_capacity() {
+            let buffer = create_vertex_buffer(device, vertices.next_power_of_two());
+            let used = Self::vertex_bytes(self.vertex_len);
+
+            encoder.copy_buffer_to_buffer(&self.vertex_buffer, 0, &buffer, 0, used);
+            self.vertex_buffer = buffer;
+        }
+
+        if indices > self.index_capacity() {
+            let buffer = create_index_buffer(device, indices.next_power_of_two());
+            let used = Self::index_bytes(self.index_len);
+
+            encoder.copy_buffer_to_buffer(&self.index_buffer, 0, &buffer, 0, used);
+            self.index_buffer = buffer;
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Returns the size of the given number of vertices in bytes.
+    fn vertex_bytes(vertices: u32) -> BufferAddress {
+        vertices as BufferAddress * std::mem::size_of::<Vertex>() as BufferAddress
+    }
+
+    /// Returns the size of the given number of indices in bytes.
+    fn index_bytes(indices: u32) -> BufferAddress {
+        indices as BufferAddress * std::mem::size_of::<u32>() as BufferAddress
+    }
+}
+
+/// Creates a vertex buffer of an arena with room for the given number of vertices.
+fn create_vertex_buffer(device: &Device, vertices: u32) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Mesh Arena Vertex Buffer"),
+        size: MeshArena::vertex_bytes(vertices),
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+/// Creates an index buffer of an arena with room for the given number of indices.
+fn create_index_buffer(device: &Device, indices: u32) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Mesh Arena Index Buffer"),
+        size: MeshArena::index_bytes(indices),
+        usage: BufferUsages::INDEX | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
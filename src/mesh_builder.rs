@@ -0,0 +1,161 @@
+use anyhow::{anyhow, ensure, Result};
+use glam::*;
+use wgpu::Device;
+
+use crate::mesh::{Mesh, Vertex};
+
+/// Assembles the vertices of a triangle list mesh from separate slices of each attribute,
+/// checking that they all have one entry per position before interleaving them into `Vertex`es.
+/// Attributes which aren't given are filled in: colors are white, normals are computed from the
+/// triangles and texture coordinates are zero.
+#[derive(Debug, Clone, Default)]
+pub struct MeshBuilder {
+    /// The positions of the vertices, which the other attributes have to match the length of.
+    positions: Vec<Vec3>,
+    /// The straight rgba colors of the vertices, if given.
+    colors: Option<Vec<Vec4>>,
+    /// The normals of the vertices, if given.
+    normals: Option<Vec<Vec3>>,
+    /// The texture coordinates of the vertices, if given.
+    uvs: Option<Vec<Vec2>>,
+    /// The indices of the triangles' vertices, or `None` if every three consecutive vertices form
+    /// a triangle.
+    indices: Option<Vec<u32>>,
+}
+
+impl MeshBuilder {
+    /// Starts a mesh with vertices at the given positions.
+    pub fn new(positions: &[Vec3]) -> Self {
+        Self {
+            positions: positions.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the straight rgba colors of the vertices.
+    pub fn with_colors(mut self, colors: &[Vec4]) -> Self {
+        self.colors = Some(colors.to_vec());
+        self
+    }
+
+    /// Sets the normals of the vertices, which are normalized when building.
+    pub fn with_normals(mut self, normals: &[Vec3]) -> Self {
+        self.normals = Some(normals.to_vec());
+        self
+    }
+
+    /// Sets the texture coordinates of the vertices.
+    pub fn with_uvs(mut self, uvs: &[Vec2]) -> Self {
+        self.uvs = Some(uvs.to_vec());
+        self
+    }
+
+    /// Sets the indices of the triangles' vertices, three per triangle.
+    pub fn with_indices(mut self, indices: &[u32]) -> Self {
+        self.indices = Some(indices.to_vec());
+        self
+    }
+
+    /// Returns the indices of the triangles, failing if they don't form whole triangles or refer
+    /// to vertices which don't exist.
+    pub fn indices(&self) -> Result<Vec<u32>> {
+        let count = self.positions.len();
+
+        let Some(indices) = &self.indices else {
+            ensure!(
+                count.is_multiple_of(3),
+                "{count} vertices without indices don't form whole triangles"
+            );
+
+            return Ok((0..count as u32).collect());
+        };
+
+        ensure!(
+            indices.len().is_multiple_of(3),
+            "{} indices don't form whole triangles",
+            indices.len()
+        );
+
+        if let Some(index) = indices.iter().find(|&&index| index as usize >= count) {
+            return Err(anyhow!(
+                "index {index} is out of bounds for {count} vertices"
+            ));
+        }
+
+        Ok(indices.clone())
+    }
+
+    /// Interleaves the attributes into vertices, failing if any of the given attributes doesn't
+    /// have as many entries as there are positions, or the indices are invalid.
+    pub fn vertices(&self) -> Result<Vec<Vertex>> {
+        let count = self.positions.len();
+
+        check_len("colors", self.colors.as_deref(), count)?;
+        check_len("normals", self.normals.as_deref(), count)?;
+        check_len("uvs", self.uvs.as_deref(), count)?;
+
+        let indices = self.indices()?;
+
+        let normals = match &self.normals {
+            Some(normals) => normals
+                .iter()
+                .map(|normal| normal.normalize_or_zero())
+                .collect(),
+            None => smooth_normals(&self.positions, &indices),
+        };
+
+        Ok((0..count)
+            .map(|index| Vertex {
+                pos: self.positions[index].into(),
+                color: self
+                    .colors
+                    .as_ref()
+                    .map_or(Vec4::ONE, |colors| colors[index])
+                    .into(),
+                normal: normals[index].into(),
+                uv: self
+                    .uvs
+                    .as_ref()
+                    .map_or(Vec2::ZERO, |uvs| uvs[index])
+                    .into(),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Uploads the assembled vertices and indices as a new mesh, failing if they're invalid as
+    /// described in `MeshBuilder::vertices`.
+    pub fn build(&self, device: &Device) -> Result<Mesh> {
+        Ok(Mesh::new(device, &self.vertices()?, &self.indices()?))
+    }
+}
+
+/// Fails if the attribute was given with a different number of entries than there are vertices.
+fn check_len<T>(name: &str, attribute: Option<&[T]>, count: usize) -> Result<()> {
+    match attribute {
+        Some(attribute) if attribute.len() != count => Err(anyhow!(
+            "got {} {name} for {count} vertices",
+            attribute.len()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Computes the normal of every vertex by averaging the normals of the triangles sharing it,
+/// weighted by their area. Vertices which aren't part of any triangle get a zero normal.
+pub(crate) fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+
+        // the cross product's length is twice the triangle's area, weighting it for free
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+
+        for index in [a, b, c] {
+            normals[index] += normal;
+        }
+    }
+
+    normals.into_iter().map(Vec3::normalize_or_zero).collect()
+}
@@ -0,0 +1,415 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::*;
+use log::*;
+
+use crate::{mesh::Vertex, mesh_builder::smooth_normals};
+
+/// The color of vertices which have neither a color of their own nor a material.
+pub const DEFAULT_COLOR: Vec4 = vec4(0.8, 0.8, 0.8, 1.0);
+
+/// The triangles of an OBJ file using the same material, ready to be uploaded as a mesh.
+#[derive(Debug, Clone)]
+pub struct ObjPart {
+    /// The name of the material the faces use, or `None` for the faces before any `usemtl`.
+    pub material: Option<String>,
+    /// The vertices of the faces, colored by the material's diffuse color multiplied with the
+    /// vertex colors.
+    pub vertices: Vec<Vertex>,
+    /// The indices of the triangles' vertices.
+    pub indices: Vec<u32>,
+}
+
+/// Loads the faces of the OBJ file at `path`, split into a part per material. The materials
+/// libraries referenced with `mtllib` are loaded relative to the file. Faces are colored with
+/// the `Kd` diffuse color of their material multiplied with the colors given after the positions
+/// of `v x y z r g b` lines, falling back to `DEFAULT_COLOR` when there are neither.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<ObjPart>> {
+    let path = path.as_ref();
+    let source =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    parse(&source, |library| {
+        let path = directory.join(library);
+
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))
+    })
+    .with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parses the source of an OBJ file into a part per material like `load`, reading the source of
+/// every referenced material library with `read_library`. Libraries which can't be read are
+/// skipped with a warning, their materials then falling back to the vertex colors.
+pub fn parse(
+    source: &str,
+    mut read_library: impl FnMut(&str) -> Result<String>,
+) -> Result<Vec<ObjPart>> {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+
+    let mut materials = HashMap::new();
+    let mut parts = Vec::<PartBuilder>::new();
+    let mut current = 0;
+
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| anyhow!("line {}: {message}", number + 1);
+
+        // everything after a `#` is a comment
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+
+        let numbers = || {
+            line.split_whitespace()
+                .skip(1)
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error(&format!("`{keyword}` has a malformed number")))
+        };
+
+        match keyword {
+            "v" => match numbers()?[..] {
+                [x, y, z] | [x, y, z, _] => {
+                    positions.push(vec3(x, y, z));
+                    colors.push(None);
+                }
+                [x, y, z, r, g, b] => {
+                    positions.push(vec3(x, y, z));
+                    colors.push(Some(vec4(r, g, b, 1.0)));
+                }
+                _ => return Err(error("`v` needs 3 coordinates, optionally followed by rgb")),
+            },
+            "vn" => match numbers()?[..] {
+                [x, y, z] => normals.push(vec3(x, y, z)),
+                _ => return Err(error("`vn` needs 3 coordinates")),
+            },
+            "vt" => match numbers()?[..] {
+                // obj textures run bottom to top, wgpu's top to bottom
+                [u] => uvs.push(vec2(u, 1.0)),
+                [u, v, ..] => uvs.push(vec2(u, 1.0 - v)),
+                _ => return Err(error("`vt` needs at least 1 coordinate")),
+            },
+            "f" => {
+                if parts.is_empty() {
+                    parts.push(PartBuilder::new(None));
+                }
+
+                let corners = words
+                    .map(|corner| {
+                        Corner::parse(corner, positions.len(), uvs.len(), normals.len())
+                            .map_err(|message| error(&message))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                if corners.len() < 3 {
+                    return Err(error("`f` needs at least 3 vertices"));
+                }
+
+                parts[current].add_face(&corners);
+            }
+            "usemtl" => {
+                let name = words.next().map(str::to_string);
+
+                current = match parts.iter().position(|part| part.material == name) {
+                    Some(index) => index,
+                    None => {
+                        parts.push(PartBuilder::new(name));
+                        parts.len() - 1
+                    }
+                };
+            }
+            "mtllib" => {
+                for library in words {
+                    match read_library(library).and_then(|source| parse_mtl(&source)) {
+                        Ok(library) => materials.extend(library),
+                        Err(error) => warn!("skipping material library {library}: {error:#}"),
+                    }
+                }
+            }
+            // objects, groups and smoothing groups don't affect how the faces are drawn, and
+            // lines and points aren't supported
+            _ => {}
+        }
+    }
+
+    Ok(parts
+        .into_iter()
+        .filter(|part| !part.indices.is_empty())
+        .map(|part| {
+            let diffuse = part.material.as_ref().and_then(|name| {
+                let diffuse = materials.get(name).copied();
+
+                if diffuse.is_none() {
+                    warn!("material {name} isn't defined by any material library");
+                }
+
+                diffuse
+            });
+
+            part.build(&positions, &colors, &normals, &uvs, diffuse)
+        })
+        .collect())
+}
+
+/// Parses the source of an MTL material library into the diffuse color of each material.
+/// Materials without a `Kd` color are white.
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, Vec4>> {
+    let mut materials = HashMap::new();
+    let mut current = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("newmtl") => {
+                let name = words.next().unwrap_or_default().to_string();
+                materials.insert(name.clone(), Vec4::ONE);
+                current = Some(name);
+            }
+            Some("Kd") => {
+                let Some(name) = &current else {
+                    bail!("line {}: `Kd` before any `newmtl`", number + 1);
+                };
+
+                let channels = words
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+                    .filter(|channels| channels.len() == 3)
+                    .ok_or_else(|| anyhow!("line {}: `Kd` needs 3 numbers", number + 1))?;
+
+                materials.insert(
+                    name.clone(),
+                    vec4(channels[0], channels[1], channels[2], 1.0),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// A vertex of a face, as the zero-based indices of its attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Corner {
+    /// The index of the position.
+    position: usize,
+    /// The index of the texture coordinates, if given.
+    uv: Option<usize>,
+    /// The index of the normal, if given.
+    normal: Option<usize>,
+}
+
+impl Corner {
+    /// Parses a `position/uv/normal` triple of a face, where the texture coordinates and normal
+    /// are optional, given how many of each attribute have been defined so far.
+    fn parse(corner: &str, positions: usize, uvs: usize, normals: usize) -> Result<Self, String> {
+        let mut indices = corner.split('/');
+
+        let position = indices.next().unwrap_or_default();
+
+        let position = resolve_index(position, positions)?
+            .ok_or_else(|| format!("face vertex `{corner}` has no position"))?;
+        let uv = resolve_index(indices.next().unwrap_or_default(), uvs)?;
+        let normal = resolve_index(indices.next().unwrap_or_default(), normals)?;
+
+        Ok(Self {
+            position,
+            uv,
+            normal,
+        })
+    }
+}
+
+/// Converts a one-based index of an OBJ face (or a negative one counting back from the last of
+/// the `count` elements defined so far) into a zero-based one, `None` if it's empty.
+fn resolve_index(index: &str, count: usize) -> Result<Option<usize>, String> {
+    if index.is_empty() {
+        return Ok(None);
+    }
+
+    let parsed = index
+        .parse::<i64>()
+        .map_err(|_| format!("malformed index `{index}`"))?;
+
+    let resolved = match parsed {
+        1.. => parsed - 1,
+        ..=-1 => count as i64 + parsed,
+        0 => return Err("indices start at 1".to_string()),
+    };
+
+    if !(0..count as i64).contains(&resolved) {
+        return Err(format!(
+            "index {parsed} is out of bounds for {count} elements"
+        ));
+    }
+
+    Ok(Some(resolved as usize))
+}
+
+/// The faces of a part being collected while parsing, with every distinct corner becoming a
+/// vertex.
+struct PartBuilder {
+    /// The name of the material of the part.
+    material: Option<String>,
+    /// The distinct corners of the faces, in the order their vertices are created.
+    corners: Vec<Corner>,
+    /// The index of the vertex of every corner.
+    lookup: HashMap<Corner, u32>,
+    /// The indices of the triangles' vertices.
+    indices: Vec<u32>,
+}
+
+impl PartBuilder {
+    /// Creates a part without any faces.
+    fn new(material: Option<String>) -> Self {
+        Self {
+            material,
+            corners: Vec::new(),
+            lookup: HashMap::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    /// Adds a polygon to the part, split into a fan of triangles around its first corner.
+    fn add_face(&mut self, corners: &[Corner]) {
+        let indices = corners
+            .iter()
+            .map(|corner| {
+                *self.lookup.entry(*corner).or_insert_with(|| {
+                    self.corners.push(*corner);
+                    self.corners.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for index in 1..indices.len() - 1 {
+            self.indices
+                .extend([indices[0], indices[index], indices[index + 1]]);
+        }
+    }
+
+    /// Creates the vertices of the part from the attributes of the file, colored with the
+    /// diffuse color of its material. If any corner lacks a normal, the normals of the whole part
+    /// are computed from its triangles instead.
+    fn build(
+        self,
+        positions: &[Vec3],
+        colors: &[Option<Vec4>],
+        normals: &[Vec3],
+        uvs: &[Vec2],
+        diffuse: Option<Vec4>,
+    ) -> ObjPart {
+        let corner_positions = self
+            .corners
+            .iter()
+            .map(|corner| positions[corner.position])
+            .collect::<Vec<_>>();
+
+        let corner_normals = match self
+            .corners
+            .iter()
+            .map(|corner| {
+                corner
+                    .normal
+                    .map(|normal| normals[normal].normalize_or_zero())
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(normals) => normals,
+            None => smooth_normals(&corner_positions, &self.indices),
+        };
+
+        let vertices = self
+            .corners
+            .iter()
+            .enumerate()
+            .map(|(index, corner)| {
+                let color = match (colors[corner.position], diffuse) {
+                    (Some(color), Some(diffuse)) => color * diffuse,
+                    (color, diffuse) => color.or(diffuse).unwrap_or(DEFAULT_COLOR),
+                };
+
+                Vertex {
+                    pos: corner_positions[index].into(),
+                    color: color.into(),
+                    normal: corner_normals[index].into(),
+                    uv: corner.uv.map_or(Vec2::ZERO, |uv| uvs[uv]).into(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        ObjPart {
+            material: self.material,
+            vertices,
+            indices: self.indices,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the colors of the part's vertices.
+    fn colors(part: &ObjPart) -> Vec<Vec4> {
+        part.vertices
+            .iter()
+            .map(|vertex| Vec4::from(vertex.color))
+            .collect()
+    }
+
+    #[test]
+    fn colors_faces_by_material() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/models/materials.obj");
+        let parts = load(path).unwrap();
+
+        let materials = parts
+            .iter()
+            .map(|part| part.material.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            materials,
+            [
+                None,
+                Some("red"),
+                Some("blue"),
+                Some("plain"),
+                Some("missing")
+            ]
+        );
+
+        // faces without a material or vertex colors are gray
+        assert_eq!(colors(&parts[0]), [DEFAULT_COLOR; 3]);
+
+        // the quad is split into two triangles sharing its corners
+        assert_eq!(parts[1].vertices.len(), 4);
+        assert_eq!(parts[1].indices.len(), 6);
+        assert_eq!(colors(&parts[1]), [vec4(1.0, 0.0, 0.0, 1.0); 4]);
+
+        assert_eq!(colors(&parts[2]), [vec4(0.0, 0.0, 0.5, 1.0); 3]);
+        assert_eq!(colors(&parts[3]), [Vec4::ONE; 3]);
+
+        let missing = colors(&parts[4]);
+        assert_eq!(missing[..3], [DEFAULT_COLOR; 3]);
+        assert_eq!(missing[3..], [vec4(0.5, 1.0, 0.5, 1.0); 3]);
+    }
+
+    #[test]
+    fn skips_unreadable_library() {
+        let source = "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+        let parts = parse(source, |_| bail!("not found")).unwrap();
+        assert_eq!(colors(&parts[0]), [DEFAULT_COLOR; 3]);
+    }
+}
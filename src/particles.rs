@@ -0,0 +1,517 @@
+use glam::*;
+use wgpu::{util::StagingBelt, *};
+
+use crate::{camera::Camera, viewport::Viewport};
+
+/// How a `ParticleSystem` emits and moves its particles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleParams {
+    /// The position particles are emitted from.
+    pub origin: Vec3,
+    /// The number of particles emitted per second. Never negative.
+    pub spawn_rate: f32,
+    /// The number of seconds a particle lives for after being emitted. Always positive.
+    pub lifetime: f32,
+    /// The acceleration applied to every particle, in units per second squared.
+    pub gravity: Vec3,
+    /// The velocity particles are emitted with, before the `spread` is added.
+    pub velocity: Vec3,
+    /// The radius of the sphere a random velocity added to every emitted particle is picked from.
+    pub spread: f32,
+    /// The width and height of the square every particle is drawn as, in world units.
+    pub size: f32,
+    /// The straight rgba color of particles when they're emitted.
+    pub start_color: Vec4,
+    /// The color particles fade to by the end of their lifetime.
+    pub end_color: Vec4,
+    /// The most particles alive at once, further ones not being emitted until others die. At
+    /// least one.
+    pub max_particles: u32,
+}
+
+/// A single particle of a `ParticleSystem`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    /// The position of the particle in world space.
+    pub position: Vec3,
+    /// The velocity of the particle, in units per second.
+    pub velocity: Vec3,
+    /// The current straight rgba color of the particle.
+    pub color: Vec4,
+    /// The number of seconds since the particle was emitted.
+    pub age: f32,
+    /// The number of seconds the particle lives for.
+    pub lifetime: f32,
+}
+
+/// A live particle, laid out as it is uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct ParticleInstance {
+    /// The position of the particle's center.
+    position: [f32; 3],
+    /// The width and height of the particle's square.
+    size: f32,
+    /// The straight rgba color of the particle.
+    color: [f32; 4],
+}
+
+/// The camera of a viewport, laid out as the particles' shader uses it.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct ParticleCameraUniform {
+    /// The camera's view-projection matrix.
+    view_proj: [[f32; 4]; 4],
+    /// The vector pointing to the right of the camera, which the particles' squares face along.
+    right: [f32; 3],
+    /// Pads the vectors to 16 bytes.
+    _padding: f32,
+    /// The vector pointing up out of the top of the camera's view.
+    up: [f32; 3],
+    /// Pads the uniform to a multiple of 16 bytes.
+    _padding_up: f32,
+}
+
+impl ParticleParams {
+    /// A fountain of orange sparks rising from the origin and falling back down.
+    pub const DEFAULT: Self = Self {
+        origin: Vec3::ZERO,
+        spawn_rate: 200.0,
+        lifetime: 2.0,
+        gravity: vec3(0.0, -9.81, 0.0),
+        velocity: vec3(0.0, 6.0, 0.0),
+        spread: 2.0,
+        size: 0.1,
+        start_color: vec4(1.0, 0.8, 0.3, 1.0),
+        end_color: vec4(1.0, 0.2, 0.0, 0.0),
+        max_particles: 1000,
+    };
+    /// The shortest lifetime particles are emitted with.
+    pub const MIN_LIFETIME: f32 = 0.001;
+
+    /// Returns the parameters with the spawn rate, spread and size clamped to zero, the lifetime
+    /// to `Self::MIN_LIFETIME` and the number of particles to one from below.
+    pub fn clamped(self) -> Self {
+        Self {
+            spawn_rate: self.spawn_rate.max(0.0),
+            lifetime: self.lifetime.max(Self::MIN_LIFETIME),
+            spread: self.spread.max(0.0),
+            size: self.size.max(0.0),
+            max_particles: self.max_particles.max(1),
+            ..self
+        }
+    }
+}
+
+impl Default for ParticleParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A stream of particles emitted from a point, moved on the cpu and drawn as camera-facing squares
+/// of soft round dots blended over the scene. The particles aren't sorted, so ones of different
+/// colors may blend over each other in the wrong order, which is hard to tell apart for small
+/// particles.
+#[derive(Debug)]
+pub struct ParticleSystem {
+    /// How the particles are emitted and moved.
+    params: ParticleParams,
+    /// The live particles, in the order they were emitted.
+    particles: Vec<Particle>,
+    /// Whether new particles are being emitted.
+    emitting: bool,
+    /// The fraction of a particle owed from previous updates at the spawn rate.
+    pending: f32,
+    /// The state of the random number generator picking the velocities of the particles.
+    seed: u32,
+    /// The vertex buffer of the live particles' `ParticleInstance`s, with room for
+    /// `ParticleParams::max_particles`.
+    instance_buffer: Buffer,
+    /// The number of instances uploaded to the `instance_buffer`.
+    instance_count: u32,
+    /// The uniform buffer of the cameras, one for every viewport at multiples of
+    /// `Camera::UNIFORM_STRIDE`.
+    camera_buffer: Buffer,
+    /// The layout of the `bind_group`, kept around to recreate the pipeline.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the camera buffer.
+    bind_group: BindGroup,
+    /// The shader module of the pipeline.
+    shader: ShaderModule,
+    /// Draws the particles as instanced squares.
+    pipeline: RenderPipeline,
+}
+
+impl ParticleSystem {
+    /// The seed of the random number generator of new particle systems.
+    const SEED: u32 = 0x9e37_79b9;
+
+    /// Creates an emitting particle system without any particles yet, drawn onto a target with the
+    /// given formats, depth test and sample count. The cameras are uploaded with
+    /// `ParticleSystem::update_camera` before drawing.
+    pub fn new(
+        device: &Device,
+        params: ParticleParams,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> Self {
+        let params = params.clamped();
+
+        let camera_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Particle Camera Uniform Buffer"),
+            size: Camera::UNIFORM_STRIDE * Viewport::MAX_COUNT as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let camera_size = std::mem::size_of::<ParticleCameraUniform>() as BufferAddress;
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(camera_size),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Particle Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &camera_buffer,
+                    offset: 0,
+                    size: BufferSize::new(camera_size),
+                }),
+            }],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/particles.wgsl").into()),
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &shader,
+            &bind_group_layout,
+            format,
+            depth_format,
+            depth_compare,
+            sample_count,
+        );
+
+        Self {
+            params,
+            particles: Vec::new(),
+            emitting: true,
+            pending: 0.0,
+            seed: Self::SEED,
+            instance_buffer: Self::create_instance_buffer(device, params.max_particles),
+            instance_count: 0,
+            camera_buffer,
+            bind_group_layout,
+            bind_group,
+            shader,
+            pipeline,
+        }
+    }
+
+    /// Creates the vertex buffer of the particles' instances, with room for `capacity` of them.
+    fn create_instance_buffer(device: &Device, capacity: u32) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("Particle Instance Buffer"),
+            size: capacity as BufferAddress
+                * std::mem::size_of::<ParticleInstance>() as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Creates the pipeline drawing the particles onto a target with the given formats, depth test
+    /// and sample count.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Particle Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Particle Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ParticleInstance>() as BufferAddress,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: &vertex_attr_array![
+                        0 => Float32x3,
+                        1 => Float32,
+                        2 => Float32x4,
+                    ],
+                }],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    write_mask: ColorWrites::ALL,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            // the particles are hidden by the meshes in front of them, but don't hide each other
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Recreates the pipeline for a target with the given formats, depth test and sample count.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_compare: CompareFunction,
+        sample_count: u32,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.shader,
+            &self.bind_group_layout,
+            format,
+            depth_format,
+            depth_compare,
+            sample_count,
+        );
+    }
+
+    /// Returns how the particles are emitted and moved.
+    pub fn params(&self) -> ParticleParams {
+        self.params
+    }
+
+    /// Sets how the particles are emitted and moved, clamped with `ParticleParams::clamped`. Live
+    /// particles keep their velocity and lifetime, the newest ones being removed if there are more
+    /// than the new `max_particles`.
+    pub fn set_params(&mut self, device: &Device, params: ParticleParams) {
+        let params = params.clamped();
+
+        if params.max_particles != self.params.max_particles {
+            self.instance_buffer = Self::create_instance_buffer(device, params.max_particles);
+            self.particles.truncate(params.max_particles as usize);
+            self.instance_count = 0;
+        }
+
+        self.params = params;
+    }
+
+    /// Returns the live particles, in the order they were emitted.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Returns whether new particles are being emitted.
+    pub fn emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// Sets whether new particles are emitted at the spawn rate. The live particles keep moving
+    /// until they die either way.
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+        self.pending = 0.0;
+    }
+
+    /// Returns whether any particles are alive or being emitted, so that the system needs to be
+    /// updated.
+    pub fn is_active(&self) -> bool {
+        !self.particles.is_empty() || (self.emitting && self.params.spawn_rate > 0.0)
+    }
+
+    /// Emits `count` particles at once, such as for a burst of sparks, as far as there's room for
+    /// them.
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.emit();
+        }
+    }
+
+    /// Removes every live particle.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.instance_count = 0;
+    }
+
+    /// Advances the particles by `dt` seconds: removes the ones which outlived their lifetime,
+    /// moves the others and emits new ones at the spawn rate.
+    pub fn update(&mut self, dt: f32) {
+        let ParticleParams {
+            gravity,
+            start_color,
+            end_color,
+            ..
+        } = self.params;
+
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+
+            particle.velocity += gravity * dt;
+            particle.position += particle.velocity * dt;
+            particle.color = start_color.lerp(end_color, particle.age / particle.lifetime);
+
+            true
+        });
+
+        if self.emitting {
+            self.pending += self.params.spawn_rate * dt;
+
+            while self.pending >= 1.0 {
+                self.pending -= 1.0;
+                self.emit();
+            }
+        }
+    }
+
+    /// Emits a single particle at the origin with a random velocity, if there's room for it.
+    fn emit(&mut self) {
+        if self.particles.len() >= self.params.max_particles as usize {
+            return;
+        }
+
+        // a random point in the unit sphere, rejecting the corners of the cube around it
+        let offset = loop {
+            let point = vec3(self.random(), self.random(), self.random()) * 2.0 - Vec3::ONE;
+
+            if point.length_squared() <= 1.0 {
+                break point;
+            }
+        };
+
+        self.particles.push(Particle {
+            position: self.params.origin,
+            velocity: self.params.velocity + offset * self.params.spread,
+            color: self.params.start_color,
+            age: 0.0,
+            lifetime: self.params.lifetime,
+        });
+    }
+
+    /// Returns the next random number between zero and one of a xorshift generator.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        // the top 24 bits fit into the mantissa of an f32 exactly
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Records uploading the live particles through the staging belt, ahead of drawing them.
+    pub fn upload(
+        &mut self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        let instances = self
+            .particles
+            .iter()
+            .map(|particle| ParticleInstance {
+                position: particle.position.to_array(),
+                size: self.params.size,
+                color: particle.color.to_array(),
+            })
+            .collect::<Vec<_>>();
+
+        self.instance_count = instances.len() as u32;
+
+        let bytes: &[u8] = bytemuck::cast_slice(&instances);
+
+        if let Some(size) = BufferSize::new(bytes.len() as BufferAddress) {
+            belt.write_buffer(encoder, &self.instance_buffer, 0, size, device)
+                .copy_from_slice(bytes);
+        }
+    }
+
+    /// Records updating the uniform buffer of the `index`th viewport with its camera through the
+    /// staging belt.
+    pub fn update_camera(
+        &self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        index: usize,
+        camera: &Camera,
+    ) {
+        let uniform = ParticleCameraUniform {
+            view_proj: camera.view_projection().to_cols_array_2d(),
+            right: camera.right().to_array(),
+            _padding: 0.0,
+            up: camera.up_vector().to_array(),
+            _padding_up: 0.0,
+        };
+
+        belt.write_buffer(
+            encoder,
+            &self.camera_buffer,
+            Camera::UNIFORM_STRIDE * index as BufferAddress,
+            BufferSize::new(std::mem::size_of::<ParticleCameraUniform>() as BufferAddress).unwrap(),
+            device,
+        )
+        .copy_from_slice(bytemuck::bytes_of(&uniform));
+    }
+
+    /// Records drawing the uploaded particles as seen by the camera of the `index`th viewport into
+    /// the render pass.
+    pub fn draw(&self, render_pass: &mut RenderPass, index: usize) {
+        if self.instance_count == 0 {
+            return;
+        }
+
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[offset]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
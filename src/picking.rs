@@ -0,0 +1,362 @@
+use std::sync::mpsc;
+
+use anyhow::Result;
+use wgpu::*;
+
+use crate::{
+    camera::Camera,
+    material::Material,
+    mesh::{InstanceRaw, Mesh, Vertex},
+    mesh_arena::MeshArena,
+    render_target::SizedTexture,
+    renderer::Renderer,
+};
+
+/// Finds the mesh under a pixel by rendering the ids of the meshes into an integer target, and
+/// reading the id under the pixel back. Unlike casting rays against bounding boxes this follows
+/// the actual (skinned or otherwise animated) triangles on the gpu, whichever is nearest at the
+/// pixel being picked. Only triangles are drawn, lines and points being too thin to click.
+#[derive(Debug)]
+pub struct PickPass {
+    /// The uniform buffer of the ids of the meshes, one at every multiple of
+    /// `Camera::UNIFORM_STRIDE`.
+    id_buffer: Buffer,
+    /// The number of ids the `id_buffer` has room for.
+    capacity: u32,
+    /// The layout of the `id_bind_group`, kept around to recreate it along with the `id_buffer`.
+    id_bind_group_layout: BindGroupLayout,
+    /// The bind group of the `id_buffer`, offset to the id of the mesh being drawn.
+    id_bind_group: BindGroup,
+    /// The pipelines by whether they draw strips and whether they're overlays, in that order.
+    pipelines: [[RenderPipeline; 2]; 2],
+    /// The target the ids are rendered into, matching the size of the surface.
+    id_texture: SizedTexture,
+    /// The depth buffer of the `id_texture`.
+    depth_texture: SizedTexture,
+    /// Whether the depth is reversed, cleared to 0 and tested with `CompareFunction::Greater`.
+    reversed_z: bool,
+}
+
+impl PickPass {
+    /// The format of the target the ids are rendered into.
+    pub const FORMAT: TextureFormat = TextureFormat::R32Uint;
+    /// The number of ids the id buffer of a new pass has room for.
+    const INITIAL_CAPACITY: u32 = 64;
+    /// The size in bytes of an id read back from the target.
+    const ID_SIZE: BufferAddress = std::mem::size_of::<u32>() as BufferAddress;
+    /// The size in bytes of the binding of an id in the id buffer, padded to the 16 bytes uniform
+    /// structs take up.
+    const ID_BINDING_SIZE: BufferAddress = 16;
+
+    /// Creates the pipelines and a target of the given size, binding the cameras through the layout
+    /// of the main pipelines' camera bind group. The depth is reversed if the cameras' projections
+    /// are, see `Camera::set_reversed_z`.
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        width: u32,
+        height: u32,
+        reversed_z: bool,
+    ) -> Self {
+        let id_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Pick Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(Self::ID_BINDING_SIZE),
+                },
+                count: None,
+            }],
+        });
+
+        let (id_buffer, id_bind_group) =
+            Self::create_ids(device, &id_bind_group_layout, Self::INITIAL_CAPACITY);
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Pick Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/pick.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Pick Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                &device.create_bind_group_layout(&Material::BIND_GROUP_LAYOUT),
+                &id_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = [false, true].map(|strip| {
+            [false, true].map(|overlay| {
+                Self::create_pipeline(device, &shader, &layout, strip, overlay, reversed_z)
+            })
+        });
+
+        Self {
+            id_buffer,
+            capacity: Self::INITIAL_CAPACITY,
+            id_bind_group_layout,
+            id_bind_group,
+            pipelines,
+            id_texture: Self::create_texture(device, "Pick Texture", width, height, Self::FORMAT),
+            depth_texture: Self::create_texture(
+                device,
+                "Pick Depth Texture",
+                width,
+                height,
+                Renderer::DEPTH_FORMAT,
+            ),
+            reversed_z,
+        }
+    }
+
+    /// Creates the id buffer with room for `capacity` ids, and its bind group.
+    fn create_ids(device: &Device, layout: &BindGroupLayout, capacity: u32) -> (Buffer, BindGroup) {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Pick Uniform Buffer"),
+            size: Camera::UNIFORM_STRIDE * capacity as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Pick Bind Group"),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: BufferSize::new(Self::ID_BINDING_SIZE),
+                }),
+            }],
+        });
+
+        (buffer, bind_group)
+    }
+
+    /// Creates a target of the pass, which can be rendered into and copied from.
+    fn create_texture(
+        device: &Device,
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> SizedTexture {
+        SizedTexture::new(
+            device,
+            label,
+            width,
+            height,
+            format,
+            1,
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        )
+    }
+
+    /// Creates the pipeline drawing triangle lists or strips, as overlays or not, with a reversed
+    /// depth or not.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        layout: &PipelineLayout,
+        strip: bool,
+        overlay: bool,
+        reversed_z: bool,
+    ) -> RenderPipeline {
+        let topology = if strip {
+            PrimitiveTopology::TriangleStrip
+        } else {
+            PrimitiveTopology::TriangleList
+        };
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Pick Pipeline"),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: Self::FORMAT,
+                    write_mask: ColorWrites::ALL,
+                    blend: None,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            // both faces are drawn, so that the inside of open meshes can be picked too
+            primitive: PrimitiveState {
+                topology,
+                strip_index_format: strip.then_some(IndexFormat::Uint32),
+                ..Default::default()
+            },
+            multisample: MultisampleState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: Renderer::DEPTH_FORMAT,
+                depth_write_enabled: !overlay,
+                depth_compare: match (overlay, reversed_z) {
+                    (true, _) => CompareFunction::Always,
+                    (false, false) => CompareFunction::Less,
+                    (false, true) => CompareFunction::Greater,
+                },
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Resizes the target to the given size, uploading the ids of `count` meshes (growing the id
+    /// buffer if they don't fit) ahead of drawing them.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32, count: u32) {
+        self.id_texture.resize(device, width, height);
+        self.depth_texture.resize(device, width, height);
+
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            (self.id_buffer, self.id_bind_group) =
+                Self::create_ids(device, &self.id_bind_group_layout, self.capacity);
+        }
+
+        let stride = Camera::UNIFORM_STRIDE as usize;
+        let mut ids = vec![0; stride * count as usize];
+
+        for (index, id) in ids.chunks_exact_mut(stride).enumerate() {
+            id[..4].copy_from_slice(&(index as u32 + 1).to_ne_bytes());
+        }
+
+        if !ids.is_empty() {
+            queue.write_buffer(&self.id_buffer, 0, &ids);
+        }
+    }
+
+    /// Returns whether the depth is reversed, which the pass has to be recreated to change.
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Begins the render pass drawing the ids, clearing the target to the background's id of 0.
+    pub fn begin<'a>(&'a self, encoder: &'a mut CommandEncoder) -> RenderPass<'a> {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Pick Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: self.id_texture.view(),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: self.depth_texture.view(),
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(if self.reversed_z { 0.0 } else { 1.0 }),
+                    store: StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// Records drawing the mesh with the id of the `index`th mesh uploaded by `PickPass::prepare`,
+    /// returning whether it was drawn. Meshes which aren't made of triangles are skipped.
+    pub fn draw(
+        &self,
+        render_pass: &mut RenderPass,
+        index: u32,
+        mesh: &Mesh,
+        arena: &MeshArena,
+    ) -> bool {
+        let strip = match mesh.topology {
+            PrimitiveTopology::TriangleList => false,
+            PrimitiveTopology::TriangleStrip => true,
+            _ => return false,
+        };
+
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index;
+
+        render_pass.set_pipeline(&self.pipelines[strip as usize][mesh.overlay() as usize]);
+        render_pass.set_bind_group(1, mesh.material.bind_group(), &[]);
+        render_pass.set_bind_group(2, &self.id_bind_group, &[offset]);
+
+        mesh.bind_buffers(render_pass, arena)
+            .draw(render_pass, 0..mesh.instance_count);
+
+        true
+    }
+
+    /// Records copying the id at the given pixel of the target into a new buffer, which is read
+    /// with `PickPass::read` once the encoder has been submitted.
+    pub fn copy_pixel(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        x: u32,
+        y: u32,
+    ) -> Buffer {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Pick Readback Buffer"),
+            size: Self::ID_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: self.id_texture.texture(),
+                mip_level: 0,
+                origin: Origin3d { x, y, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        buffer
+    }
+
+    /// Waits for the copy recorded by `PickPass::copy_pixel` and returns the index of the mesh
+    /// drawn at the pixel, `None` if there was none.
+    pub fn read(device: &Device, buffer: &Buffer) -> Result<Option<u32>> {
+        let (sender, receiver) = mpsc::channel();
+
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        device.poll(Maintain::Wait);
+        receiver.recv()??;
+
+        let id = {
+            let view = buffer.slice(..).get_mapped_range();
+            u32::from_ne_bytes([view[0], view[1], view[2], view[3]])
+        };
+
+        buffer.unmap();
+
+        Ok(id.checked_sub(1))
+    }
+}
@@ -0,0 +1,74 @@
+use wgpu::{util::*, *};
+
+/// Brightness and gamma adjustments applied to the shaded colors of meshes. Both are applied in
+/// linear space, before the colors are encoded for the srgb surface (or tonemapped, when rendering
+/// into a high dynamic range offscreen target), so they behave the same whatever the surface's
+/// format. The skybox and clear color aren't adjusted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostParams {
+    /// The exponent the linear colors are raised to the inverse of, values above 1 brightening the
+    /// midtones and values below 1 darkening them. Always positive.
+    pub gamma: f32,
+    /// The scalar the linear colors are multiplied with, after the gamma has been applied.
+    pub brightness: f32,
+}
+
+/// The `PostParams`, laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct PostUniform {
+    /// The exponent the colors are raised to the inverse of.
+    gamma: f32,
+    /// The scalar the colors are multiplied with.
+    brightness: f32,
+    /// Pads the uniform to the 16 bytes required by some backends.
+    _padding: [f32; 2],
+}
+
+impl PostParams {
+    /// The parameters which leave the colors unchanged.
+    pub const DEFAULT: Self = Self {
+        gamma: 1.0,
+        brightness: 1.0,
+    };
+    /// The smallest gamma the colors are adjusted with, as a gamma of zero is undefined.
+    pub const MIN_GAMMA: f32 = 0.01;
+
+    /// Returns the parameters with the gamma clamped to `Self::MIN_GAMMA` and the brightness to
+    /// zero from below.
+    pub fn clamped(self) -> Self {
+        Self {
+            gamma: self.gamma.max(Self::MIN_GAMMA),
+            brightness: self.brightness.max(0.0),
+        }
+    }
+
+    /// Creates the uniform buffer holding the parameters.
+    pub fn create_buffer(&self, device: &Device) -> Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Post Params Uniform Buffer"),
+            contents: bytemuck::bytes_of(&self.uniform()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        })
+    }
+
+    /// Uploads the parameters to a buffer created with `PostParams::create_buffer`.
+    pub fn write_buffer(&self, queue: &Queue, buffer: &Buffer) {
+        queue.write_buffer(buffer, 0, bytemuck::bytes_of(&self.uniform()));
+    }
+
+    /// Returns the parameters as they're laid out on the gpu.
+    fn uniform(&self) -> PostUniform {
+        PostUniform {
+            gamma: self.gamma,
+            brightness: self.brightness,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+impl Default for PostParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
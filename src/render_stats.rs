@@ -0,0 +1,34 @@
+use wgpu::PrimitiveTopology;
+
+use crate::mesh::Mesh;
+
+/// Counts of what the renderer drew in a frame, after frustum culling, summed over all views.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// The number of meshes drawn, each being a single draw call.
+    pub draw_calls: u32,
+    /// The number of triangles drawn, over all instances. Lines and points aren't counted.
+    pub triangles: u64,
+    /// The number of vertices processed, over all instances.
+    pub vertices: u64,
+}
+
+impl RenderStats {
+    /// Counts a single draw call of all instances of the mesh.
+    pub fn record(&mut self, mesh: &Mesh) {
+        let count = mesh.draw_count() as u64;
+        let instances = mesh.instance_count as u64;
+
+        // strips share all but the first two vertices between consecutive triangles, including
+        // the degenerate ones stitching rows together
+        let triangles = match mesh.topology {
+            PrimitiveTopology::TriangleList => count / 3,
+            PrimitiveTopology::TriangleStrip => count.saturating_sub(2),
+            _ => 0,
+        };
+
+        self.draw_calls += 1;
+        self.triangles += triangles * instances;
+        self.vertices += count * instances;
+    }
+}
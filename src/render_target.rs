@@ -0,0 +1,316 @@
+use wgpu::*;
+
+/// A texture and its view tracking a size, such as that of the surface, which are only recreated
+/// when the size actually changes.
+#[derive(Debug)]
+pub struct SizedTexture {
+    /// The label of the texture, reused when recreating it.
+    label: &'static str,
+    /// The texture, recreated on resize.
+    texture: Texture,
+    /// The view of the whole `texture`.
+    view: TextureView,
+}
+
+/// An offscreen texture which can be rendered into, and then sampled by a later pass.
+#[derive(Debug)]
+pub struct RenderTarget {
+    /// The color texture rendered into.
+    color: SizedTexture,
+    /// The depth texture, if the target has one.
+    depth: Option<SizedTexture>,
+}
+
+/// Copies a render target onto another texture of the same size (usually the surface), by drawing
+/// a fullscreen triangle sampling it.
+#[derive(Debug)]
+pub struct BlitPass {
+    /// The layout of the `bind_group`, kept around to rebind resized sources.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the source texture.
+    bind_group: BindGroup,
+    /// Draws the fullscreen triangle.
+    pipeline: RenderPipeline,
+}
+
+impl SizedTexture {
+    /// Creates a two dimensional texture of the given size, format, sample count and usages.
+    pub fn new(
+        device: &Device,
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        sample_count: u32,
+        usage: TextureUsages,
+    ) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+
+        Self {
+            label,
+            view: texture.create_view(&TextureViewDescriptor::default()),
+            texture,
+        }
+    }
+
+    /// Recreates the texture with a new size, keeping its format, sample count and usages. Does
+    /// nothing if the size didn't change.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        if self.size() == (width, height) {
+            return;
+        }
+
+        *self = Self::new(
+            device,
+            self.label,
+            width,
+            height,
+            self.format(),
+            self.texture.sample_count(),
+            self.texture.usage(),
+        );
+    }
+
+    /// Returns the texture.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Returns the view of the whole texture.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Returns the format of the texture.
+    pub fn format(&self) -> TextureFormat {
+        self.texture.format()
+    }
+
+    /// Returns the size of the texture, as `(width, height)`.
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+}
+
+impl RenderTarget {
+    /// Creates a target of the given size and color format, with a depth texture of
+    /// `depth_format` if given.
+    pub fn new(
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        depth_format: Option<TextureFormat>,
+    ) -> Self {
+        let color = SizedTexture::new(
+            device,
+            "Render Target Texture",
+            width,
+            height,
+            format,
+            1,
+            TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+        );
+
+        let depth = depth_format.map(|format| {
+            SizedTexture::new(
+                device,
+                "Render Target Depth Texture",
+                width,
+                height,
+                format,
+                1,
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            )
+        });
+
+        Self { color, depth }
+    }
+
+    /// Recreates the textures of the target with a new size, keeping their formats.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.color.resize(device, width, height);
+
+        if let Some(depth) = &mut self.depth {
+            depth.resize(device, width, height);
+        }
+    }
+
+    /// Returns the color texture of the target.
+    pub fn texture(&self) -> &Texture {
+        self.color.texture()
+    }
+
+    /// Returns the view of the color texture of the target.
+    pub fn view(&self) -> &TextureView {
+        self.color.view()
+    }
+
+    /// Returns the view of the depth texture of the target, if it has one.
+    pub fn depth_view(&self) -> Option<&TextureView> {
+        self.depth.as_ref().map(SizedTexture::view)
+    }
+
+    /// Returns the format of the color texture of the target.
+    pub fn format(&self) -> TextureFormat {
+        self.color.format()
+    }
+
+    /// Returns the size of the target, as `(width, height)`.
+    pub fn size(&self) -> (u32, u32) {
+        self.color.size()
+    }
+}
+
+impl BlitPass {
+    /// Creates a pass copying `source` onto textures of the given format.
+    pub fn new(device: &Device, source: &RenderTarget, format: TextureFormat) -> Self {
+        // the source is read with `textureLoad` rather than sampled, so that formats which can't
+        // be filtered (such as `Rgba32Float`) can be copied as well
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/blit.wgsl").into()),
+        });
+
+        let pipeline =
+            create_fullscreen_pipeline(device, "Blit", &shader, &bind_group_layout, format);
+
+        Self {
+            bind_group: Self::create_bind_group(device, &bind_group_layout, source),
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Binds a new source to copy from, such as after the previous one was resized.
+    pub fn set_source(&mut self, device: &Device, source: &RenderTarget) {
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, source);
+    }
+
+    /// Creates the bind group of the source texture.
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        source: &RenderTarget,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(source.view()),
+            }],
+        })
+    }
+
+    /// Records copying the source onto `view` into the encoder.
+    pub fn draw(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        draw_fullscreen(
+            encoder,
+            "Blit Render Pass",
+            view,
+            &self.pipeline,
+            &self.bind_group,
+        );
+    }
+}
+
+/// Creates a pipeline drawing a fullscreen triangle with the `vs_main` and `fs_main` entry points
+/// of the shader onto textures of the given format, as used by the passes sampling a render target.
+pub fn create_fullscreen_pipeline(
+    device: &Device,
+    label: &str,
+    shader: &ShaderModule,
+    bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+) -> RenderPipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&format!("{label} Pipeline Layout")),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(&format!("{label} Pipeline")),
+        layout: Some(&layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format,
+                write_mask: ColorWrites::ALL,
+                blend: None,
+            })],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState::default(),
+        multisample: MultisampleState::default(),
+        depth_stencil: None,
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Records drawing a fullscreen triangle with the given pipeline and bind group onto `view`.
+pub fn draw_fullscreen(
+    encoder: &mut CommandEncoder,
+    label: &str,
+    view: &TextureView,
+    pipeline: &RenderPipeline,
+    bind_group: &BindGroup,
+) {
+    let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: Operations {
+                // every pixel is overwritten
+                load: LoadOp::Clear(Color::BLACK),
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
@@ -1,133 +1,898 @@
-use std::sync::Arc;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use wgpu::*;
-use winit::{dpi::PhysicalSize, window::Window};
+#[cfg(feature = "debug")]
+use glam::Mat4;
+use glam::{Vec3, Vec4};
+use wgpu::{util::StagingBelt, *};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::Window,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use log::*;
 
+#[cfg(feature = "debug-ui")]
+use crate::debug_ui::{UiFrame, UiPainter};
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::FileWatcher;
 use crate::{
+    background::BackgroundGradient,
     camera::Camera,
-    mesh::{Mesh, Vertex},
+    camera_uniform::{self, CameraUniform},
+    capture::{CapturedFrame, FrameReadback},
+    compute::ComputePass,
+    config::AppConfig,
+    depth_debug::DepthDebugPass,
+    fxaa::FxaaPass,
+    gpu_report::GpuReport,
+    gpu_timer::GpuTimer,
+    grid::{Grid, GridParams},
+    labels::Labels,
+    light::{DirectionalLight, PointLight, PointLights},
+    lines::LinePass,
+    material::Material,
+    mesh::{InstanceRaw, Mesh, MeshBuffers, Vertex},
+    mesh_arena::MeshArena,
+    particles::{ParticleParams, ParticleSystem},
+    picking::PickPass,
+    post::PostParams,
+    render_stats::RenderStats,
+    render_target::{BlitPass, RenderTarget, SizedTexture},
+    scene::{MeshId, Scene},
+    shadow::ShadowMap,
+    skybox::Skybox,
+    texture::TextureOptions,
+    tonemap::TonemapPass,
+    viewport::Viewport,
 };
 
+/// The offscreen target the scene is rendered into, along with the passes copying it onto the
+/// surface.
+#[derive(Debug)]
+struct Offscreen {
+    /// The target the scene is rendered into.
+    target: RenderTarget,
+    /// Maps the `target` into the displayable range, if it has a high dynamic range format.
+    tonemapping: Option<Tonemapping>,
+    /// Copies the `target` onto the surface, when it isn't tonemapped.
+    blit: BlitPass,
+    /// Copies the displayable image onto the surface while anti-aliasing it, if its format can be
+    /// filtered.
+    fxaa: Option<FxaaPass>,
+}
+
+/// The tonemapping of a high dynamic range offscreen target.
+#[derive(Debug)]
+struct Tonemapping {
+    /// Tonemaps the offscreen target, either straight onto the surface or into the `output`.
+    pass: TonemapPass,
+    /// The tonemapped image in the format of the surface, to be anti-aliased with FXAA.
+    output: RenderTarget,
+}
+
+/// A camera rendering the scene into a viewport of the surface.
+#[derive(Debug)]
+struct View {
+    /// The camera, with the aspect ratio of the `viewport`.
+    camera: Camera,
+    /// The part of the surface the camera renders into.
+    viewport: Viewport,
+}
+
+/// Where the renderer's frames end up.
+#[derive(Debug)]
+enum Output {
+    /// The surface of a window, whose textures are presented on screen.
+    Window {
+        /// The window the `surface` presents to.
+        window: Arc<Window>,
+        /// The surface onto which meshes will be rendered.
+        surface: Surface<'static>,
+    },
+    /// A texture of the size and format of the surface configuration, which frames are rendered
+    /// into (and can be captured from) without being presented anywhere.
+    Headless(SizedTexture),
+}
+
+impl Output {
+    /// Applies a new configuration, reconfiguring the window's surface or resizing the headless
+    /// texture.
+    fn configure(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        match self {
+            Self::Window { surface, .. } => surface.configure(device, config),
+            Self::Headless(texture) => texture.resize(device, config.width, config.height),
+        }
+    }
+
+    /// Returns the texture a frame is rendered into, which is the acquired `surface_texture` for
+    /// windows.
+    fn frame_texture<'a>(&'a self, surface_texture: Option<&'a SurfaceTexture>) -> &'a Texture {
+        match (self, surface_texture) {
+            (_, Some(surface_texture)) => &surface_texture.texture,
+            (Self::Headless(texture), None) => texture.texture(),
+            (Self::Window { .. }, None) => {
+                unreachable!("frames of a window are rendered into a texture of its surface")
+            }
+        }
+    }
+}
+
+/// The settings a pipeline of `Pipelines` is created with, identifying it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    /// How the vertices are assembled into primitives.
+    topology: PrimitiveTopology,
+    /// The faces of triangles which aren't drawn, always `None` for other topologies.
+    cull_mode: Option<Face>,
+    /// Whether the pipeline alpha blends, testing against but not writing to the depth buffer.
+    transparent: bool,
+    /// Whether the pipeline draws over everything, neither testing against nor writing to the depth
+    /// buffer.
+    overlay: bool,
+    /// How triangles are rasterized, always `PolygonMode::Fill` for other topologies.
+    polygon_mode: PolygonMode,
+    /// Whether the depth of triangles is offset by the renderer's depth bias, always `false` for
+    /// other topologies which can't be biased.
+    depth_biased: bool,
+}
+
+impl PipelineKey {
+    /// The key of opaque filled triangle lists culled with `Renderer::DEFAULT_CULL_MODE`, which
+    /// most meshes are drawn with.
+    const DEFAULT: Self = Self {
+        topology: PrimitiveTopology::TriangleList,
+        cull_mode: Renderer::DEFAULT_CULL_MODE,
+        transparent: false,
+        overlay: false,
+        polygon_mode: PolygonMode::Fill,
+        depth_biased: false,
+    };
+
+    /// Returns the key of the pipeline drawing the mesh, triangles being culled with `cull_mode`
+    /// and rasterized with `polygon_mode`.
+    fn of(mesh: &Mesh, cull_mode: Option<Face>, polygon_mode: PolygonMode) -> Self {
+        let triangles = matches!(
+            mesh.topology,
+            PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip
+        );
+
+        Self {
+            topology: mesh.topology,
+            cull_mode: cull_mode.filter(|_| triangles),
+            transparent: mesh.transparent,
+            overlay: mesh.overlay(),
+            polygon_mode: if triangles {
+                polygon_mode
+            } else {
+                PolygonMode::Fill
+            },
+            depth_biased: mesh.depth_biased() && triangles,
+        }
+    }
+}
+
+/// The set of pipelines meshes are drawn with, all sharing the same shader and layout. Only few of
+/// the combinations of settings are ever drawn with, so each pipeline is created the first time a
+/// frame needs it with `Pipelines::prepare`, and kept until the settings they share change.
+#[derive(Debug)]
+struct Pipelines {
+    /// The shader module every pipeline is built from.
+    shader: ShaderModule,
+    /// The layout of the bind groups every pipeline is built with.
+    layout: PipelineLayout,
+    /// The format of the color target the pipelines draw onto.
+    texture_format: TextureFormat,
+    /// The depth test of the pipelines which aren't overlays.
+    depth_compare: CompareFunction,
+    /// The bias of the depth biased pipelines, already flipped for a reversed depth.
+    depth_bias: DepthBiasState,
+    /// The number of samples per pixel of the targets the pipelines draw onto.
+    sample_count: u32,
+    /// The pipelines created so far, by the settings they were created with.
+    pipelines: HashMap<PipelineKey, RenderPipeline>,
+}
+
+impl Pipelines {
+    /// Returns the pipeline created with the given settings, which has to have been prepared
+    /// since the pipelines were last created or invalidated.
+    fn get(&self, key: PipelineKey) -> &RenderPipeline {
+        &self.pipelines[&key]
+    }
+
+    /// Creates the pipeline with the given settings, unless it already exists.
+    fn prepare(&mut self, device: &Device, key: PipelineKey) {
+        self.pipelines.entry(key).or_insert_with(|| {
+            Renderer::create_render_pipeline(
+                device,
+                &self.shader,
+                self.texture_format,
+                &self.layout,
+                key,
+                self.depth_compare,
+                self.depth_bias,
+                self.sample_count,
+            )
+        });
+    }
+
+    /// Drops every pipeline created so far, to be created again with the new settings once
+    /// they're prepared.
+    fn invalidate(
+        &mut self,
+        texture_format: TextureFormat,
+        depth_compare: CompareFunction,
+        depth_bias: DepthBiasState,
+        sample_count: u32,
+    ) {
+        self.texture_format = texture_format;
+        self.depth_compare = depth_compare;
+        self.depth_bias = depth_bias;
+        self.sample_count = sample_count;
+        self.pipelines.clear();
+    }
+}
+
 /// A wgpu-backend based renderer that holds a connection to the GPU, can create buffers, and render meshes.
 #[derive(Debug)]
 pub struct Renderer {
+    /// The wgpu instance the surface and `adapter` were created from, kept around to recreate a
+    /// lost surface.
+    instance: Instance,
+    /// The physical rendering device, queried for the capabilities it supports.
+    adapter: Adapter,
+    /// The features supported by the `adapter`.
+    adapter_features: Features,
+    /// The capabilities of the `adapter`, `device` and surface, collected when they were created.
+    gpu_report: GpuReport,
+    /// The limits of the `device`.
+    limits: Limits,
     /// A handle to the rendering device, which in most cases will be a GPU.
     device: Device,
     /// A queue onto which messages can be passed to the `device` to be processed.
     queue: Queue,
+    /// Set by the `device`'s lost callback once the device becomes unusable (such as after a driver
+    /// update or a gpu reset), so that the renderer can be rebuilt.
+    device_lost: Arc<AtomicBool>,
+    /// The configuration the renderer was created with, kept around to rebuild it after the device
+    /// is lost.
+    config: AppConfig,
 
-    /// The pipeline through which data is transformed through the `device` to eventually be
-    /// rendered onto the `surface`.
-    pipeline: RenderPipeline,
+    /// The source of the shader set with `Renderer::set_shader`, kept around to rebuild the
+    /// renderer with it. `None` while the main shader is used.
+    custom_shader: Option<String>,
+    /// The pipelines through which data is transformed through the `device` to eventually be
+    /// rendered onto the `surface`, along with the shader module they're built from.
+    pipelines: Pipelines,
+    /// Watches the shader source on disk so the pipelines can be rebuilt when it changes.
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: FileWatcher,
 
-    /// The surface onto which meshes will be rendered.
-    surface: Surface<'static>,
-    /// The configuration of the `surface`.
+    /// The window or headless texture frames are rendered into.
+    output: Output,
+    /// The configuration of the `output`'s surface, which headless textures follow the size and
+    /// format of.
     surface_config: SurfaceConfiguration,
 
-    /// The uniform buffer of the camera's view projection matrix.
+    /// The number of samples taken per pixel, 1 meaning multisampling is disabled.
+    sample_count: u32,
+    /// Whether the depth is reversed, cleared to 0 and tested with `CompareFunction::Greater`.
+    reversed_z: bool,
+    /// The bias of the depth of depth biased meshes, with the standard (not reversed) depth.
+    depth_bias: DepthBiasState,
+    /// The offscreen target the scene is rendered into, or `None` if it's rendered straight onto
+    /// the surface.
+    offscreen: Option<Offscreen>,
+    /// The multisampled color target that is resolved onto the `surface`, present when the
+    /// `sample_count` is greater than 1.
+    msaa_texture: Option<SizedTexture>,
+    /// The depth buffer of the rendered frame, sharing the `sample_count` of the color target.
+    depth_texture: SizedTexture,
+    /// The color the surface is cleared to before drawing.
+    clear_color: Color,
+
+    /// The uniform buffer of the `views`' cameras and the width of lines, uploaded at the start of
+    /// each frame.
     camera_buffer: Buffer,
-    /// The bind group of the camera's uniform buffer.
+    /// The brightness and gamma adjustments applied to the colors of the meshes.
+    post_params: PostParams,
+    /// The uniform buffer of the `post_params`, bound along with the cameras.
+    post_params_buffer: Buffer,
+    /// The plane the fragments of meshes in front of are discarded, for looking at cross sections.
+    clip_plane: Option<Vec4>,
+    /// The uniform buffer of the `clip_plane`, bound along with the cameras.
+    clip_plane_buffer: Buffer,
+    /// The layout of the `camera_bind_group`, kept around to recreate the pipelines.
+    camera_bind_group_layout: BindGroupLayout,
+    /// The bind group of the camera's uniform buffer, offset to the camera of the view being drawn.
     camera_bind_group: BindGroup,
+    /// The cameras the scene is rendered from and the viewports they render into, the first one
+    /// following the camera given to `Renderer::update_camera_buffer`.
+    views: Vec<View>,
+    /// Batches the uniform and instance uploads of each frame into the frame's command encoder,
+    /// instead of writing every buffer through the queue separately.
+    staging_belt: StagingBelt,
+
+    /// The meshes currently being rendered.
+    scene: Scene,
+    /// The buffers shared by the meshes added with `Renderer::add_shared_mesh`.
+    mesh_arena: MeshArena,
+    /// The point lights lighting the `scene`.
+    point_lights: PointLights,
+    /// The directional light lighting the `scene`, and the shadows it casts.
+    shadow_map: ShadowMap,
+
+    /// The reference grid drawn on the ground plane of the camera's coordinate system.
+    grid: Grid,
+    /// Whether the reference `grid` is currently being drawn.
+    grid_visible: bool,
+    /// The faces of triangles which aren't drawn.
+    cull_mode: Option<Face>,
+    /// Whether only the edges of triangles are drawn.
+    wireframe: bool,
+    /// The width of lines in logical pixels, which lines wider than a physical pixel are drawn
+    /// with by the `line_pass`.
+    line_width: f32,
+    /// Draws lines (and the edges of triangles in wireframe) wider than a pixel.
+    line_pass: LinePass,
+    /// The text labels drawn over the scene in the next frame, queued with `Renderer::draw_label`.
+    labels: Labels,
 
-    /// The mesh currently being rendered.
-    /// TODO: make more fleshed out scene system?
-    mesh: Mesh,
+    /// The axis gizmo drawn at the origin.
+    axes: Mesh,
+    /// Whether the axis gizmo is currently being drawn.
+    axes_visible: bool,
+
+    /// The cubemap drawn behind the scene, if one has been set.
+    skybox: Option<Skybox>,
+    /// Whether the `skybox` is currently being drawn, instead of clearing to the `clear_color`.
+    skybox_visible: bool,
+    /// The gradient drawn behind the scene instead of the solid `clear_color` while the `skybox`
+    /// isn't, if one has been set.
+    background: Option<BackgroundGradient>,
+
+    /// The particles blended over the scene, if a particle system has been set.
+    particles: Option<ParticleSystem>,
+
+    /// Renders the ids of the meshes for `Renderer::pick`, created by the first pick.
+    pick_pass: Option<PickPass>,
+
+    /// The compute shader run over the vertices of a storage mesh before the render pass, if one
+    /// has been set.
+    compute_pass: Option<ComputePass>,
+    /// The workgroup counts of the `compute_pass` dispatch requested for the next frame.
+    compute_dispatch: Option<(u32, u32, u32)>,
+
+    /// Measures the gpu time of the render pass, if the device supports timestamp queries.
+    gpu_timer: Option<GpuTimer>,
+    /// The counts of what was drawn in the last rendered frame.
+    render_stats: RenderStats,
+    /// The cpu time spent recording and submitting the commands of the last rendered frame, in
+    /// milliseconds.
+    encode_time: f32,
+    /// The scalar the colors of a high dynamic range offscreen target are multiplied with before
+    /// being tonemapped.
+    exposure: f32,
+    /// Whether the scene is anti-aliased with FXAA while being copied onto the surface.
+    fxaa: bool,
+    /// Draws the depth buffer in place of the scene while the depth visualization is enabled.
+    depth_debug: Option<DepthDebugPass>,
+    /// The distance shown as white by the depth visualization.
+    depth_debug_range: f32,
+    /// Whether a frame has been rendered yet. The first frame is rendered inside of an error scope,
+    /// so that mistakes in the shader's bindings are reported rather than leaving the screen black.
+    rendered_first_frame: bool,
+
+    /// Paints the debug overlay on top of the scene.
+    #[cfg(feature = "debug-ui")]
+    ui_painter: UiPainter,
+    /// The frame of the debug overlay to paint next.
+    #[cfg(feature = "debug-ui")]
+    ui_frame: Option<UiFrame>,
 }
 
 impl Renderer {
+    /// The default length of each line of the axis gizmo.
+    pub const AXES_LENGTH: f32 = 1.0;
+    /// The color the surface is cleared to by default.
+    pub const DEFAULT_CLEAR_COLOR: Color = Color {
+        r: 0.01,
+        g: 0.01,
+        b: 0.01,
+        a: 1.0,
+    };
+    /// The default width of lines in logical pixels.
+    pub const DEFAULT_LINE_WIDTH: f32 = 1.0;
+    /// The widest lines can be drawn, in logical pixels.
+    pub const MAX_LINE_WIDTH: f32 = 32.0;
+    /// The depth bias of depth biased meshes by default, pulling them a little towards the camera
+    /// (and more so at grazing angles) to win against coplanar faces.
+    pub const DEFAULT_DEPTH_BIAS: DepthBiasState = DepthBiasState {
+        constant: -2,
+        slope_scale: -1.0,
+        clamp: 0.0,
+    };
+    /// The number of samples per pixel requested by default.
+    pub const DEFAULT_SAMPLE_COUNT: u32 = 4;
+    /// The default exposure of high dynamic range offscreen targets.
+    pub const DEFAULT_EXPOSURE: f32 = 1.0;
+    /// The faces of triangles which aren't drawn by default.
+    pub const DEFAULT_CULL_MODE: Option<Face> = Some(Face::Back);
+    /// The cull modes triangles can be drawn with.
+    pub const CULL_MODES: [Option<Face>; 3] = [Some(Face::Back), None, Some(Face::Front)];
+    /// The features the device is created with when the adapter supports them, the parts of the
+    /// renderer depending on them (such as gpu timing or wireframes) being disabled otherwise.
+    /// Without push constants, tints are stored in the meshes' materials instead.
+    pub const OPTIONAL_FEATURES: Features = Features::TIMESTAMP_QUERY
+        .union(Features::POLYGON_MODE_LINE)
+        .union(Features::PUSH_CONSTANTS);
+    /// The size in bytes of the push constants of every draw, holding the tint of the mesh.
+    pub const PUSH_CONSTANT_SIZE: u32 = std::mem::size_of::<Vec4>() as u32;
+    /// The range of frame latencies the surface can be configured with.
+    pub const FRAME_LATENCIES: RangeInclusive<u32> = 1..=3;
+    /// The format of the texture headless renderers render into.
+    pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+    /// The format of the depth buffer.
+    pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+    /// The size in bytes of the chunks the `staging_belt` allocates to upload through. Larger
+    /// uploads get a chunk of their own.
+    pub const STAGING_BELT_CHUNK_SIZE: BufferAddress = 4096;
+    /// The environment variable overriding which backends are used, as a comma separated list
+    /// (such as `vulkan` or `dx12,gl`).
+    pub const BACKEND_ENV: &'static str = "D3_TEMPLATE_BACKEND";
+    /// The path of the shader source, read at runtime when hot reloading.
+    #[cfg(feature = "hot-reload")]
+    pub const SHADER_PATH: &'static str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shader/main.wgsl");
+
     /// Creates a new open connection to the rendering device, and sets up a rendering pipeline.
-    pub async fn new(window: Arc<Window>, camera: &Camera) -> Result<Self> {
-        let (surface, surface_config, device, queue) = Self::initialize_wgpu(window).await?;
+    pub async fn new(window: Arc<Window>, camera: &Camera, config: &AppConfig) -> Result<Self> {
+        let size = window.inner_size();
+
+        Self::create(Some(window), size, camera, config).await
+    }
+
+    /// Creates a renderer without a window, rendering frames of the given size into a texture of
+    /// `Renderer::HEADLESS_FORMAT` instead, for benchmarks and tests. Frames aren't presented,
+    /// but can be read back with `Renderer::capture_frame`.
+    pub async fn new_headless(
+        size: PhysicalSize<u32>,
+        camera: &Camera,
+        config: &AppConfig,
+    ) -> Result<Self> {
+        Self::create(None, size, camera, config).await
+    }
+
+    /// Creates a renderer presenting to the window, or rendering headlessly into a texture of the
+    /// given size without one.
+    async fn create(
+        window: Option<Arc<Window>>,
+        size: PhysicalSize<u32>,
+        camera: &Camera,
+        config: &AppConfig,
+    ) -> Result<Self> {
+        let (instance, output, surface_config, adapter, device, queue, gpu_report) =
+            Self::initialize_wgpu(window, size, config).await?;
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost = device_lost.clone();
+
+        device.set_device_lost_callback(move |reason, message| {
+            // the callback also runs when the device is dropped, such as after being rebuilt
+            if matches!(
+                reason,
+                DeviceLostReason::Dropped | DeviceLostReason::ReplacedCallback
+            ) {
+                return;
+            }
+
+            error!("the device was lost ({reason:?}): {message}");
+            lost.store(true, Ordering::Relaxed);
+        });
+
+        // the viewports' cameras follow the depth of the renderer, see `Renderer::set_reversed_z`
+        let mut camera = camera.clone();
+        camera.set_reversed_z(config.reversed_z);
+
+        let post_params = PostParams::DEFAULT;
+        let post_params_buffer = post_params.create_buffer(&device);
+        let clip_plane_buffer = camera_uniform::create_clip_plane_buffer(&device, None);
 
         let (camera_buffer, camera_bind_group_layout, camera_bind_group) =
-            camera.create_buffer(&device);
+            camera_uniform::create_buffer(
+                &device,
+                &camera,
+                Viewport::MAX_COUNT as u32,
+                &post_params_buffer,
+                &clip_plane_buffer,
+            );
+
+        let offscreen = config.offscreen_format.map(|format| {
+            Offscreen::new(
+                &adapter,
+                &device,
+                &surface_config,
+                format,
+                Self::DEFAULT_EXPOSURE,
+            )
+        });
+
+        let format = config
+            .offscreen_format
+            .unwrap_or(Self::surface_view_format(&surface_config));
 
-        let pipeline = Self::create_render_pipeline(
+        let sample_count =
+            Self::supported_sample_count(&adapter, format, Self::DEFAULT_SAMPLE_COUNT);
+
+        // the device is dropped if creating the renderer fails, so the scope doesn't have to be
+        // popped when returning early
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let shader = Self::create_shader(&device)?;
+
+        let reversed_z = config.reversed_z;
+        let depth_compare = Self::depth_compare(reversed_z);
+        let depth_bias = Self::DEFAULT_DEPTH_BIAS;
+
+        let mut pipelines = Self::create_pipelines(
+            &device,
+            shader,
+            format,
+            &camera_bind_group_layout,
+            depth_compare,
+            Self::oriented_depth_bias(depth_bias, reversed_z),
+            sample_count,
+        );
+        // the rest are created as the scene needs them, this one checks that the shader fits
+        pipelines.prepare(&device, PipelineKey::DEFAULT);
+        let line_pass = LinePass::new(
+            &device,
+            &camera_bind_group_layout,
+            format,
+            depth_compare,
+            sample_count,
+        );
+        let labels = Labels::new(
             &device,
-            surface_config.format,
-            &[&camera_bind_group_layout],
+            &queue,
+            &camera_bind_group_layout,
+            format,
+            Self::DEPTH_FORMAT,
+            sample_count,
         );
 
-        let mesh = Mesh::new(
+        if let Some(error) = device.pop_error_scope().await {
+            return Err(anyhow!("failed to create the render pipelines: {error}"));
+        }
+
+        let msaa_texture =
+            Self::create_msaa_texture(&device, &surface_config, format, sample_count);
+        let depth_texture =
+            Self::create_depth_texture(&device, &surface_config, sample_count, false);
+
+        let grid = Grid::new(
             &device,
-            &[
-                Vertex {
-                    pos: [0.0, 0.5, 0.0],
-                    color: [1.0, 0.0, 0.0],
-                },
-                Vertex {
-                    pos: [-0.5, -0.5, 0.0],
-                    color: [0.0, 1.0, 0.0],
-                },
-                Vertex {
-                    pos: [0.5, -0.5, 0.0],
-                    color: [0.0, 0.0, 1.0],
-                },
-            ],
-            &[0, 1, 2],
+            GridParams::DEFAULT,
+            format,
+            Self::DEPTH_FORMAT,
+            depth_compare,
+            sample_count,
         );
+        let axes = Mesh::axes(&device, Self::AXES_LENGTH);
+
+        let scene = Scene::from_default(&device, config.default_scene);
+
+        let point_lights = PointLights::new(&device);
+        let mut shadow_map = ShadowMap::new(&device);
+        shadow_map.set_light(config.default_scene.light());
+        let mesh_arena = MeshArena::new(&device);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        #[cfg(feature = "debug-ui")]
+        let ui_painter = UiPainter::new(&device, Self::surface_view_format(&surface_config));
 
         Ok(Self {
+            instance,
+            adapter_features: adapter.features(),
+            gpu_report,
+            limits: device.limits(),
+            adapter,
             device,
             queue,
-            pipeline,
-            surface,
+            device_lost,
+            config: config.clone(),
+            custom_shader: None,
+            pipelines,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: FileWatcher::new(Self::SHADER_PATH)?,
+            output,
             surface_config,
-            mesh,
+            offscreen,
+            sample_count,
+            reversed_z,
+            depth_bias,
+            msaa_texture,
+            depth_texture,
+            clear_color: Self::DEFAULT_CLEAR_COLOR,
+            scene,
+            mesh_arena,
+            point_lights,
+            shadow_map,
+            grid,
+            grid_visible: true,
+            cull_mode: Self::DEFAULT_CULL_MODE,
+            wireframe: false,
+            line_width: Self::DEFAULT_LINE_WIDTH,
+            line_pass,
+            labels,
+            axes,
+            axes_visible: true,
             camera_buffer,
+            post_params,
+            post_params_buffer,
+            clip_plane: None,
+            clip_plane_buffer,
+            camera_bind_group_layout,
             camera_bind_group,
+            views: vec![View {
+                camera,
+                viewport: Viewport::FULL,
+            }],
+            staging_belt: StagingBelt::new(Self::STAGING_BELT_CHUNK_SIZE),
+            skybox: None,
+            skybox_visible: true,
+            background: None,
+            particles: None,
+            pick_pass: None,
+            compute_pass: None,
+            compute_dispatch: None,
+            gpu_timer,
+            render_stats: RenderStats::default(),
+            encode_time: 0.0,
+            exposure: Self::DEFAULT_EXPOSURE,
+            fxaa: false,
+            depth_debug: None,
+            depth_debug_range: DepthDebugPass::DEFAULT_RANGE,
+            rendered_first_frame: false,
+            #[cfg(feature = "debug-ui")]
+            ui_painter,
+            #[cfg(feature = "debug-ui")]
+            ui_frame: None,
         })
     }
 
-    /// Initializes wgpu by creating a surface, and initializing the device and queue.
+    /// Initializes wgpu by creating a surface for the window (or a headless texture of the given
+    /// size without one), and initializing the adapter, device and queue.
     async fn initialize_wgpu(
-        window: Arc<Window>,
-    ) -> Result<(Surface<'static>, SurfaceConfiguration, Device, Queue)> {
-        let surface_size = window.inner_size();
+        window: Option<Arc<Window>>,
+        size: PhysicalSize<u32>,
+        app_config: &AppConfig,
+    ) -> Result<(
+        Instance,
+        Output,
+        SurfaceConfiguration,
+        Adapter,
+        Device,
+        Queue,
+        GpuReport,
+    )> {
+        let backends = match std::env::var(Self::BACKEND_ENV) {
+            Ok(list) => util::parse_backends_from_comma_list(&list.to_lowercase()),
+            Err(_) => app_config.backends,
+        };
 
-        let instance = Instance::new(InstanceDescriptor {
-            backends: Backends::all(),
-            flags: InstanceFlags::empty(),
-            ..Default::default()
-        });
+        let power_preference = app_config.power_preference;
 
-        let surface = instance.create_surface(window)?;
+        let (instance, surface, adapter) =
+            Self::request_adapter(window.clone(), backends, power_preference).await?;
 
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .unwrap();
+        let info = adapter.get_info();
+        info!(
+            "using adapter {} ({:?}, {:?}, driver {} {}) preferring {power_preference:?}",
+            info.name, info.backend, info.device_type, info.driver, info.driver_info
+        );
+
+        let limits = adapter.limits();
+
+        let mut required_features = adapter.features() & Self::OPTIONAL_FEATURES;
+
+        // the gl backend emulates push constants with uniforms anyway, and reads them back
+        // misaligned, which aborts debug builds
+        if limits.max_push_constant_size < Self::PUSH_CONSTANT_SIZE || info.backend == Backend::Gl {
+            required_features.remove(Features::PUSH_CONSTANTS);
+        }
+
+        let max_push_constant_size = if required_features.contains(Features::PUSH_CONSTANTS) {
+            Self::PUSH_CONSTANT_SIZE
+        } else {
+            0
+        };
 
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
                     label: Some("Device Descriptor"),
-                    required_features: Features::empty(),
-                    required_limits: Limits::default(),
-                    memory_hints: MemoryHints::Performance,
+                    required_features,
+                    required_limits: Limits {
+                        max_push_constant_size,
+                        ..Limits::default()
+                    },
+                    memory_hints: app_config.memory_hints.clone(),
                 },
+                // wgpu 22 doesn't record traces of the calls made to it (gfx-rs/wgpu#5974)
                 None,
             )
             .await?;
 
-        let config = Self::create_surface_config(&surface, &adapter, surface_size);
-        surface.configure(&device, &config);
+        // validation errors outside of an error scope are logged rather than panicking
+        device.on_uncaptured_error(Box::new(|error| error!("uncaptured wgpu error: {error}")));
+
+        let gpu_report = GpuReport::new(&adapter, &device, surface.as_ref());
+        debug!("gpu report:\n{gpu_report}");
+
+        let frame_latency = Self::supported_frame_latency(app_config.frame_latency);
+
+        let (output, config) = match (window, surface) {
+            (Some(window), Some(surface)) => {
+                let config = Self::create_surface_config(
+                    &surface,
+                    &adapter,
+                    size,
+                    app_config.vsync,
+                    frame_latency,
+                    app_config.alpha_mode,
+                );
+                surface.configure(&device, &config);
+
+                (Output::Window { window, surface }, config)
+            }
+            _ => {
+                let config = Self::create_headless_config(size, frame_latency);
+                let texture = SizedTexture::new(
+                    &device,
+                    "Headless Texture",
+                    config.width,
+                    config.height,
+                    config.format,
+                    1,
+                    config.usage,
+                );
+
+                (Output::Headless(texture), config)
+            }
+        };
+
+        Ok((instance, output, config, adapter, device, queue, gpu_report))
+    }
+
+    /// Creates a surface for the window (if there is one) and requests an adapter for it. Some
+    /// setups (such as virtual machines and remote desktops) have no adapter reporting support for
+    /// the surface, so if there's none, any adapter is requested instead, and then the fallback
+    /// (software) adapter. Each request first considers the given backends, and then all of them.
+    async fn request_adapter(
+        window: Option<Arc<Window>>,
+        backends: Backends,
+        power_preference: PowerPreference,
+    ) -> Result<(Instance, Option<Surface<'static>>, Adapter)> {
+        let backend_sets = if backends == Backends::all() {
+            vec![backends]
+        } else {
+            vec![backends, Backends::all()]
+        };
+
+        // the instances of the backend sets and their surfaces, created once they're needed
+        let mut instances: Vec<(Instance, Option<Surface<'static>>)> = Vec::new();
+
+        for (compatible, force_fallback_adapter) in [(true, false), (false, false), (false, true)] {
+            // without a surface, the first request already considered any adapter
+            if !compatible && !force_fallback_adapter && window.is_none() {
+                continue;
+            }
+
+            for (i, backends) in backend_sets.iter().enumerate() {
+                if i == instances.len() {
+                    let instance = Instance::new(InstanceDescriptor {
+                        backends: *backends,
+                        flags: InstanceFlags::empty(),
+                        ..Default::default()
+                    });
+
+                    let surface = window
+                        .clone()
+                        .map(|window| instance.create_surface(window))
+                        .transpose()?;
+
+                    instances.push((instance, surface));
+                }
+
+                if i > 0 {
+                    warn!("falling back to all backends");
+                }
+
+                let (instance, surface) = &instances[i];
+
+                let adapter = instance
+                    .request_adapter(&RequestAdapterOptions {
+                        power_preference,
+                        force_fallback_adapter,
+                        compatible_surface: surface.as_ref().filter(|_| compatible),
+                    })
+                    .await;
+
+                let Some(adapter) = adapter else {
+                    match (compatible, force_fallback_adapter) {
+                        (true, _) if surface.is_some() => {
+                            warn!(
+                                "no adapter compatible with the window's surface for {backends:?}"
+                            )
+                        }
+                        (_, false) => warn!("no adapter found for {backends:?}"),
+                        (_, true) => warn!("no fallback adapter found for {backends:?}"),
+                    }
+
+                    continue;
+                };
+
+                if let Some(surface) = surface {
+                    if !adapter.is_surface_supported(surface) {
+                        warn!(
+                            "adapter {} doesn't report support for the window's surface, \
+                             presenting to it may fail",
+                            adapter.get_info().name
+                        );
+                    }
+                }
+
+                let (instance, surface) = instances.swap_remove(i);
+
+                return Ok((instance, surface, adapter));
+            }
+
+            if compatible && window.is_some() {
+                warn!(
+                    "falling back to adapters which don't report support for the window's surface"
+                );
+            } else if !force_fallback_adapter {
+                warn!("falling back to the fallback (software) adapter");
+            }
+        }
+
+        let tried = match window {
+            Some(_) => {
+                "adapters compatible with the window's surface, any adapter and the fallback"
+            }
+            None => "any adapter and the fallback",
+        };
 
-        Ok((surface, config, device, queue))
+        Err(anyhow!(
+            "no adapter found for {}, tried {tried} adapter",
+            backend_sets
+                .iter()
+                .map(|backends| format!("{backends:?}"))
+                .collect::<Vec<_>>()
+                .join(" and ")
+        ))
     }
 
-    /// Creates a configuration for a surface given the window size.
+    /// Creates a configuration for a surface given the window size, whether to sync presentation to
+    /// the display's refresh rate, the number of frames which may be queued up and the preferred
+    /// alpha mode, falling back to the surface's default one if it isn't supported.
     fn create_surface_config(
         surface: &Surface,
         adapter: &Adapter,
         size: PhysicalSize<u32>,
+        vsync: bool,
+        frame_latency: u32,
+        alpha_mode: Option<CompositeAlphaMode>,
     ) -> SurfaceConfiguration {
         let capabilities = surface.get_capabilities(adapter);
         let format = capabilities
@@ -137,138 +902,2115 @@ impl Renderer {
             .find(TextureFormat::is_srgb)
             .unwrap_or(capabilities.formats[0]);
 
+        // without a native srgb format, frames are rendered through an srgb view of the surface, so
+        // that colors are encoded the same way on every platform
+        let view_format = format.add_srgb_suffix();
+
+        let view_formats = if format.is_srgb() {
+            info!("using srgb surface format {format:?}");
+            vec![]
+        } else if view_format != format {
+            info!(
+                "surface has no srgb format, rendering to {format:?} through {view_format:?} views"
+            );
+            vec![view_format]
+        } else {
+            warn!("surface has no srgb format, colors are written to {format:?} unencoded");
+            vec![]
+        };
+
+        let default_alpha_mode = capabilities.alpha_modes[0];
+
+        let alpha_mode = match alpha_mode {
+            Some(mode) if capabilities.alpha_modes.contains(&mode) => mode,
+            Some(mode) => {
+                warn!(
+                    "the surface doesn't support the alpha mode {mode:?}, falling back to \
+                     {default_alpha_mode:?} (supported: {:?})",
+                    capabilities.alpha_modes
+                );
+                default_alpha_mode
+            }
+            None => default_alpha_mode,
+        };
+
         let PhysicalSize { width, height } = size;
 
         SurfaceConfiguration {
             format,
             width,
             height,
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            present_mode: PresentMode::AutoNoVsync,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: capabilities.alpha_modes[0],
+            // copying from the surface allows capturing frames, where it's supported
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | (capabilities.usages & TextureUsages::COPY_SRC),
+            present_mode: if vsync {
+                PresentMode::AutoVsync
+            } else {
+                PresentMode::AutoNoVsync
+            },
+            desired_maximum_frame_latency: frame_latency,
+            alpha_mode,
+            view_formats,
+        }
+    }
+
+    /// Creates the configuration headless textures of the given size follow, as if they were the
+    /// textures of a surface.
+    fn create_headless_config(size: PhysicalSize<u32>, frame_latency: u32) -> SurfaceConfiguration {
+        SurfaceConfiguration {
+            format: Self::HEADLESS_FORMAT,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            present_mode: PresentMode::Fifo,
+            desired_maximum_frame_latency: frame_latency,
+            alpha_mode: CompositeAlphaMode::Opaque,
             view_formats: vec![],
         }
     }
 
-    /// Creates a render pipeline using the default shaders and settings.
-    fn create_render_pipeline(
+    /// Returns the format the surface's textures are rendered to through, which is an srgb view
+    /// format if the surface was configured with one.
+    fn surface_view_format(config: &SurfaceConfiguration) -> TextureFormat {
+        config
+            .view_formats
+            .first()
+            .copied()
+            .unwrap_or(config.format)
+    }
+
+    /// Returns the highest sample count not above `requested` that the adapter supports for
+    /// multisampling textures of the given format.
+    fn supported_sample_count(adapter: &Adapter, format: TextureFormat, requested: u32) -> u32 {
+        let flags = adapter.get_texture_format_features(format).flags;
+
+        let count = [8, 4, 2]
+            .into_iter()
+            .find(|&count| count <= requested && flags.sample_count_supported(count))
+            .unwrap_or(1);
+
+        if count != requested {
+            warn!("sample count {requested} is not supported, falling back to {count}");
+        }
+
+        count
+    }
+
+    /// Creates the shader module of the pipelines. The source is baked into the binary, unless the
+    /// `hot-reload` feature is enabled, in which case it's read from disk.
+    fn create_shader(device: &Device) -> Result<ShaderModule> {
+        #[cfg(not(feature = "hot-reload"))]
+        let source = Cow::Borrowed(include_str!("../assets/shader/main.wgsl"));
+        #[cfg(feature = "hot-reload")]
+        let source = Cow::Owned(std::fs::read_to_string(Self::SHADER_PATH)?);
+
+        Ok(Self::create_shader_module(device, source))
+    }
+
+    /// Creates a shader module from the source of a shader for the main pipelines, appending the
+    /// definition of `draw_tint` matching the device's features.
+    fn create_shader_module(device: &Device, source: Cow<'_, str>) -> ShaderModule {
+        // the tint of every draw is read from a push constant where they're supported, and from
+        // the material otherwise
+        let tint = if device.features().contains(Features::PUSH_CONSTANTS) {
+            include_str!("../assets/shader/tint_push_constant.wgsl")
+        } else {
+            include_str!("../assets/shader/tint_uniform.wgsl")
+        };
+
+        device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Main Shader"),
+            source: ShaderSource::Wgsl(source + tint),
+        })
+    }
+
+    /// Replaces the shader the meshes are drawn with by the given WGSL source, rebuilding the
+    /// pipelines from it. The shader is bound the same way as `assets/shader/main.wgsl`: it needs
+    /// the `vs_main` and `fs_main` entry points, reads the vertex and instance attributes at the
+    /// same locations and may only use the main shader's bind groups. A `draw_tint` function
+    /// returning the tint of the current draw is appended to the source, the same as for the main
+    /// shader. If the shader fails to compile or doesn't match the pipelines, the error is
+    /// returned and the previous shader is kept. The shader is carried over by
+    /// `Renderer::rebuild`, but replaced again when hot reloading picks up a change on disk.
+    pub fn set_shader(&mut self, wgsl: &str) -> Result<()> {
+        self.replace_shader(Cow::Borrowed(wgsl))?;
+        self.custom_shader = Some(wgsl.to_string());
+
+        Ok(())
+    }
+
+    /// Returns the source given to `Renderer::set_shader`, or `None` if the meshes are drawn with
+    /// the main shader.
+    pub fn custom_shader(&self) -> Option<&str> {
+        self.custom_shader.as_deref()
+    }
+
+    /// Creates the shader module and pipelines from the given source inside of an error scope,
+    /// replacing the current ones only if no errors were reported.
+    fn replace_shader(&mut self, source: Cow<'_, str>) -> Result<()> {
+        self.device.push_error_scope(ErrorFilter::Validation);
+
+        let shader = Self::create_shader_module(&self.device, source);
+
+        let mut pipelines = Self::create_pipelines(
+            &self.device,
+            shader,
+            self.format(),
+            &self.camera_bind_group_layout,
+            Self::depth_compare(self.reversed_z),
+            Self::oriented_depth_bias(self.depth_bias, self.reversed_z),
+            self.sample_count,
+        );
+
+        // the pipelines the scene is drawn with are created right away, so that a shader which
+        // doesn't match them is rejected here rather than while rendering
+        pipelines.prepare(&self.device, PipelineKey::DEFAULT);
+
+        for key in self.pipeline_keys() {
+            pipelines.prepare(&self.device, key);
+        }
+
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(anyhow!("failed to create the shader: {error}"));
+        }
+
+        self.pipelines = pipelines;
+
+        Ok(())
+    }
+
+    /// Rebuilds the shader and pipelines from the shader source on disk. If the new shader fails to
+    /// compile, the previous pipelines are kept.
+    #[cfg(feature = "hot-reload")]
+    fn reload_shader(&mut self) {
+        let source = match std::fs::read_to_string(Self::SHADER_PATH) {
+            Ok(source) => source,
+            Err(error) => return error!("failed to read {}: {error}", Self::SHADER_PATH),
+        };
+
+        if let Err(error) = self.replace_shader(Cow::Owned(source)) {
+            return error!("failed to reload shader, keeping the previous one: {error:#}");
+        }
+
+        self.custom_shader = None;
+
+        info!("reloaded shader {}", Self::SHADER_PATH);
+    }
+
+    /// Creates the set of pipelines used to draw the scene, without creating any of the pipelines
+    /// yet.
+    fn create_pipelines(
         device: &Device,
+        shader: ShaderModule,
         texture_format: TextureFormat,
-        bind_group_layouts: &[&BindGroupLayout],
-    ) -> RenderPipeline {
-        let shader = device.create_shader_module(include_wgsl!("../assets/shader/main.wgsl"));
+        camera_bind_group_layout: &BindGroupLayout,
+        depth_compare: CompareFunction,
+        depth_bias: DepthBiasState,
+        sample_count: u32,
+    ) -> Pipelines {
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&Material::BIND_GROUP_LAYOUT);
+        let point_lights_bind_group_layout =
+            device.create_bind_group_layout(&PointLights::BIND_GROUP_LAYOUT);
+        let shadow_map_bind_group_layout =
+            device.create_bind_group_layout(&ShadowMap::BIND_GROUP_LAYOUT);
+
+        let push_constant_ranges = if device.features().contains(Features::PUSH_CONSTANTS) {
+            &[PushConstantRange {
+                stages: ShaderStages::FRAGMENT,
+                range: 0..Self::PUSH_CONSTANT_SIZE,
+            }][..]
+        } else {
+            &[]
+        };
 
         let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Render Pipline Layout Descriptor"),
-            bind_group_layouts,
-            push_constant_ranges: &[],
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                &material_bind_group_layout,
+                &point_lights_bind_group_layout,
+                &shadow_map_bind_group_layout,
+            ],
+            push_constant_ranges,
         });
 
+        Pipelines {
+            shader,
+            layout,
+            texture_format,
+            depth_compare,
+            depth_bias,
+            sample_count,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Returns the keys of the pipelines the meshes which are currently visible are drawn with.
+    /// Some of them may end up being drawn as thick lines instead, without their pipeline.
+    fn pipeline_keys(&self) -> impl Iterator<Item = PipelineKey> + '_ {
+        let polygon_mode = self.polygon_mode();
+
+        self.scene
+            .iter()
+            .map(|(_, mesh)| mesh)
+            .filter(|mesh| mesh.visible())
+            .chain(self.axes_visible.then_some(&self.axes))
+            .map(move |mesh| PipelineKey::of(mesh, self.cull_mode, polygon_mode))
+    }
+
+    /// Creates the pipelines the meshes which are currently visible are drawn with, which haven't
+    /// been drawn with since the pipelines were last invalidated.
+    fn prepare_pipelines(&mut self) {
+        let keys = self.pipeline_keys().collect::<Vec<_>>();
+
+        for key in keys {
+            self.pipelines.prepare(&self.device, key);
+        }
+    }
+
+    /// Returns how triangles are rasterized, as lines while drawing wireframes.
+    fn polygon_mode(&self) -> PolygonMode {
+        if self.wireframe {
+            PolygonMode::Line
+        } else {
+            PolygonMode::Fill
+        }
+    }
+
+    /// Creates the multisampled color target matching the size of the surface, or `None` if
+    /// multisampling is disabled.
+    fn create_msaa_texture(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> Option<SizedTexture> {
+        (sample_count > 1).then(|| {
+            SizedTexture::new(
+                device,
+                "Multisampled Color Texture",
+                config.width,
+                config.height,
+                format,
+                sample_count,
+                TextureUsages::RENDER_ATTACHMENT,
+            )
+        })
+    }
+
+    /// Creates a depth buffer matching the size of the surface, which can be bound as a texture if
+    /// it's `sampled`.
+    fn create_depth_texture(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+        sampled: bool,
+    ) -> SizedTexture {
+        // only sampled by the depth visualization, as multisampled depth buffers which can be bound
+        // keep the color target from being resolved on some backends (such as gl)
+        let usage = if sampled {
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING
+        } else {
+            TextureUsages::RENDER_ATTACHMENT
+        };
+
+        SizedTexture::new(
+            device,
+            "Depth Texture",
+            config.width,
+            config.height,
+            Self::DEPTH_FORMAT,
+            sample_count,
+            usage,
+        )
+    }
+
+    /// Creates a render pipeline using the default shaders and the settings of `key`.
+    #[allow(clippy::too_many_arguments)]
+    fn create_render_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        texture_format: TextureFormat,
+        layout: &PipelineLayout,
+        key: PipelineKey,
+        depth_compare: CompareFunction,
+        depth_bias: DepthBiasState,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        let blend = key.transparent.then_some(BlendState::ALPHA_BLENDING);
+
         device.create_render_pipeline(&RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&layout),
+            layout: Some(layout),
             vertex: VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
                 compilation_options: PipelineCompilationOptions::default(),
             },
             fragment: Some(FragmentState {
-                module: &shader,
+                module: shader,
                 entry_point: "fs_main",
                 targets: &[Some(ColorTargetState {
                     format: texture_format,
                     write_mask: ColorWrites::ALL,
-                    blend: None,
+                    blend,
                 })],
                 compilation_options: PipelineCompilationOptions::default(),
             }),
             primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
+                topology: key.topology,
+                strip_index_format: key.topology.is_strip().then_some(IndexFormat::Uint32),
                 front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
+                cull_mode: key.cull_mode,
                 unclipped_depth: false,
-                polygon_mode: PolygonMode::Fill,
+                polygon_mode: key.polygon_mode,
                 conservative: false,
             },
-            multisample: MultisampleState::default(),
-            depth_stencil: None,
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: Self::DEPTH_FORMAT,
+                depth_write_enabled: blend.is_none() && !key.overlay,
+                depth_compare: if key.overlay {
+                    CompareFunction::Always
+                } else {
+                    depth_compare
+                },
+                stencil: StencilState::default(),
+                bias: if key.depth_biased {
+                    depth_bias
+                } else {
+                    DepthBiasState::default()
+                },
+            }),
             multiview: None,
             cache: None,
         })
     }
 
-    /// Updates the camera's uniform buffer with a new view-projection matrix.
-    pub fn update_camera_buffer(&mut self, view_proj: glam::Mat4) {
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0 as BufferAddress,
-            bytemuck::cast_slice(&view_proj.to_cols_array()),
-        );
+    /// Updates the camera of the first viewport, which is uploaded to its uniform buffer when the
+    /// next frame is rendered.
+    pub fn update_camera_buffer(&mut self, camera: &Camera) {
+        self.set_viewport_camera(0, camera);
     }
 
-    /// Reconfigures the target `surface` to the new rendering size.
-    pub fn resize(&mut self, size: PhysicalSize<u32>) {
-        let PhysicalSize { width, height } = size;
+    /// Renders the scene from each of the cameras into its viewport from now on, replacing the
+    /// previous viewports. The aspect ratio of each camera is derived from its viewport. The first
+    /// camera keeps being replaced by `Renderer::update_camera_buffer`, while the others can be
+    /// moved with `Renderer::set_viewport_camera`. Only the first `Viewport::MAX_COUNT` viewports
+    /// are used, and an empty slice restores a single viewport covering the whole surface.
+    pub fn set_viewports(&mut self, viewports: &[(Camera, Viewport)]) {
+        if viewports.len() > Viewport::MAX_COUNT {
+            warn!(
+                "only the first {} of {} viewports are rendered",
+                Viewport::MAX_COUNT,
+                viewports.len()
+            );
+        }
 
-        assert!(width > 0, "cannot resize to zero width");
-        assert!(height > 0, "cannot resize to zero height");
+        let first = self.views.swap_remove(0).camera;
 
-        self.surface_config.width = width;
-        self.surface_config.height = height;
+        self.views = viewports
+            .iter()
+            .take(Viewport::MAX_COUNT)
+            .map(|(camera, viewport)| View {
+                camera: camera.clone(),
+                viewport: *viewport,
+            })
+            .collect();
+
+        if self.views.is_empty() {
+            self.views.push(View {
+                camera: first,
+                viewport: Viewport::FULL,
+            });
+        }
 
-        self.surface.configure(&self.device, &self.surface_config);
+        for index in 0..self.views.len() {
+            let camera = self.views[index].camera.clone();
+            self.set_viewport_camera(index, &camera);
+        }
     }
 
-    /// Begins a render pass and renders the currently active meshes to the `surface`.
-    pub fn render(&mut self) -> Result<()> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Returns the number of viewports the scene is rendered into.
+    pub fn viewport_count(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Replaces the camera of the `index`th viewport, adjusting its aspect ratio to the viewport.
+    /// Does nothing if there is no such viewport.
+    pub fn set_viewport_camera(&mut self, index: usize, camera: &Camera) {
+        let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+
+        if let Some(view) = self.views.get_mut(index) {
+            view.camera = camera.clone();
+            view.camera.resize(view.viewport.size(size));
+            view.camera.set_reversed_z(self.reversed_z);
+        }
+    }
+
+    /// Reads back the view-projection matrix of the first view's camera as the gpu sees it, by
+    /// copying it out of the uniform buffer and waiting for the copy to finish. The buffer is
+    /// uploaded at the start of every frame, so this is the matrix the last frame was rendered
+    /// with. Blocks on the device, so it's meant for debugging only.
+    #[cfg(feature = "debug")]
+    pub fn dump_camera_uniform(&self) -> Mat4 {
+        let size = std::mem::size_of::<Mat4>() as BufferAddress;
+
+        let staging = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Camera Uniform Readback Buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
+                label: Some("Camera Uniform Readback Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color {
-                            r: 0.01,
-                            g: 0.01,
-                            b: 0.01,
-                            a: 1.0,
-                        }),
-                        store: StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+        encoder.copy_buffer_to_buffer(&self.camera_buffer, 0, &staging, 0, size);
+        self.queue.submit(std::iter::once(encoder.finish()));
 
-            render_pass.set_pipeline(&self.pipeline);
+        let (sender, receiver) = std::sync::mpsc::channel();
 
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        staging.slice(..).map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
 
-            render_pass.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.mesh.index_buffer.slice(..), IndexFormat::Uint32);
+        self.device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .expect("the readback was dropped before mapping finished")
+            .expect("failed to map the camera uniform readback buffer");
 
-            render_pass.draw(0..self.mesh.count, 0..1);
-        }
+        let columns: [f32; 16] =
+            bytemuck::pod_read_unaligned(&staging.slice(..).get_mapped_range());
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        Mat4::from_cols_array(&columns)
+    }
+
+    /// Returns the format the scene is rendered in, which is the format of the offscreen target if
+    /// there is one, and of the surface otherwise.
+    pub fn format(&self) -> TextureFormat {
+        match &self.offscreen {
+            Some(offscreen) => offscreen.target.format(),
+            None => Self::surface_view_format(&self.surface_config),
+        }
+    }
+
+    /// Returns the number of samples currently taken per pixel.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sets the number of samples taken per pixel for multisample anti-aliasing (1, 2, 4 or 8),
+    /// falling back to the highest lower count if the adapter doesn't support it.
+    pub fn set_sample_count(&mut self, count: u32) {
+        let format = self.format();
+        let count = Self::supported_sample_count(&self.adapter, format, count);
+
+        if count == self.sample_count {
+            return;
+        }
+
+        self.sample_count = count;
+        self.recreate_pipelines();
+    }
+
+    /// Returns whether the depth is reversed, cleared to 0 and tested with
+    /// `CompareFunction::Greater`.
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Sets whether the depth is reversed, recreating the pipelines which test against it and
+    /// reversing the projections of the viewports' cameras along with it (see
+    /// `Camera::set_reversed_z` for the tradeoff). The cameras passed to
+    /// `Renderer::update_camera_buffer` and `Renderer::set_viewport_camera` are reversed as well,
+    /// so the app's own camera can keep its standard projection.
+    pub fn set_reversed_z(&mut self, reversed_z: bool) {
+        if reversed_z == self.reversed_z {
+            return;
+        }
+
+        self.reversed_z = reversed_z;
+        self.recreate_pipelines();
+
+        // the pick pass is recreated with the new depth the next time it's used
+        self.pick_pass = None;
+
+        for view in &mut self.views {
+            view.camera.set_reversed_z(reversed_z);
+        }
+    }
+
+    /// Returns the bias of the depth of depth biased meshes, as given to `Renderer::set_depth_bias`.
+    pub fn depth_bias(&self) -> DepthBiasState {
+        self.depth_bias
+    }
+
+    /// Sets the bias offsetting the depth of the meshes marked with `Mesh::set_depth_biased`,
+    /// recreating the pipelines which apply it. The bias is given for the standard depth, where
+    /// negative values pull the meshes towards the camera, and is flipped while the depth is
+    /// reversed so that it keeps pulling the same way. The `constant` is in the smallest steps of
+    /// the depth buffer, and `slope_scale` is multiplied with how steeply the depth changes across
+    /// a triangle, which grows at grazing angles where coplanar faces fight the most.
+    pub fn set_depth_bias(&mut self, depth_bias: DepthBiasState) {
+        if depth_bias == self.depth_bias {
+            return;
+        }
+
+        self.depth_bias = depth_bias;
+        self.recreate_pipelines();
+    }
+
+    /// Returns a depth bias given for the standard depth, flipped if the depth is reversed.
+    fn oriented_depth_bias(depth_bias: DepthBiasState, reversed_z: bool) -> DepthBiasState {
+        if reversed_z {
+            DepthBiasState {
+                constant: -depth_bias.constant,
+                slope_scale: -depth_bias.slope_scale,
+                ..depth_bias
+            }
+        } else {
+            depth_bias
+        }
+    }
+
+    /// Returns the depth test of the pipelines drawing the scene, `CompareFunction::Greater` if the
+    /// depth is reversed and `CompareFunction::Less` otherwise.
+    fn depth_compare(reversed_z: bool) -> CompareFunction {
+        if reversed_z {
+            CompareFunction::Greater
+        } else {
+            CompareFunction::Less
+        }
+    }
+
+    /// Recreates the pipelines and targets which depend on the `sample_count`, `reversed_z` or
+    /// `depth_bias`.
+    fn recreate_pipelines(&mut self) {
+        let format = self.format();
+        let count = self.sample_count;
+        let depth_compare = Self::depth_compare(self.reversed_z);
+
+        self.pipelines.invalidate(
+            format,
+            depth_compare,
+            Self::oriented_depth_bias(self.depth_bias, self.reversed_z),
+            count,
+        );
+
+        if let Some(skybox) = &mut self.skybox {
+            skybox.recreate_pipeline(&self.device, format, Self::DEPTH_FORMAT, count);
+        }
+
+        if let Some(background) = &mut self.background {
+            background.recreate_pipeline(&self.device, format, Self::DEPTH_FORMAT, count);
+        }
+
+        if let Some(particles) = &mut self.particles {
+            particles.recreate_pipeline(
+                &self.device,
+                format,
+                Self::DEPTH_FORMAT,
+                depth_compare,
+                count,
+            );
+        }
+
+        self.grid.recreate_pipeline(
+            &self.device,
+            format,
+            Self::DEPTH_FORMAT,
+            depth_compare,
+            count,
+        );
+        self.line_pass
+            .recreate_pipelines(&self.device, format, depth_compare, count);
+        self.labels
+            .recreate_pipeline(&self.device, format, Self::DEPTH_FORMAT, count);
+
+        self.msaa_texture =
+            Self::create_msaa_texture(&self.device, &self.surface_config, format, count);
+        self.depth_texture = Self::create_depth_texture(
+            &self.device,
+            &self.surface_config,
+            count,
+            self.depth_debug.is_some(),
+        );
+
+        if self.depth_debug.is_some() {
+            self.depth_debug = Some(self.create_depth_debug_pass());
+        }
+    }
+
+    /// Returns whether the depth buffer is drawn in place of the scene.
+    pub fn depth_debug(&self) -> bool {
+        self.depth_debug.is_some()
+    }
+
+    /// Sets whether the depth buffer is drawn in place of the scene, as shades of gray growing
+    /// lighter with the linear distance from the camera up to `Renderer::depth_debug_range`. Shows
+    /// where depth precision runs out and which surfaces are coplanar.
+    pub fn set_depth_debug(&mut self, enabled: bool) {
+        if enabled == self.depth_debug.is_some() {
+            return;
+        }
+
+        self.depth_texture = Self::create_depth_texture(
+            &self.device,
+            &self.surface_config,
+            self.sample_count,
+            enabled,
+        );
+        self.depth_debug = enabled.then(|| self.create_depth_debug_pass());
+    }
+
+    /// Returns the distance shown as white by the depth visualization.
+    pub fn depth_debug_range(&self) -> f32 {
+        self.depth_debug_range
+    }
+
+    /// Sets the distance shown as white by the depth visualization, clamped to stay positive.
+    pub fn set_depth_debug_range(&mut self, range: f32) {
+        self.depth_debug_range = range.max(Camera::Z_NEAR);
+
+        if let Some(pass) = &self.depth_debug {
+            pass.set_range(&self.queue, self.depth_debug_range);
+        }
+    }
+
+    /// Creates the pass drawing the depth buffer, matching its current sample count.
+    fn create_depth_debug_pass(&self) -> DepthDebugPass {
+        DepthDebugPass::new(
+            &self.device,
+            Self::surface_view_format(&self.surface_config),
+            self.sample_count,
+            self.depth_debug_range,
+            self.reversed_z,
+        )
+    }
+
+    /// Returns the scalar the colors of a high dynamic range offscreen target are multiplied with
+    /// before being tonemapped.
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Sets the scalar the colors of a high dynamic range offscreen target are multiplied with
+    /// before being tonemapped. Has no effect on other targets.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+
+        if let Some(tonemapping) = self
+            .offscreen
+            .as_ref()
+            .and_then(|offscreen| offscreen.tonemapping.as_ref())
+        {
+            tonemapping.pass.set_exposure(&self.queue, exposure);
+        }
+    }
+
+    /// Returns the brightness and gamma adjustments applied to the colors of the meshes.
+    pub fn post_params(&self) -> PostParams {
+        self.post_params
+    }
+
+    /// Sets the brightness and gamma adjustments applied to the colors of the meshes, clamped with
+    /// `PostParams::clamped`.
+    pub fn set_post_params(&mut self, params: PostParams) {
+        self.post_params = params.clamped();
+        self.post_params
+            .write_buffer(&self.queue, &self.post_params_buffer);
+    }
+
+    /// Returns the plane the meshes are clipped against, if any.
+    pub fn clip_plane(&self) -> Option<Vec4> {
+        self.clip_plane
+    }
+
+    /// Sets the plane equation `(a, b, c, d)` the meshes are clipped against, discarding their
+    /// fragments at world positions `p` in front of it, where `dot(p, (a, b, c)) + d > 0`. With a
+    /// normalized `(a, b, c)`, `-d` is the plane's distance from the origin along it. The clipped
+    /// parts still cast shadows, while the grid and other helpers aren't clipped. `None` turns
+    /// clipping off.
+    pub fn set_clip_plane(&mut self, plane: Option<Vec4>) {
+        self.clip_plane = plane;
+        camera_uniform::write_clip_plane(&self.queue, &self.clip_plane_buffer, plane);
+    }
+
+    /// Sets the exponent the linear colors of the meshes are raised to the inverse of, 1 leaving
+    /// them unchanged.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.set_post_params(PostParams {
+            gamma,
+            ..self.post_params
+        });
+    }
+
+    /// Sets the scalar the linear colors of the meshes are multiplied with, 1 leaving them
+    /// unchanged.
+    pub fn set_brightness(&mut self, brightness: f32) {
+        self.set_post_params(PostParams {
+            brightness,
+            ..self.post_params
+        });
+    }
+
+    /// Returns whether the scene is anti-aliased with FXAA.
+    pub fn fxaa(&self) -> bool {
+        self.fxaa
+    }
+
+    /// Sets whether the scene is anti-aliased with FXAA after being rendered. Without an offscreen
+    /// target, one with the format of the surface is created, so that nothing else has to be
+    /// recreated.
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        if enabled && self.offscreen.is_none() {
+            self.offscreen = Some(Offscreen::new(
+                &self.adapter,
+                &self.device,
+                &self.surface_config,
+                Self::surface_view_format(&self.surface_config),
+                self.exposure,
+            ));
+        }
+
+        if enabled && self.offscreen.as_ref().is_some_and(|o| o.fxaa.is_none()) {
+            warn!("FXAA is not supported with an offscreen format which can't be filtered");
+        }
+
+        self.fxaa = enabled;
+    }
+
+    /// Returns the gpu time of the last measured render pass in milliseconds, or `None` if timestamp
+    /// queries aren't supported by the adapter.
+    pub fn last_gpu_time(&self) -> Option<f32> {
+        self.gpu_timer.as_ref()?.last_time()
+    }
+
+    /// Returns the cpu time spent recording and submitting the commands of the last rendered frame
+    /// in milliseconds, not counting the wait for the surface's next texture or for presenting it.
+    pub fn last_encode_time(&self) -> f32 {
+        self.encode_time
+    }
+
+    /// Returns the number of draw calls, triangles and vertices of the meshes drawn in the last
+    /// rendered frame, after frustum culling.
+    pub fn last_render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    /// Returns the color the surface is cleared to before drawing.
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Sets the color the surface is cleared to before drawing, visible wherever nothing is drawn.
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    /// Sets the frame of the debug overlay to paint on top of the scene, until replaced.
+    #[cfg(feature = "debug-ui")]
+    pub fn set_debug_ui_frame(&mut self, frame: UiFrame) {
+        self.ui_frame = Some(frame);
+    }
+
+    /// Returns the meshes currently being rendered.
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Returns a mutable reference to the meshes currently being rendered.
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// Replaces the scene with one saved with `Scene::save`, keeping the current scene if loading
+    /// fails.
+    #[cfg(feature = "serde")]
+    pub fn load_scene(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.scene = Scene::load(&self.device, path)?;
+        Ok(())
+    }
+
+    /// Replaces the scene with `Scene::stress_test` of `count` cubes.
+    pub fn load_stress_test(&mut self, count: usize) {
+        self.scene = Scene::stress_test(&self.device, count);
+    }
+
+    /// Replaces the scene with `Scene::random` of `count` cubes laid out from `seed`.
+    pub fn load_random_scene(&mut self, seed: u64, count: usize) {
+        self.scene = Scene::random(&self.device, seed, count);
+    }
+
+    /// Uploads a new mesh built from the given vertices and indices to the scene.
+    pub fn add_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> MeshId {
+        self.scene.add(Mesh::new(&self.device, vertices, indices))
+    }
+
+    /// Uploads a new mesh built from the given vertices and indices into the renderer's shared
+    /// buffers, see `Mesh::new_shared`, and adds it to the scene. The space isn't reclaimed when
+    /// the mesh is removed again.
+    pub fn add_shared_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> MeshId {
+        let mesh = Mesh::new_shared(
+            &self.device,
+            &self.queue,
+            &mut self.mesh_arena,
+            vertices,
+            indices,
+        );
+
+        self.scene.add(mesh)
+    }
+
+    /// Returns the buffers shared by the meshes added with `Renderer::add_shared_mesh`.
+    pub fn mesh_arena(&self) -> &MeshArena {
+        &self.mesh_arena
+    }
+
+    /// Uploads a new mesh to the scene whose vertices can be written by a compute shader, see
+    /// `Mesh::new_storage`.
+    pub fn add_storage_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> MeshId {
+        self.scene
+            .add(Mesh::new_storage(&self.device, vertices, indices))
+    }
+
+    /// Tints a mesh of the scene, multiplying the color of its shaded fragments with `tint` until
+    /// it's reset with `None`, such as to highlight a selection. The tint is pushed as a constant
+    /// with the mesh's draw call, leaving its buffers and material untouched, or stored in the
+    /// material's uniform on devices without push constants (unsharing the material first, so the
+    /// meshes sharing it aren't tinted too).
+    pub fn set_tint(&mut self, id: MeshId, tint: Option<Vec4>) {
+        let push_constants = self.device.features().contains(Features::PUSH_CONSTANTS);
+
+        let Some(mesh) = self.scene.get_mut(id) else {
+            return;
+        };
+
+        mesh.set_tint(tint);
+
+        if !push_constants {
+            mesh.material.unshare(&self.device);
+            mesh.material
+                .set_tint(&self.queue, tint.unwrap_or(Material::DEFAULT_TINT));
+        }
+    }
+
+    /// Returns the mesh drawn at the given cursor position (in physical pixels from the top left
+    /// corner of the surface), by rendering the ids of the meshes with a `PickPass` and reading
+    /// back the one under the cursor. The meshes are seen from the camera of the viewport the
+    /// cursor is over, with the cameras and transforms uploaded for the last rendered frame.
+    /// Blocks until the gpu is done, so it's meant for clicks rather than every frame.
+    pub fn pick(&mut self, cursor: PhysicalPosition<f64>) -> Option<MeshId> {
+        let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+        let (x, y) = (cursor.x.floor(), cursor.y.floor());
+
+        if x < 0.0 || y < 0.0 || x >= size.width as f64 || y >= size.height as f64 {
+            return None;
+        }
+
+        let (x, y) = (x as u32, y as u32);
+
+        // later views are drawn over earlier ones
+        let (index, (view_x, view_y, width, height)) = self
+            .views
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, view)| (index, view.viewport.pixels(size)))
+            .find(|&(_, (view_x, view_y, width, height))| {
+                (view_x..view_x + width).contains(&x) && (view_y..view_y + height).contains(&y)
+            })?;
+
+        let frustum = self.views[index].camera.frustum();
+
+        // overlays are drawn last, over everything else
+        let (overlays, mut meshes): (Vec<_>, Vec<_>) = self
+            .scene
+            .iter()
+            .filter(|(_, mesh)| mesh.visible() && frustum.intersects_aabb(&mesh.world_bounds()))
+            .partition(|(_, mesh)| mesh.overlay());
+        meshes.extend(overlays);
+
+        let pick_pass = self.pick_pass.get_or_insert_with(|| {
+            PickPass::new(
+                &self.device,
+                &self.camera_bind_group_layout,
+                size.width,
+                size.height,
+                self.reversed_z,
+            )
+        });
+
+        pick_pass.prepare(
+            &self.device,
+            &self.queue,
+            size.width,
+            size.height,
+            meshes.len() as u32,
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Pick Encoder"),
+            });
+
+        {
+            let mut render_pass = pick_pass.begin(&mut encoder);
+            let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+            // only the pixel under the cursor is rasterized
+            render_pass.set_viewport(
+                view_x as f32,
+                view_y as f32,
+                width as f32,
+                height as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_scissor_rect(x, y, 1, 1);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[offset]);
+
+            for (index, (_, mesh)) in meshes.iter().enumerate() {
+                pick_pass.draw(&mut render_pass, index as u32, mesh, &self.mesh_arena);
+            }
+        }
+
+        let buffer = pick_pass.copy_pixel(&self.device, &mut encoder, x, y);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        match PickPass::read(&self.device, &buffer) {
+            Ok(picked) => picked
+                .and_then(|index| meshes.get(index as usize))
+                .map(|&(id, _)| id),
+            Err(error) => {
+                warn!("failed to read the picked mesh back: {error}");
+                None
+            }
+        }
+    }
+
+    /// Removes a mesh from the scene, returning it if it was present.
+    pub fn remove_mesh(&mut self, id: MeshId) -> Option<Mesh> {
+        self.scene.remove(id)
+    }
+
+    /// Draws a copy of the mesh for every one of the given `instances` from now on, all within a
+    /// single draw call. The instance count is only bounded by the rendering device's maximum
+    /// buffer size.
+    pub fn draw_instanced(&mut self, mesh: MeshId, instances: &[InstanceRaw]) {
+        if let Some(mesh) = self.scene.get_mut(mesh) {
+            mesh.set_instances(&self.device, instances);
+        }
+    }
+
+    /// Lights the scene with the given point lights, replacing the previous ones. Only the first
+    /// `PointLights::MAX_COUNT` lights are used. Without any lights the scene is drawn unlit.
+    pub fn set_point_lights(&mut self, lights: &[PointLight]) {
+        if lights.len() > PointLights::MAX_COUNT {
+            warn!(
+                "only the first {} of {} point lights are used",
+                PointLights::MAX_COUNT,
+                lights.len()
+            );
+        }
+
+        self.point_lights.set(&self.queue, lights);
+    }
+
+    /// Returns the point lights lighting the scene.
+    pub fn point_lights(&self) -> &[PointLight] {
+        self.point_lights.lights()
+    }
+
+    /// Returns the directional light lighting the scene, if there is one.
+    pub fn directional_light(&self) -> Option<DirectionalLight> {
+        self.shadow_map.light()
+    }
+
+    /// Sets the directional light lighting the scene and casting shadows from its opaque meshes,
+    /// `None` removing it. The shadows cover the bounds of the whole scene.
+    pub fn set_directional_light(&mut self, light: Option<DirectionalLight>) {
+        self.shadow_map.set_light(light);
+    }
+
+    /// Returns whether the reference grid is currently being drawn.
+    pub fn grid_visible(&self) -> bool {
+        self.grid_visible
+    }
+
+    /// Sets whether the reference grid on the ground plane should be drawn.
+    pub fn set_grid_visible(&mut self, visible: bool) {
+        self.grid_visible = visible;
+    }
+
+    /// Returns the spacing, colors and fade distance of the reference grid.
+    pub fn grid_params(&self) -> GridParams {
+        self.grid.params()
+    }
+
+    /// Sets the spacing, colors and fade distance of the reference grid, clamped with
+    /// `GridParams::clamped`.
+    pub fn set_grid_params(&mut self, params: GridParams) {
+        self.grid.set_params(&self.queue, params);
+    }
+
+    /// Sets the distance between two neighbouring lines of the reference grid.
+    pub fn set_grid_spacing(&mut self, spacing: f32) {
+        self.set_grid_params(GridParams {
+            spacing,
+            ..self.grid_params()
+        });
+    }
+
+    /// Sets the color of the minor lines of the reference grid, its alpha blending them over the
+    /// scene.
+    pub fn set_grid_color(&mut self, color: Vec4) {
+        self.set_grid_params(GridParams {
+            color,
+            ..self.grid_params()
+        });
+    }
+
+    /// Sets the distance from the camera at which the reference grid has completely faded out.
+    pub fn set_grid_fade_distance(&mut self, fade_distance: f32) {
+        self.set_grid_params(GridParams {
+            fade_distance,
+            ..self.grid_params()
+        });
+    }
+
+    /// Returns whether the axis gizmo is currently being drawn.
+    pub fn axes_visible(&self) -> bool {
+        self.axes_visible
+    }
+
+    /// Sets whether the axis gizmo at the origin should be drawn.
+    pub fn set_axes_visible(&mut self, visible: bool) {
+        self.axes_visible = visible;
+    }
+
+    /// Returns a summary of the capabilities of the gpu, collected when the renderer was created.
+    pub fn gpu_report(&self) -> &GpuReport {
+        &self.gpu_report
+    }
+
+    /// Returns the features supported by the adapter, not all of which are enabled on the device.
+    pub fn adapter_features(&self) -> Features {
+        self.adapter_features
+    }
+
+    /// Returns the features enabled on the device, the supported subset of
+    /// `Renderer::OPTIONAL_FEATURES`.
+    pub fn features(&self) -> Features {
+        self.device.features() & Self::OPTIONAL_FEATURES
+    }
+
+    /// Returns the limits of the device.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Returns the device the renderer's resources live on, for creating buffers, textures and
+    /// pipelines of one's own which can be used alongside them.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the queue the renderer submits its work to.
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Returns the format of the surface's textures (or of the texture headless renderers render
+    /// into). Surfaces without a native srgb format are rendered to through srgb views of it,
+    /// which pipelines drawing onto the frames target instead, see `TextureFormat::add_srgb_suffix`.
+    pub fn surface_format(&self) -> TextureFormat {
+        self.surface_config.format
+    }
+
+    /// Returns whether only the edges of triangles are drawn.
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Sets whether only the edges of triangles are drawn, returning whether the setting was
+    /// applied. Wireframes need `Features::POLYGON_MODE_LINE`, so they stay disabled on devices
+    /// without it.
+    pub fn set_wireframe(&mut self, enabled: bool) -> bool {
+        if enabled && !self.features().contains(Features::POLYGON_MODE_LINE) {
+            warn!("wireframes aren't supported by the adapter");
+            return false;
+        }
+
+        self.wireframe = enabled;
+        true
+    }
+
+    /// Returns the width lines are drawn with, in logical pixels.
+    pub fn line_width(&self) -> f32 {
+        self.line_width
+    }
+
+    /// Sets the width lines (the axes, line meshes and the grid, along with the edges of triangles
+    /// in wireframe) are drawn with in logical pixels, clamped between 1 and `MAX_LINE_WIDTH`. The
+    /// width is multiplied with the window's scale factor, so that lines look equally wide across
+    /// displays. Lines wider than a physical pixel are expanded into antialiased quads, drawn
+    /// unlit in their vertex colors, which skinned meshes are drawn in their bind pose with.
+    pub fn set_line_width(&mut self, width: f32) {
+        self.line_width = width.clamp(1.0, Self::MAX_LINE_WIDTH);
+    }
+
+    /// Returns the ratio of physical to logical pixels of the window, 1 for headless renderers.
+    pub fn scale_factor(&self) -> f32 {
+        match &self.output {
+            Output::Window { window, .. } => window.scale_factor() as f32,
+            Output::Headless(_) => 1.0,
+        }
+    }
+
+    /// Returns whether the mesh is drawn as thick lines by the `line_pass`, given the width of lines
+    /// in physical pixels: line meshes whenever lines are wider than a pixel, and triangles only in
+    /// wireframe.
+    fn draws_thick_lines(mesh: &Mesh, line_width: f32, wireframe: bool) -> bool {
+        line_width > 1.0
+            && match mesh.topology {
+                PrimitiveTopology::LineList | PrimitiveTopology::LineStrip => true,
+                PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip => wireframe,
+                PrimitiveTopology::PointList => false,
+            }
+    }
+
+    /// Queues a white label for the next frame, drawn over the scene centered on where `position`
+    /// is on screen. Labels only last for a single frame, so they're queued again before every
+    /// frame they should be drawn in; see `Labels` for how they're drawn.
+    pub fn draw_label(&mut self, position: Vec3, text: &str) {
+        self.draw_colored_label(position, text, Labels::DEFAULT_COLOR);
+    }
+
+    /// Queues a label in the straight rgba `color` for the next frame, like
+    /// `Renderer::draw_label`.
+    pub fn draw_colored_label(&mut self, position: Vec3, text: &str, color: Vec4) {
+        // the font's pixels are kept whole so that they stay sharp
+        let scale = (Labels::SCALE * self.scale_factor()).round().max(1.0);
+
+        self.labels.push(position, text, color, scale);
+    }
+
+    /// Returns the faces of triangles which aren't drawn.
+    pub fn cull_mode(&self) -> Option<Face> {
+        self.cull_mode
+    }
+
+    /// Sets the faces of triangles which aren't drawn, `None` drawing both sides (for example to
+    /// see the inside of a mesh with flipped winding).
+    pub fn set_cull_mode(&mut self, cull_mode: Option<Face>) {
+        self.cull_mode = cull_mode;
+    }
+
+    /// Sets the cubemap drawn behind the scene from six square faces of `face_size * face_size`
+    /// rgba pixels, ordered as +x, -x, +y, -y, +z and -z. The options are reduced to what the
+    /// adapter supports.
+    pub fn set_skybox(&mut self, face_size: u32, faces: [&[u8]; 6], options: TextureOptions) {
+        self.skybox = Some(Skybox::new(
+            &self.device,
+            &self.queue,
+            face_size,
+            faces,
+            &options.supported(&self.adapter),
+            self.format(),
+            Self::DEPTH_FORMAT,
+            self.sample_count,
+        ));
+    }
+
+    /// Removes the skybox, clearing to the `clear_color` behind the scene again.
+    pub fn remove_skybox(&mut self) {
+        self.skybox = None;
+    }
+
+    /// Returns whether the skybox is currently being drawn, if one has been set.
+    pub fn skybox_visible(&self) -> bool {
+        self.skybox_visible
+    }
+
+    /// Sets whether the skybox should be drawn, if one has been set.
+    pub fn set_skybox_visible(&mut self, visible: bool) {
+        self.skybox_visible = visible;
+    }
+
+    /// Draws a vertical gradient from `top` to `bottom` (in linear rgba, like the clear color)
+    /// behind the scene instead of clearing to the solid clear color, spanning every viewport. The
+    /// skybox is drawn instead while it's visible.
+    pub fn set_background_gradient(&mut self, top: Color, bottom: Color) {
+        match &mut self.background {
+            Some(background) => background.set_colors(&self.queue, top, bottom),
+            None => {
+                self.background = Some(BackgroundGradient::new(
+                    &self.device,
+                    top,
+                    bottom,
+                    self.format(),
+                    Self::DEPTH_FORMAT,
+                    self.sample_count,
+                ));
+            }
+        }
+    }
+
+    /// Removes the background gradient, clearing to the solid clear color behind the scene again.
+    pub fn remove_background_gradient(&mut self) {
+        self.background = None;
+    }
+
+    /// Returns the top and bottom colors of the background gradient, if one has been set.
+    pub fn background_gradient(&self) -> Option<(Color, Color)> {
+        self.background.as_ref().map(BackgroundGradient::colors)
+    }
+
+    /// Sets the particle system blended over the scene to a new one emitting with the given
+    /// parameters, replacing any previous one along with its particles. It's advanced with
+    /// `Renderer::update_particles`.
+    pub fn set_particles(&mut self, params: ParticleParams) {
+        self.particles = Some(ParticleSystem::new(
+            &self.device,
+            params,
+            self.format(),
+            Self::DEPTH_FORMAT,
+            Self::depth_compare(self.reversed_z),
+            self.sample_count,
+        ));
+    }
+
+    /// Removes the particle system, along with its particles.
+    pub fn remove_particles(&mut self) {
+        self.particles = None;
+    }
+
+    /// Returns the particle system, if one has been set.
+    pub fn particles(&self) -> Option<&ParticleSystem> {
+        self.particles.as_ref()
+    }
+
+    /// Returns the particle system for changing its parameters or emitting bursts, if one has been
+    /// set.
+    pub fn particles_mut(&mut self) -> Option<&mut ParticleSystem> {
+        self.particles.as_mut()
+    }
+
+    /// Sets the parameters of the particle system, if one has been set.
+    pub fn set_particle_params(&mut self, params: ParticleParams) {
+        if let Some(particles) = &mut self.particles {
+            particles.set_params(&self.device, params);
+        }
+    }
+
+    /// Advances the particles of the particle system by `dt` seconds, if one has been set.
+    pub fn update_particles(&mut self, dt: f32) {
+        if let Some(particles) = &mut self.particles {
+            particles.update(dt);
+        }
+    }
+
+    /// Sets the compute shader run by `Renderer::dispatch_compute` to the `entry_point` of the WGSL
+    /// `source`, reading and writing the vertex buffer of `mesh` bound at group 0, binding 0. The
+    /// mesh has to have been added with `Renderer::add_storage_mesh`.
+    pub fn set_compute_shader(
+        &mut self,
+        source: &str,
+        entry_point: &str,
+        mesh: MeshId,
+    ) -> Result<()> {
+        if !self
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return Err(anyhow!("the adapter doesn't support compute shaders"));
+        }
+
+        let mesh = self
+            .scene
+            .get(mesh)
+            .ok_or_else(|| anyhow!("no mesh with the id {mesh:?}"))?;
+
+        self.device.push_error_scope(ErrorFilter::Validation);
+
+        let (MeshBuffers::Owned { vertex, .. } | MeshBuffers::Unindexed { vertex }) = &mesh.buffers
+        else {
+            return Err(anyhow!(
+                "the vertices of shared meshes can't be written by compute shaders"
+            ));
+        };
+
+        let compute_pass = ComputePass::new(&self.device, source, entry_point, vertex);
+
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(anyhow!("failed to create the compute pipeline: {error}"));
+        }
+
+        self.compute_pass = Some(compute_pass);
+        Ok(())
+    }
+
+    /// Removes the compute shader, dropping any dispatch that hasn't been recorded yet.
+    pub fn remove_compute_shader(&mut self) {
+        self.compute_pass = None;
+        self.compute_dispatch = None;
+    }
+
+    /// Dispatches `x * y * z` workgroups of the compute shader in the next frame, before the scene
+    /// is drawn. Does nothing if no compute shader has been set.
+    pub fn dispatch_compute(&mut self, x: u32, y: u32, z: u32) {
+        if self.compute_pass.is_some() {
+            self.compute_dispatch = Some((x, y, z));
+        }
+    }
+
+    /// Rebuilds the axis gizmo with lines of the given `length`.
+    pub fn set_axes_length(&mut self, length: f32) {
+        self.axes = Mesh::axes(&self.device, length);
+    }
+
+    /// Clamps a frame latency to `Self::FRAME_LATENCIES`, warning if it's out of range.
+    fn supported_frame_latency(latency: u32) -> u32 {
+        let supported = latency.clamp(*Self::FRAME_LATENCIES.start(), *Self::FRAME_LATENCIES.end());
+
+        if supported != latency {
+            warn!("frame latency {latency} is out of range, using {supported}");
+        }
+
+        supported
+    }
+
+    /// Returns the number of frames the gpu may queue up ahead of the one being displayed.
+    pub fn frame_latency(&self) -> u32 {
+        self.surface_config.desired_maximum_frame_latency
+    }
+
+    /// Sets the number of frames the gpu may queue up ahead of the one being displayed,
+    /// reconfiguring the surface. The latency is clamped to `Self::FRAME_LATENCIES`, returning the
+    /// latency which is used.
+    pub fn set_frame_latency(&mut self, latency: u32) -> u32 {
+        let latency = Self::supported_frame_latency(latency);
+
+        if latency != self.surface_config.desired_maximum_frame_latency {
+            self.surface_config.desired_maximum_frame_latency = latency;
+            self.output.configure(&self.device, &self.surface_config);
+        }
+
+        latency
+    }
+
+    /// Reconfigures the target `surface` to the new rendering size.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let PhysicalSize { width, height } = size;
+
+        assert!(width > 0, "cannot resize to zero width");
+        assert!(height > 0, "cannot resize to zero height");
+
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+
+        self.output.configure(&self.device, &self.surface_config);
+
+        if let Some(offscreen) = &mut self.offscreen {
+            offscreen.resize(&self.device, width, height);
+        }
+
+        if let Some(msaa_texture) = &mut self.msaa_texture {
+            msaa_texture.resize(&self.device, width, height);
+        }
+        self.depth_texture.resize(&self.device, width, height);
+
+        for view in &mut self.views {
+            view.camera.resize(view.viewport.size(size));
+        }
+    }
+
+    /// Recreates the surface from the window after it has been lost (such as after a gpu reset),
+    /// since the handle of a lost surface can't be reconfigured.
+    fn recreate_surface(&mut self) -> Result<()> {
+        let Output::Window { window, surface } = &mut self.output else {
+            return Ok(());
+        };
+
+        warn!("the surface was lost, recreating it");
+
+        *surface = self.instance.create_surface(window.clone())?;
+        surface.configure(&self.device, &self.surface_config);
+
+        Ok(())
+    }
+
+    /// Returns whether the device was lost (such as after a driver update or a gpu reset), after
+    /// which nothing is rendered until the renderer is rebuilt with `Renderer::rebuild`.
+    pub fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Rebuilds the renderer from scratch for the same window (or headless size) after the device
+    /// was lost, with a new adapter, device and surface. The scene, lights, cameras and settings are carried over,
+    /// the meshes being uploaded again with `Scene::recreate`. The skybox and compute shader can't
+    /// be recreated, and are removed with a warning.
+    pub async fn rebuild(&mut self) -> Result<()> {
+        warn!("rebuilding the renderer");
+
+        let camera = &self.views[0].camera;
+
+        let mut renderer = match &self.output {
+            Output::Window { window, .. } => {
+                Self::new(window.clone(), camera, &self.config).await?
+            }
+            Output::Headless(_) => {
+                let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+
+                Self::new_headless(size, camera, &self.config).await?
+            }
+        };
+
+        renderer.scene = self.scene.recreate(&renderer.device);
+        if let Some(axes) = self.axes.recreate(&renderer.device) {
+            renderer.axes = axes;
+        }
+
+        renderer.set_point_lights(self.point_lights.lights());
+        renderer.set_directional_light(self.directional_light());
+        renderer.views = std::mem::take(&mut self.views);
+
+        renderer.clear_color = self.clear_color;
+        renderer.set_post_params(self.post_params);
+        renderer.set_clip_plane(self.clip_plane);
+        renderer.set_grid_params(self.grid_params());
+        renderer.grid_visible = self.grid_visible;
+        renderer.axes_visible = self.axes_visible;
+        renderer.skybox_visible = self.skybox_visible;
+
+        if let Some((top, bottom)) = self.background_gradient() {
+            renderer.set_background_gradient(top, bottom);
+        }
+
+        if let Some(particles) = &self.particles {
+            renderer.set_particles(particles.params());
+        }
+
+        if let Some(source) = &self.custom_shader {
+            if let Err(error) = renderer.set_shader(source) {
+                warn!("the custom shader can't be recreated on the new device: {error:#}");
+            }
+        }
+
+        renderer.cull_mode = self.cull_mode;
+        renderer.set_wireframe(self.wireframe);
+        renderer.line_width = self.line_width;
+        renderer.set_sample_count(self.sample_count);
+        renderer.set_reversed_z(self.reversed_z);
+        renderer.set_depth_bias(self.depth_bias);
+        renderer.set_frame_latency(self.frame_latency());
+        renderer.set_exposure(self.exposure);
+        renderer.set_fxaa(self.fxaa);
+        renderer.set_depth_debug_range(self.depth_debug_range);
+        renderer.set_depth_debug(self.depth_debug());
+
+        if self.skybox.is_some() {
+            warn!("the skybox can't be recreated on the new device, removing it");
+        }
+
+        if self.compute_pass.is_some() {
+            warn!("the compute shader can't be recreated on the new device, removing it");
+        }
+
+        *self = renderer;
+
+        Ok(())
+    }
+
+    /// Begins a render pass and renders the currently active meshes to the `surface`, through the
+    /// offscreen target if there is one.
+    pub fn render(&mut self) -> Result<()> {
+        self.render_frame(false).map(drop)
+    }
+
+    /// Renders a frame and reads it back as it appears on screen, including the debug overlay.
+    /// Fails if the surface can't be copied from, or isn't in an 8 bit rgba or bgra format.
+    pub fn capture_frame(&mut self) -> Result<CapturedFrame> {
+        FrameReadback::check(self.surface_config.format, self.surface_config.usage)?;
+
+        self.render_frame(true)?.ok_or_else(|| {
+            anyhow!("the frame was skipped while the surface couldn't be rendered to")
+        })
+    }
+
+    /// Clears the surface to a color and presents it, without drawing the scene or anything else.
+    /// Being independent of the scene's pipelines and buffers, it's the smallest check that the
+    /// device and surface work. The color is given in linear space, like the `clear_color`.
+    pub fn clear(&mut self, color: Color) -> Result<()> {
+        let Some((surface_texture, view)) = self.acquire_frame()? else {
+            return Ok(());
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Clear Encoder"),
+            });
+
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(color),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.present(surface_texture);
 
         Ok(())
     }
+
+    /// Acquires the next texture of the surface (`None` for headless renderers) along with a view
+    /// of the texture the frame is rendered into, in the surface's view format. Returns `None` if
+    /// the frame has to be skipped, restoring an outdated or lost surface for the next one.
+    fn acquire_frame(&mut self) -> Result<Option<(Option<SurfaceTexture>, TextureView)>> {
+        let surface = match &self.output {
+            Output::Window { surface, .. } => surface,
+            Output::Headless(texture) => {
+                let view = texture
+                    .texture()
+                    .create_view(&TextureViewDescriptor::default());
+
+                return Ok(Some((None, view)));
+            }
+        };
+
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            Err(SurfaceError::Outdated) => {
+                self.output.configure(&self.device, &self.surface_config);
+                return Ok(None);
+            }
+            Err(SurfaceError::Lost) => {
+                self.recreate_surface()?;
+                return Ok(None);
+            }
+            Err(SurfaceError::Timeout) => {
+                warn!("timed out acquiring the next frame, skipping it");
+                return Ok(None);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(Self::surface_view_format(&self.surface_config)),
+            ..Default::default()
+        });
+
+        Ok(Some((Some(output), view)))
+    }
+
+    /// Presents a finished frame on screen. Headless renderers wait for the frame to finish
+    /// instead, since without a surface to pace them frames would otherwise pile up unbounded.
+    fn present(&self, surface_texture: Option<SurfaceTexture>) {
+        match surface_texture {
+            Some(surface_texture) => surface_texture.present(),
+            None => {
+                self.device.poll(Maintain::Wait);
+            }
+        }
+    }
+
+    /// Returns whether the renderer renders into a texture rather than a window, having been
+    /// created with `Renderer::new_headless`.
+    pub fn is_headless(&self) -> bool {
+        matches!(self.output, Output::Headless(_))
+    }
+
+    /// Renders a frame onto the surface, reading it back if `capture` is set. Returns `None` if the
+    /// frame had to be skipped.
+    fn render_frame(&mut self, capture: bool) -> Result<Option<CapturedFrame>> {
+        self.render_stats = RenderStats::default();
+
+        #[cfg(feature = "hot-reload")]
+        if self.shader_watcher.changed() {
+            self.reload_shader();
+        }
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.poll(&self.device);
+        }
+
+        let Some((surface_texture, view)) = self.acquire_frame()? else {
+            // the labels are redrawn every frame, so ones queued for a skipped frame would pile up
+            self.labels.clear();
+            return Ok(None);
+        };
+
+        // the first frame is rendered inside an error scope, returning its validation errors from
+        // `render`. The scope is popped however encoding went, as one left pushed would capture the
+        // errors of every later frame
+        let first_frame = !self.rendered_first_frame;
+
+        if first_frame {
+            self.device.push_error_scope(ErrorFilter::Validation);
+        }
+
+        let readback = self.encode_frame(surface_texture.as_ref(), &view, capture);
+
+        if first_frame {
+            if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+                return Err(anyhow!("failed to render the first frame: {error}"));
+            }
+        }
+
+        let readback = readback?;
+        self.rendered_first_frame = true;
+
+        let captured = readback
+            .map(|readback| readback.read(&self.device))
+            .transpose()?;
+
+        self.present(surface_texture);
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.map();
+        }
+
+        Ok(captured)
+    }
+
+    /// Records and submits the commands rendering a frame into `view`, along with copying the
+    /// frame's texture for reading it back if `capture` is set.
+    fn encode_frame(
+        &mut self,
+        surface_texture: Option<&SurfaceTexture>,
+        view: &TextureView,
+        capture: bool,
+    ) -> Result<Option<FrameReadback>> {
+        let encode_start = Instant::now();
+
+        // the render pass borrows the pipelines, so the ones it's missing are created beforehand
+        self.prepare_pipelines();
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.upload_frame_data(&mut encoder);
+
+        if let (Some(compute_pass), Some((x, y, z))) =
+            (&self.compute_pass, self.compute_dispatch.take())
+        {
+            compute_pass.dispatch(&mut encoder, x, y, z);
+        }
+
+        self.shadow_map.draw(
+            &mut encoder,
+            &self.mesh_arena,
+            self.scene.iter().map(|(_, mesh)| mesh).filter(|mesh| {
+                mesh.visible()
+                    && !mesh.transparent
+                    && mesh.topology == PrimitiveTopology::TriangleList
+            }),
+        );
+
+        let scene_view = match &self.offscreen {
+            Some(offscreen) => offscreen.target.view(),
+            None => view,
+        };
+
+        // when multisampling, render into the multisampled target and resolve it onto the scene's
+        // target
+        let (target, resolve_target) = match &self.msaa_texture {
+            Some(msaa_texture) => (msaa_texture.view(), Some(scene_view)),
+            None => (scene_view, None),
+        };
+
+        let mut stats = RenderStats::default();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: self.depth_texture.view(),
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(if self.reversed_z { 0.0 } else { 1.0 }),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self.gpu_timer.as_ref().map(GpuTimer::timestamp_writes),
+            });
+
+            let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+
+            for (index, view) in self.views.iter().enumerate() {
+                let (x, y, width, height) = view.viewport.pixels(size);
+
+                render_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+                render_pass.set_scissor_rect(x, y, width, height);
+
+                self.draw_view(&mut render_pass, index, &view.camera, &mut stats);
+            }
+        }
+
+        self.render_stats = stats;
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.resolve(&mut encoder);
+        }
+
+        // the depth visualization covers the whole surface, so the scene isn't copied onto it
+        match (&self.depth_debug, &self.offscreen) {
+            (Some(depth_debug), _) => {
+                depth_debug.draw(&self.device, &mut encoder, self.depth_texture.view(), view)
+            }
+            (None, Some(offscreen)) => offscreen.draw(&mut encoder, view, self.fxaa),
+            (None, None) => {}
+        }
+
+        #[cfg(feature = "debug-ui")]
+        let ui_command_buffers = match &self.ui_frame {
+            Some(frame) => self.ui_painter.paint(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                view,
+                [self.surface_config.width, self.surface_config.height],
+                frame,
+            ),
+            None => Vec::new(),
+        };
+        #[cfg(not(feature = "debug-ui"))]
+        let ui_command_buffers = Vec::new();
+
+        let readback = capture
+            .then(|| {
+                let texture = self.output.frame_texture(surface_texture);
+
+                FrameReadback::new(&self.device, &mut encoder, texture)
+            })
+            .transpose()?;
+
+        self.staging_belt.finish();
+        self.queue.submit(
+            ui_command_buffers
+                .into_iter()
+                .chain(std::iter::once(encoder.finish())),
+        );
+        self.staging_belt.recall();
+
+        self.encode_time = encode_start.elapsed().as_secs_f32() * 1000.0;
+
+        Ok(readback)
+    }
+
+    /// Records uploading the camera's matrices, the directional light and the changed transforms of
+    /// the scene's meshes through the `staging_belt`, ahead of the frame's render pass.
+    fn upload_frame_data(&mut self, encoder: &mut CommandEncoder) {
+        // read every frame rather than on scale factor changes, as it's a single multiplication
+        let line_width = self.line_width * self.scale_factor();
+        let surface_size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+        let belt = &mut self.staging_belt;
+
+        for (index, view) in self.views.iter().enumerate() {
+            let size = view.viewport.size(surface_size);
+
+            let uniform = CameraUniform {
+                view_proj: view.camera.view_projection().to_cols_array_2d(),
+                viewport_size: [size.width as f32, size.height as f32],
+                line_width,
+                _padding: 0.0,
+            };
+
+            belt.write_buffer(
+                encoder,
+                &self.camera_buffer,
+                Camera::UNIFORM_STRIDE * index as BufferAddress,
+                BufferSize::new(std::mem::size_of::<CameraUniform>() as BufferAddress).unwrap(),
+                &self.device,
+            )
+            .copy_from_slice(bytemuck::bytes_of(&uniform));
+
+            if let Some(skybox) = &self.skybox {
+                skybox.update_camera(
+                    belt,
+                    encoder,
+                    &self.device,
+                    index,
+                    view.camera.rotation_projection(),
+                );
+            }
+
+            self.grid
+                .update_camera(belt, encoder, &self.device, index, &view.camera, line_width);
+
+            if let Some(particles) = &self.particles {
+                particles.update_camera(belt, encoder, &self.device, index, &view.camera);
+            }
+        }
+
+        if let Some(particles) = &mut self.particles {
+            particles.upload(belt, encoder, &self.device);
+        }
+
+        // the labels only last for the frame they were queued for
+        self.labels.upload(belt, encoder, &self.device);
+        self.labels.clear();
+
+        self.shadow_map
+            .upload(belt, encoder, &self.device, &self.scene.bounds());
+
+        for (_, mesh) in self.scene.iter_mut() {
+            mesh.upload_transform(belt, encoder, &self.device);
+            mesh.upload_joints(belt, encoder, &self.device);
+
+            if Self::draws_thick_lines(mesh, line_width, self.wireframe) {
+                mesh.prepare_lines(&self.device);
+            }
+        }
+
+        if self.axes_visible && Self::draws_thick_lines(&self.axes, line_width, false) {
+            self.axes.prepare_lines(&self.device);
+        }
+    }
+
+    /// Records drawing the scene as seen by the camera of the `index`th view into the render pass,
+    /// counting the drawn meshes into `stats`.
+    fn draw_view(
+        &self,
+        render_pass: &mut RenderPass,
+        index: usize,
+        camera: &Camera,
+        stats: &mut RenderStats,
+    ) {
+        match self.skybox.as_ref().filter(|_| self.skybox_visible) {
+            Some(skybox) => skybox.draw(render_pass, index),
+            None => {
+                if let Some(background) = &self.background {
+                    background.draw(render_pass);
+                }
+            }
+        }
+
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+        let bind_groups = |render_pass: &mut RenderPass| {
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[offset]);
+            render_pass.set_bind_group(2, self.point_lights.bind_group(), &[]);
+            render_pass.set_bind_group(3, self.shadow_map.bind_group(), &[]);
+        };
+
+        bind_groups(render_pass);
+
+        let frustum = camera.frustum();
+
+        let (mut overlay, rest): (Vec<_>, Vec<_>) = self
+            .scene
+            .iter()
+            .map(|(_, mesh)| mesh)
+            .filter(|mesh| mesh.visible() && frustum.intersects_aabb(&mesh.world_bounds()))
+            .partition(|mesh| mesh.overlay());
+
+        let (mut transparent, opaque): (Vec<_>, Vec<_>) =
+            rest.into_iter().partition(|mesh| mesh.transparent);
+
+        for mesh in opaque {
+            self.draw_mesh(render_pass, mesh, stats);
+        }
+
+        // the grid blends over the opaque meshes in front of the plane, and is hidden by the ones
+        // behind it, so it's drawn after them with its own bind groups
+        if self.grid_visible {
+            self.grid.draw(render_pass, index);
+            bind_groups(render_pass);
+        }
+
+        if self.axes_visible {
+            self.draw_mesh(render_pass, &self.axes, stats);
+        }
+
+        // transparent meshes are drawn back to front so that they blend over each other correctly
+        let back_to_front = |a: &&Mesh, b: &&Mesh| {
+            let distance = |mesh: &Mesh| mesh.center().distance_squared(camera.eye);
+            distance(b).total_cmp(&distance(a))
+        };
+
+        transparent.sort_by(back_to_front);
+
+        for mesh in transparent {
+            self.draw_mesh(render_pass, mesh, stats);
+        }
+
+        // the particles blend over the transparent meshes with their own bind groups, which the
+        // overlays replace again
+        if let Some(particles) = &self.particles {
+            particles.draw(render_pass, index);
+            bind_groups(render_pass);
+        }
+
+        // overlays ignore the depth buffer, so they're drawn last over everything else, sorted the
+        // same way so that the nearest one ends up on top
+        overlay.sort_by(back_to_front);
+
+        for mesh in overlay {
+            self.draw_mesh(render_pass, mesh, stats);
+        }
+
+        // the labels are drawn over the overlays with their own pipeline, last so that nothing has
+        // to be rebound after them
+        self.labels
+            .draw(render_pass, &self.camera_bind_group, index);
+    }
+
+    /// Records the draw commands of a single mesh into the render pass, with the pipeline matching
+    /// its topology, transparency and whether it's an overlay, counting it into `stats`.
+    fn draw_mesh(&self, render_pass: &mut RenderPass, mesh: &Mesh, stats: &mut RenderStats) {
+        let polygon_mode = self.polygon_mode();
+
+        let line_width = self.line_width * self.scale_factor();
+        // meshes which couldn't be expanded into quads are drawn with thin lines instead
+        let lines = Self::draws_thick_lines(mesh, line_width, self.wireframe)
+            .then(|| mesh.line_buffer())
+            .flatten();
+
+        render_pass.set_pipeline(match lines {
+            Some(_) => self.line_pass.pipeline(mesh.transparent, mesh.overlay()),
+            None => self
+                .pipelines
+                .get(PipelineKey::of(mesh, self.cull_mode, polygon_mode)),
+        });
+        render_pass.set_bind_group(1, mesh.material.bind_group(), &[]);
+
+        if self.device.features().contains(Features::PUSH_CONSTANTS) {
+            let tint = mesh.tint().unwrap_or(Material::DEFAULT_TINT);
+            render_pass.set_push_constants(
+                ShaderStages::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&tint.to_array()),
+            );
+        }
+
+        match lines {
+            Some((buffer, count)) => {
+                render_pass.set_vertex_buffer(0, buffer.slice(..));
+                render_pass.set_vertex_buffer(1, mesh.instance_buffer.slice(..));
+                render_pass.draw(0..count, 0..mesh.instance_count);
+            }
+            None => mesh
+                .bind_buffers(render_pass, &self.mesh_arena)
+                .draw(render_pass, 0..mesh.instance_count),
+        }
+
+        stats.record(mesh);
+    }
+}
+
+impl Offscreen {
+    /// Creates an offscreen target of the given format matching the size of the surface, along with
+    /// the passes copying it onto the surface. Targets with a floating point format are tonemapped.
+    fn new(
+        adapter: &Adapter,
+        device: &Device,
+        config: &SurfaceConfiguration,
+        format: TextureFormat,
+        exposure: f32,
+    ) -> Self {
+        let surface_format = Renderer::surface_view_format(config);
+
+        let target = RenderTarget::new(device, config.width, config.height, format, None);
+        let blit = BlitPass::new(device, &target, surface_format);
+
+        let hdr = matches!(
+            format,
+            TextureFormat::Rgba16Float | TextureFormat::Rgba32Float | TextureFormat::Rg11b10Float
+        );
+
+        let tonemapping = hdr.then(|| Tonemapping {
+            pass: TonemapPass::new(device, &target, surface_format, exposure),
+            output: RenderTarget::new(device, config.width, config.height, surface_format, None),
+        });
+
+        // the anti-aliased image is the tonemapped one if there is one, which has the (always
+        // filterable) format of the surface
+        let fxaa_source = tonemapping.as_ref().map_or(&target, |t| &t.output);
+
+        let filterable = adapter
+            .get_texture_format_features(fxaa_source.format())
+            .flags
+            .contains(TextureFormatFeatureFlags::FILTERABLE);
+
+        let fxaa = filterable.then(|| FxaaPass::new(device, fxaa_source, surface_format));
+
+        Self {
+            target,
+            tonemapping,
+            blit,
+            fxaa,
+        }
+    }
+
+    /// Recreates the targets with a new size, rebinding them to the passes reading them.
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.target.resize(device, width, height);
+        self.blit.set_source(device, &self.target);
+
+        let fxaa_source = match &mut self.tonemapping {
+            Some(Tonemapping { pass, output }) => {
+                output.resize(device, width, height);
+                pass.set_source(device, &self.target);
+                output
+            }
+            None => &self.target,
+        };
+
+        if let Some(fxaa) = &mut self.fxaa {
+            fxaa.set_source(device, fxaa_source);
+        }
+    }
+
+    /// Records copying the rendered scene onto `view`, tonemapping and anti-aliasing it on the way
+    /// when enabled.
+    fn draw(&self, encoder: &mut CommandEncoder, view: &TextureView, fxaa: bool) {
+        let fxaa = self.fxaa.as_ref().filter(|_| fxaa);
+
+        match (&self.tonemapping, fxaa) {
+            (Some(tonemapping), Some(fxaa)) => {
+                tonemapping.pass.draw(encoder, tonemapping.output.view());
+                fxaa.draw(encoder, view);
+            }
+            (Some(tonemapping), None) => tonemapping.pass.draw(encoder, view),
+            (None, Some(fxaa)) => fxaa.draw(encoder, view),
+            (None, None) => self.blit.draw(encoder, view),
+        }
+    }
 }
@@ -0,0 +1,501 @@
+#[cfg(feature = "serde")]
+use std::{collections::HashMap, fs, path::Path};
+
+#[cfg(feature = "serde")]
+use anyhow::{anyhow, bail, Context, Result};
+use glam::*;
+use log::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use wgpu::{Device, PrimitiveTopology};
+
+use crate::{
+    bounds::Aabb,
+    config::DefaultScene,
+    mesh::{Mesh, Vertex},
+};
+#[cfg(feature = "serde")]
+use crate::{
+    material::Material,
+    mesh::{InstanceRaw, MeshSource, Spin},
+};
+
+/// A handle to a mesh stored inside of a `Scene`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(usize);
+
+/// A collection of meshes that are rendered together.
+#[derive(Debug, Default)]
+pub struct Scene {
+    /// The meshes of the scene, indexed by their `MeshId`. Removed meshes leave an empty slot
+    /// behind so that the remaining ids stay valid.
+    meshes: Vec<Option<Mesh>>,
+}
+
+/// A mesh as it is saved to and loaded from scene files. Meshes loaded from files are saved as a
+/// reference to the file, while the vertices of other meshes are saved inline.
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedMesh {
+    source: MeshSource,
+    /// The vertices and indices of `MeshSource::Vertices` meshes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    geometry: Option<(Vec<Vertex>, Vec<u32>)>,
+    topology: PrimitiveTopology,
+    transform: Mat4,
+    instances: Vec<InstanceRaw>,
+    spin: Option<Spin>,
+    color: Vec4,
+    transparent: bool,
+    #[serde(default)]
+    overlay: bool,
+    #[serde(default)]
+    depth_biased: bool,
+    #[serde(default)]
+    hidden: bool,
+}
+
+impl Scene {
+    /// Creates a new empty scene.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mesh to the scene, returning a handle to it.
+    pub fn add(&mut self, mesh: Mesh) -> MeshId {
+        self.meshes.push(Some(mesh));
+        MeshId(self.meshes.len() - 1)
+    }
+
+    /// Removes a mesh from the scene, returning it if it was present.
+    pub fn remove(&mut self, id: MeshId) -> Option<Mesh> {
+        self.meshes.get_mut(id.0)?.take()
+    }
+
+    /// Returns a reference to the mesh with the given id.
+    pub fn get(&self, id: MeshId) -> Option<&Mesh> {
+        self.meshes.get(id.0)?.as_ref()
+    }
+
+    /// Returns a mutable reference to the mesh with the given id.
+    pub fn get_mut(&mut self, id: MeshId) -> Option<&mut Mesh> {
+        self.meshes.get_mut(id.0)?.as_mut()
+    }
+
+    /// Shows or hides the mesh with the given id, see `Mesh::set_visible`. Does nothing if there is
+    /// no such mesh.
+    pub fn set_visible(&mut self, id: MeshId, visible: bool) {
+        if let Some(mesh) = self.get_mut(id) {
+            mesh.set_visible(visible);
+        }
+    }
+
+    /// Returns an iterator over all the meshes in the scene, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (MeshId, &Mesh)> {
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, mesh)| Some((MeshId(i), mesh.as_ref()?)))
+    }
+
+    /// Returns an iterator over mutable references to all the meshes in the scene.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (MeshId, &mut Mesh)> {
+        self.meshes
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, mesh)| Some((MeshId(i), mesh.as_mut()?)))
+    }
+
+    /// Intersects a world-space ray with the visible meshes of the scene using
+    /// `Mesh::ray_intersect`, returning the nearest mesh hit and the distance along `dir` to the
+    /// hit.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3) -> Option<(MeshId, f32)> {
+        self.iter()
+            .filter(|(_, mesh)| mesh.visible())
+            .filter_map(|(id, mesh)| Some((id, mesh.ray_intersect(origin, dir)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Returns the world-space bounding box containing every visible mesh in the scene.
+    pub fn bounds(&self) -> Aabb {
+        self.iter()
+            .filter(|(_, mesh)| mesh.visible())
+            .map(|(_, mesh)| mesh.world_bounds())
+            .fold(Aabb::EMPTY, |bounds, mesh| bounds.union(&mesh))
+    }
+
+    /// Removes every static mesh from the scene (visible opaque triangle lists which aren't
+    /// spinning, skinned, overlays or depth biased, and still have their geometry), merging them into a single
+    /// mesh drawn with one draw call, or `None` if there are none. The transforms, instances and
+    /// material colors of the meshes are baked into the merged vertices, so they can no longer be
+    /// moved or recolored individually.
+    pub fn merge_static(&mut self, device: &Device) -> Option<Mesh> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for slot in &mut self.meshes {
+            let Some(mesh) = slot.take_if(|mesh| {
+                mesh.vertices().is_some()
+                    && mesh.visible()
+                    && !mesh.transparent
+                    && !mesh.overlay()
+                    && !mesh.depth_biased()
+                    && mesh.spin().is_none()
+                    && mesh.skin().is_none()
+                    && mesh.topology == PrimitiveTopology::TriangleList
+            }) else {
+                continue;
+            };
+
+            let color = mesh.material.color();
+            let mesh_vertices = mesh.vertices().unwrap_or_default();
+            let mesh_indices = mesh.indices().unwrap_or_default();
+
+            for instance in mesh.instances() {
+                let transform = mesh.model_matrix() * Mat4::from_cols_array_2d(&instance.model);
+                let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+
+                let offset = vertices.len() as u32;
+
+                vertices.extend(mesh_vertices.iter().map(|vertex| {
+                    Vertex {
+                        pos: transform.transform_point3(vertex.pos.into()).into(),
+                        color: (Vec4::from(vertex.color) * color).into(),
+                        normal: (normal_matrix * Vec3::from(vertex.normal))
+                            .normalize_or_zero()
+                            .into(),
+                        ..*vertex
+                    }
+                }));
+                indices.extend(mesh_indices.iter().map(|index| index + offset));
+            }
+        }
+
+        (!vertices.is_empty()).then(|| Mesh::new(device, &vertices, &indices))
+    }
+
+    /// Saves the meshes of the scene to a JSON file, along with their transforms, instances, spins
+    /// and materials. Meshes loaded from files are saved as references to those files, which must
+    /// still be around when the scene is loaded again. Fails if the geometry of a mesh which isn't
+    /// loaded from a file was discarded.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let meshes = self
+            .iter()
+            .map(|(id, mesh)| {
+                let geometry = match mesh.source {
+                    MeshSource::Vertices => {
+                        let (Some(vertices), Some(indices)) = (mesh.vertices(), mesh.indices())
+                        else {
+                            bail!("the geometry of mesh {id:?} was discarded");
+                        };
+
+                        Some((vertices.to_vec(), indices.to_vec()))
+                    }
+                    _ => None,
+                };
+
+                Ok(SavedMesh {
+                    source: mesh.source.clone(),
+                    geometry,
+                    topology: mesh.topology,
+                    transform: mesh.model_matrix(),
+                    instances: mesh.instances().to_vec(),
+                    spin: mesh.spin(),
+                    color: mesh.material.color(),
+                    transparent: mesh.transparent,
+                    overlay: mesh.overlay(),
+                    depth_biased: mesh.depth_biased(),
+                    hidden: !mesh.visible(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        fs::write(path, serde_json::to_string_pretty(&meshes)?)?;
+        Ok(())
+    }
+
+    /// Loads a scene saved with `Scene::save`, recreating the meshes on the device. Fails naming
+    /// the file if one of the files the meshes were loaded from is missing.
+    #[cfg(feature = "serde")]
+    pub fn load(device: &Device, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let saved: Vec<SavedMesh> = serde_json::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("failed to read scene {}", path.display()))?,
+        )?;
+
+        // the meshes of every referenced file, each taken out once it's been placed in the scene
+        let mut files = HashMap::new();
+        let mut scene = Self::new();
+
+        for saved in saved {
+            let mut mesh = match &saved.source {
+                MeshSource::Vertices => {
+                    let (vertices, indices) = saved.geometry.as_ref().ok_or_else(|| {
+                        anyhow!("a mesh of scene {} has no vertices", path.display())
+                    })?;
+
+                    Mesh::new(device, vertices, indices)
+                }
+                MeshSource::Gltf { path, index } => {
+                    Self::load_file_mesh(device, &mut files, path, *index, load_gltf)?
+                }
+                MeshSource::Obj { path, index } => {
+                    Self::load_file_mesh(device, &mut files, path, *index, load_obj)?
+                }
+            };
+
+            mesh.topology = saved.topology;
+            mesh.set_transform(saved.transform);
+            mesh.set_instances(device, &saved.instances);
+            mesh.material = Material::new(device, saved.color);
+            mesh.transparent = saved.transparent;
+            mesh.set_overlay(saved.overlay);
+            mesh.set_depth_biased(saved.depth_biased);
+            mesh.set_visible(!saved.hidden);
+
+            match saved.spin {
+                Some(spin) => mesh.set_spin(spin.axis, spin.rate),
+                None => mesh.stop_spin(),
+            }
+
+            scene.add(mesh);
+        }
+
+        Ok(scene)
+    }
+
+    /// Takes the `index`th mesh out of the file at `path`, loading the file into `files` with
+    /// `load` if it hasn't been yet.
+    #[cfg(feature = "serde")]
+    fn load_file_mesh(
+        device: &Device,
+        files: &mut HashMap<std::path::PathBuf, Vec<Option<Mesh>>>,
+        path: &Path,
+        index: usize,
+        load: fn(&Device, &Path) -> Result<Vec<Mesh>>,
+    ) -> Result<Mesh> {
+        if !path.exists() {
+            bail!(
+                "the mesh file {} referenced by the scene doesn't exist",
+                path.display()
+            );
+        }
+
+        // a file is loaded again when the mesh has already been taken by an earlier copy
+        let loaded = match files.get(path) {
+            Some(meshes) => !matches!(meshes.get(index), Some(None)),
+            None => false,
+        };
+
+        if !loaded {
+            let meshes = load(device, path)?;
+            files.insert(path.to_path_buf(), meshes.into_iter().map(Some).collect());
+        }
+
+        files
+            .get_mut(path)
+            .and_then(|meshes| meshes.get_mut(index)?.take())
+            .ok_or_else(|| anyhow!("{} has no mesh {index}", path.display()))
+    }
+
+    /// Uploads a copy of the scene onto another device with `Mesh::recreate`, such as the one
+    /// replacing a lost device. The meshes keep their ids, except for those whose geometry was
+    /// discarded, which are dropped with a warning.
+    pub fn recreate(&self, device: &Device) -> Self {
+        let meshes = self
+            .meshes
+            .iter()
+            .enumerate()
+            .map(|(index, mesh)| {
+                let recreated = mesh.as_ref()?.recreate(device);
+
+                if recreated.is_none() {
+                    warn!(
+                        "mesh {:?} can't be recreated, as its geometry was discarded",
+                        MeshId(index)
+                    );
+                }
+
+                recreated
+            })
+            .collect();
+
+        Self { meshes }
+    }
+
+    /// Creates the scene a new renderer starts out with, made of one of the primitives of `Mesh`.
+    /// The lights going with it are given by `DefaultScene::light`.
+    pub fn from_default(device: &Device, scene: DefaultScene) -> Self {
+        let mesh = match scene {
+            DefaultScene::Triangle => {
+                let vertex = |pos, color| Vertex {
+                    pos,
+                    color,
+                    normal: [0.0, 0.0, 1.0],
+                    ..Default::default()
+                };
+
+                let mut triangle = Mesh::new(
+                    device,
+                    &[
+                        vertex([0.0, 0.5, 0.0], [1.0, 0.0, 0.0, 1.0]),
+                        vertex([-0.5, -0.5, 0.0], [0.0, 1.0, 0.0, 1.0]),
+                        vertex([0.5, -0.5, 0.0], [0.0, 0.0, 1.0, 1.0]),
+                    ],
+                    &[0, 1, 2],
+                );
+                triangle.set_spin(Vec3::Z, 0.5);
+
+                Some(triangle)
+            }
+            DefaultScene::Cube => {
+                let mut cube = Mesh::cube(device, 1.0, vec4(0.8, 0.8, 0.8, 1.0));
+                cube.set_spin(Vec3::Y, 0.5);
+
+                Some(cube)
+            }
+            DefaultScene::Sphere => {
+                Some(Mesh::sphere(device, 0.6, 48, 24, vec4(0.8, 0.8, 0.8, 1.0)))
+            }
+            DefaultScene::Empty => None,
+        };
+
+        let mut default = Self::new();
+        default.meshes.extend(mesh.map(Some));
+        default
+    }
+
+    /// Creates a scene of `count` spinning cubes laid out in a square grid on the xz plane around
+    /// the origin, for measuring how the renderer scales with the number of meshes. Every cube is
+    /// a separate mesh, and so takes a draw call of its own. The layout assumes
+    /// `CoordinateSystem::YUp`, like the primitives of `Mesh`.
+    pub fn stress_test(device: &Device, count: usize) -> Self {
+        const SPACING: f32 = 2.0;
+
+        let side = (count as f32).sqrt().ceil() as usize;
+        let offset = (side.saturating_sub(1)) as f32 * SPACING / 2.0;
+
+        let mut scene = Self::new();
+
+        for index in 0..count {
+            let (row, column) = (index / side, index % side);
+            let position = vec3(
+                column as f32 * SPACING - offset,
+                0.0,
+                row as f32 * SPACING - offset,
+            );
+
+            // spread the hues of the cubes around the color wheel
+            let hue = index as f32 / count as f32 * std::f32::consts::TAU;
+            let color =
+                Vec3::splat(0.5) + vec3(hue.cos(), (hue + 2.1).cos(), (hue + 4.2).cos()) / 2.0;
+
+            let mut mesh = Mesh::cube(device, 1.0, color.extend(1.0));
+            mesh.set_transform(Mat4::from_translation(position));
+            mesh.set_spin(Vec3::Y, 0.5 + (index % 5) as f32 * 0.25);
+
+            scene.add(mesh);
+        }
+
+        scene
+    }
+
+    /// Creates a scene of `count` cubes of random sizes, colors and orientations scattered around
+    /// the origin, laid out the same way for the same `seed` on every run and platform (see
+    /// `SplitMix64`). The cubes don't spin, so that frames rendered from the scene are
+    /// reproducible too.
+    pub fn random(device: &Device, seed: u64, count: usize) -> Self {
+        let mut random = SplitMix64::new(seed);
+
+        // keep the density of the cubes about the same however many there are
+        let extent = (count as f32).cbrt() * 1.5;
+
+        let mut scene = Self::new();
+
+        for _ in 0..count {
+            let position = (random.vec3() * 2.0 - Vec3::ONE) * extent;
+            let size = 0.3 + random.next_f32() * 0.7;
+            let color = Vec3::splat(0.2) + random.vec3() * 0.8;
+
+            let axis = (random.vec3() * 2.0 - Vec3::ONE)
+                .try_normalize()
+                .unwrap_or(Vec3::Y);
+            let angle = random.next_f32() * std::f32::consts::TAU;
+
+            let mut mesh = Mesh::cube(device, size, color.extend(1.0));
+            mesh.set_transform(Mat4::from_rotation_translation(
+                Quat::from_axis_angle(axis, angle),
+                position,
+            ));
+
+            scene.add(mesh);
+        }
+
+        scene
+    }
+
+    /// Advances the animations of all the meshes in the scene by `dt` seconds.
+    pub fn animate(&mut self, dt: f32) {
+        for (_, mesh) in self.iter_mut() {
+            mesh.animate(dt);
+        }
+    }
+}
+
+/// The SplitMix64 generator (Steele, Lea and Flood, "Fast Splittable Pseudorandom Number
+/// Generators", 2014) seeding `Scene::random`. It only adds, multiplies and shifts 64 bit integers
+/// with wrapping, so a seed produces the same sequence on every platform, and is converted to
+/// floats exactly. Any seed (including zero) is fine.
+#[derive(Debug, Clone)]
+struct SplitMix64 {
+    /// The state, advanced by the golden ratio with every number.
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Creates a generator starting from `seed`.
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next number of the sequence.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next number between zero (inclusive) and one (exclusive).
+    fn next_f32(&mut self) -> f32 {
+        // the top 24 bits fit into the mantissa of an f32 exactly
+        (self.next_u64() >> 40) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns a vector of the next three numbers between zero and one.
+    fn vec3(&mut self) -> Vec3 {
+        vec3(self.next_f32(), self.next_f32(), self.next_f32())
+    }
+}
+
+/// Loads the meshes of a glTF file referenced by a saved scene.
+#[cfg(all(feature = "serde", feature = "gltf"))]
+fn load_gltf(device: &Device, path: &Path) -> Result<Vec<Mesh>> {
+    Mesh::from_gltf(device, path).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Fails to load the glTF file referenced by a saved scene, since glTF support is disabled.
+#[cfg(all(feature = "serde", not(feature = "gltf")))]
+fn load_gltf(_: &Device, path: &Path) -> Result<Vec<Mesh>> {
+    bail!("loading {} requires the `gltf` feature", path.display())
+}
+
+/// Loads the meshes of an OBJ file referenced by a saved scene.
+#[cfg(feature = "serde")]
+fn load_obj(device: &Device, path: &Path) -> Result<Vec<Mesh>> {
+    Mesh::from_obj(device, path)
+}
@@ -0,0 +1,325 @@
+use glam::*;
+use wgpu::{util::*, *};
+
+use crate::{
+    bounds::Aabb,
+    light::DirectionalLight,
+    material::Material,
+    mesh::{InstanceRaw, Mesh, Vertex},
+    mesh_arena::MeshArena,
+};
+
+/// The directional light and the matrix of its shadow map, laid out as they are uploaded to the
+/// rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct DirectionalLightUniform {
+    view_proj: [[f32; 4]; 4],
+    direction: [f32; 3],
+    enabled: u32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+/// The directional light of the scene along with its shadow map, a depth texture the scene is
+/// rendered into from the light's point of view. The light and the shadow map are bound to group 3
+/// while meshes are drawn, so that shadowed fragments can be darkened.
+#[derive(Debug)]
+pub struct ShadowMap {
+    /// The light casting the shadows, or `None` if the scene has no directional light.
+    light: Option<DirectionalLight>,
+    /// The uniform buffer of the `DirectionalLightUniform`.
+    uniform_buffer: Buffer,
+    /// The view of the depth texture the scene is rendered into from the light.
+    depth_view: TextureView,
+    /// The bind group of the uniform buffer, used while rendering the shadow map.
+    depth_bind_group: BindGroup,
+    /// The bind group of the uniform buffer, the depth texture and its comparison sampler, used
+    /// while rendering the scene.
+    bind_group: BindGroup,
+    /// Renders the depth of the scene's meshes from the light.
+    pipeline: RenderPipeline,
+}
+
+impl ShadowMap {
+    /// The width and height of the shadow map in texels.
+    pub const SIZE: u32 = 2048;
+    /// The format of the shadow map.
+    pub const FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+    /// The layout of the bind group used while rendering the scene.
+    pub const BIND_GROUP_LAYOUT: BindGroupLayoutDescriptor<'static> = BindGroupLayoutDescriptor {
+        label: Some("Shadow Map Bind Group Layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Depth,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                count: None,
+            },
+        ],
+    };
+
+    /// Creates the shadow map, without a light casting shadows into it.
+    pub fn new(device: &Device) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Shadow Map Texture"),
+            size: Extent3d {
+                width: Self::SIZE,
+                height: Self::SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let depth_view = texture.create_view(&TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Shadow Map Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            compare: Some(CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Directional Light Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Self::uniform(None, Mat4::IDENTITY)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let depth_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Shadow Map Depth Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let depth_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Shadow Map Depth Bind Group"),
+            layout: &depth_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Shadow Map Bind Group"),
+            layout: &device.create_bind_group_layout(&Self::BIND_GROUP_LAYOUT),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&depth_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/shadow.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[
+                &depth_bind_group_layout,
+                &device.create_bind_group_layout(&Material::BIND_GROUP_LAYOUT),
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: None,
+            primitive: PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            multisample: MultisampleState::default(),
+            // surfaces facing the light would otherwise shadow themselves in stripes ("shadow
+            // acne"), due to the limited resolution of the shadow map
+            depth_stencil: Some(DepthStencilState {
+                format: Self::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            light: None,
+            uniform_buffer,
+            depth_view,
+            depth_bind_group,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Returns the light casting the shadows, if there is one.
+    pub fn light(&self) -> Option<DirectionalLight> {
+        self.light
+    }
+
+    /// Sets the light casting the shadows, `None` removing the directional light from the scene.
+    pub fn set_light(&mut self, light: Option<DirectionalLight>) {
+        self.light = light;
+    }
+
+    /// Returns the view-projection matrix of the light, an orthographic projection along its
+    /// direction fitting the sphere around `bounds`.
+    pub fn view_projection(light: &DirectionalLight, bounds: &Aabb) -> Mat4 {
+        let center = bounds.center();
+        let radius = (bounds.size().length() / 2.0).max(0.01);
+
+        let direction = light.direction.normalize_or(Vec3::NEG_Y);
+        let up = if direction.y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+
+        let view = Mat4::look_at_rh(center - direction * 2.0 * radius, center, up);
+        let projection = Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, 4.0 * radius);
+
+        projection * view
+    }
+
+    /// Builds the uniform of the light, disabled if there is none.
+    fn uniform(light: Option<DirectionalLight>, view_projection: Mat4) -> DirectionalLightUniform {
+        DirectionalLightUniform {
+            view_proj: view_projection.to_cols_array_2d(),
+            direction: light
+                .map_or(Vec3::ZERO, |light| light.direction.normalize_or_zero())
+                .into(),
+            enabled: light.is_some() as u32,
+            color: light.map_or(Vec3::ZERO, |light| light.color).into(),
+            _padding: 0.0,
+        }
+    }
+
+    /// Records uploading the light through the staging belt, with the shadow map covering
+    /// `bounds`.
+    pub fn upload(
+        &self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        bounds: &Aabb,
+    ) {
+        let view_projection = match &self.light {
+            Some(light) if !bounds.is_empty() => Self::view_projection(light, bounds),
+            _ => Mat4::IDENTITY,
+        };
+
+        let uniform = Self::uniform(self.light, view_projection);
+        let bytes = bytemuck::bytes_of(&uniform);
+
+        belt.write_buffer(
+            encoder,
+            &self.uniform_buffer,
+            0,
+            BufferSize::new(bytes.len() as BufferAddress).unwrap(),
+            device,
+        )
+        .copy_from_slice(bytes);
+    }
+
+    /// Records rendering the depth of the given meshes from the light into the shadow map, taking
+    /// shared meshes from the arena. Does nothing if there is no light.
+    pub fn draw<'a>(
+        &self,
+        encoder: &mut CommandEncoder,
+        arena: &MeshArena,
+        meshes: impl Iterator<Item = &'a Mesh>,
+    ) {
+        if self.light.is_none() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Shadow Render Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.depth_bind_group, &[]);
+
+        for mesh in meshes {
+            // the material holds the joints skinned meshes are deformed by
+            render_pass.set_bind_group(1, mesh.material.bind_group(), &[]);
+            mesh.bind_buffers(&mut render_pass, arena)
+                .draw(&mut render_pass, 0..mesh.instance_count);
+        }
+    }
+
+    /// Returns the bind group of the light and the shadow map.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+}
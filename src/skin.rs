@@ -0,0 +1,423 @@
+use std::ops::{Add, Mul};
+
+use glam::*;
+#[cfg(feature = "gltf")]
+use log::*;
+
+/// The joints deforming a skinned mesh, along with the animations moving them. Every vertex of
+/// the mesh is transformed by a blend of the matrices of the joints it's weighted to (see
+/// `Vertex::joint_indices`), which are uploaded to the gpu as a palette whenever the pose changes.
+#[derive(Debug, Clone)]
+pub struct Skin {
+    /// The hierarchy of nodes the joints are part of, including the ones which only carry the
+    /// transforms of their children.
+    pub nodes: Vec<SkinNode>,
+    /// The index into `nodes` of every joint, in the order the vertices refer to them.
+    pub joints: Vec<usize>,
+    /// The matrix of every joint transforming the mesh from its bind pose into the joint's local
+    /// space.
+    pub inverse_bind_matrices: Vec<Mat4>,
+    /// The animations which can be played with `Skin::play`.
+    pub animations: Vec<Animation>,
+
+    /// The index of the animation being played, if any.
+    playing: Option<usize>,
+    /// The time into the playing animation, in seconds.
+    time: f32,
+    /// Whether the pose changed since the joint matrices were last uploaded.
+    dirty: bool,
+}
+
+/// A node of a skin's hierarchy, placed relative to its parent.
+#[derive(Debug, Clone, Copy)]
+pub struct SkinNode {
+    /// The index of the parent node, or `None` for the roots.
+    pub parent: Option<usize>,
+    /// The translation of the node when it isn't animated.
+    pub translation: Vec3,
+    /// The rotation of the node when it isn't animated.
+    pub rotation: Quat,
+    /// The scale of the node when it isn't animated.
+    pub scale: Vec3,
+}
+
+/// A named animation of the nodes of a skin, looping once played.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    /// The name the animation is played by.
+    pub name: String,
+    /// The time of the last keyframe of any channel, in seconds, after which the animation loops.
+    pub duration: f32,
+    /// The properties of the nodes the animation moves.
+    pub channels: Vec<Channel>,
+}
+
+/// The keyframes of a single property of a node.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    /// The index of the node in the skin's `nodes`.
+    pub node: usize,
+    /// How the values between the keyframes are found.
+    pub interpolation: Interpolation,
+    /// The time of every keyframe in seconds, in ascending order.
+    pub times: Vec<f32>,
+    /// The value of every keyframe. Cubic splines have three values per keyframe: the incoming
+    /// tangent, the value and the outgoing tangent.
+    pub keyframes: Keyframes,
+}
+
+/// The values of the keyframes of a channel, for the property they animate.
+#[derive(Debug, Clone)]
+pub enum Keyframes {
+    /// Translations of the node.
+    Translations(Vec<Vec3>),
+    /// Rotations of the node.
+    Rotations(Vec<Quat>),
+    /// Scales of the node.
+    Scales(Vec<Vec3>),
+}
+
+/// How the values of a channel between its keyframes are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds the value of the previous keyframe.
+    Step,
+    /// Interpolates linearly between the keyframes, spherically for rotations.
+    Linear,
+    /// Follows a cubic hermite spline through the keyframes, shaped by their tangents.
+    CubicSpline,
+}
+
+impl Skin {
+    /// Creates a skin in its rest pose, without any animation playing. Joints missing an inverse
+    /// bind matrix get the identity.
+    pub fn new(
+        nodes: Vec<SkinNode>,
+        joints: Vec<usize>,
+        mut inverse_bind_matrices: Vec<Mat4>,
+        animations: Vec<Animation>,
+    ) -> Self {
+        inverse_bind_matrices.resize(joints.len(), Mat4::IDENTITY);
+
+        Self {
+            nodes,
+            joints,
+            inverse_bind_matrices,
+            animations,
+            playing: None,
+            time: 0.0,
+            dirty: true,
+        }
+    }
+
+    /// Returns the names of the animations which can be played.
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.animations
+            .iter()
+            .map(|animation| animation.name.as_str())
+    }
+
+    /// Returns the name of the animation being played, if any.
+    pub fn playing(&self) -> Option<&str> {
+        self.playing
+            .map(|index| self.animations[index].name.as_str())
+    }
+
+    /// Starts playing the animation with the given name from its beginning, returning `false` if
+    /// there is none.
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(index) = self
+            .animations
+            .iter()
+            .position(|animation| animation.name == name)
+        else {
+            return false;
+        };
+
+        self.playing = Some(index);
+        self.time = 0.0;
+        self.dirty = true;
+
+        true
+    }
+
+    /// Stops the playing animation, returning the joints to their rest pose.
+    pub fn stop(&mut self) {
+        self.playing = None;
+        self.time = 0.0;
+        self.dirty = true;
+    }
+
+    /// Advances the playing animation by `dt` seconds, looping back to its beginning after its
+    /// duration.
+    pub fn advance(&mut self, dt: f32) {
+        let Some(index) = self.playing else {
+            return;
+        };
+
+        let duration = self.animations[index].duration;
+
+        self.time = if duration > 0.0 {
+            (self.time + dt).rem_euclid(duration)
+        } else {
+            0.0
+        };
+        self.dirty = true;
+    }
+
+    /// Returns whether the pose changed since the last call, clearing the flag.
+    pub(crate) fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Returns the matrix of every joint for the current pose, transforming the vertices weighted
+    /// to it from their bind pose into the pose.
+    pub fn joint_matrices(&self) -> Vec<Mat4> {
+        let mut locals = self
+            .nodes
+            .iter()
+            .map(|node| (node.translation, node.rotation, node.scale))
+            .collect::<Vec<_>>();
+
+        if let Some(index) = self.playing {
+            for channel in &self.animations[index].channels {
+                let Some(local) = locals.get_mut(channel.node) else {
+                    continue;
+                };
+
+                channel.apply(self.time, local);
+            }
+        }
+
+        let locals = locals
+            .into_iter()
+            .map(|(translation, rotation, scale)| {
+                Mat4::from_scale_rotation_translation(scale, rotation, translation)
+            })
+            .collect::<Vec<_>>();
+
+        let mut globals = vec![None; self.nodes.len()];
+
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| {
+                self.global_transform(joint, &locals, &mut globals) * *inverse_bind
+            })
+            .collect()
+    }
+
+    /// Returns the transform of the node relative to the roots of the hierarchy, caching it and
+    /// the transforms of its ancestors in `globals`.
+    fn global_transform(&self, node: usize, locals: &[Mat4], globals: &mut [Option<Mat4>]) -> Mat4 {
+        let Some(&local) = locals.get(node) else {
+            return Mat4::IDENTITY;
+        };
+
+        if let Some(global) = globals[node] {
+            return global;
+        }
+
+        let global = match self.nodes[node].parent {
+            Some(parent) => self.global_transform(parent, locals, globals) * local,
+            None => local,
+        };
+
+        globals[node] = Some(global);
+        global
+    }
+
+    /// Loads the joints of a glTF skin, along with every animation of the document. The hierarchy
+    /// holds all the nodes of the document, so that the joints are placed by all of their
+    /// ancestors. Animations of morph targets aren't supported and are skipped with a warning.
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf(
+        document: &gltf::Document,
+        skin: &gltf::Skin,
+        buffers: &[gltf::buffer::Data],
+    ) -> Self {
+        let mut nodes = document
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+
+                SkinNode {
+                    parent: None,
+                    translation: translation.into(),
+                    rotation: Quat::from_array(rotation),
+                    scale: scale.into(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for node in document.nodes() {
+            for child in node.children() {
+                nodes[child.index()].parent = Some(node.index());
+            }
+        }
+
+        let joints = skin.joints().map(|joint| joint.index()).collect();
+
+        let inverse_bind_matrices = skin
+            .reader(|buffer| Some(&buffers[buffer.index()]))
+            .read_inverse_bind_matrices()
+            .map_or_else(Vec::new, |matrices| {
+                matrices
+                    .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+                    .collect()
+            });
+
+        let animations = document
+            .animations()
+            .map(|animation| Animation::from_gltf(&animation, buffers))
+            .collect();
+
+        Self::new(nodes, joints, inverse_bind_matrices, animations)
+    }
+}
+
+impl Animation {
+    /// Loads the channels of a glTF animation, named by its index if it has no name.
+    #[cfg(feature = "gltf")]
+    fn from_gltf(animation: &gltf::Animation, buffers: &[gltf::buffer::Data]) -> Self {
+        use gltf::animation::util::ReadOutputs;
+
+        let name = animation
+            .name()
+            .map_or_else(|| animation.index().to_string(), str::to_string);
+
+        let channels = animation
+            .channels()
+            .filter_map(|channel| {
+                let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                let keyframes = match reader.read_outputs()? {
+                    ReadOutputs::Translations(values) => {
+                        Keyframes::Translations(values.map(Vec3::from).collect())
+                    }
+                    ReadOutputs::Rotations(values) => {
+                        Keyframes::Rotations(values.into_f32().map(Quat::from_array).collect())
+                    }
+                    ReadOutputs::Scales(values) => {
+                        Keyframes::Scales(values.map(Vec3::from).collect())
+                    }
+                    ReadOutputs::MorphTargetWeights(_) => {
+                        warn!("skipping unsupported morph target channel of animation {name}");
+                        return None;
+                    }
+                };
+
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+
+                Some(Channel {
+                    node: channel.target().node().index(),
+                    interpolation,
+                    times: reader.read_inputs()?.collect(),
+                    keyframes,
+                })
+            })
+            .collect::<Vec<Channel>>();
+
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+
+        Self {
+            name,
+            duration,
+            channels,
+        }
+    }
+}
+
+impl Channel {
+    /// Overwrites the animated property of a node's translation, rotation and scale with its value
+    /// at `time`. Channels with fewer values than keyframes leave it unchanged.
+    fn apply(&self, time: f32, (translation, rotation, scale): &mut (Vec3, Quat, Vec3)) {
+        let interpolation = self.interpolation;
+
+        match &self.keyframes {
+            Keyframes::Translations(values) => {
+                if let Some(value) = sample(&self.times, values, interpolation, time, Vec3::lerp) {
+                    *translation = value;
+                }
+            }
+            Keyframes::Rotations(values) => {
+                if let Some(value) = sample(&self.times, values, interpolation, time, Quat::slerp) {
+                    *rotation = value.normalize();
+                }
+            }
+            Keyframes::Scales(values) => {
+                if let Some(value) = sample(&self.times, values, interpolation, time, Vec3::lerp) {
+                    *scale = value;
+                }
+            }
+        }
+    }
+}
+
+/// Finds the value of the keyframes at `time`, holding the first and last values before and after
+/// them. Returns `None` if there are no keyframes or fewer values than they need.
+fn sample<T>(
+    times: &[f32],
+    values: &[T],
+    interpolation: Interpolation,
+    time: f32,
+    lerp: impl Fn(T, T, f32) -> T,
+) -> Option<T>
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let last = times.len().checked_sub(1)?;
+    let stride = match interpolation {
+        Interpolation::CubicSpline => 3,
+        _ => 1,
+    };
+
+    if values.len() < times.len() * stride {
+        return None;
+    }
+
+    // cubic splines store the value between the tangents
+    let value = |keyframe: usize| values[keyframe * stride + stride / 2];
+
+    let next = times.partition_point(|&keyframe| keyframe <= time);
+
+    if next == 0 {
+        return Some(value(0));
+    }
+
+    if next > last {
+        return Some(value(last));
+    }
+
+    let previous = next - 1;
+    let span = times[next] - times[previous];
+    let t = if span > 0.0 {
+        (time - times[previous]) / span
+    } else {
+        0.0
+    };
+
+    Some(match interpolation {
+        Interpolation::Step => value(previous),
+        Interpolation::Linear => lerp(value(previous), value(next), t),
+        Interpolation::CubicSpline => {
+            // the tangents are given per second, and scaled to the span of the keyframes
+            let out_tangent = values[previous * 3 + 2] * span;
+            let in_tangent = values[next * 3] * span;
+
+            let (t2, t3) = (t * t, t * t * t);
+
+            value(previous) * (2.0 * t3 - 3.0 * t2 + 1.0)
+                + out_tangent * (t3 - 2.0 * t2 + t)
+                + value(next) * (3.0 * t2 - 2.0 * t3)
+                + in_tangent * (t3 - t2)
+        }
+    })
+}
@@ -0,0 +1,266 @@
+use glam::*;
+use wgpu::{util::*, *};
+
+use crate::{
+    camera::Camera,
+    texture::{write_mip_chain, TextureOptions},
+    viewport::Viewport,
+};
+
+/// A cubemap drawn behind the scene, following the rotation of the camera but not its position.
+#[derive(Debug)]
+pub struct Skybox {
+    /// The uniform buffer of the inverses of the cameras' view-projection matrices without
+    /// translation, one for every viewport at multiples of `Camera::UNIFORM_STRIDE`.
+    uniform_buffer: Buffer,
+    /// The layout of the `bind_group`, kept around to recreate the pipeline.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the uniform buffer and the cubemap texture and sampler.
+    bind_group: BindGroup,
+    /// The shader module of the pipeline.
+    shader: ShaderModule,
+    /// Draws a fullscreen triangle sampling the cubemap.
+    pipeline: RenderPipeline,
+}
+
+impl Skybox {
+    /// The format of the faces of the cubemap.
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    /// Creates a skybox from six square faces of `face_size * face_size` rgba pixels, ordered as
+    /// +x, -x, +y, -y, +z and -z, sampled with the given (supported) options. The cameras are
+    /// uploaded with `Skybox::update_camera` before drawing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        face_size: u32,
+        faces: [&[u8]; 6],
+        options: &TextureOptions,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let size = Extent3d {
+            width: face_size,
+            height: face_size,
+            depth_or_array_layers: 6,
+        };
+
+        // the faces are always colors, whatever the options say
+        let levels = options
+            .with_srgb(true)
+            .mip_chain(face_size, face_size, 6, &faces.concat());
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Skybox Texture"),
+            size,
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        write_mip_chain(queue, &texture, &levels);
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("Skybox Texture View"),
+            dimension: Some(TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let sampler = options.create_sampler(device, "Skybox Sampler");
+
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Skybox Uniform Buffer"),
+            size: Camera::UNIFORM_STRIDE * Viewport::MAX_COUNT as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Skybox Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(
+                            std::mem::size_of::<Mat4>() as BufferAddress
+                        ),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: BufferSize::new(std::mem::size_of::<Mat4>() as BufferAddress),
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Skybox Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/skybox.wgsl").into()),
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &shader,
+            &bind_group_layout,
+            format,
+            depth_format,
+            sample_count,
+        );
+
+        Self {
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            shader,
+            pipeline,
+        }
+    }
+
+    /// Creates the pipeline drawing the skybox onto a target with the given formats and sample
+    /// count.
+    fn create_pipeline(
+        device: &Device,
+        shader: &ShaderModule,
+        bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    write_mask: ColorWrites::ALL,
+                    blend: None,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            // the skybox is drawn before anything else without writing depth, so that all of the
+            // scene's geometry is drawn over it
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Recreates the pipeline for a target with the given formats and sample count.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.shader,
+            &self.bind_group_layout,
+            format,
+            depth_format,
+            sample_count,
+        );
+    }
+
+    /// Records updating the uniform buffer of the `index`th viewport with its camera's new
+    /// `Camera::rotation_projection` matrix through the staging belt.
+    pub fn update_camera(
+        &self,
+        belt: &mut StagingBelt,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        index: usize,
+        rotation_projection: Mat4,
+    ) {
+        belt.write_buffer(
+            encoder,
+            &self.uniform_buffer,
+            Camera::UNIFORM_STRIDE * index as BufferAddress,
+            BufferSize::new(std::mem::size_of::<Mat4>() as BufferAddress).unwrap(),
+            device,
+        )
+        .copy_from_slice(bytemuck::cast_slice(
+            &rotation_projection.inverse().to_cols_array(),
+        ));
+    }
+
+    /// Records drawing the skybox as seen by the camera of the `index`th viewport into the render
+    /// pass.
+    pub fn draw(&self, render_pass: &mut RenderPass, index: usize) {
+        let offset = Camera::UNIFORM_STRIDE as DynamicOffset * index as DynamicOffset;
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[offset]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,363 @@
+use log::*;
+use wgpu::*;
+
+/// The options of how a texture is sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureOptions {
+    /// The maximum number of samples taken along the slope of a surface seen at a grazing angle,
+    /// keeping textures such as floors sharp in the distance. `1` disables anisotropic filtering.
+    pub anisotropy_clamp: u16,
+    /// Whether a full chain of mipmaps is generated for the texture, keeping it from shimmering when
+    /// seen from afar. Textures drawn at their own resolution (such as ui or atlases) shouldn't be
+    /// mipmapped.
+    pub mipmaps: bool,
+    /// Whether the texels are srgb encoded colors, which are decoded into linear space when
+    /// sampled. Textures holding data rather than colors (such as normal maps) shouldn't be, and
+    /// skyboxes always are.
+    pub srgb: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            anisotropy_clamp: Self::ANISOTROPY_CLAMP,
+            mipmaps: false,
+            srgb: true,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// The default anisotropy clamp, disabling anisotropic filtering.
+    pub const ANISOTROPY_CLAMP: u16 = 1;
+    /// The highest anisotropy clamp supported by the rendering device.
+    pub const MAX_ANISOTROPY_CLAMP: u16 = 16;
+
+    /// Sets the anisotropy clamp of the texture.
+    pub fn with_anisotropy_clamp(mut self, clamp: u16) -> Self {
+        self.anisotropy_clamp = clamp;
+        self
+    }
+
+    /// Sets whether a full chain of mipmaps is generated for the texture.
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Sets whether the texels of the texture are srgb encoded colors.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Returns the format of the texels of a texture with the options.
+    pub fn format(&self) -> TextureFormat {
+        if self.srgb {
+            Texture::FORMAT
+        } else {
+            Texture::LINEAR_FORMAT
+        }
+    }
+
+    /// Returns the options reduced to what the adapter supports, logging the options that had to
+    /// be reduced.
+    pub fn supported(mut self, adapter: &Adapter) -> Self {
+        let max_clamp = if adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::ANISOTROPIC_FILTERING)
+        {
+            Self::MAX_ANISOTROPY_CLAMP
+        } else {
+            1
+        };
+
+        let clamp = self.anisotropy_clamp.clamp(1, max_clamp);
+
+        if clamp != self.anisotropy_clamp {
+            warn!(
+                "anisotropy clamp {} isn't supported, using {clamp} instead",
+                self.anisotropy_clamp
+            );
+        }
+
+        self.anisotropy_clamp = clamp;
+        self
+    }
+
+    /// Creates a linearly filtered sampler with the options, which should have been checked with
+    /// `TextureOptions::supported` beforehand.
+    pub fn create_sampler(&self, device: &Device, label: &str) -> Sampler {
+        device.create_sampler(&SamplerDescriptor {
+            label: Some(label),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the mip levels of `layers` layers of `width * height` rgba pixels, the full chain
+    /// computed on the cpu if mipmaps are enabled, or only the given pixels otherwise.
+    pub(crate) fn mip_chain(
+        &self,
+        width: u32,
+        height: u32,
+        layers: u32,
+        rgba: &[u8],
+    ) -> Vec<MipLevel> {
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            rgba: rgba.to_vec(),
+        }];
+
+        if self.mipmaps {
+            for _ in 1..mip_level_count(width, height) {
+                let level = downsample(levels.last().unwrap(), layers, self.srgb);
+                levels.push(level);
+            }
+        }
+
+        levels
+    }
+}
+
+/// A 2d rgba texture sampled by shaders, along with its view and sampler.
+#[derive(Debug)]
+pub struct Texture {
+    /// The texture, with a full mip chain if its options asked for mipmaps.
+    texture: wgpu::Texture,
+    /// The view over every mip level of the texture.
+    view: TextureView,
+    /// The sampler created from the options of the texture.
+    sampler: Sampler,
+}
+
+impl Texture {
+    /// The format of the texels of a texture of srgb encoded colors.
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+    /// The format of the texels of a texture which isn't srgb encoded.
+    pub const LINEAR_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+    /// Creates a texture from `width * height` pixels of rgba bytes, sampled with the given
+    /// (supported) options. The bytes are srgb encoded unless the options say otherwise.
+    pub fn from_rgba(
+        device: &Device,
+        queue: &Queue,
+        label: &str,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        options: &TextureOptions,
+    ) -> Self {
+        let levels = options.mip_chain(width, height, 1, rgba);
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: options.format(),
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        write_mip_chain(queue, &texture, &levels);
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = options.create_sampler(device, label);
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Returns the underlying texture.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Returns the view over every mip level of the texture.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Returns the sampler of the texture.
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+}
+
+/// A single mip level of a texture, as the size of its layers and their rgba bytes.
+#[derive(Debug)]
+pub(crate) struct MipLevel {
+    /// The width of the level in pixels.
+    pub width: u32,
+    /// The height of the level in pixels.
+    pub height: u32,
+    /// The rgba bytes of every layer of the level, one after the other.
+    pub rgba: Vec<u8>,
+}
+
+/// Returns the number of mip levels of a full mip chain of a texture, down to a single pixel.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    width.max(height).max(1).ilog2() + 1
+}
+
+/// Uploads every level of the mip chain into the texture, which must have been created with as many
+/// mip levels and layers.
+pub(crate) fn write_mip_chain(queue: &Queue, texture: &wgpu::Texture, levels: &[MipLevel]) {
+    let layers = texture.depth_or_array_layers();
+
+    for (mip_level, level) in levels.iter().enumerate() {
+        queue.write_texture(
+            ImageCopyTexture {
+                texture,
+                mip_level: mip_level as u32,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &level.rgba,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * level.width),
+                rows_per_image: Some(level.height),
+            },
+            Extent3d {
+                width: level.width,
+                height: level.height,
+                depth_or_array_layers: layers,
+            },
+        );
+    }
+}
+
+/// Converts an srgb encoded color channel into linear space.
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear color channel into srgb space.
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Downsamples every layer of `level` to half its size (rounded down, but at least one pixel),
+/// averaging each 2x2 block of pixels in linear space (decoding them first if they're `srgb`).
+/// Pixels past the edges of odd sizes are clamped to the last row or column.
+fn downsample(level: &MipLevel, layers: u32, srgb: bool) -> MipLevel {
+    let width = (level.width / 2).max(1);
+    let height = (level.height / 2).max(1);
+
+    let layer_size = (4 * level.width * level.height) as usize;
+    let mut rgba = Vec::with_capacity((4 * width * height * layers) as usize);
+
+    for layer in level.rgba.chunks_exact(layer_size).take(layers as usize) {
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (2 * x + dx).min(level.width - 1);
+                    let sy = (2 * y + dy).min(level.height - 1);
+                    let pixel = &layer[(4 * (sy * level.width + sx)) as usize..][..4];
+
+                    for channel in 0..3 {
+                        sum[channel] += if srgb {
+                            srgb_to_linear(pixel[channel])
+                        } else {
+                            pixel[channel] as f32 / 255.0
+                        };
+                    }
+                    // alpha isn't srgb encoded
+                    sum[3] += pixel[3] as f32 / 255.0;
+                }
+
+                rgba.extend(sum[..3].iter().map(|sum| {
+                    if srgb {
+                        linear_to_srgb(sum / 4.0)
+                    } else {
+                        (sum / 4.0 * 255.0).round() as u8
+                    }
+                }));
+                rgba.push((sum[3] / 4.0 * 255.0).round() as u8);
+            }
+        }
+    }
+
+    MipLevel {
+        width,
+        height,
+        rgba,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mip_chain_length() {
+        for (width, height) in [
+            (1, 1),
+            (2, 2),
+            (256, 256),
+            (255, 255),
+            (640, 480),
+            (1, 1000),
+        ] {
+            let expected = (width.max(height) as f32).log2().floor() as u32 + 1;
+            assert_eq!(mip_level_count(width, height), expected, "{width}x{height}");
+        }
+
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(1024, 16), 11);
+        assert_eq!(mip_level_count(0, 0), 1);
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_pixel() {
+        let (width, height) = (6, 3);
+        let rgba = vec![255; 4 * width * height];
+        let levels = TextureOptions::default().with_mipmaps(true).mip_chain(
+            width as u32,
+            height as u32,
+            1,
+            &rgba,
+        );
+
+        let sizes = levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [(6, 3), (3, 1), (1, 1)]);
+
+        // averaging opaque white stays opaque white
+        for level in &levels {
+            assert_eq!(level.rgba.len(), (4 * level.width * level.height) as usize);
+            assert!(level.rgba.iter().all(|&byte| byte == 255));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use wgpu::{util::*, *};
+
+use crate::render_target::{create_fullscreen_pipeline, draw_fullscreen, RenderTarget};
+
+/// The settings of the tonemapping pass, laid out as they are uploaded to the rendering device.
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct TonemapUniform {
+    /// The scalar the hdr colors are multiplied with before being mapped.
+    exposure: f32,
+    /// Pads the uniform to the 16 bytes required by some backends.
+    _padding: [f32; 3],
+}
+
+/// Maps a high dynamic range render target into the displayable range with the ACES filmic curve,
+/// while copying it onto another texture of the same size.
+#[derive(Debug)]
+pub struct TonemapPass {
+    /// The uniform buffer of the `TonemapUniform`.
+    uniform_buffer: Buffer,
+    /// The layout of the `bind_group`, kept around to rebind resized sources.
+    bind_group_layout: BindGroupLayout,
+    /// The bind group of the source texture and the uniform buffer.
+    bind_group: BindGroup,
+    /// Draws the fullscreen triangle.
+    pipeline: RenderPipeline,
+}
+
+impl TonemapPass {
+    /// Creates a pass tonemapping `source` onto textures of the given format.
+    pub fn new(
+        device: &Device,
+        source: &RenderTarget,
+        format: TextureFormat,
+        exposure: f32,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Tonemap Uniform Buffer"),
+            contents: bytemuck::bytes_of(&TonemapUniform {
+                exposure,
+                _padding: [0.0; 3],
+            }),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Tonemap Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Tonemap Shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shader/tonemap.wgsl").into()),
+        });
+
+        let pipeline =
+            create_fullscreen_pipeline(device, "Tonemap", &shader, &bind_group_layout, format);
+
+        Self {
+            bind_group: Self::create_bind_group(
+                device,
+                &bind_group_layout,
+                &uniform_buffer,
+                source,
+            ),
+            uniform_buffer,
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Binds a new source to tonemap, such as after the previous one was resized.
+    pub fn set_source(&mut self, device: &Device, source: &RenderTarget) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            source,
+        );
+    }
+
+    /// Updates the scalar the hdr colors are multiplied with before being mapped.
+    pub fn set_exposure(&self, queue: &Queue, exposure: f32) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&TonemapUniform {
+                exposure,
+                _padding: [0.0; 3],
+            }),
+        );
+    }
+
+    /// Creates the bind group of the source texture and uniform buffer.
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        uniform_buffer: &Buffer,
+        source: &RenderTarget,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Tonemap Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source.view()),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Records tonemapping the source onto `view` into the encoder.
+    pub fn draw(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        draw_fullscreen(
+            encoder,
+            "Tonemap Render Pass",
+            view,
+            &self.pipeline,
+            &self.bind_group,
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use glam::*;
+use winit::{dpi::PhysicalPosition, event::*};
+
+use crate::camera::Camera;
+
+/// Moves the camera with touchscreen and touchpad gestures, in addition to the keyboard and mouse.
+/// Dragging a single finger looks around like the mouse does, dragging two fingers pans the camera
+/// and pinching them moves it forwards and backwards. Touchpad pinch and rotation gestures zoom and
+/// roll the camera.
+#[derive(Debug, Default)]
+pub struct TouchInput {
+    /// The positions of the fingers currently touching the screen, by the id of their touch.
+    touches: HashMap<u64, PhysicalPosition<f64>>,
+}
+
+impl TouchInput {
+    /// The distance the camera pans for every pixel two fingers are dragged by.
+    pub const PAN_SPEED: f32 = 0.01;
+    /// The distance the camera moves forward for every pixel two fingers are spread apart by.
+    pub const ZOOM_SPEED: f32 = 0.02;
+    /// The distance the camera moves forward for a touchpad pinch doubling the magnification.
+    pub const PINCH_DISTANCE: f32 = 5.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of fingers currently touching the screen.
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    /// Tracks a finger touching, moving along or lifting off the screen, moving the camera as it's
+    /// dragged. Only drags of one or two fingers move the camera.
+    pub fn handle_touch(&mut self, touch: Touch, camera: &mut Camera, dt: f32) {
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, touch.location);
+            }
+            TouchPhase::Moved => {
+                let Some(previous) = self.touches.insert(touch.id, touch.location) else {
+                    return;
+                };
+
+                let delta = position(touch.location) - position(previous);
+
+                match self.touches.len() {
+                    1 => camera.update_rotation_angles((delta.x as f64, delta.y as f64), dt),
+                    2 => {
+                        let other = self
+                            .touches
+                            .iter()
+                            .find(|(&id, _)| id != touch.id)
+                            .map(|(_, &location)| position(location))
+                            .unwrap();
+
+                        // the midpoint between the fingers moves half as far as the moving finger
+                        Self::pan(camera, delta / 2.0);
+
+                        let spread = other.distance(position(touch.location))
+                            - other.distance(position(previous));
+                        camera.eye += camera.forward() * spread * Self::ZOOM_SPEED;
+                    }
+                    _ => {}
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+            }
+        }
+    }
+
+    /// Moves the camera forward for a touchpad pinch magnifying by `delta` (negative when pinching
+    /// in).
+    pub fn pinch(camera: &mut Camera, delta: f64) {
+        camera.eye += camera.forward() * delta as f32 * Self::PINCH_DISTANCE;
+    }
+
+    /// Rolls the camera along with a touchpad rotation gesture of `delta` degrees counterclockwise,
+    /// so that the scene turns with the fingers.
+    pub fn rotate(camera: &mut Camera, delta: f32) {
+        camera.roll += delta.to_radians();
+    }
+
+    /// Moves the camera across the view so that the scene follows fingers dragged by `delta`
+    /// pixels.
+    fn pan(camera: &mut Camera, delta: Vec2) {
+        camera.eye += (camera.rolled_up() * delta.y - camera.right() * delta.x) * Self::PAN_SPEED;
+    }
+}
+
+/// Converts a position on the screen in pixels into a vector.
+fn position(location: PhysicalPosition<f64>) -> Vec2 {
+    vec2(location.x as f32, location.y as f32)
+}
@@ -0,0 +1,187 @@
+use glam::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The placement of an object as a translation, rotation and scale (TRS), applied in reverse:
+/// scaled first, then rotated and finally translated. Easier to edit than a model matrix, and the
+/// quaternion rotation can be animated without running into gimbal lock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transform {
+    /// The position of the object's origin.
+    pub translation: Vec3,
+    /// The orientation of the object, a normalized quaternion.
+    pub rotation: Quat,
+    /// The scale of the object along each of its local axes.
+    pub scale: Vec3,
+}
+
+impl Transform {
+    /// The transform leaving objects where they are.
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    /// Creates a transform which only moves objects by `translation`.
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform which only rotates objects by `rotation`.
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform which only scales objects by `scale`.
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Decomposes a model matrix into its translation, rotation and scale. Matrices which shear
+    /// (such as a non-uniform scale applied after a rotation) can't be represented, and lose their
+    /// shear.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Returns the model matrix scaling, rotating and then translating objects.
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Replaces the translation of the transform.
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Replaces the rotation of the transform.
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Replaces the scale of the transform.
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Scales the transform evenly along every axis.
+    pub fn with_uniform_scale(self, scale: f32) -> Self {
+        self.with_scale(Vec3::splat(scale))
+    }
+
+    /// Rotates the transform so that its forward axis (`-Z`) points from its translation towards
+    /// `target`, keeping its local `+Y` as close to `up` as possible. Leaves the rotation as it is
+    /// if the target is at the translation, or straight along `up`.
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        let forward = (target - self.translation).normalize_or_zero();
+        let right = forward.cross(up).normalize_or_zero();
+
+        if forward != Vec3::ZERO && right != Vec3::ZERO {
+            let up = right.cross(forward);
+            self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+        }
+
+        self
+    }
+
+    /// Moves the transform by `offset`, in world space.
+    pub fn translate(&mut self, offset: Vec3) {
+        self.translation += offset;
+    }
+
+    /// Rotates the transform by `rotation` around its own axes, renormalizing the result so that
+    /// errors don't build up when rotating every frame.
+    pub fn rotate_local(&mut self, rotation: Quat) {
+        self.rotation = (self.rotation * rotation).normalize();
+    }
+
+    /// Rotates the transform by `rotation` around the world's axes through its translation,
+    /// renormalizing the result.
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+
+    /// Returns where the transform places a point given in the object's local space.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.translation + self.rotation * (self.scale * point)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Mat4> for Transform {
+    fn from(matrix: Mat4) -> Self {
+        Self::from_matrix(matrix)
+    }
+}
+
+impl From<Transform> for Mat4 {
+    fn from(transform: Transform) -> Self {
+        transform.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_round_trip() {
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            Quat::from_euler(EulerRot::YXZ, 0.7, -0.3, 1.9),
+            Quat::from_axis_angle(vec3(1.0, 2.0, 3.0).normalize(), 2.5),
+        ];
+        let scales = [Vec3::ONE, vec3(2.0, 0.5, 1.0), vec3(0.1, 3.0, 7.5)];
+
+        for rotation in rotations {
+            for scale in scales {
+                let transform = Transform::from_translation(vec3(1.0, -2.0, 3.5))
+                    .with_rotation(rotation)
+                    .with_scale(scale);
+                let decomposed = Transform::from_matrix(transform.to_matrix());
+
+                assert!(
+                    decomposed
+                        .translation
+                        .abs_diff_eq(transform.translation, 1e-5),
+                    "{decomposed:?}"
+                );
+                assert!(
+                    decomposed.scale.abs_diff_eq(transform.scale, 1e-5),
+                    "{decomposed:?}"
+                );
+                // `q` and `-q` are the same rotation
+                assert!(
+                    decomposed.rotation.dot(transform.rotation).abs() > 1.0 - 1e-5,
+                    "{decomposed:?}"
+                );
+            }
+        }
+    }
+}
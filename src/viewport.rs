@@ -0,0 +1,56 @@
+use winit::dpi::PhysicalSize;
+
+/// A rectangle of the surface a camera renders into, in fractions of the surface's size so that it
+/// covers the same part of the surface after resizing. The origin is the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// The left edge of the rectangle, from 0 to 1.
+    pub x: f32,
+    /// The top edge of the rectangle, from 0 to 1.
+    pub y: f32,
+    /// The width of the rectangle, from 0 to 1.
+    pub width: f32,
+    /// The height of the rectangle, from 0 to 1.
+    pub height: f32,
+}
+
+impl Viewport {
+    /// The maximum number of viewports rendered at once.
+    pub const MAX_COUNT: usize = 4;
+    /// The viewport covering the whole surface.
+    pub const FULL: Self = Self::new(0.0, 0.0, 1.0, 1.0);
+
+    /// Creates a viewport from its top left corner and size, in fractions of the surface's size.
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the rectangle covered on a surface of the given size, in pixels as
+    /// `(x, y, width, height)`. The rectangle is clamped to the surface, and always covers at least
+    /// one pixel.
+    pub fn pixels(&self, size: PhysicalSize<u32>) -> (u32, u32, u32, u32) {
+        let edges = |start: f32, length: f32, total: u32| {
+            let total = total.max(1);
+            let start = ((start * total as f32).round() as u32).min(total - 1);
+            let end = (((start as f32 + length * total as f32).round()) as u32).min(total);
+
+            (start, end.max(start + 1) - start)
+        };
+
+        let (x, width) = edges(self.x, self.width, size.width);
+        let (y, height) = edges(self.y, self.height, size.height);
+
+        (x, y, width, height)
+    }
+
+    /// Returns the size of the rectangle covered on a surface of the given size, in pixels.
+    pub fn size(&self, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        let (_, _, width, height) = self.pixels(size);
+        PhysicalSize::new(width, height)
+    }
+}
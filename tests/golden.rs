@@ -0,0 +1,241 @@
+//! Renders the golden scenes headlessly and compares them against the golden images in
+//! `assets/golden`, like the `golden` example does, so that `cargo test --features golden` catches
+//! changes to how things are drawn. Checks which need a rendering device but no golden image live
+//! here too.
+
+use d3_template::{
+    camera::CoordinateSystem,
+    capture::CapturedFrame,
+    config::AppConfig,
+    golden::{self, GoldenScene, Tolerance},
+    mesh::{DrawRange, Mesh, Vertex},
+};
+use glam::*;
+
+/// Returns the golden scene called `name`.
+fn scene(name: &str) -> &'static GoldenScene {
+    golden::SCENES
+        .iter()
+        .find(|scene| scene.name == name)
+        .unwrap_or_else(|| panic!("there's no golden scene called {name}"))
+}
+
+#[test]
+fn scenes_match_golden_images() {
+    let config = AppConfig::default();
+    let dir = golden::golden_dir();
+
+    let errors: Vec<_> = golden::SCENES
+        .iter()
+        .filter_map(|scene| {
+            golden::render(scene, &config)
+                .and_then(|frame| golden::check(&dir, scene.name, &frame, Tolerance::DEFAULT))
+                .err()
+        })
+        .map(|error| format!("{error:#}"))
+        .collect();
+
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn material_color_multiplies_vertex_colors() {
+    let frame = golden::render(scene("material_color"), &AppConfig::default()).unwrap();
+
+    // the center of the cube's front face
+    assert_eq!(pixel(&frame, IVec2::ZERO), [255, 0, 0, 255]);
+}
+
+/// Returns the number of pixels along the edges of what's drawn which are blended with the black
+/// background, being less than three quarters as bright as the brightest of their neighbors.
+fn blended_edge_pixels(frame: &CapturedFrame) -> usize {
+    let brightness = |x: u32, y: u32| {
+        let pixel = ((y * frame.width + x) * 4) as usize;
+        frame.rgba[pixel..pixel + 3]
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0) as u32
+    };
+
+    (1..frame.height - 1)
+        .flat_map(|y| (1..frame.width - 1).map(move |x| (x, y)))
+        .filter(|&(x, y)| {
+            let neighbors: Vec<_> = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&neighbor| neighbor != (x, y))
+                .map(|(nx, ny)| brightness(nx, ny))
+                .collect();
+
+            let pixel = brightness(x, y);
+            let brightest = neighbors.iter().copied().max().unwrap_or(0);
+
+            pixel > 0 && neighbors.contains(&0) && pixel * 4 < brightest * 3
+        })
+        .count()
+}
+
+#[test]
+fn multisampling_blends_diagonal_edges() {
+    let config = AppConfig::default();
+
+    let aliased = golden::render(scene("triangle"), &config).unwrap();
+    let antialiased = golden::render(scene("triangle_msaa"), &config).unwrap();
+
+    assert_ne!(aliased.rgba, antialiased.rgba);
+    assert_eq!(blended_edge_pixels(&aliased), 0);
+    assert!(blended_edge_pixels(&antialiased) > 0);
+}
+
+#[test]
+fn coplanar_decal_fights_floor_without_depth_bias() {
+    let unbiased = GoldenScene {
+        name: "depth_bias_unbiased",
+        setup: |renderer, camera| {
+            (scene("depth_bias").setup)(renderer, camera);
+
+            for (_, mesh) in renderer.scene_mut().iter_mut() {
+                mesh.set_depth_biased(false);
+            }
+        },
+        sample_count: 1,
+    };
+
+    let frame = golden::render(&unbiased, &AppConfig::default()).unwrap();
+    let expected = golden::read_png(&golden::golden_dir().join("depth_bias.png")).unwrap();
+    let comparison = golden::compare(&frame, &expected, Tolerance::DEFAULT).unwrap();
+
+    assert!(!comparison.passes(Tolerance::DEFAULT));
+}
+
+/// Returns the vertices of a white unit square facing the camera, and the indices of its two
+/// triangles sharing the diagonal from the bottom left to the top right corner.
+fn quad() -> ([Vertex; 4], [u32; 6]) {
+    let vertices = [
+        [-0.5, -0.5, 0.0],
+        [0.5, -0.5, 0.0],
+        [0.5, 0.5, 0.0],
+        [-0.5, 0.5, 0.0],
+    ]
+    .map(|pos| Vertex {
+        pos,
+        color: [1.0; 4],
+        ..Default::default()
+    });
+
+    (vertices, [0, 1, 2, 0, 2, 3])
+}
+
+/// Returns the pixel of the frame at an offset from its center, with y pointing down.
+fn pixel(frame: &CapturedFrame, offset: IVec2) -> [u8; 4] {
+    let position = ivec2(frame.width as i32, frame.height as i32) / 2 + offset;
+    let start = (position.y as u32 * frame.width + position.x as u32) as usize * 4;
+
+    frame.rgba[start..start + 4].try_into().unwrap()
+}
+
+/// Asserts that both triangles of the `quad` cover the frame, and nothing is drawn around it.
+fn assert_covers_quad(frame: &CapturedFrame) {
+    const WHITE: [u8; 4] = [255; 4];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    // the bottom right and top left triangles
+    assert_eq!(pixel(frame, ivec2(16, 16)), WHITE);
+    assert_eq!(pixel(frame, ivec2(-16, -16)), WHITE);
+
+    for offset in [ivec2(48, 0), ivec2(-48, 0), ivec2(0, 48), ivec2(0, -48)] {
+        assert_eq!(pixel(frame, offset), BLACK, "at {offset}");
+    }
+}
+
+#[test]
+fn quad_mesh_draws_both_triangles() {
+    let scene = GoldenScene {
+        name: "quad",
+        setup: |renderer, camera| {
+            let (vertices, indices) = quad();
+            let quad = Mesh::new(renderer.device(), &vertices, &indices);
+
+            golden::stage(renderer, camera, vec3(0.0, 0.0, 2.0), [quad]);
+        },
+        sample_count: 1,
+    };
+
+    assert_covers_quad(&golden::render(&scene, &AppConfig::default()).unwrap());
+}
+
+#[test]
+fn shared_mesh_draws_from_middle_of_arena() {
+    let scene = GoldenScene {
+        name: "shared_quad",
+        setup: |renderer, camera| {
+            golden::stage(renderer, camera, vec3(0.0, 0.0, 2.0), []);
+
+            // a first quad out of view filling the start of the arena, which would be drawn in
+            // place of the second one if its offsets were ignored
+            let (mut vertices, indices) = quad();
+            for vertex in &mut vertices {
+                vertex.pos[0] += 5.0;
+            }
+            let first = renderer.add_shared_mesh(&vertices, &indices);
+
+            let (vertices, indices) = quad();
+            let second = renderer.add_shared_mesh(&vertices, &indices);
+
+            let scene = renderer.scene_mut();
+            scene.set_visible(first, false);
+
+            assert_eq!(
+                scene.get(second).unwrap().draw_range(),
+                DrawRange::Indexed {
+                    indices: 6..12,
+                    base_vertex: 4
+                }
+            );
+        },
+        sample_count: 1,
+    };
+
+    assert_covers_quad(&golden::render(&scene, &AppConfig::default()).unwrap());
+}
+
+#[test]
+fn grid_lies_flat_in_either_coordinate_system() {
+    let config = AppConfig::default();
+
+    // the same view from above the grid, once with y up and once with z up, which only differ by
+    // mirroring the grid's symmetric lines
+    let y_up = GoldenScene {
+        name: "grid_y_up",
+        setup: |renderer, camera| {
+            renderer.set_grid_visible(true);
+
+            golden::stage(renderer, camera, vec3(0.5, 5.0, 0.5), []);
+        },
+        sample_count: 1,
+    };
+    let z_up = GoldenScene {
+        name: "grid_z_up",
+        setup: |renderer, camera| {
+            renderer.set_grid_visible(true);
+            camera.set_coordinate_system(CoordinateSystem::ZUp);
+
+            golden::stage(renderer, camera, vec3(0.5, -0.5, 5.0), []);
+        },
+        sample_count: 1,
+    };
+
+    let y_up = golden::render(&y_up, &config).unwrap();
+    let z_up = golden::render(&z_up, &config).unwrap();
+    let comparison = golden::compare(&z_up, &y_up, Tolerance::DEFAULT).unwrap();
+
+    assert!(y_up
+        .rgba
+        .chunks_exact(4)
+        .any(|pixel| pixel[..3] != [0, 0, 0]));
+    assert!(
+        comparison.passes(Tolerance::DEFAULT),
+        "{} pixels differ",
+        comparison.mismatched
+    );
+}